@@ -1,12 +1,18 @@
 pub struct Code {}
 
+impl Default for Code {
+    fn default() -> Self {
+        Code::new()
+    }
+}
+
 impl Code {
     pub fn new() -> Code {
         Code {}
     }
 
-    pub fn dest(&self, mnemonic: &str) -> &str {
-        match mnemonic {
+    pub fn dest(&self, mnemonic: &str) -> Option<&str> {
+        let bits = match mnemonic {
             "" => "000",
             "M" => "001",
             "D" => "010",
@@ -15,12 +21,13 @@ impl Code {
             "AM" => "101",
             "AD" => "110",
             "AMD" => "111",
-            _ => panic!("Invalid instruction: dest"),
-        }
+            _ => return None,
+        };
+        Some(bits)
     }
 
-    pub fn comp(&self, mnemonic: &str) -> &str {
-        match mnemonic {
+    pub fn comp(&self, mnemonic: &str) -> Option<&str> {
+        let bits = match mnemonic {
             "0" => "101010",
             "1" => "111111",
             "-1" => "111010",
@@ -39,12 +46,13 @@ impl Code {
             "A-D" | "M-D" => "000111",
             "D&A" | "D&M" => "000000",
             "D|A" | "D|M" => "010101",
-            _ => panic!("Invalid instruction: comp"),
-        }
+            _ => return None,
+        };
+        Some(bits)
     }
 
-    pub fn jump(&self, mnemonic: &str) -> &str {
-        match mnemonic {
+    pub fn jump(&self, mnemonic: &str) -> Option<&str> {
+        let bits = match mnemonic {
             "" => "000",
             "JGT" => "001",
             "JEQ" => "010",
@@ -53,7 +61,8 @@ impl Code {
             "JNE" => "101",
             "JLE" => "110",
             "JMP" => "111",
-            _ => panic!("Invalid instruction: jump {mnemonic}"),
-        }
+            _ => return None,
+        };
+        Some(bits)
     }
 }
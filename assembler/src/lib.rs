@@ -0,0 +1,87 @@
+pub mod code;
+pub mod parser;
+pub mod symbol_table;
+
+use code::Code;
+use parser::{CommandType, ParseError, Parser};
+use symbol_table::SymbolTable;
+
+const MEMORY_OFFSET: usize = 15;
+const MAX_ADDRESS: usize = 32767;
+const ROM_SIZE: usize = 32768;
+
+/// Translates a Hack assembly program into `.hack` text, one 16 bit
+/// binary instruction per line.
+pub fn assemble(asm_code: &str) -> Result<String, ParseError> {
+    let mut parser = Parser::from_code(asm_code);
+    let code = Code::new();
+    let mut symbol_table = SymbolTable::new();
+    let mut hack = String::new();
+
+    // first pass: labels get the address of the next instruction
+    let mut rom_address = 0;
+    while parser.has_more_commands() {
+        parser.advance();
+
+        match parser.command_type() {
+            CommandType::LCommand => {
+                if !(symbol_table.contains(&parser.symbol())) {
+                    symbol_table.add_entry(parser.symbol(), rom_address);
+                }
+            }
+            _ => rom_address += 1,
+        }
+    }
+
+    if rom_address > ROM_SIZE {
+        return Err(ParseError::ProgramTooLarge(rom_address));
+    }
+
+    parser.commnad_number = 0;
+    let mut variable_num = 0;
+
+    while parser.has_more_commands() {
+        parser.advance();
+
+        match parser.command_type() {
+            CommandType::ACommand => {
+                let symbol = parser.symbol();
+                let address = match symbol.parse::<usize>() {
+                    Ok(value) if value <= MAX_ADDRESS => value,
+                    Ok(_) => return Err(ParseError::InvalidInstruction(parser.command())),
+                    Err(_) => {
+                        if !symbol_table.contains(&symbol) {
+                            variable_num += 1;
+                            symbol_table.add_entry(symbol.clone(), variable_num + MEMORY_OFFSET);
+                        }
+                        let address = symbol_table.get_address(&symbol);
+                        // a label after the last instruction of a full ROM
+                        if address > MAX_ADDRESS {
+                            return Err(ParseError::AddressOutOfRange(symbol, address));
+                        }
+                        address
+                    }
+                };
+                // 0 + vvvvvvvvvvvvvvv
+                hack.push_str(&format!("0{:015b}\n", address));
+            }
+
+            CommandType::CCommand => {
+                let invalid = || ParseError::InvalidInstruction(parser.command());
+                let comp = parser.comp();
+                let a = if comp.contains('M') { "1" } else { "0" };
+
+                hack.push_str("111");
+                hack.push_str(a);
+                hack.push_str(code.comp(&comp).ok_or_else(invalid)?);
+                hack.push_str(code.dest(&parser.dest()).ok_or_else(invalid)?);
+                hack.push_str(code.jump(&parser.jump()).ok_or_else(invalid)?);
+                hack.push('\n');
+            }
+
+            CommandType::LCommand => (),
+        }
+    }
+
+    Ok(hack)
+}
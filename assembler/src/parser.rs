@@ -19,6 +19,11 @@ pub enum CommandType {
 pub enum ParseError {
     InvalidFilename(String),
     ReadLineError,
+    InvalidInstruction(String),
+    // a label or variable whose address does not fit in an A-instruction
+    AddressOutOfRange(String, usize),
+    // the number of instructions, more than the ROM holds
+    ProgramTooLarge(usize),
 }
 
 impl Display for ParseError {
@@ -30,6 +35,19 @@ impl Display for ParseError {
             ParseError::ReadLineError => {
                 write!(f, "ParseError: reading line error")
             }
+            ParseError::InvalidInstruction(ref s) => {
+                write!(f, "ParseError: invalid instruction {}", s)
+            }
+            ParseError::AddressOutOfRange(ref s, address) => {
+                write!(
+                    f,
+                    "ParseError: address {} of {} is out of range",
+                    address, s
+                )
+            }
+            ParseError::ProgramTooLarge(size) => {
+                write!(f, "ParseError: {} instructions do not fit in the ROM", size)
+            }
         }
     }
 }
@@ -41,21 +59,13 @@ impl Parser {
         match File::open(filename) {
             Ok(file) => {
                 let reader = BufReader::new(file);
-                let mut commands = Vec::new();
+                let mut code = String::new();
                 for line in reader.lines() {
-                    let line = line
-                        .unwrap()
-                        .replace("\n", "")
-                        .replace("\r", "")
-                        .replace(" ", "");
-                    if !Parser::is_commnet_or_whitespace(&line) {
-                        commands.push(line);
-                    }
+                    let line = line.map_err(|_| ParseError::ReadLineError)?;
+                    code.push_str(&line);
+                    code.push('\n');
                 }
-                Ok(Parser {
-                    commands,
-                    commnad_number: 0,
-                })
+                Ok(Parser::from_code(&code))
             }
             _ => {
                 let err = ParseError::InvalidFilename(filename.to_string());
@@ -64,6 +74,22 @@ impl Parser {
         }
     }
 
+    pub fn from_code(asm_code: &str) -> Parser {
+        let mut commands = Vec::new();
+        for line in asm_code.lines() {
+            // drop trailing comments, e.g. `D=M // x`
+            let line = line.split("//").next().unwrap_or("");
+            let line = line.replace("\r", "").replace(" ", "").replace("\t", "");
+            if !Parser::is_commnet_or_whitespace(&line) {
+                commands.push(line);
+            }
+        }
+        Parser {
+            commands,
+            commnad_number: 0,
+        }
+    }
+
     pub fn has_more_commands(&self) -> bool {
         self.commnad_number < self.commands.len()
     }
@@ -100,7 +126,7 @@ impl Parser {
     pub fn comp(&self) -> String {
         let mut command = self.command();
         let mut dest = self.dest();
-        dest.push_str("=");
+        dest.push('=');
         command = command.replace(&dest, "");
         let mut comp = Vec::new();
         for c in command.chars() {
@@ -120,11 +146,11 @@ impl Parser {
             return "".to_string();
         }
         let mut dest_comp = self.dest();
-        if dest_comp != "" {
-            dest_comp.push_str("=");
+        if !dest_comp.is_empty() {
+            dest_comp.push('=');
         }
         dest_comp.push_str(&self.comp());
-        dest_comp.push_str(";");
+        dest_comp.push(';');
         command = command.replace(&dest_comp, "");
         let mut jump = Vec::new();
         for c in command.chars() {
@@ -138,7 +164,7 @@ impl Parser {
     }
 
     fn is_commnet_or_whitespace(command: &str) -> bool {
-        command.starts_with("//") || command == ""
+        command.starts_with("//") || command.is_empty()
     }
 
     pub fn command_type(&self) -> CommandType {
@@ -5,6 +5,12 @@ pub struct SymbolTable {
     table: HashMap<String, usize>,
 }
 
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
 impl SymbolTable {
     pub fn new() -> Self {
         let mut table = HashMap::new();
@@ -0,0 +1,32 @@
+use assembler::parser::ParseError;
+
+#[test]
+fn resolves_labels_and_variables() {
+    let hack = assembler::assemble("@i\nM=1\n(LOOP)\n@LOOP\n0;JMP\n").unwrap();
+    assert_eq!(
+        hack,
+        "0000000000010000\n\
+         1110111111001000\n\
+         0000000000000010\n\
+         1110101010000111\n"
+    );
+}
+
+#[test]
+fn rejects_a_program_larger_than_the_rom() {
+    let asm = "D=0\n".repeat(32769);
+    assert!(matches!(
+        assembler::assemble(&asm),
+        Err(ParseError::ProgramTooLarge(32769))
+    ));
+}
+
+#[test]
+fn rejects_a_label_past_the_last_address() {
+    // a full ROM, with END after its last instruction
+    let asm = "@END\n".to_string() + &"D=0\n".repeat(32767) + "(END)\n";
+    assert!(matches!(
+        assembler::assemble(&asm),
+        Err(ParseError::AddressOutOfRange(symbol, 32768)) if symbol == "END"
+    ));
+}
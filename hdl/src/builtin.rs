@@ -0,0 +1,271 @@
+use crate::parser::{parse_chip, ChipDef};
+
+// Interfaces of the chips the simulator implements natively, as in the
+// course's builtInChips directory. Nand is the only true primitive, the
+// others are used when a chip's own .hdl file is missing or still a stub.
+const BUILTINS: [(&str, &str); 21] = [
+    ("Nand", "IN a, b; OUT out;"),
+    ("Not", "IN in; OUT out;"),
+    ("And", "IN a, b; OUT out;"),
+    ("Or", "IN a, b; OUT out;"),
+    ("Xor", "IN a, b; OUT out;"),
+    ("Mux", "IN a, b, sel; OUT out;"),
+    ("DMux", "IN in, sel; OUT a, b;"),
+    ("Not16", "IN in[16]; OUT out[16];"),
+    ("And16", "IN a[16], b[16]; OUT out[16];"),
+    ("Or16", "IN a[16], b[16]; OUT out[16];"),
+    ("Mux16", "IN a[16], b[16], sel; OUT out[16];"),
+    ("Or8Way", "IN in[8]; OUT out;"),
+    (
+        "Mux4Way16",
+        "IN a[16], b[16], c[16], d[16], sel[2]; OUT out[16];",
+    ),
+    (
+        "Mux8Way16",
+        "IN a[16], b[16], c[16], d[16], e[16], f[16], g[16], h[16], sel[3]; OUT out[16];",
+    ),
+    ("DMux4Way", "IN in, sel[2]; OUT a, b, c, d;"),
+    ("DMux8Way", "IN in, sel[3]; OUT a, b, c, d, e, f, g, h;"),
+    ("HalfAdder", "IN a, b; OUT sum, carry;"),
+    ("FullAdder", "IN a, b, c; OUT sum, carry;"),
+    ("Add16", "IN a[16], b[16]; OUT out[16];"),
+    ("Inc16", "IN in[16]; OUT out[16];"),
+    (
+        "ALU",
+        "IN x[16], y[16], zx, nx, zy, ny, f, no; OUT out[16], zr, ng;",
+    ),
+];
+
+// Chips with internal memory: interface, the inputs that only matter on
+// the clock edge, and the number of words they store. Their outputs depend
+// on the stored words and the remaining (combinational) inputs only, which
+// is what lets a chip feed a DFF's output back into its input.
+const SEQUENTIAL: [(&str, &str, &str, usize); 14] = [
+    ("DFF", "IN in; OUT out;", "in", 1),
+    ("Bit", "IN in, load; OUT out;", "in, load", 1),
+    ("Register", "IN in[16], load; OUT out[16];", "in, load", 1),
+    ("ARegister", "IN in[16], load; OUT out[16];", "in, load", 1),
+    ("DRegister", "IN in[16], load; OUT out[16];", "in, load", 1),
+    (
+        "PC",
+        "IN in[16], load, inc, reset; OUT out[16];",
+        "in, load, inc, reset",
+        1,
+    ),
+    (
+        "RAM8",
+        "IN in[16], load, address[3]; OUT out[16];",
+        "in, load",
+        8,
+    ),
+    (
+        "RAM64",
+        "IN in[16], load, address[6]; OUT out[16];",
+        "in, load",
+        64,
+    ),
+    (
+        "RAM512",
+        "IN in[16], load, address[9]; OUT out[16];",
+        "in, load",
+        512,
+    ),
+    (
+        "RAM4K",
+        "IN in[16], load, address[12]; OUT out[16];",
+        "in, load",
+        4096,
+    ),
+    (
+        "RAM16K",
+        "IN in[16], load, address[14]; OUT out[16];",
+        "in, load",
+        16384,
+    ),
+    (
+        "Screen",
+        "IN in[16], load, address[13]; OUT out[16];",
+        "in, load",
+        8192,
+    ),
+    ("Keyboard", "OUT out[16];", "", 1),
+    ("ROM32K", "IN address[15]; OUT out[16];", "", 32768),
+];
+
+// Built-in chips that are described in HDL over the native chips above,
+// so that their registers and memories can still be inspected by name
+// (ARegister[], RAM16K[0], ...) from a test script.
+const COMPOSITES: [(&str, &str); 2] = [
+    (
+        "Memory",
+        "CHIP Memory {
+            IN in[16], load, address[15];
+            OUT out[16];
+            PARTS:
+            DMux4Way(in=load, sel=address[13..14], a=loadLow, b=loadHigh, c=loadScreen, d=loadKeyboard);
+            Or(a=loadLow, b=loadHigh, out=loadRam);
+            RAM16K(in=in, load=loadRam, address=address[0..13], out=ram);
+            Screen(in=in, load=loadScreen, address=address[0..12], out=screen);
+            Keyboard(out=keyboard);
+            Mux4Way16(a=ram, b=ram, c=screen, d=keyboard, sel=address[13..14], out=out);
+        }",
+    ),
+    (
+        "CPU",
+        "CHIP CPU {
+            IN inM[16], instruction[16], reset;
+            OUT outM[16], writeM, addressM[15], pc[15];
+            PARTS:
+            Not(in=instruction[15], out=isA);
+            Mux16(a=instruction, b=alu, sel=instruction[15], out=aIn);
+            Or(a=isA, b=instruction[5], out=loadA);
+            ARegister(in=aIn, load=loadA, out=a, out[0..14]=addressM);
+            And(a=instruction[15], b=instruction[4], out=loadD);
+            DRegister(in=alu, load=loadD, out=d);
+            Mux16(a=a, b=inM, sel=instruction[12], out=am);
+            ALU(x=d, y=am, zx=instruction[11], nx=instruction[10], zy=instruction[9],
+                ny=instruction[8], f=instruction[7], no=instruction[6],
+                out=alu, out=outM, zr=zr, ng=ng);
+            And(a=instruction[15], b=instruction[3], out=writeM);
+            Or(a=zr, b=ng, out=notPositive);
+            Not(in=notPositive, out=positive);
+            And(a=instruction[2], b=ng, out=jlt);
+            And(a=instruction[1], b=zr, out=jeq);
+            And(a=instruction[0], b=positive, out=jgt);
+            Or(a=jlt, b=jeq, out=jle);
+            Or(a=jle, b=jgt, out=jumpBits);
+            And(a=instruction[15], b=jumpBits, out=jump);
+            PC(in=a, load=jump, inc=true, reset=reset, out[0..14]=pc);
+        }",
+    ),
+];
+
+/// True for the chips implemented natively, which may appear after
+/// `BUILTIN` in a chip definition.
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.iter().any(|(builtin, _)| *builtin == name)
+        || SEQUENTIAL.iter().any(|(builtin, ..)| *builtin == name)
+}
+
+/// The built-in chip definition for `name`, if there is one.
+pub fn definition(name: &str) -> Option<ChipDef> {
+    let code = if let Some((_, pins)) = BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+        format!("CHIP {} {{ {} BUILTIN {}; }}", name, pins, name)
+    } else if let Some((_, pins, clocked, _)) =
+        SEQUENTIAL.iter().find(|(builtin, ..)| *builtin == name)
+    {
+        let clocked = match *clocked {
+            "" => String::new(),
+            clocked => format!("CLOCKED {};", clocked),
+        };
+        format!("CHIP {} {{ {} BUILTIN {}; {} }}", name, pins, name, clocked)
+    } else {
+        let (_, code) = COMPOSITES.iter().find(|(builtin, _)| *builtin == name)?;
+        code.to_string()
+    };
+    parse_chip(&code, "<builtin>").ok()
+}
+
+/// Number of words a built-in chip stores, 0 for combinational chips.
+pub fn memory_size(name: &str) -> usize {
+    SEQUENTIAL
+        .iter()
+        .find(|(builtin, ..)| *builtin == name)
+        .map_or(0, |(.., size)| *size)
+}
+
+/// Computes the outputs of a built-in chip from its inputs, both given in
+/// the order they are declared in, and the words it stores.
+pub fn evaluate(name: &str, inputs: &[u16], memory: &[u16]) -> Vec<u16> {
+    let bit = |index: usize| inputs[index] & 1;
+    let bool_value = |value: bool| value as u16;
+    let read = |address: u16| memory[address as usize % memory.len()];
+
+    match name {
+        "DFF" | "Bit" | "Register" | "ARegister" | "DRegister" | "PC" | "Keyboard" => {
+            vec![memory[0]]
+        }
+        "RAM8" | "RAM64" | "RAM512" | "RAM4K" | "RAM16K" | "Screen" => vec![read(inputs[2])],
+        "ROM32K" => vec![read(inputs[0])],
+        "Nand" => vec![1 ^ (bit(0) & bit(1))],
+        "Not" => vec![1 ^ bit(0)],
+        "And" => vec![bit(0) & bit(1)],
+        "Or" => vec![bit(0) | bit(1)],
+        "Xor" => vec![bit(0) ^ bit(1)],
+        "Mux" => vec![if bit(2) == 0 { bit(0) } else { bit(1) }],
+        "DMux" => {
+            let (input, sel) = (bit(0), bit(1));
+            vec![input & (1 ^ sel), input & sel]
+        }
+        "Not16" => vec![!inputs[0]],
+        "And16" => vec![inputs[0] & inputs[1]],
+        "Or16" => vec![inputs[0] | inputs[1]],
+        "Mux16" => vec![if bit(2) == 0 { inputs[0] } else { inputs[1] }],
+        "Or8Way" => vec![bool_value(inputs[0] & 0xff != 0)],
+        "Mux4Way16" => vec![inputs[(inputs[4] & 3) as usize]],
+        "Mux8Way16" => vec![inputs[(inputs[8] & 7) as usize]],
+        "DMux4Way" => demux(bit(0), inputs[1] & 3, 4),
+        "DMux8Way" => demux(bit(0), inputs[1] & 7, 8),
+        "HalfAdder" => vec![bit(0) ^ bit(1), bit(0) & bit(1)],
+        "FullAdder" => {
+            let sum = bit(0) + bit(1) + bit(2);
+            vec![sum & 1, sum >> 1]
+        }
+        "Add16" => vec![inputs[0].wrapping_add(inputs[1])],
+        "Inc16" => vec![inputs[0].wrapping_add(1)],
+        "ALU" => alu(inputs),
+        _ => Vec::new(),
+    }
+}
+
+/// The word a sequential chip stores on the rising clock edge, as an
+/// `(address, value)` pair, given the inputs at that moment.
+pub fn clock(name: &str, inputs: &[u16], memory: &[u16]) -> Option<(usize, u16)> {
+    let load = || inputs[1] & 1 == 1;
+
+    match name {
+        "DFF" => Some((0, inputs[0] & 1)),
+        "Bit" if load() => Some((0, inputs[0] & 1)),
+        "Register" | "ARegister" | "DRegister" if load() => Some((0, inputs[0])),
+        "PC" => {
+            let flag = |index: usize| inputs[index] & 1 == 1;
+            if flag(3) {
+                Some((0, 0))
+            } else if flag(1) {
+                Some((0, inputs[0]))
+            } else if flag(2) {
+                Some((0, memory[0].wrapping_add(1)))
+            } else {
+                None
+            }
+        }
+        "RAM8" | "RAM64" | "RAM512" | "RAM4K" | "RAM16K" | "Screen" if load() => {
+            Some((inputs[2] as usize % memory.len(), inputs[0]))
+        }
+        _ => None,
+    }
+}
+
+fn demux(input: u16, sel: u16, ways: u16) -> Vec<u16> {
+    (0..ways)
+        .map(|way| if way == sel { input } else { 0 })
+        .collect()
+}
+
+// inputs: x, y, zx, nx, zy, ny, f, no
+fn alu(inputs: &[u16]) -> Vec<u16> {
+    let flag = |index: usize| inputs[index] & 1 == 1;
+    let mut x = if flag(2) { 0 } else { inputs[0] };
+    if flag(3) {
+        x = !x;
+    }
+    let mut y = if flag(4) { 0 } else { inputs[1] };
+    if flag(5) {
+        y = !y;
+    }
+    let mut out = if flag(6) { x.wrapping_add(y) } else { x & y };
+    if flag(7) {
+        out = !out;
+    }
+    vec![out, (out == 0) as u16, out >> 15]
+}
@@ -0,0 +1,758 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::builtin;
+use crate::parser::{parse_chip, ChipDef, Connection, PinRef};
+use crate::HdlError;
+
+// wires 0 and 1 are the constants `false` and `true`
+const FALSE: usize = 0;
+const TRUE: usize = 1;
+
+/// Finds chip definitions the way the course simulator does: `Name.hdl`
+/// next to the chip being loaded first, the built-in chip otherwise. A
+/// file that is still a stub (no parts) also falls back to the built-in,
+/// so a chip can be tested before all of its parts are written.
+pub struct ChipLoader {
+    dir: PathBuf,
+    cache: HashMap<String, Rc<ChipDef>>,
+}
+
+impl ChipLoader {
+    pub fn new(dir: &Path) -> Self {
+        ChipLoader {
+            dir: dir.to_path_buf(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Reads the chip at `path` and returns it with a loader for its parts.
+    /// A missing file is looked up among the built-in chips.
+    pub fn open(path: &Path) -> Result<(ChipDef, Self), HdlError> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut loader = ChipLoader::new(dir);
+        let def = if path.is_file() {
+            read_chip(path)?
+        } else {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            loader.load(&name)?.as_ref().clone()
+        };
+        Ok((def, loader))
+    }
+
+    pub fn load(&mut self, name: &str) -> Result<Rc<ChipDef>, HdlError> {
+        if let Some(def) = self.cache.get(name) {
+            return Ok(def.clone());
+        }
+
+        let path = self.dir.join(name.to_string() + ".hdl");
+        let def = if path.is_file() {
+            let def = read_chip(&path)?;
+            if def.name != name {
+                return Err(HdlError::Chip {
+                    chip: name.to_string(),
+                    message: format!("{} defines chip {}", path.display(), def.name),
+                });
+            }
+            match builtin::definition(name) {
+                Some(builtin) if def.is_stub() => builtin,
+                _ => def,
+            }
+        } else {
+            builtin::definition(name).ok_or_else(|| HdlError::Chip {
+                chip: name.to_string(),
+                message: format!(
+                    "{} not found and there is no built-in chip of that name",
+                    path.display()
+                ),
+            })?
+        };
+
+        let def = Rc::new(def);
+        self.cache.insert(name.to_string(), def.clone());
+        Ok(def)
+    }
+}
+
+pub fn read_chip(path: &Path) -> Result<ChipDef, HdlError> {
+    let code = fs::read_to_string(path).map_err(|err| HdlError::Io {
+        path: path.display().to_string(),
+        err,
+    })?;
+    parse_chip(&code, &path.display().to_string())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PinKind {
+    Input,
+    Output,
+    Internal,
+}
+
+pub struct Pin {
+    pub name: String,
+    pub width: usize,
+    pub kind: PinKind,
+    // bit 0 first
+    wires: Vec<usize>,
+}
+
+impl Pin {
+    /// The wires of the pin, bit 0 first, numbered like the gates' wires.
+    pub fn wires(&self) -> &[usize] {
+        &self.wires
+    }
+}
+
+pub enum GateKind {
+    Nand,
+    Builtin(String),
+}
+
+/// One primitive of the flattened chip. `inputs` and `outputs` hold the
+/// wires of each pin in declaration order; `clocked` marks the inputs that
+/// are only read on the clock edge.
+pub struct Gate {
+    pub kind: GateKind,
+    pub inputs: Vec<Vec<usize>>,
+    pub outputs: Vec<Vec<usize>>,
+    pub clocked: Vec<bool>,
+    // words stored by a sequential built-in, and the write latched by tick
+    memory: Vec<u16>,
+    pending: Option<(usize, u16)>,
+}
+
+impl Gate {
+    fn builtin_name(&self) -> Option<&str> {
+        match &self.kind {
+            GateKind::Builtin(name) => Some(name),
+            GateKind::Nand => None,
+        }
+    }
+}
+
+/// A chip flattened into Nand gates and built-in parts, ordered so that
+/// one pass over the gates evaluates the whole chip. Sequential parts
+/// change their outputs only when the clock goes through `tick`/`tock`.
+pub struct Chip {
+    name: String,
+    pins: Vec<Pin>,
+    gates: Vec<Gate>,
+    values: Vec<bool>,
+}
+
+impl Chip {
+    /// Loads `path` and the parts it uses from the same directory, as
+    /// `ChipLoader::open` finds them.
+    pub fn load(path: &Path) -> Result<Self, HdlError> {
+        let (def, mut loader) = ChipLoader::open(path)?;
+        Self::build(&def, &mut loader)
+    }
+
+    pub fn build(def: &ChipDef, loader: &mut ChipLoader) -> Result<Self, HdlError> {
+        let mut builder = Builder {
+            loader,
+            parent: vec![FALSE, TRUE],
+            driven: vec![true, true],
+            gates: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        let mut pins = Vec::new();
+        let mut pin_wires = HashMap::new();
+        for (decls, kind) in [
+            (&def.inputs, PinKind::Input),
+            (&def.outputs, PinKind::Output),
+        ] {
+            for decl in decls {
+                let wires = builder.new_wires(decl.width);
+                if kind == PinKind::Input {
+                    for wire in &wires {
+                        builder.driven[*wire] = true;
+                    }
+                }
+                pin_wires.insert(decl.name.clone(), wires.clone());
+                pins.push(Pin {
+                    name: decl.name.clone(),
+                    width: decl.width,
+                    kind,
+                    wires,
+                });
+            }
+        }
+
+        let mut internals: Vec<(String, Vec<usize>)> =
+            builder.instantiate(def, &pin_wires)?.into_iter().collect();
+        internals.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, wires) in internals {
+            pins.push(Pin {
+                name,
+                width: wires.len(),
+                kind: PinKind::Internal,
+                wires,
+            });
+        }
+
+        builder.finish(def, pins)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    pub fn pin(&self, name: &str) -> Option<&Pin> {
+        self.pins.iter().find(|pin| pin.name == name)
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    pub fn wire_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Sets an input pin, or a word of a built-in part's memory when
+    /// `name` is written as `Part[address]`. Call `eval` to update the
+    /// outputs.
+    pub fn set(&mut self, name: &str, value: u16) -> Result<(), HdlError> {
+        if let Some((part, address)) = memory_ref(name) {
+            let chip = self.name.clone();
+            let word = self
+                .memory_mut(part)
+                .and_then(|memory| memory.get_mut(address))
+                .ok_or_else(|| no_memory(&chip, name))?;
+            *word = value;
+            return Ok(());
+        }
+
+        let pin = self
+            .pins
+            .iter()
+            .find(|pin| pin.name == name && pin.kind == PinKind::Input)
+            .ok_or_else(|| HdlError::Chip {
+                chip: self.name.clone(),
+                message: format!("{} is not an input pin", name),
+            })?;
+        write_bits(&mut self.values, &pin.wires, value);
+        Ok(())
+    }
+
+    /// Reads a pin, or a word of a built-in part's memory as in `set`. A
+    /// word latched by `tick` already shows here, as in the course's
+    /// simulator, although the part's output only changes on `tock`.
+    pub fn get(&self, name: &str) -> Result<u16, HdlError> {
+        if let (None, Some((part, address))) = (self.pin(name), memory_ref(name)) {
+            let gate = self
+                .memory_gate(part)
+                .filter(|gate| address < gate.memory.len())
+                .ok_or_else(|| no_memory(&self.name, name))?;
+            return Ok(match gate.pending {
+                Some((pending, value)) if pending == address => value,
+                _ => gate.memory[address],
+            });
+        }
+
+        let pin = self.pin(name).ok_or_else(|| HdlError::Chip {
+            chip: self.name.clone(),
+            message: format!("there is no pin {}", name),
+        })?;
+        Ok(read_bits(&self.values, &pin.wires))
+    }
+
+    /// The memory of the first built-in part named `part`, such as the
+    /// `RAM16K` inside `Memory` or the `ARegister` inside `CPU`.
+    pub fn memory(&self, part: &str) -> Option<&[u16]> {
+        self.memory_gate(part).map(|gate| gate.memory.as_slice())
+    }
+
+    pub fn memory_mut(&mut self, part: &str) -> Option<&mut [u16]> {
+        self.gates
+            .iter_mut()
+            .find(|gate| gate.builtin_name() == Some(part) && !gate.memory.is_empty())
+            .map(|gate| gate.memory.as_mut_slice())
+    }
+
+    fn memory_gate(&self, part: &str) -> Option<&Gate> {
+        self.gates
+            .iter()
+            .find(|gate| gate.builtin_name() == Some(part) && !gate.memory.is_empty())
+    }
+
+    /// First half of a clock cycle: the sequential parts latch their inputs.
+    /// Their outputs stay the same until `tock`.
+    pub fn tick(&mut self) {
+        self.eval();
+        for gate in self.gates.iter_mut() {
+            if let GateKind::Builtin(name) = &gate.kind {
+                if !gate.memory.is_empty() {
+                    let inputs = read_inputs(&self.values, &gate.inputs);
+                    gate.pending = builtin::clock(name, &inputs, &gate.memory);
+                }
+            }
+        }
+    }
+
+    /// Second half of a clock cycle: the latched values become visible.
+    pub fn tock(&mut self) {
+        for gate in self.gates.iter_mut() {
+            if let Some((address, value)) = gate.pending.take() {
+                gate.memory[address] = value;
+            }
+        }
+        self.eval();
+    }
+
+    pub fn eval(&mut self) {
+        for gate in &self.gates {
+            match &gate.kind {
+                GateKind::Nand => {
+                    let a = self.values[gate.inputs[0][0]];
+                    let b = self.values[gate.inputs[1][0]];
+                    self.values[gate.outputs[0][0]] = !(a && b);
+                }
+                GateKind::Builtin(name) => {
+                    let inputs = read_inputs(&self.values, &gate.inputs);
+                    let outputs = builtin::evaluate(name, &inputs, &gate.memory);
+                    for (wires, value) in gate.outputs.iter().zip(outputs) {
+                        write_bits(&mut self.values, wires, value);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// `Part[address]`, with `Part[]` standing for `Part[0]`
+fn memory_ref(name: &str) -> Option<(&str, usize)> {
+    let (part, address) = name.strip_suffix(']')?.split_once('[')?;
+    match address {
+        "" => Some((part, 0)),
+        address => Some((part, address.parse().ok()?)),
+    }
+}
+
+fn no_memory(chip: &str, name: &str) -> HdlError {
+    HdlError::Chip {
+        chip: chip.to_string(),
+        message: format!("there is no pin or built-in memory {}", name),
+    }
+}
+
+fn read_inputs(values: &[bool], inputs: &[Vec<usize>]) -> Vec<u16> {
+    inputs
+        .iter()
+        .map(|wires| read_bits(values, wires))
+        .collect()
+}
+
+fn read_bits(values: &[bool], wires: &[usize]) -> u16 {
+    wires.iter().enumerate().fold(0, |value, (bit, wire)| {
+        value | ((values[*wire] as u16) << bit)
+    })
+}
+
+fn write_bits(values: &mut [bool], wires: &[usize], value: u16) {
+    for (bit, wire) in wires.iter().enumerate() {
+        values[*wire] = value >> bit & 1 == 1;
+    }
+}
+
+// Wires are merged with union-find while the parts are instantiated, so a
+// part's pins and the chip wires they are connected to end up as one wire.
+struct Builder<'a> {
+    loader: &'a mut ChipLoader,
+    parent: Vec<usize>,
+    // per root: something already writes to this wire
+    driven: Vec<bool>,
+    gates: Vec<Gate>,
+    // chips being instantiated, to catch a chip that contains itself
+    stack: Vec<String>,
+}
+
+impl Builder<'_> {
+    fn new_wires(&mut self, width: usize) -> Vec<usize> {
+        (0..width)
+            .map(|_| {
+                self.parent.push(self.parent.len());
+                self.driven.push(false);
+                self.parent.len() - 1
+            })
+            .collect()
+    }
+
+    fn find(&mut self, mut wire: usize) -> usize {
+        while self.parent[wire] != wire {
+            self.parent[wire] = self.parent[self.parent[wire]];
+            wire = self.parent[wire];
+        }
+        wire
+    }
+
+    // false if both wires already have a source
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return true;
+        }
+        if self.driven[a] && self.driven[b] {
+            return false;
+        }
+        // keep the constants as roots so they can be found again
+        let (root, child) = if b <= TRUE { (b, a) } else { (a, b) };
+        self.parent[child] = root;
+        self.driven[root] |= self.driven[child];
+        true
+    }
+
+    // Adds the gates of `def`, whose own pins are already wired up in
+    // `pins`. Returns the internal pins of the chip.
+    fn instantiate(
+        &mut self,
+        def: &ChipDef,
+        pins: &HashMap<String, Vec<usize>>,
+    ) -> Result<HashMap<String, Vec<usize>>, HdlError> {
+        if let Some(name) = &def.builtin {
+            self.add_builtin(def, name, pins)?;
+            return Ok(HashMap::new());
+        }
+
+        if self.stack.contains(&def.name) {
+            return Err(HdlError::Chip {
+                chip: def.name.clone(),
+                message: "uses itself as a part".to_string(),
+            });
+        }
+        self.stack.push(def.name.clone());
+
+        let mut part_defs = Vec::new();
+        for part in &def.parts {
+            part_defs.push(self.loader.load(&part.name)?);
+        }
+
+        let internals = self.internal_pins(def, &part_defs)?;
+
+        for (part, part_def) in def.parts.iter().zip(&part_defs) {
+            let mut part_pins = HashMap::new();
+            for decl in part_def.inputs.iter().chain(&part_def.outputs) {
+                part_pins.insert(decl.name.clone(), self.new_wires(decl.width));
+            }
+            self.instantiate(part_def, &part_pins)?;
+
+            for connection in &part.connections {
+                self.connect(def, part_def, connection, &part_pins, pins, &internals)?;
+            }
+        }
+
+        self.stack.pop();
+        Ok(internals)
+    }
+
+    fn add_builtin(
+        &mut self,
+        def: &ChipDef,
+        name: &str,
+        pins: &HashMap<String, Vec<usize>>,
+    ) -> Result<(), HdlError> {
+        let native = match builtin::definition(name) {
+            Some(native) if builtin::is_builtin(name) => native,
+            _ => {
+                return Err(HdlError::Chip {
+                    chip: def.name.clone(),
+                    message: format!("there is no built-in chip {}", name),
+                })
+            }
+        };
+        let inputs = def.inputs.iter().map(|pin| pins[&pin.name].clone());
+        let clocked = def
+            .inputs
+            .iter()
+            .map(|pin| native.clocked.contains(&pin.name))
+            .collect();
+        let outputs: Vec<Vec<usize>> = def
+            .outputs
+            .iter()
+            .map(|pin| pins[&pin.name].clone())
+            .collect();
+
+        for wire in outputs.iter().flatten() {
+            let root = self.find(*wire);
+            self.driven[root] = true;
+        }
+
+        let kind = if name == "Nand" {
+            GateKind::Nand
+        } else {
+            GateKind::Builtin(name.to_string())
+        };
+        self.gates.push(Gate {
+            kind,
+            inputs: inputs.collect(),
+            outputs,
+            clocked,
+            memory: vec![0; builtin::memory_size(name)],
+            pending: None,
+        });
+        Ok(())
+    }
+
+    // Internal pins are the names on the right of a part's output pin that
+    // are not pins of the chip itself. Their width is that of the output.
+    fn internal_pins(
+        &mut self,
+        def: &ChipDef,
+        part_defs: &[Rc<ChipDef>],
+    ) -> Result<HashMap<String, Vec<usize>>, HdlError> {
+        let mut internals: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (part, part_def) in def.parts.iter().zip(part_defs) {
+            for connection in &part.connections {
+                let external = &connection.external;
+                let output = match part_def.output(&connection.internal.name) {
+                    Some(output) => output,
+                    None => continue,
+                };
+                if external.is_constant()
+                    || def.input(&external.name).is_some()
+                    || def.output(&external.name).is_some()
+                {
+                    continue;
+                }
+
+                let error = |message: String| connection_error(def, connection, message);
+                if external.range.is_some() {
+                    return Err(error(format!(
+                        "internal pin {} cannot be subscripted",
+                        external.name
+                    )));
+                }
+                let width = range_width(&connection.internal, output.width)
+                    .ok_or_else(|| error(out_of_range(&connection.internal, &part.name)))?;
+
+                match internals.get(&external.name) {
+                    Some(wires) if wires.len() != width => {
+                        return Err(error(format!(
+                            "internal pin {} is {} bits wide here but {} bits elsewhere",
+                            external.name,
+                            width,
+                            wires.len()
+                        )))
+                    }
+                    Some(_) => (),
+                    None => {
+                        let wires = self.new_wires(width);
+                        internals.insert(external.name.clone(), wires);
+                    }
+                }
+            }
+        }
+
+        Ok(internals)
+    }
+
+    fn connect(
+        &mut self,
+        def: &ChipDef,
+        part_def: &ChipDef,
+        connection: &Connection,
+        part_pins: &HashMap<String, Vec<usize>>,
+        pins: &HashMap<String, Vec<usize>>,
+        internals: &HashMap<String, Vec<usize>>,
+    ) -> Result<(), HdlError> {
+        let error = |message: String| connection_error(def, connection, message);
+        let (internal, external) = (&connection.internal, &connection.external);
+
+        let is_output = part_def.output(&internal.name).is_some();
+        let part_wires = part_pins.get(&internal.name).ok_or_else(|| {
+            error(format!(
+                "{} has no pin named {}",
+                part_def.name, internal.name
+            ))
+        })?;
+        let part_bits = select(part_wires, internal.range)
+            .ok_or_else(|| error(out_of_range(internal, &part_def.name)))?;
+
+        let external_bits = if external.is_constant() {
+            if is_output {
+                return Err(error(format!(
+                    "output pin {} cannot be connected to {}",
+                    internal.name, external.name
+                )));
+            }
+            let constant = if external.name == "true" { TRUE } else { FALSE };
+            vec![constant; part_bits.len()]
+        } else {
+            if is_output && def.input(&external.name).is_some() {
+                return Err(error(format!(
+                    "input pin {} cannot be driven by a part",
+                    external.name
+                )));
+            }
+            if !is_output && def.output(&external.name).is_some() {
+                return Err(error(format!(
+                    "output pin {} cannot be used as a part's input",
+                    external.name
+                )));
+            }
+            let wires = pins
+                .get(&external.name)
+                .or_else(|| internals.get(&external.name))
+                .ok_or_else(|| {
+                    error(format!(
+                        "{} is not a pin of {} and no part outputs it",
+                        external.name, def.name
+                    ))
+                })?;
+            select(wires, external.range).ok_or_else(|| error(out_of_range(external, &def.name)))?
+        };
+
+        if part_bits.len() != external_bits.len() {
+            return Err(error(format!(
+                "{} is {} bits wide but {} is {} bits wide",
+                pin_text(internal),
+                part_bits.len(),
+                pin_text(external),
+                external_bits.len()
+            )));
+        }
+
+        for (part_bit, external_bit) in part_bits.iter().zip(&external_bits) {
+            if !self.union(*part_bit, *external_bit) {
+                return Err(error(format!(
+                    "{} is driven by more than one part",
+                    pin_text(external)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Replaces every wire by its root, numbers the roots densely and sorts
+    // the gates so that each one comes after the gates it depends on.
+    fn finish(mut self, def: &ChipDef, mut pins: Vec<Pin>) -> Result<Chip, HdlError> {
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        index.insert(FALSE, FALSE);
+        index.insert(TRUE, TRUE);
+        let mut renumber = |builder: &mut Self, wires: &mut Vec<usize>| {
+            for wire in wires.iter_mut() {
+                let root = builder.find(*wire);
+                let next = index.len();
+                *wire = *index.entry(root).or_insert(next);
+            }
+        };
+
+        let mut gates = std::mem::take(&mut self.gates);
+        for gate in gates.iter_mut() {
+            for wires in gate.inputs.iter_mut().chain(gate.outputs.iter_mut()) {
+                renumber(&mut self, wires);
+            }
+        }
+        for pin in pins.iter_mut() {
+            renumber(&mut self, &mut pin.wires);
+        }
+        let wire_count = index.len();
+
+        let mut driver = vec![None; wire_count];
+        for (gate_index, gate) in gates.iter().enumerate() {
+            for wire in gate.outputs.iter().flatten() {
+                driver[*wire] = Some(gate_index);
+            }
+        }
+
+        // Kahn's algorithm over gate -> gate dependencies. Clocked inputs
+        // are not dependencies, so loops through a DFF are allowed.
+        let mut dependents = vec![Vec::new(); gates.len()];
+        let mut waiting_on = vec![0; gates.len()];
+        for (gate_index, gate) in gates.iter().enumerate() {
+            let combinational = gate
+                .inputs
+                .iter()
+                .zip(&gate.clocked)
+                .filter(|(_, clocked)| !**clocked);
+            for wire in combinational.flat_map(|(wires, _)| wires) {
+                if let Some(source) = driver[*wire] {
+                    dependents[source].push(gate_index);
+                    waiting_on[gate_index] += 1;
+                }
+            }
+        }
+        let mut order: Vec<usize> = (0..gates.len())
+            .filter(|gate| waiting_on[*gate] == 0)
+            .collect();
+        let mut next = 0;
+        while next < order.len() {
+            for dependent in std::mem::take(&mut dependents[order[next]]) {
+                waiting_on[dependent] -= 1;
+                if waiting_on[dependent] == 0 {
+                    order.push(dependent);
+                }
+            }
+            next += 1;
+        }
+        if order.len() < gates.len() {
+            return Err(HdlError::Chip {
+                chip: def.name.clone(),
+                message: "contains a combinational loop".to_string(),
+            });
+        }
+
+        let mut gates: Vec<Option<Gate>> = gates.into_iter().map(Some).collect();
+        let gates = order
+            .into_iter()
+            .filter_map(|gate| gates[gate].take())
+            .collect();
+
+        let mut values = vec![false; wire_count];
+        values[TRUE] = true;
+        Ok(Chip {
+            name: def.name.clone(),
+            pins,
+            gates,
+            values,
+        })
+    }
+}
+
+fn select(wires: &[usize], range: Option<(usize, usize)>) -> Option<Vec<usize>> {
+    match range {
+        None => Some(wires.to_vec()),
+        Some((from, to)) if to < wires.len() => Some(wires[from..=to].to_vec()),
+        Some(_) => None,
+    }
+}
+
+fn range_width(pin: &PinRef, width: usize) -> Option<usize> {
+    match pin.range {
+        None => Some(width),
+        Some((from, to)) if to < width => Some(to - from + 1),
+        Some(_) => None,
+    }
+}
+
+fn pin_text(pin: &PinRef) -> String {
+    match pin.range {
+        None => pin.name.clone(),
+        Some((from, to)) if from == to => format!("{}[{}]", pin.name, from),
+        Some((from, to)) => format!("{}[{}..{}]", pin.name, from, to),
+    }
+}
+
+fn out_of_range(pin: &PinRef, chip: &str) -> String {
+    format!("{} is out of range for {}", pin_text(pin), chip)
+}
+
+fn connection_error(def: &ChipDef, connection: &Connection, message: String) -> HdlError {
+    HdlError::Syntax {
+        file: def.file.clone(),
+        line: connection.line,
+        message,
+    }
+}
@@ -0,0 +1,41 @@
+pub mod builtin;
+pub mod chip;
+pub mod parser;
+pub mod stats;
+pub mod test_script;
+pub mod verilog;
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum HdlError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    Syntax {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    Chip {
+        chip: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for HdlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "{}: {}", path, err),
+            Self::Syntax {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+            Self::Chip { chip, message } => write!(f, "{}: {}", chip, message),
+        }
+    }
+}
+
+impl std::error::Error for HdlError {}
@@ -0,0 +1,115 @@
+use std::{env, fs, path::Path, process};
+
+use hdl::chip::{Chip, ChipLoader, PinKind};
+use hdl::stats::{ChipStats, Hierarchy};
+use hdl::test_script::run_script;
+use hdl::verilog;
+use hdl::HdlError;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let target_name = args.get(1).unwrap_or_else(|| {
+        eprintln!("usage: hdl <Chip.hdl [--stats | --verilog [out.v]] | script.tst [--keys KEYS]>");
+        process::exit(1);
+    });
+
+    if target_name.ends_with(".tst") {
+        // keys to press for the script's `while` loops, e.g. `--keys KY`
+        let keys: Vec<u16> = match args.get(2).map(|arg| arg.as_str()) {
+            Some("--keys") => args
+                .get(3)
+                .map(|keys| keys.chars().map(|key| key as u16).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        run_test_script(target_name, &keys);
+        return;
+    }
+
+    let path = Path::new(target_name);
+    let result = match args.get(2).map(|arg| arg.as_str()) {
+        Some("--stats") => print_stats(path),
+        Some("--verilog") => {
+            let output = args
+                .get(3)
+                .map(|output| Path::new(output).to_path_buf())
+                .unwrap_or_else(|| path.with_extension("v"));
+            write_verilog(path, &output)
+        }
+        _ => print_pins(path),
+    };
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn print_pins(path: &Path) -> Result<(), HdlError> {
+    let chip = Chip::load(path)?;
+
+    println!("CHIP {}: {} gates", chip.name(), chip.gates().len());
+    for pin in chip.pins() {
+        let kind = match pin.kind {
+            PinKind::Input => "IN",
+            PinKind::Output => "OUT",
+            PinKind::Internal => "internal",
+        };
+        println!("    {:<8} {}[{}]", kind, pin.name, pin.width);
+    }
+    Ok(())
+}
+
+// Nand gates are counted with each built-in part replaced by its usual
+// Nand implementation, see `stats::Cost`.
+fn print_stats(path: &Path) -> Result<(), HdlError> {
+    let (def, mut loader) = ChipLoader::open(path)?;
+    let chip = Chip::build(&def, &mut loader)?;
+    let stats = ChipStats::new(&chip);
+    let hierarchy = Hierarchy::build(&def, &mut loader)?;
+
+    println!("CHIP {}", chip.name());
+    println!("    Nand gates: {}", stats.nands);
+    println!("    DFFs:       {}", stats.dffs);
+    println!("    Depth:      {}", stats.depth);
+    if !stats.builtins.is_empty() {
+        let builtins: Vec<String> = stats
+            .builtins
+            .iter()
+            .map(|(name, count)| format!("{} x{}", name, count))
+            .collect();
+        println!("    Built-in:   {}", builtins.join(", "));
+    }
+    println!();
+    print!("{}", hierarchy.report());
+    Ok(())
+}
+
+fn run_test_script(script_name: &str, keys: &[u16]) {
+    let result = run_script(script_name, keys).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+
+    match result.mismatch {
+        Some(mismatch) => {
+            eprintln!("Comparison failure at line {}", mismatch.line);
+            eprintln!("expected: {}", mismatch.expected);
+            eprintln!("actual:   {}", mismatch.actual);
+            process::exit(1);
+        }
+        None if result.compare_file.is_some() => {
+            println!("End of script - Comparison ended successfully")
+        }
+        None => println!("End of script"),
+    }
+}
+
+fn write_verilog(path: &Path, output: &Path) -> Result<(), HdlError> {
+    let (def, mut loader) = ChipLoader::open(path)?;
+    Chip::build(&def, &mut loader)?;
+    let code = verilog::export(&def, &mut loader)?;
+    fs::write(output, code).map_err(|err| HdlError::Io {
+        path: output.display().to_string(),
+        err,
+    })
+}
@@ -0,0 +1,374 @@
+use crate::HdlError;
+
+/// `name` or `name[width]` in an `IN`/`OUT` declaration.
+#[derive(Clone)]
+pub struct PinDecl {
+    pub name: String,
+    pub width: usize,
+}
+
+/// One side of a part connection: `a`, `a[3]` or `a[0..7]`.
+#[derive(Clone)]
+pub struct PinRef {
+    pub name: String,
+    // inclusive bit range, None for the whole pin
+    pub range: Option<(usize, usize)>,
+}
+
+impl PinRef {
+    pub fn is_constant(&self) -> bool {
+        self.name == "true" || self.name == "false"
+    }
+}
+
+/// `internal=external` inside a part, e.g. `a=x[0..7]`.
+#[derive(Clone)]
+pub struct Connection {
+    pub internal: PinRef,
+    pub external: PinRef,
+    pub line: usize,
+}
+
+#[derive(Clone)]
+pub struct Part {
+    pub name: String,
+    pub connections: Vec<Connection>,
+    pub line: usize,
+}
+
+#[derive(Clone)]
+pub struct ChipDef {
+    pub name: String,
+    pub inputs: Vec<PinDecl>,
+    pub outputs: Vec<PinDecl>,
+    pub parts: Vec<Part>,
+    // `BUILTIN Name;` instead of PARTS
+    pub builtin: Option<String>,
+    // `CLOCKED a, b;`
+    pub clocked: Vec<String>,
+    // file the chip was read from, used in error messages
+    pub file: String,
+}
+
+impl ChipDef {
+    pub fn input(&self, name: &str) -> Option<&PinDecl> {
+        self.inputs.iter().find(|pin| pin.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&PinDecl> {
+        self.outputs.iter().find(|pin| pin.name == name)
+    }
+
+    /// A chip file whose PARTS section is still empty, like the skeletons
+    /// handed out with the course.
+    pub fn is_stub(&self) -> bool {
+        self.builtin.is_none() && self.parts.is_empty()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(usize),
+    Symbol(&'static str),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Identifier(name) => name.clone(),
+            Token::Number(value) => value.to_string(),
+            Token::Symbol(symbol) => symbol.to_string(),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 10] = ["..", "{", "}", "(", ")", "[", "]", ",", ";", ":"];
+
+fn tokenize(code: &str, file: &str) -> Result<Vec<(Token, usize)>, HdlError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = code.chars().collect();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number.parse().map_err(|_| HdlError::Syntax {
+                file: file.to_string(),
+                line,
+                message: format!("number {} is too large", number),
+            })?;
+            tokens.push((Token::Number(value), line));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Identifier(chars[start..i].iter().collect()), line));
+        } else if c == '=' {
+            tokens.push((Token::Symbol("="), line));
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| HdlError::Syntax {
+                    file: file.to_string(),
+                    line,
+                    message: format!("unexpected character '{}'", c),
+                })?;
+            tokens.push((Token::Symbol(symbol), line));
+            i += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses one `.hdl` file. `file` is only used in error messages.
+pub fn parse_chip(code: &str, file: &str) -> Result<ChipDef, HdlError> {
+    let mut parser = Parser {
+        tokens: tokenize(code, file)?,
+        position: 0,
+        file: file.to_string(),
+    };
+    parser.parse_chip()
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    file: String,
+}
+
+impl Parser {
+    fn parse_chip(&mut self) -> Result<ChipDef, HdlError> {
+        self.expect_keyword("CHIP")?;
+        let name = self.expect_identifier()?;
+        self.expect_symbol("{")?;
+
+        let mut chip = ChipDef {
+            name,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            parts: Vec::new(),
+            builtin: None,
+            clocked: Vec::new(),
+            file: self.file.clone(),
+        };
+
+        if self.is_keyword("IN") {
+            self.advance();
+            chip.inputs = self.parse_pin_decls()?;
+        }
+        if self.is_keyword("OUT") {
+            self.advance();
+            chip.outputs = self.parse_pin_decls()?;
+        }
+
+        if self.is_keyword("BUILTIN") {
+            self.advance();
+            chip.builtin = Some(self.expect_identifier()?);
+            self.expect_symbol(";")?;
+        } else {
+            self.expect_keyword("PARTS")?;
+            self.expect_symbol(":")?;
+            while !self.is_symbol("}") && !self.is_keyword("CLOCKED") {
+                chip.parts.push(self.parse_part()?);
+            }
+        }
+
+        if self.is_keyword("CLOCKED") {
+            self.advance();
+            loop {
+                chip.clocked.push(self.expect_identifier()?);
+                if !self.is_symbol(",") {
+                    break;
+                }
+                self.advance();
+            }
+            self.expect_symbol(";")?;
+        }
+
+        self.expect_symbol("}")?;
+        if self.position < self.tokens.len() {
+            return Err(self.error("expected end of file after the chip"));
+        }
+        Ok(chip)
+    }
+
+    fn parse_pin_decls(&mut self) -> Result<Vec<PinDecl>, HdlError> {
+        let mut pins = Vec::new();
+        loop {
+            let name = self.expect_identifier()?;
+            let mut width = 1;
+            if self.is_symbol("[") {
+                self.advance();
+                width = self.expect_number()?;
+                if width == 0 || width > 16 {
+                    return Err(self.error(&format!("pin {} must be 1 to 16 bits wide", name)));
+                }
+                self.expect_symbol("]")?;
+            }
+            pins.push(PinDecl { name, width });
+            if !self.is_symbol(",") {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_symbol(";")?;
+        Ok(pins)
+    }
+
+    fn parse_part(&mut self) -> Result<Part, HdlError> {
+        let line = self.line();
+        let name = self.expect_identifier()?;
+        self.expect_symbol("(")?;
+
+        let mut connections = Vec::new();
+        loop {
+            let line = self.line();
+            let internal = self.parse_pin_ref()?;
+            self.expect_symbol("=")?;
+            let external = self.parse_pin_ref()?;
+            connections.push(Connection {
+                internal,
+                external,
+                line,
+            });
+            if !self.is_symbol(",") {
+                break;
+            }
+            self.advance();
+        }
+
+        self.expect_symbol(")")?;
+        self.expect_symbol(";")?;
+        Ok(Part {
+            name,
+            connections,
+            line,
+        })
+    }
+
+    fn parse_pin_ref(&mut self) -> Result<PinRef, HdlError> {
+        let name = self.expect_identifier()?;
+        let mut range = None;
+        if self.is_symbol("[") {
+            self.advance();
+            let from = self.expect_number()?;
+            let mut to = from;
+            if self.is_symbol("..") {
+                self.advance();
+                to = self.expect_number()?;
+            }
+            if to < from {
+                return Err(self.error(&format!("invalid range {}[{}..{}]", name, from, to)));
+            }
+            self.expect_symbol("]")?;
+            range = Some((from, to));
+        }
+        Ok(PinRef { name, range })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(found)) if *found == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(found)) if found == keyword)
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), HdlError> {
+        if !self.is_symbol(symbol) {
+            return Err(self.error(&format!("expected '{}'", symbol)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), HdlError> {
+        if !self.is_keyword(keyword) {
+            return Err(self.error(&format!("expected {}", keyword)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, HdlError> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    fn expect_number(&mut self) -> Result<usize, HdlError> {
+        match self.peek() {
+            Some(Token::Number(value)) => {
+                let value = *value;
+                self.advance();
+                Ok(value)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn error(&self, message: &str) -> HdlError {
+        let found = match self.peek() {
+            Some(token) => format!(", found '{}'", token.text()),
+            None => ", found end of file".to_string(),
+        };
+        HdlError::Syntax {
+            file: self.file.clone(),
+            line: self.line(),
+            message: message.to_string() + &found,
+        }
+    }
+}
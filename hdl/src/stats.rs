@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::chip::{Chip, ChipLoader, GateKind, PinKind};
+use crate::parser::ChipDef;
+use crate::HdlError;
+
+/// What a built-in chip costs when it is counted as if it were built from
+/// Nand gates and DFFs, following the usual course implementation of each
+/// chip (Mux from Not/And/Or, Add16 as a ripple carry adder, RAMs as
+/// trees of registers). `depth` is the longest path in Nand gates from an
+/// input to an output; for the memories it is the path from `address` to
+/// `out`, since their other inputs are only read on the clock edge.
+/// Keyboard and ROM32K are not logic and cost nothing.
+#[derive(Clone, Copy, Default)]
+pub struct Cost {
+    pub nands: usize,
+    pub dffs: usize,
+    pub depth: usize,
+}
+
+// name, nands, dffs, depth
+const COSTS: [(&str, usize, usize, usize); 32] = [
+    ("Not", 1, 0, 1),
+    ("And", 2, 0, 2),
+    ("Or", 3, 0, 2),
+    ("Xor", 4, 0, 3),
+    ("Mux", 8, 0, 5),
+    ("DMux", 5, 0, 3),
+    ("Not16", 16, 0, 1),
+    ("And16", 32, 0, 2),
+    ("Or16", 48, 0, 2),
+    ("Mux16", 128, 0, 5),
+    ("Or8Way", 21, 0, 6),
+    ("Mux4Way16", 384, 0, 9),
+    ("Mux8Way16", 896, 0, 13),
+    ("DMux4Way", 15, 0, 5),
+    ("DMux8Way", 35, 0, 7),
+    ("HalfAdder", 6, 0, 3),
+    ("FullAdder", 15, 0, 7),
+    ("Add16", 231, 0, 62),
+    ("Inc16", 231, 0, 62),
+    ("ALU", 1125, 0, 90),
+    ("DFF", 0, 1, 0),
+    ("Bit", 8, 1, 0),
+    ("Register", 128, 16, 0),
+    ("ARegister", 128, 16, 0),
+    ("DRegister", 128, 16, 0),
+    ("PC", 743, 16, 0),
+    ("RAM8", 1955, 128, 13),
+    ("RAM64", 16571, 1024, 26),
+    ("RAM512", 133499, 8192, 39),
+    ("RAM4K", 1068923, 65536, 52),
+    ("RAM16K", 4276091, 262144, 61),
+    ("Screen", 2137979, 131072, 57),
+];
+
+pub fn builtin_cost(name: &str) -> Cost {
+    COSTS.iter().find(|(builtin, ..)| *builtin == name).map_or(
+        Cost::default(),
+        |(_, nands, dffs, depth)| Cost {
+            nands: *nands,
+            dffs: *dffs,
+            depth: *depth,
+        },
+    )
+}
+
+/// A chip and its parts, with identical parts of the same chip merged.
+pub struct Hierarchy {
+    pub name: String,
+    // number of instances of this part in its parent
+    pub count: usize,
+    pub builtin: bool,
+    // per instance, including all parts below
+    pub nands: usize,
+    pub dffs: usize,
+    pub parts: Vec<Hierarchy>,
+}
+
+impl Hierarchy {
+    pub fn build(def: &ChipDef, loader: &mut ChipLoader) -> Result<Self, HdlError> {
+        let mut stack = Vec::new();
+        Self::build_part(def, loader, &mut stack)
+    }
+
+    fn build_part(
+        def: &ChipDef,
+        loader: &mut ChipLoader,
+        stack: &mut Vec<String>,
+    ) -> Result<Self, HdlError> {
+        let mut node = Hierarchy {
+            name: def.name.clone(),
+            count: 1,
+            builtin: def.builtin.is_some(),
+            nands: 0,
+            dffs: 0,
+            parts: Vec::new(),
+        };
+        if let Some(name) = &def.builtin {
+            let cost = if name == "Nand" {
+                Cost {
+                    nands: 1,
+                    ..Cost::default()
+                }
+            } else {
+                builtin_cost(name)
+            };
+            node.nands = cost.nands;
+            node.dffs = cost.dffs;
+            return Ok(node);
+        }
+
+        if stack.contains(&def.name) {
+            return Err(HdlError::Chip {
+                chip: def.name.clone(),
+                message: "uses itself as a part".to_string(),
+            });
+        }
+        stack.push(def.name.clone());
+        for part in &def.parts {
+            if let Some(known) = node.parts.iter_mut().find(|known| known.name == part.name) {
+                known.count += 1;
+                continue;
+            }
+            let part_def = loader.load(&part.name)?;
+            node.parts.push(Self::build_part(&part_def, loader, stack)?);
+        }
+        stack.pop();
+
+        node.nands = node.parts.iter().map(|part| part.count * part.nands).sum();
+        node.dffs = node.parts.iter().map(|part| part.count * part.dffs).sum();
+        Ok(node)
+    }
+
+    /// One line per part, indented by depth, e.g. `  Mux16 x4  (128 Nand)`.
+    pub fn report(&self) -> String {
+        let mut text = String::new();
+        self.write_report(&mut text, 0);
+        text
+    }
+
+    fn write_report(&self, text: &mut String, level: usize) {
+        let count = match self.count {
+            1 => String::new(),
+            count => format!(" x{}", count),
+        };
+        let mut cost = vec![format!("{} Nand", self.nands)];
+        if self.dffs > 0 {
+            cost.push(format!("{} DFF", self.dffs));
+        }
+        if self.builtin && self.name != "Nand" {
+            cost.push("built-in".to_string());
+        }
+        let _ = writeln!(
+            text,
+            "{}{}{}  ({})",
+            "  ".repeat(level),
+            self.name,
+            count,
+            cost.join(", ")
+        );
+        for part in &self.parts {
+            part.write_report(text, level + 1);
+        }
+    }
+}
+
+/// Totals over the flattened chip.
+pub struct ChipStats {
+    pub nands: usize,
+    pub dffs: usize,
+    // the critical path in Nand gates
+    pub depth: usize,
+    // built-in parts other than Nand, by name
+    pub builtins: BTreeMap<String, usize>,
+}
+
+impl ChipStats {
+    /// Paths start at the chip's inputs and at the outputs of sequential
+    /// parts, and end at the chip's outputs and at clocked inputs.
+    pub fn new(chip: &Chip) -> Self {
+        let mut stats = ChipStats {
+            nands: 0,
+            dffs: 0,
+            depth: 0,
+            builtins: BTreeMap::new(),
+        };
+        let mut depth = vec![0; chip.wire_count()];
+
+        // the gates are already in evaluation order
+        for gate in chip.gates() {
+            let cost = match &gate.kind {
+                GateKind::Nand => Cost {
+                    nands: 1,
+                    dffs: 0,
+                    depth: 1,
+                },
+                GateKind::Builtin(name) => {
+                    *stats.builtins.entry(name.clone()).or_insert(0) += 1;
+                    builtin_cost(name)
+                }
+            };
+            stats.nands += cost.nands;
+            stats.dffs += cost.dffs;
+
+            let input_depth = gate
+                .inputs
+                .iter()
+                .zip(&gate.clocked)
+                .filter(|(_, clocked)| !**clocked)
+                .flat_map(|(wires, _)| wires)
+                .map(|wire| depth[*wire])
+                .max()
+                .unwrap_or(0);
+            for wire in gate.outputs.iter().flatten() {
+                depth[*wire] = input_depth + cost.depth;
+            }
+        }
+
+        // a clocked input may be driven by gates ordered after its part
+        let clocked_inputs = chip.gates().iter().flat_map(|gate| {
+            gate.inputs
+                .iter()
+                .zip(&gate.clocked)
+                .filter(|(_, clocked)| **clocked)
+                .flat_map(|(wires, _)| wires)
+        });
+        let outputs = chip
+            .pins()
+            .iter()
+            .filter(|pin| pin.kind == PinKind::Output)
+            .flat_map(|pin| pin.wires());
+        for wire in clocked_inputs.chain(outputs) {
+            stats.depth = stats.depth.max(depth[*wire]);
+        }
+        stats
+    }
+}
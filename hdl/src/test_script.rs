@@ -0,0 +1,565 @@
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::chip::Chip;
+use crate::HdlError;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(String),
+    Syntax(String),
+    Hdl(HdlError),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "{}", message),
+            Self::Syntax(message) => write!(f, "script error: {}", message),
+            Self::Hdl(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<HdlError> for ScriptError {
+    fn from(err: HdlError) -> Self {
+        ScriptError::Hdl(err)
+    }
+}
+
+enum Command {
+    Load(String),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Output,
+    Set(String, u16),
+    Eval,
+    Tick,
+    Tock,
+    Repeat(usize, Vec<Command>),
+    While(Condition, Vec<Command>),
+    Echo(String),
+    // `ROM32K load Prog.hack`
+    LoadMemory(String, String),
+}
+
+// `while out <> 75 { ... }`
+struct Condition {
+    pin: String,
+    operator: String,
+    value: u16,
+}
+
+impl Condition {
+    fn holds(&self, value: u16) -> bool {
+        let (value, expected) = (value as i16, self.value as i16);
+        match self.operator.as_str() {
+            "=" => value == expected,
+            "<>" => value != expected,
+            "<" => value < expected,
+            ">" => value > expected,
+            "<=" => value <= expected,
+            _ => value >= expected,
+        }
+    }
+}
+
+// a while loop that has not ended by then is waiting for input that a
+// headless run will never get
+const MAX_WHILE_ITERATIONS: usize = 1_000_000;
+
+#[derive(Clone)]
+struct OutputColumn {
+    name: String,
+    format: char,
+    left: usize,
+    width: usize,
+    right: usize,
+}
+
+impl OutputColumn {
+    fn parse(spec: &str) -> Result<Self, ScriptError> {
+        let invalid = || ScriptError::Syntax(format!("invalid output column {}", spec));
+        let (name, format) = spec.split_once('%').unwrap_or((spec, "B1.1.1"));
+        let mut chars = format.chars();
+        let format = chars.next().ok_or_else(invalid)?;
+        let widths: Vec<usize> = chars
+            .as_str()
+            .split('.')
+            .map(|width| width.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        if widths.len() != 3 || !"DXBS".contains(format) {
+            return Err(invalid());
+        }
+        Ok(OutputColumn {
+            name: name.to_string(),
+            format,
+            left: widths[0],
+            width: widths[1],
+            right: widths[2],
+        })
+    }
+
+    fn header(&self) -> String {
+        let total = self.left + self.width + self.right;
+        let name: String = self.name.chars().take(total).collect();
+        let padding = total - name.len();
+        format!(
+            "{}{}{}",
+            " ".repeat(padding / 2),
+            name,
+            " ".repeat(padding - padding / 2)
+        )
+    }
+
+    // %S columns such as `time` are left aligned text
+    fn text(&self, text: &str) -> String {
+        format!(
+            "{}{:<width$}{}",
+            " ".repeat(self.left),
+            text,
+            " ".repeat(self.right),
+            width = self.width
+        )
+    }
+
+    // 16 bit pins are shown as signed numbers in %D, narrower pins are not
+    fn value(&self, value: u16, pin_width: usize) -> String {
+        let digits = |text: String| {
+            let skip = text.len().saturating_sub(self.width);
+            text[skip..].to_string()
+        };
+        let value = match self.format {
+            'X' => digits(format!("{:04X}", value)),
+            'B' => digits(format!("{:016b}", value)),
+            _ if pin_width == 16 => (value as i16).to_string(),
+            _ => value.to_string(),
+        };
+        format!(
+            "{}{:>width$}{}",
+            " ".repeat(self.left),
+            value,
+            " ".repeat(self.right),
+            width = self.width
+        )
+    }
+}
+
+pub struct Mismatch {
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+pub struct ScriptResult {
+    pub output: String,
+    pub output_file: Option<PathBuf>,
+    pub compare_file: Option<PathBuf>,
+    pub mismatch: Option<Mismatch>,
+}
+
+/// Runs a hardware simulator test script such as projects/01/And.tst. As
+/// with the VM emulator scripts, the output file is written next to the
+/// script and compared line by line with the `compare-to` file, where a
+/// field of `*`s matches anything.
+///
+/// There is no keyboard to hold down in a headless run, so each `while`
+/// loop starts by pressing the next of `keys` on the chip's `Keyboard`.
+pub fn run_script(script_name: &str, keys: &[u16]) -> Result<ScriptResult, ScriptError> {
+    let script = fs::read_to_string(script_name)
+        .map_err(|err| ScriptError::Io(format!("{}: {}", script_name, err)))?;
+    let dir = Path::new(script_name)
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+    let tokens = tokenize(&script);
+    let commands = parse_commands(&mut tokens.iter().peekable(), false)?;
+
+    let mut runner = ScriptRunner {
+        dir,
+        chip: None,
+        time: 0,
+        ticked: false,
+        keys: keys.iter().copied().collect(),
+        columns: Vec::new(),
+        output: String::new(),
+        output_file: None,
+        compare_file: None,
+    };
+    runner.execute(&commands)?;
+
+    if let Some(output_file) = &runner.output_file {
+        fs::write(output_file, &runner.output)
+            .map_err(|err| ScriptError::Io(format!("{}: {}", output_file.display(), err)))?;
+    }
+
+    let mismatch = match &runner.compare_file {
+        Some(compare_file) => {
+            let expected = fs::read_to_string(compare_file)
+                .map_err(|err| ScriptError::Io(format!("{}: {}", compare_file.display(), err)))?;
+            compare(&expected, &runner.output)
+        }
+        None => None,
+    };
+
+    Ok(ScriptResult {
+        output: runner.output,
+        output_file: runner.output_file,
+        compare_file: runner.compare_file,
+        mismatch,
+    })
+}
+
+fn compare(expected: &str, actual: &str) -> Option<Mismatch> {
+    let strip = |line: &str| line.split_whitespace().collect::<String>();
+    let matches = |expected: &str, actual: &str| {
+        let (expected, actual) = (strip(expected), strip(actual));
+        let expected_fields: Vec<&str> = expected.split('|').collect();
+        let actual_fields: Vec<&str> = actual.split('|').collect();
+        expected_fields.len() == actual_fields.len()
+            && expected_fields
+                .iter()
+                .zip(&actual_fields)
+                .all(|(expected, actual)| {
+                    expected == actual
+                        || (!expected.is_empty() && expected.chars().all(|c| c == '*'))
+                })
+    };
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 0;
+
+    loop {
+        line += 1;
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (Some(expected), Some(actual)) if matches(expected, actual) => (),
+            (expected, actual) => {
+                return Some(Mismatch {
+                    line,
+                    expected: expected.unwrap_or("").to_string(),
+                    actual: actual.unwrap_or("").to_string(),
+                })
+            }
+        }
+    }
+}
+
+struct ScriptRunner {
+    dir: PathBuf,
+    chip: Option<Chip>,
+    // clock cycles so far, shown as `3` after a tock and `3+` after a tick
+    time: usize,
+    ticked: bool,
+    keys: VecDeque<u16>,
+    columns: Vec<OutputColumn>,
+    output: String,
+    output_file: Option<PathBuf>,
+    compare_file: Option<PathBuf>,
+}
+
+impl ScriptRunner {
+    fn execute(&mut self, commands: &[Command]) -> Result<(), ScriptError> {
+        for command in commands {
+            match command {
+                Command::Load(file) => {
+                    self.chip = Some(Chip::load(&self.dir.join(file))?);
+                    self.time = 0;
+                    self.ticked = false;
+                }
+                Command::OutputFile(file) => self.output_file = Some(self.dir.join(file)),
+                Command::CompareTo(file) => self.compare_file = Some(self.dir.join(file)),
+                Command::OutputList(columns) => {
+                    self.columns = columns.clone();
+                    let headers: Vec<String> =
+                        self.columns.iter().map(|column| column.header()).collect();
+                    self.output += &format!("|{}|\n", headers.join("|"));
+                }
+                Command::Output => {
+                    let chip = self.chip()?;
+                    let time = format!("{}{}", self.time, if self.ticked { "+" } else { "" });
+                    let mut values = Vec::new();
+                    for column in &self.columns {
+                        if column.name == "time" {
+                            values.push(column.text(&time));
+                            continue;
+                        }
+                        let width = chip.pin(&column.name).map_or(16, |pin| pin.width);
+                        values.push(column.value(chip.get(&column.name)?, width));
+                    }
+                    self.output += &format!("|{}|\n", values.join("|"));
+                }
+                Command::Set(pin, value) => self.chip_mut()?.set(pin, *value)?,
+                Command::Eval => self.chip_mut()?.eval(),
+                Command::Tick => {
+                    self.chip_mut()?.tick();
+                    self.ticked = true;
+                }
+                Command::Tock => {
+                    self.chip_mut()?.tock();
+                    self.time += 1;
+                    self.ticked = false;
+                }
+                Command::Repeat(count, body) => {
+                    for _ in 0..*count {
+                        self.execute(body)?;
+                    }
+                }
+                Command::While(condition, body) => {
+                    if let Some(key) = self.keys.pop_front() {
+                        self.chip_mut()?.set("Keyboard[]", key)?;
+                    }
+                    let mut iterations = 0;
+                    while condition.holds(self.chip()?.get(&condition.pin)?) {
+                        if iterations == MAX_WHILE_ITERATIONS {
+                            return Err(ScriptError::Syntax(format!(
+                                "while {} {} {} is still true after {} iterations",
+                                condition.pin,
+                                condition.operator,
+                                condition.value as i16,
+                                iterations
+                            )));
+                        }
+                        self.execute(body)?;
+                        iterations += 1;
+                    }
+                }
+                Command::Echo(text) => println!("{}", text),
+                Command::LoadMemory(part, file) => {
+                    let path = self.dir.join(file);
+                    let words = read_hack(&path)?;
+                    let memory = self.chip_mut()?.memory_mut(part).ok_or_else(|| {
+                        ScriptError::Syntax(format!("there is no built-in {} to load", part))
+                    })?;
+                    if words.len() > memory.len() {
+                        return Err(ScriptError::Syntax(format!(
+                            "{} does not fit in {}",
+                            path.display(),
+                            part
+                        )));
+                    }
+                    memory.fill(0);
+                    memory[..words.len()].copy_from_slice(&words);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn chip(&self) -> Result<&Chip, ScriptError> {
+        self.chip
+            .as_ref()
+            .ok_or_else(|| ScriptError::Syntax("no chip has been loaded".to_string()))
+    }
+
+    fn chip_mut(&mut self) -> Result<&mut Chip, ScriptError> {
+        self.chip
+            .as_mut()
+            .ok_or_else(|| ScriptError::Syntax("no chip has been loaded".to_string()))
+    }
+}
+
+// one 16 digit binary word per line, as written by the assembler
+fn read_hack(path: &Path) -> Result<Vec<u16>, ScriptError> {
+    let code = fs::read_to_string(path)
+        .map_err(|err| ScriptError::Io(format!("{}: {}", path.display(), err)))?;
+    code.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            u16::from_str_radix(line, 2).map_err(|_| {
+                ScriptError::Syntax(format!(
+                    "{}: line {} is not a binary word",
+                    path.display(),
+                    index + 1
+                ))
+            })
+        })
+        .collect()
+}
+
+// %B0101, %XFF, %D-3 or a plain decimal number
+fn parse_value(text: &str) -> Option<u16> {
+    let (radix, digits) = match text.get(..2) {
+        Some("%B") => (2, &text[2..]),
+        Some("%X") => (16, &text[2..]),
+        Some("%D") => (10, &text[2..]),
+        _ => (10, text),
+    };
+    let value = i32::from_str_radix(digits, radix).ok()?;
+    if !(-32768..=65535).contains(&value) {
+        return None;
+    }
+    Some(value as u16)
+}
+
+fn tokenize(script: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = script.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            push_token(&mut tokens, &mut token);
+            continue;
+        }
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut last = ' ';
+            for c in chars.by_ref() {
+                if last == '*' && c == '/' {
+                    break;
+                }
+                last = c;
+            }
+            push_token(&mut tokens, &mut token);
+            continue;
+        }
+        if c == '"' {
+            push_token(&mut tokens, &mut token);
+            token.push(c);
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                token.push(c);
+            }
+            tokens.push(std::mem::take(&mut token));
+            continue;
+        }
+        if c.is_whitespace() {
+            push_token(&mut tokens, &mut token);
+        } else if ",;!{}".contains(c) {
+            push_token(&mut tokens, &mut token);
+            tokens.push(c.to_string());
+        } else {
+            token.push(c);
+        }
+    }
+    push_token(&mut tokens, &mut token);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<String>, token: &mut String) {
+    if !token.is_empty() {
+        tokens.push(std::mem::take(token));
+    }
+}
+
+fn parse_commands<'a, I>(
+    tokens: &mut std::iter::Peekable<I>,
+    in_block: bool,
+) -> Result<Vec<Command>, ScriptError>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut commands = Vec::new();
+
+    while let Some(word) = tokens.next() {
+        let mut args = Vec::new();
+        match word.as_str() {
+            "}" if in_block => return Ok(commands),
+            "," | ";" | "!" => continue,
+            "repeat" => {
+                let count = tokens
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or_else(|| ScriptError::Syntax("repeat needs a count".to_string()))?;
+                if tokens.next().map(|token| token.as_str()) != Some("{") {
+                    return Err(ScriptError::Syntax("repeat needs a block".to_string()));
+                }
+                commands.push(Command::Repeat(count, parse_commands(tokens, true)?));
+                continue;
+            }
+            "while" => {
+                let mut condition = Vec::new();
+                for token in tokens.by_ref() {
+                    if token == "{" {
+                        break;
+                    }
+                    condition.push(token.as_str());
+                }
+                let condition = parse_condition(&condition)?;
+                commands.push(Command::While(condition, parse_commands(tokens, true)?));
+                continue;
+            }
+            _ => (),
+        }
+
+        while let Some(arg) = tokens.peek() {
+            if [",", ";", "!", "}"].contains(&arg.as_str()) {
+                break;
+            }
+            args.push(tokens.next().unwrap().clone());
+        }
+
+        let missing = || ScriptError::Syntax(format!("{} needs an argument", word));
+        let command = match word.as_str() {
+            "load" => Command::Load(args.first().cloned().ok_or_else(missing)?),
+            "output-file" => Command::OutputFile(args.first().cloned().ok_or_else(missing)?),
+            "compare-to" => Command::CompareTo(args.first().cloned().ok_or_else(missing)?),
+            "output-list" => Command::OutputList(
+                args.iter()
+                    .map(|column| OutputColumn::parse(column))
+                    .collect::<Result<_, _>>()?,
+            ),
+            "output" => Command::Output,
+            "eval" => Command::Eval,
+            "tick" => Command::Tick,
+            "tock" => Command::Tock,
+            "echo" => Command::Echo(args.join(" ").trim_start_matches('"').to_string()),
+            "clear-echo" => continue,
+            "set" => {
+                if args.len() != 2 {
+                    return Err(missing());
+                }
+                let value = parse_value(&args[1])
+                    .ok_or_else(|| ScriptError::Syntax(format!("invalid value {}", args[1])))?;
+                Command::Set(args[0].clone(), value)
+            }
+            _ if args.len() == 2 && args[0] == "load" => {
+                Command::LoadMemory(word.clone(), args[1].clone())
+            }
+            _ => return Err(ScriptError::Syntax(format!("unknown command {}", word))),
+        };
+        commands.push(command);
+    }
+
+    if in_block {
+        return Err(ScriptError::Syntax("block is not closed".to_string()));
+    }
+    Ok(commands)
+}
+
+// `out <> 75`, also written without spaces as `out<>75`
+fn parse_condition(tokens: &[&str]) -> Result<Condition, ScriptError> {
+    let text = tokens.concat();
+    let invalid = || ScriptError::Syntax(format!("invalid while condition {}", tokens.join(" ")));
+    let (position, operator) = ["<>", "<=", ">=", "=", "<", ">"]
+        .iter()
+        .filter_map(|operator| text.find(operator).map(|position| (position, *operator)))
+        .min_by_key(|(position, _)| *position)
+        .ok_or_else(invalid)?;
+    let pin = &text[..position];
+    let value = parse_value(&text[position + operator.len()..]).ok_or_else(invalid)?;
+    if pin.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Condition {
+        pin: pin.to_string(),
+        operator: operator.to_string(),
+        value,
+    })
+}
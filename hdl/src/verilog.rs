@@ -0,0 +1,401 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    rc::Rc,
+};
+
+use crate::builtin;
+use crate::chip::ChipLoader;
+use crate::parser::{ChipDef, Part, PinDecl, PinRef};
+use crate::HdlError;
+
+// HDL names that cannot be used as Verilog identifiers get a `_` appended
+const KEYWORDS: [&str; 28] = [
+    "always",
+    "and",
+    "assign",
+    "begin",
+    "buf",
+    "case",
+    "default",
+    "else",
+    "end",
+    "endcase",
+    "endmodule",
+    "for",
+    "if",
+    "initial",
+    "inout",
+    "input",
+    "integer",
+    "module",
+    "nand",
+    "nor",
+    "not",
+    "or",
+    "output",
+    "parameter",
+    "reg",
+    "wire",
+    "xnor",
+    "xor",
+];
+
+/// Writes `def` and every chip below it as Verilog modules, parts before
+/// the chips that use them. Built-in chips become behavioral modules;
+/// chips with clocked parts get an extra `clk` input, and all registers
+/// and memories are written on its rising edge, which is when `tock`
+/// makes them visible in the simulator. The chip is assumed to have been
+/// checked by `Chip::build` already.
+pub fn export(def: &ChipDef, loader: &mut ChipLoader) -> Result<String, HdlError> {
+    let mut exporter = Exporter {
+        loader,
+        clocked: HashMap::new(),
+        written: HashSet::new(),
+        text: String::new(),
+    };
+    let _ = writeln!(
+        exporter.text,
+        "// Chip {} and its parts, generated from HDL.\n",
+        def.name
+    );
+    exporter.write_chip(def)?;
+    Ok(exporter.text)
+}
+
+struct Exporter<'a> {
+    loader: &'a mut ChipLoader,
+    // per chip: it has clocked parts somewhere below it
+    clocked: HashMap<String, bool>,
+    written: HashSet<String>,
+    text: String,
+}
+
+impl Exporter<'_> {
+    fn write_chip(&mut self, def: &ChipDef) -> Result<(), HdlError> {
+        if !self.written.insert(def.name.clone()) {
+            return Ok(());
+        }
+
+        let mut part_defs = Vec::new();
+        for part in &def.parts {
+            let part_def = self.loader.load(&part.name)?;
+            self.write_chip(&part_def)?;
+            part_defs.push(part_def);
+        }
+
+        let clocked = match &def.builtin {
+            Some(name) => {
+                builtin::definition(name).is_some_and(|native| !native.clocked.is_empty())
+            }
+            None => part_defs
+                .iter()
+                .any(|part_def| self.clocked[&part_def.name]),
+        };
+        self.clocked.insert(def.name.clone(), clocked);
+
+        self.write_header(def, clocked);
+        match &def.builtin {
+            Some(name) => self.text += &builtin_body(name),
+            None => self.write_parts(def, &part_defs),
+        }
+        self.text += "endmodule\n\n";
+        Ok(())
+    }
+
+    fn write_header(&mut self, def: &ChipDef, clocked: bool) {
+        let mut ports: Vec<String> = def
+            .inputs
+            .iter()
+            .map(|pin| format!("input {}{}", width_decl(pin.width), ident(&pin.name)))
+            .chain(
+                def.outputs
+                    .iter()
+                    .map(|pin| format!("output {}{}", width_decl(pin.width), ident(&pin.name))),
+            )
+            .collect();
+        if clocked {
+            ports.push("input clk".to_string());
+        }
+        let _ = writeln!(
+            self.text,
+            "module {} (\n    {}\n);",
+            ident(&def.name),
+            ports.join(",\n    ")
+        );
+    }
+
+    fn write_parts(&mut self, def: &ChipDef, part_defs: &[Rc<ChipDef>]) {
+        // internal pins, as wide as the part outputs that drive them
+        let mut internals: Vec<(String, usize)> = Vec::new();
+        for (part, part_def) in def.parts.iter().zip(part_defs) {
+            for connection in &part.connections {
+                let external = &connection.external;
+                let output = match part_def.output(&connection.internal.name) {
+                    Some(output) => output,
+                    None => continue,
+                };
+                if external.is_constant()
+                    || def.input(&external.name).is_some()
+                    || def.output(&external.name).is_some()
+                    || internals.iter().any(|(name, _)| *name == external.name)
+                {
+                    continue;
+                }
+                let width = match connection.internal.range {
+                    Some((from, to)) => to - from + 1,
+                    None => output.width,
+                };
+                internals.push((external.name.clone(), width));
+            }
+        }
+        for (name, width) in &internals {
+            let _ = writeln!(self.text, "    wire {}{};", width_decl(*width), ident(name));
+        }
+
+        let width_of = |name: &str| {
+            def.input(name)
+                .or_else(|| def.output(name))
+                .map(|pin| pin.width)
+                .or_else(|| {
+                    internals
+                        .iter()
+                        .find(|(internal, _)| internal == name)
+                        .map(|(_, width)| *width)
+                })
+                .unwrap_or(1)
+        };
+
+        for (index, (part, part_def)) in def.parts.iter().zip(part_defs).enumerate() {
+            let instance = format!("part{}", index);
+            let mut ports = Vec::new();
+            for pin in part_def.inputs.iter().chain(&part_def.outputs) {
+                let is_output = part_def.output(&pin.name).is_some();
+                let port = self.write_port(part, pin, is_output, &instance, &width_of);
+                ports.push(format!(".{}({})", ident(&pin.name), port));
+            }
+            if self.clocked[&part_def.name] {
+                ports.push(".clk(clk)".to_string());
+            }
+            let _ = writeln!(
+                self.text,
+                "    {} {} ({});",
+                ident(&part_def.name),
+                instance,
+                ports.join(", ")
+            );
+        }
+    }
+
+    // What to connect to one pin of a part. A pin with a single whole-pin
+    // connection is connected directly; otherwise it gets its own wire that
+    // is assembled from (or split into) the connected pieces.
+    fn write_port(
+        &mut self,
+        part: &Part,
+        pin: &PinDecl,
+        is_output: bool,
+        instance: &str,
+        width_of: &dyn Fn(&str) -> usize,
+    ) -> String {
+        let connections: Vec<_> = part
+            .connections
+            .iter()
+            .filter(|connection| connection.internal.name == pin.name)
+            .collect();
+        let expression = |external: &PinRef, width: usize| {
+            if external.is_constant() {
+                constant(external.name == "true", width)
+            } else {
+                slice(
+                    &ident(&external.name),
+                    width_of(&external.name),
+                    external.range,
+                )
+            }
+        };
+
+        match connections.as_slice() {
+            [] if is_output => return String::new(),
+            [] => return constant(false, pin.width),
+            [connection] if connection.internal.range.is_none() => {
+                return expression(&connection.external, pin.width)
+            }
+            _ => (),
+        }
+
+        let wire = format!("{}_{}", instance, pin.name);
+        let _ = writeln!(self.text, "    wire {}{};", width_decl(pin.width), wire);
+        let mut covered = vec![false; pin.width];
+        for connection in connections {
+            let (from, to) = connection.internal.range.unwrap_or((0, pin.width - 1));
+            let part_bits = slice(&wire, pin.width, connection.internal.range);
+            let external = expression(&connection.external, to - from + 1);
+            if is_output {
+                let _ = writeln!(self.text, "    assign {} = {};", external, part_bits);
+            } else {
+                let _ = writeln!(self.text, "    assign {} = {};", part_bits, external);
+                covered[from..=to].fill(true);
+            }
+        }
+        // unconnected input bits are false
+        if !is_output {
+            let mut bit = 0;
+            while bit < pin.width {
+                if covered[bit] {
+                    bit += 1;
+                    continue;
+                }
+                let from = bit;
+                while bit < pin.width && !covered[bit] {
+                    bit += 1;
+                }
+                let bits = slice(&wire, pin.width, Some((from, bit - 1)));
+                let _ = writeln!(
+                    self.text,
+                    "    assign {} = {};",
+                    bits,
+                    constant(false, bit - from)
+                );
+            }
+        }
+        wire
+    }
+}
+
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn width_decl(width: usize) -> String {
+    match width {
+        1 => String::new(),
+        width => format!("[{}:0] ", width - 1),
+    }
+}
+
+// `name`, `name[3]` or `name[7:0]`; one-bit wires are never subscripted
+fn slice(name: &str, width: usize, range: Option<(usize, usize)>) -> String {
+    match range {
+        _ if width == 1 => name.to_string(),
+        None => name.to_string(),
+        Some((from, to)) if from == to => format!("{}[{}]", name, from),
+        Some((from, to)) => format!("{}[{}:{}]", name, to, from),
+    }
+}
+
+fn constant(value: bool, width: usize) -> String {
+    let bit = if value { "1'b1" } else { "1'b0" };
+    match width {
+        1 => bit.to_string(),
+        width => format!("{{{}{{{}}}}}", width, bit),
+    }
+}
+
+// Module bodies for the built-in chips, over the pin names of their HDL
+// interfaces.
+fn builtin_body(name: &str) -> String {
+    let ram = |size: usize| {
+        format!(
+            "    reg [15:0] memory [0:{}];\n    \
+             assign out = memory[address];\n    \
+             always @(posedge clk)\n        \
+             if (load) memory[address] <= in;\n",
+            size - 1
+        )
+    };
+    let register = |width: usize| {
+        let bits = width_decl(width);
+        format!(
+            "    reg {}state = 0;\n    \
+             assign out = state;\n    \
+             always @(posedge clk)\n        \
+             if (load) state <= in;\n",
+            bits
+        )
+    };
+
+    let body = match name {
+        "Nand" => "    assign out = ~(a & b);\n",
+        "Not" | "Not16" => "    assign out = ~in;\n",
+        "And" | "And16" => "    assign out = a & b;\n",
+        "Or" | "Or16" => "    assign out = a | b;\n",
+        "Xor" => "    assign out = a ^ b;\n",
+        "Mux" | "Mux16" => "    assign out = sel ? b : a;\n",
+        "DMux" => "    assign a = sel ? 1'b0 : in;\n    assign b = sel ? in : 1'b0;\n",
+        "Or8Way" => "    assign out = |in;\n",
+        "Mux4Way16" => "    assign out = sel[1] ? (sel[0] ? d : c) : (sel[0] ? b : a);\n",
+        "Mux8Way16" => {
+            "    assign out = sel[2]\n        \
+             ? (sel[1] ? (sel[0] ? h : g) : (sel[0] ? f : e))\n        \
+             : (sel[1] ? (sel[0] ? d : c) : (sel[0] ? b : a));\n"
+        }
+        "DMux4Way" => {
+            "    assign a = in & (sel == 2'd0);\n    \
+             assign b = in & (sel == 2'd1);\n    \
+             assign c = in & (sel == 2'd2);\n    \
+             assign d = in & (sel == 2'd3);\n"
+        }
+        "DMux8Way" => {
+            "    assign a = in & (sel == 3'd0);\n    \
+             assign b = in & (sel == 3'd1);\n    \
+             assign c = in & (sel == 3'd2);\n    \
+             assign d = in & (sel == 3'd3);\n    \
+             assign e = in & (sel == 3'd4);\n    \
+             assign f = in & (sel == 3'd5);\n    \
+             assign g = in & (sel == 3'd6);\n    \
+             assign h = in & (sel == 3'd7);\n"
+        }
+        "HalfAdder" => "    assign sum = a ^ b;\n    assign carry = a & b;\n",
+        "FullAdder" => "    assign {carry, sum} = a + b + c;\n",
+        "Add16" => "    assign out = a + b;\n",
+        "Inc16" => "    assign out = in + 16'd1;\n",
+        "ALU" => {
+            "    wire [15:0] x1 = zx ? 16'd0 : x;\n    \
+             wire [15:0] x2 = nx ? ~x1 : x1;\n    \
+             wire [15:0] y1 = zy ? 16'd0 : y;\n    \
+             wire [15:0] y2 = ny ? ~y1 : y1;\n    \
+             wire [15:0] result = f ? x2 + y2 : x2 & y2;\n    \
+             assign out = no ? ~result : result;\n    \
+             assign zr = out == 16'd0;\n    \
+             assign ng = out[15];\n"
+        }
+        "DFF" => {
+            "    reg state = 0;\n    \
+             assign out = state;\n    \
+             always @(posedge clk)\n        \
+             state <= in;\n"
+        }
+        "Bit" => return register(1),
+        "Register" | "ARegister" | "DRegister" => return register(16),
+        "PC" => {
+            "    reg [15:0] state = 0;\n    \
+             assign out = state;\n    \
+             always @(posedge clk)\n        \
+             if (reset) state <= 16'd0;\n        \
+             else if (load) state <= in;\n        \
+             else if (inc) state <= state + 16'd1;\n"
+        }
+        "RAM8" => return ram(8),
+        "RAM64" => return ram(64),
+        "RAM512" => return ram(512),
+        "RAM4K" => return ram(4096),
+        "RAM16K" => return ram(16384),
+        "Screen" => return ram(8192),
+        "Keyboard" => {
+            "    // drive from the board's keyboard controller\n    assign out = 16'd0;\n"
+        }
+        "ROM32K" => {
+            "    parameter PROGRAM = \"program.hack\";\n    \
+             reg [15:0] memory [0:32767];\n    \
+             initial $readmemb(PROGRAM, memory);\n    \
+             assign out = memory[address];\n"
+        }
+        _ => "",
+    };
+    body.to_string()
+}
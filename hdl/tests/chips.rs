@@ -0,0 +1,124 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use hdl::chip::Chip;
+use hdl::test_script::run_script;
+
+const XOR: &str = "CHIP Xor {
+    IN a, b;
+    OUT out;
+
+    PARTS:
+    Nand(a=a, b=b, out=nand);
+    Nand(a=a, b=nand, out=x);
+    Nand(a=nand, b=b, out=y);
+    Nand(a=x, b=y, out=out);
+}
+";
+
+// the built-in And16 passes `in` through, to test slicing both ways
+const HALVES: &str = "CHIP Halves {
+    IN in[16];
+    OUT low[8], high[8], bit0, swapped[16];
+
+    PARTS:
+    And16(a=in, b=true, out[0..7]=low, out[8..15]=high, out[0]=bit0);
+    And16(a[0..7]=in[8..15], a[8..15]=in[0..7], b=true, out=swapped);
+}
+";
+
+const LOOP: &str = "CHIP Loop {
+    IN a;
+    OUT out;
+
+    PARTS:
+    Nand(a=a, b=feedback, out=feedback, out=out);
+}
+";
+
+// a fresh directory for `chips`, each given as (name, HDL)
+fn chip_dir(test: &str, chips: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hdl-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, hdl) in chips {
+        fs::write(dir.join(format!("{}.hdl", name)), hdl).unwrap();
+    }
+    dir
+}
+
+fn project_file(file: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects")
+        .join(file)
+}
+
+#[test]
+fn evaluates_a_chip_built_from_nand() {
+    let dir = chip_dir("xor", &[("Xor", XOR)]);
+    let mut chip = Chip::load(&dir.join("Xor.hdl")).unwrap();
+
+    assert_eq!(chip.gates().len(), 4);
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        chip.set("a", a).unwrap();
+        chip.set("b", b).unwrap();
+        chip.eval();
+        assert_eq!(chip.get("out").unwrap(), a ^ b, "Xor({}, {})", a, b);
+    }
+    assert!(chip.set("out", 1).is_err());
+}
+
+#[test]
+fn connects_sub_buses() {
+    let dir = chip_dir("halves", &[("Halves", HALVES)]);
+    let mut chip = Chip::load(&dir.join("Halves.hdl")).unwrap();
+
+    chip.set("in", 0x12b5).unwrap();
+    chip.eval();
+    assert_eq!(chip.get("low").unwrap(), 0xb5);
+    assert_eq!(chip.get("high").unwrap(), 0x12);
+    assert_eq!(chip.get("bit0").unwrap(), 1);
+    assert_eq!(chip.get("swapped").unwrap(), 0xb512);
+}
+
+#[test]
+fn rejects_a_combinational_loop() {
+    let dir = chip_dir("loop", &[("Loop", LOOP)]);
+    let err = Chip::load(&dir.join("Loop.hdl")).err().unwrap();
+    assert_eq!(err.to_string(), "Loop: contains a combinational loop");
+}
+
+#[test]
+fn reports_the_line_of_a_syntax_error() {
+    let dir = chip_dir(
+        "syntax",
+        &[("Bad", "CHIP Bad {\n    IN a\n    OUT out;\n}\n")],
+    );
+    let err = Chip::load(&dir.join("Bad.hdl")).err().unwrap();
+    let message = err.to_string();
+    assert!(
+        message.starts_with(&format!("{}:3:", dir.join("Bad.hdl").display())),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn passes_the_course_test_script() {
+    let dir = chip_dir("xor-script", &[("Xor", XOR)]);
+    for file in ["Xor.tst", "Xor.cmp"] {
+        fs::copy(project_file(&format!("01/{}", file)), dir.join(file)).unwrap();
+    }
+
+    let result = run_script(&dir.join("Xor.tst").display().to_string(), &[]).unwrap();
+    if let Some(mismatch) = result.mismatch {
+        panic!(
+            "line {}: expected {}, got {}",
+            mismatch.line, mismatch.expected, mismatch.actual
+        );
+    }
+    assert_eq!(result.output.lines().count(), 5);
+}
@@ -0,0 +1,117 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use hdl::chip::Chip;
+use hdl::test_script::run_script;
+
+const BIT: &str = "CHIP Bit {
+    IN in, load;
+    OUT out;
+
+    PARTS:
+    Mux(a=previous, b=in, sel=load, out=next);
+    DFF(in=next, out=previous, out=out);
+}
+";
+
+// flips on every clock cycle, through a loop that only a DFF breaks
+const TOGGLE: &str = "CHIP Toggle {
+    IN reset;
+    OUT out;
+
+    PARTS:
+    Not(in=state, out=flipped);
+    Mux(a=flipped, b=false, sel=reset, out=next);
+    DFF(in=next, out=state, out=out);
+}
+";
+
+fn chip_dir(test: &str, chips: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hdl-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, hdl) in chips {
+        fs::write(dir.join(format!("{}.hdl", name)), hdl).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn changes_outputs_on_tock() {
+    let dir = chip_dir("bit", &[("Bit", BIT)]);
+    let mut chip = Chip::load(&dir.join("Bit.hdl")).unwrap();
+
+    chip.set("in", 1).unwrap();
+    chip.set("load", 1).unwrap();
+    chip.tick();
+    assert_eq!(chip.get("out").unwrap(), 0);
+    chip.tock();
+    assert_eq!(chip.get("out").unwrap(), 1);
+
+    chip.set("in", 0).unwrap();
+    chip.set("load", 0).unwrap();
+    chip.tick();
+    chip.tock();
+    assert_eq!(chip.get("out").unwrap(), 1);
+}
+
+#[test]
+fn allows_loops_through_a_dff() {
+    let dir = chip_dir("toggle", &[("Toggle", TOGGLE)]);
+    let mut chip = Chip::load(&dir.join("Toggle.hdl")).unwrap();
+
+    chip.set("reset", 1).unwrap();
+    chip.tick();
+    chip.tock();
+    assert_eq!(chip.get("out").unwrap(), 0);
+
+    chip.set("reset", 0).unwrap();
+    let mut outputs = Vec::new();
+    for _ in 0..4 {
+        chip.tick();
+        chip.tock();
+        outputs.push(chip.get("out").unwrap());
+    }
+    assert_eq!(outputs, [1, 0, 1, 0]);
+}
+
+#[test]
+fn simulates_the_built_in_memories() {
+    let dir = chip_dir("ram16k", &[]);
+    let mut chip = Chip::load(&dir.join("RAM16K.hdl")).unwrap();
+
+    chip.set("in", 1234).unwrap();
+    chip.set("address", 16383).unwrap();
+    chip.set("load", 1).unwrap();
+    chip.tick();
+    chip.tock();
+    chip.set("load", 0).unwrap();
+    chip.set("address", 0).unwrap();
+    chip.eval();
+    assert_eq!(chip.get("out").unwrap(), 0);
+    assert_eq!(chip.get("RAM16K[16383]").unwrap(), 1234);
+
+    chip.set("RAM16K[0]", 77).unwrap();
+    chip.eval();
+    assert_eq!(chip.get("out").unwrap(), 77);
+}
+
+#[test]
+fn passes_the_course_bit_script() {
+    let dir = chip_dir("bit-script", &[("Bit", BIT)]);
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/03/a");
+    for file in ["Bit.tst", "Bit.cmp"] {
+        fs::copy(project.join(file), dir.join(file)).unwrap();
+    }
+
+    let result = run_script(&dir.join("Bit.tst").display().to_string(), &[]).unwrap();
+    if let Some(mismatch) = result.mismatch {
+        panic!(
+            "line {}: expected {}, got {}",
+            mismatch.line, mismatch.expected, mismatch.actual
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::{env, fs, path::PathBuf, process};
+
+use hdl::chip::{Chip, ChipLoader};
+use hdl::stats::{ChipStats, Hierarchy};
+use hdl::verilog;
+
+const XOR: &str = "CHIP Xor {
+    IN a, b;
+    OUT out;
+
+    PARTS:
+    Nand(a=a, b=b, out=nand);
+    Nand(a=a, b=nand, out=x);
+    Nand(a=nand, b=b, out=y);
+    Nand(a=x, b=y, out=out);
+}
+";
+
+const BIT: &str = "CHIP Bit {
+    IN in, load;
+    OUT out;
+
+    PARTS:
+    Mux(a=previous, b=in, sel=load, out=next);
+    DFF(in=next, out=previous, out=out);
+}
+";
+
+const PARITY: &str = "CHIP Parity {
+    IN in[4];
+    OUT out;
+
+    PARTS:
+    Xor(a=in[0], b=in[1], out=low);
+    Xor(a=in[2], b=in[3], out=high);
+    Xor(a=low, b=high, out=out);
+}
+";
+
+fn chip_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hdl-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, hdl) in [("Xor", XOR), ("Bit", BIT), ("Parity", PARITY)] {
+        fs::write(dir.join(format!("{}.hdl", name)), hdl).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn counts_nands_and_the_critical_path() {
+    let dir = chip_dir("stats");
+
+    let stats = ChipStats::new(&Chip::load(&dir.join("Parity.hdl")).unwrap());
+    assert_eq!(stats.nands, 12);
+    assert_eq!(stats.dffs, 0);
+    assert_eq!(stats.depth, 6);
+    assert!(stats.builtins.is_empty());
+
+    let stats = ChipStats::new(&Chip::load(&dir.join("Bit.hdl")).unwrap());
+    assert_eq!(stats.nands, 8);
+    assert_eq!(stats.dffs, 1);
+    assert_eq!(stats.builtins.get("Mux"), Some(&1));
+}
+
+#[test]
+fn reports_the_part_hierarchy() {
+    let dir = chip_dir("hierarchy");
+    let (def, mut loader) = ChipLoader::open(&dir.join("Parity.hdl")).unwrap();
+    let hierarchy = Hierarchy::build(&def, &mut loader).unwrap();
+
+    assert_eq!(
+        hierarchy.report(),
+        "Parity  (12 Nand)\n  Xor x3  (4 Nand)\n    Nand x4  (1 Nand)\n"
+    );
+}
+
+#[test]
+fn exports_parts_before_the_chips_that_use_them() {
+    let dir = chip_dir("verilog");
+    let (def, mut loader) = ChipLoader::open(&dir.join("Parity.hdl")).unwrap();
+    let text = verilog::export(&def, &mut loader).unwrap();
+
+    let position = |module: &str| {
+        text.find(&format!("module {} (", module))
+            .unwrap_or_else(|| panic!("no module {} in\n{}", module, text))
+    };
+    assert!(position("Nand") < position("Xor"));
+    assert!(position("Xor") < position("Parity"));
+    assert_eq!(text.matches("module Xor (").count(), 1);
+    assert!(!text.contains("clk"));
+
+    let (def, mut loader) = ChipLoader::open(&dir.join("Bit.hdl")).unwrap();
+    let text = verilog::export(&def, &mut loader).unwrap();
+    let bit = &text[text.find("module Bit (").unwrap()..];
+    assert!(bit.contains("input clk"), "{}", bit);
+    assert!(bit.contains(".clk(clk)"), "{}", bit);
+}
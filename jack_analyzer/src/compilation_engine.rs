@@ -1,14 +1,17 @@
 use crate::jack_tokenizer::JackTokenizer;
-use crate::jack_tokenizer::Token::{Identifier, IntegerConstant, Keyword, StringConstant, Symbol};
+use crate::jack_tokenizer::Token::{Keyword, Symbol};
 use crate::jack_tokenizer::{Keywords, Symbols, Token};
+use crate::syntax_tree::Node;
 
 use std::fs::File;
 use std::io;
 use std::io::Write;
 
-pub struct CompilationEngine {
-    pub file: File,
+pub struct CompilationEngine<W: Write = File> {
+    pub file: W,
     pub tokenizer: JackTokenizer,
+    // the rules being read, the innermost last
+    rules: Vec<Node>,
 }
 
 impl CompilationEngine {
@@ -18,11 +21,31 @@ impl CompilationEngine {
         Ok(CompilationEngine {
             file: File::create(file_name)?,
             tokenizer,
+            rules: Vec::new(),
         })
     }
+}
+
+impl<W: Write> CompilationEngine<W> {
+    /// Writes the parse tree XML to `out` instead of `X_compile.xml`.
+    pub fn with_writer(tokenizer: JackTokenizer, out: W) -> Self {
+        CompilationEngine {
+            file: out,
+            tokenizer,
+            rules: Vec::new(),
+        }
+    }
 
+    /// Writes the parse tree XML.
     pub fn compile_class(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<class>\n".as_bytes())?;
+        let tree = self.parse_class()?;
+        tree.write_xml(&mut self.file)
+    }
+
+    /// The parse tree of the class, for writing in other formats.
+    pub fn parse_class(&mut self) -> Result<Node, io::Error> {
+        self.rules.clear();
+        self.open("class");
         self.write_token_and_advance()?;
 
         // write class name
@@ -41,13 +64,11 @@ impl CompilationEngine {
         // write "}"
         self.write_token_and_advance()?;
 
-        self.file.write_all("</class>\n".as_bytes())?;
-
-        Ok(())
+        Ok(self.rules.pop().expect("the class rule is open"))
     }
 
     fn compile_class_var_dec(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<classVarDec>\n".as_bytes())?;
+        self.open("classVarDec");
 
         while !Self::is_semicolon(self.tokenizer.token_type()) {
             self.write_token_and_advance()?;
@@ -56,12 +77,12 @@ impl CompilationEngine {
         // write ";"
         self.write_token_and_advance()?;
 
-        self.file.write_all("</classVarDec>\n".as_bytes())?;
+        self.close();
         Ok(())
 
     }
     fn compile_subroutine(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<subroutineDec>\n".as_bytes())?;
+        self.open("subroutineDec");
         // write ('constrctor' | 'function' | 'method')
         self.write_token_and_advance()?;
         // write ('void' | type)
@@ -76,7 +97,7 @@ impl CompilationEngine {
         // write ')'
         self.write_token_and_advance()?;
 
-        self.file.write_all("<subroutineBody>\n".as_bytes())?;
+        self.open("subroutineBody");
         // write '{'
         self.write_token_and_advance()?;
 
@@ -89,31 +110,31 @@ impl CompilationEngine {
 
         // write '}'
         self.write_token_and_advance()?;
-        self.file.write_all("</subroutineBody>\n".as_bytes())?;
-        self.file.write_all("</subroutineDec>\n".as_bytes())?;
+        self.close();
+        self.close();
         Ok(())
     }
     fn compile_parameter_list(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<parameterList>\n".as_bytes())?;
+        self.open("parameterList");
         while !Self::is_right_paran(self.tokenizer.token_type()) {
             self.write_token_and_advance()?;
         }
         // write ')'
-        self.file.write_all("</parameterList>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
     fn compile_var_dec(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<varDec>\n".as_bytes())?;
+        self.open("varDec");
         while !Self::is_semicolon(self.tokenizer.token_type()) {
             self.write_token_and_advance()?;
         }
         // write ')'
         self.write_token_and_advance()?;
-        self.file.write_all("</varDec>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
     fn compile_statements(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<statements>\n".as_bytes())?;
+        self.open("statements");
         while Self::is_statement(self.tokenizer.token_type()) {
             match self.tokenizer.token_type() {
                 Keyword(Keywords::Let) => self.compile_let()?,
@@ -124,12 +145,12 @@ impl CompilationEngine {
                 _ => (),
             };
         }
-        self.file.write_all("</statements>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
 
     fn compile_let(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<letStatement>\n".as_bytes())?;
+        self.open("letStatement");
 
         // write let
         self.write_token_and_advance()?;
@@ -149,12 +170,12 @@ impl CompilationEngine {
 
         // write ";"
         self.write_token_and_advance()?;
-        self.file.write_all("</letStatement>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
 
     fn compile_if(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<ifStatement>\n".as_bytes())?;
+        self.open("ifStatement");
 
         // write if
         self.write_token_and_advance()?;
@@ -178,11 +199,11 @@ impl CompilationEngine {
             // write '}'
             self.write_token_and_advance()?;
         }
-        self.file.write_all("</ifStatement>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
     fn compile_do(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<doStatement>\n".as_bytes())?;
+        self.open("doStatement");
         // write "do"
         self.write_token_and_advance()?;
 
@@ -209,11 +230,11 @@ impl CompilationEngine {
 
         // write ';'
         self.write_token_and_advance()?;
-        self.file.write_all("</doStatement>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
     fn compile_while(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<whileStatement>\n".as_bytes())?;
+        self.open("whileStatement");
 
         // write while
         self.write_token_and_advance()?;
@@ -228,12 +249,12 @@ impl CompilationEngine {
         self.compile_statements()?;
         // write "}"
         self.write_token_and_advance()?;
-        self.file.write_all("</whileStatement>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
 
     fn compile_return(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<returnStatement>\n".as_bytes())?;
+        self.open("returnStatement");
         // write return
         self.write_token_and_advance()?;
         if !Self::is_semicolon(self.tokenizer.token_type()) {
@@ -241,22 +262,22 @@ impl CompilationEngine {
         }
         // write ";"
         self.write_token_and_advance()?;
-        self.file.write_all("</returnStatement>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
     fn compile_expression(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<expression>\n".as_bytes())?;
+        self.open("expression");
         self.compile_term()?;
         while Self::is_op_token(self.tokenizer.token_type()) {
             self.write_token_and_advance()?;
             self.compile_term()?;
         }
-        self.file.write_all("</expression>\n".as_bytes())?;
+        self.close();
 
         Ok(())
     }
     fn compile_term(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<term>\n".as_bytes())?;
+        self.open("term");
         match self.tokenizer.token_type() {
             Symbol(Symbols::LParen(_)) => {
                 // write '('
@@ -302,12 +323,12 @@ impl CompilationEngine {
                 }
             }
         }
-        self.file.write_all("</term>\n".as_bytes())?;
+        self.close();
 
         Ok(())
     }
     fn compile_expression_list(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<expressionList>\n".as_bytes())?;
+        self.open("expressionList");
         if !Self::is_right_paran(self.tokenizer.token_type()) {
             self.compile_expression()?;
             while Self::is_comma(self.tokenizer.token_type()) {
@@ -316,111 +337,104 @@ impl CompilationEngine {
                 self.compile_expression()?;
             }
         }
-        self.file.write_all("</expressionList>\n".as_bytes())?;
+        self.close();
         Ok(())
     }
 
     fn write_token_and_advance(&mut self) -> Result<(), io::Error> {
-        self.tokenizer.write_current_token(&mut self.file)?;
+        let token = Node::Token {
+            token: self.tokenizer.token_type(),
+            location: self.tokenizer.location(),
+        };
+        self.add(token);
         self.tokenizer.advance();
         Ok(())
     }
 
-    fn is_class_var_dec_token(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Static) | Keyword(Keywords::Field) => true,
-            _ => false,
-        }
+    fn open(&mut self, name: &'static str) {
+        self.rules.push(Node::Rule {
+            name,
+            children: Vec::new(),
+        });
     }
 
-    fn is_subroutine_dec_token(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Constructor)
-            | Keyword(Keywords::Function)
-            | Keyword(Keywords::Method) => true,
-            _ => false,
+    // finishes the innermost rule, as a child of the one around it
+    fn close(&mut self) {
+        if let Some(rule) = self.rules.pop() {
+            self.add(rule);
         }
     }
 
-    fn is_comma(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::Comma(_)) => true,
-            _ => false,
+    fn add(&mut self, node: Node) {
+        if let Some(Node::Rule { children, .. }) = self.rules.last_mut() {
+            children.push(node);
         }
     }
 
-    fn is_return_token(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Return) => true,
-            _ => false,
-        }
+    fn is_class_var_dec_token(token: Token) -> bool {
+        matches!(token, Keyword(Keywords::Static) | Keyword(Keywords::Field))
+    }
+
+    fn is_subroutine_dec_token(token: Token) -> bool {
+        matches!(
+            token,
+            Keyword(Keywords::Constructor)
+                | Keyword(Keywords::Function)
+                | Keyword(Keywords::Method)
+        )
+    }
+
+    fn is_comma(token: Token) -> bool {
+        matches!(token, Symbol(Symbols::Comma(_)))
     }
 
     fn is_left_paran(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::LParen(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::LParen(_)))
     }
 
     fn is_right_paran(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::RParen(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::RParen(_)))
     }
 
     fn is_semicolon(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::Semicolon(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::Semicolon(_)))
     }
 
     fn is_statement(token: Token) -> bool {
-        match token {
+        matches!(
+            token,
             Keyword(Keywords::Let)
-            | Keyword(Keywords::If)
-            | Keyword(Keywords::While)
-            | Keyword(Keywords::Do)
-            | Keyword(Keywords::Return) => true,
-            _ => false,
-        }
+                | Keyword(Keywords::If)
+                | Keyword(Keywords::While)
+                | Keyword(Keywords::Do)
+                | Keyword(Keywords::Return)
+        )
     }
 
     fn is_right_curly(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::RCurly(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::RCurly(_)))
     }
 
     fn is_left_square(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::LSquare(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::LSquare(_)))
     }
 
     fn is_op_token(token: Token) -> bool {
-        match token {
+        matches!(
+            token,
             Symbol(Symbols::Plus(_))
-            | Symbol(Symbols::Minus(_))
-            | Symbol(Symbols::Mult(_))
-            | Symbol(Symbols::Div(_))
-            | Symbol(Symbols::And(_))
-            | Symbol(Symbols::Or(_))
-            | Symbol(Symbols::Greater(_))
-            | Symbol(Symbols::Less(_))
-            | Symbol(Symbols::Eq(_)) => true,
-            _ => false,
-        }
+                | Symbol(Symbols::Minus(_))
+                | Symbol(Symbols::Mult(_))
+                | Symbol(Symbols::Div(_))
+                | Symbol(Symbols::And(_))
+                | Symbol(Symbols::Or(_))
+                | Symbol(Symbols::Greater(_))
+                | Symbol(Symbols::Less(_))
+                | Symbol(Symbols::Eq(_))
+        )
     }
 
     fn is_else(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Else) => true,
-            _ => false,
-        }
+        matches!(token, Keyword(Keywords::Else))
     }
 }
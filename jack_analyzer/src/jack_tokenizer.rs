@@ -6,6 +6,8 @@ use std::{
 pub struct JackTokenizer {
     pub tokens: Vec<Token>,
     pub token_index: usize,
+    // where each token starts
+    pub locations: Vec<Location>,
 }
 
 const SYMBOLS: [char; 19] = [
@@ -93,15 +95,134 @@ pub enum Token {
     Identifier(String),
 }
 
+/// Where a token starts, counting lines and columns from 1 as editors do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+// Reads the source one character at a time, keeping track of the line and
+// column (from 1) for errors and token locations.
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(line: usize, column: usize, message: String) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {}, column {}: {}", line, column, message),
+        )
+    }
+
+    fn tokens(&mut self) -> Result<Vec<(Token, Location)>, io::Error> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek(0) {
+            let (line, column) = (self.line, self.column);
+            let location = Location { line, column };
+            if c.is_whitespace() {
+                self.next();
+            } else if c == '/' && self.peek(1) == Some('/') {
+                while self.peek(0).is_some_and(|c| c != '\n') {
+                    self.next();
+                }
+            } else if c == '/' && self.peek(1) == Some('*') {
+                self.next();
+                self.next();
+                while !(self.peek(0) == Some('*') && self.peek(1) == Some('/')) {
+                    if self.next().is_none() {
+                        return Err(Lexer::error(
+                            line,
+                            column,
+                            "unterminated comment".to_string(),
+                        ));
+                    }
+                }
+                self.next();
+                self.next();
+            } else if c == '"' {
+                self.next();
+                let mut string = String::new();
+                loop {
+                    match self.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            let message = "unterminated string constant".to_string();
+                            return Err(Lexer::error(line, column, message));
+                        }
+                        Some(c) => string.push(c),
+                    }
+                }
+                tokens.push((Token::StringConstant(string), location));
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .peek(0)
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    word.push(c);
+                    self.next();
+                }
+                if c.is_ascii_digit() {
+                    let value = word.parse().map_err(|_| {
+                        Lexer::error(line, column, format!("invalid integer constant {}", word))
+                    })?;
+                    tokens.push((Token::IntegerConstant(value), location));
+                } else if JackTokenizer::is_keyword(&word) {
+                    tokens.push((JackTokenizer::make_keyword_token(&word), location));
+                } else {
+                    tokens.push((Token::Identifier(word), location));
+                }
+            } else if JackTokenizer::is_symbol(c) {
+                self.next();
+                tokens.push((JackTokenizer::make_symbol_token(c), location));
+            } else {
+                let message = format!("unexpected character '{}'", c);
+                return Err(Lexer::error(line, column, message));
+            }
+        }
+        Ok(tokens)
+    }
+}
+
 impl JackTokenizer {
     pub fn new(file_name: &str) -> Result<Self, io::Error> {
         let jack_code = fs::read_to_string(file_name)?;
-        let jack_code_without_comments = JackTokenizer::remove_comments(&jack_code);
+        JackTokenizer::from_code(&jack_code)
+    }
 
-        let tokens = JackTokenizer::split_to_tokens(&jack_code_without_comments);
+    pub fn from_code(jack_code: &str) -> Result<Self, io::Error> {
+        let mut lexer = Lexer {
+            chars: jack_code.chars().collect(),
+            position: 0,
+            line: 1,
+            column: 1,
+        };
+        let (tokens, locations) = lexer.tokens()?.into_iter().unzip();
         Ok(JackTokenizer {
             tokens,
             token_index: 0,
+            locations,
         })
     }
 
@@ -125,6 +246,7 @@ impl JackTokenizer {
                     )?;
                 }
                 Token::StringConstant(s) => {
+                    let s = JackTokenizer::escape_xml(s);
                     file.write_all(
                         format!("<stringConstant> {} </stringConstant>\n", s).as_bytes(),
                     )?;
@@ -140,43 +262,56 @@ impl JackTokenizer {
         Ok(())
     }
 
-    pub fn write_current_token(&self, file: &mut File) -> Result<(), io::Error> {
-        let token = &self.tokens[self.token_index];
+    pub fn write_current_token<W: Write>(&self, file: &mut W) -> Result<(), io::Error> {
+        JackTokenizer::write_token(&self.tokens[self.token_index], file)
+    }
 
+    /// One line of the `<tokens>` XML, e.g. `<symbol> &lt; </symbol>`.
+    pub fn write_token<W: Write>(token: &Token, file: &mut W) -> Result<(), io::Error> {
+        let kind = JackTokenizer::token_kind(token);
+        let value = JackTokenizer::escape_xml(&JackTokenizer::token_value(token));
+        file.write_all(format!("<{0}> {1} </{0}>\n", kind, value).as_bytes())
+    }
+
+    /// The name the course's XML gives the kind of token.
+    pub fn token_kind(token: &Token) -> &'static str {
         match token {
-            Token::Keyword(keyword) => {
-                let keyword = JackTokenizer::keywords_to_string(keyword);
-                file.write_all(format!("<keyword> {} </keyword>\n", keyword).as_bytes())?;
-            }
-            Token::Symbol(symbol) => {
-                let c = JackTokenizer::symbols_to_string(symbol);
-                file.write_all(format!("<symbol> {} </symbol>\n", c).as_bytes())?;
-            }
-            Token::IntegerConstant(num) => {
-                file.write_all(
-                    format!("<integerConstant> {} </integerConstant>\n", num).as_bytes(),
-                )?;
-            }
-            Token::StringConstant(s) => {
-                file.write_all(format!("<stringConstant> {} </stringConstant>\n", s).as_bytes())?;
-            }
-            Token::Identifier(var_name) => {
-                file.write_all(format!("<identifier> {} </identifier>\n", var_name).as_bytes())?;
-            }
+            Token::Keyword(_) => "keyword",
+            Token::Symbol(_) => "symbol",
+            Token::IntegerConstant(_) => "integerConstant",
+            Token::StringConstant(_) => "stringConstant",
+            Token::Identifier(_) => "identifier",
         }
-        Ok(())
+    }
+
+    /// The token's text, without the quotes of a string constant.
+    pub fn token_value(token: &Token) -> String {
+        match token {
+            Token::Keyword(keyword) => JackTokenizer::keywords_to_string(keyword),
+            Token::Symbol(symbol) => JackTokenizer::symbol_char(symbol).to_string(),
+            Token::IntegerConstant(value) => value.to_string(),
+            Token::StringConstant(value) | Token::Identifier(value) => value.clone(),
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        self.locations[self.token_index]
     }
 
     pub fn write_token_file(&mut self, file_name: &str) -> Result<(), io::Error> {
         let file_name = file_name.replace(".jack", "") + "_token.xml";
         File::create(&file_name)?;
         let mut file = OpenOptions::new().append(true).open(file_name)?;
-        file.write_all("<tokens>\n".as_bytes())?;
+        self.write_tokens(&mut file)
+    }
+
+    pub fn write_tokens<W: Write>(&mut self, out: &mut W) -> Result<(), io::Error> {
+        out.write_all("<tokens>\n".as_bytes())?;
         while self.has_more_tokens() {
-            self.write_current_token(&mut file)?;
+            self.write_current_token(out)?;
             self.advance();
         }
-        file.write_all("</tokens>\n".as_bytes())?;
+        out.write_all("</tokens>\n".as_bytes())?;
         Ok(())
     }
     pub fn advance(&mut self) {
@@ -198,7 +333,7 @@ impl JackTokenizer {
             _ => &Keywords::Class,
         };
 
-        JackTokenizer::keywords_to_string(&keyword)
+        JackTokenizer::keywords_to_string(keyword)
     }
 
     pub fn symbol(&self) -> String {
@@ -208,7 +343,7 @@ impl JackTokenizer {
             _ => &Symbols::And('&'),
         };
 
-        JackTokenizer::symbols_to_string(&symbol)
+        JackTokenizer::symbols_to_string(symbol)
     }
 
     pub fn identifier(&self) -> String {
@@ -239,8 +374,8 @@ impl JackTokenizer {
         SYMBOLS.contains(&c)
     }
 
-    fn is_keyword(str: &String) -> bool {
-        KEYWORDS.contains(&str.as_str())
+    fn is_keyword(word: &str) -> bool {
+        KEYWORDS.contains(&word)
     }
 
     fn make_symbol_token(symbol: char) -> Token {
@@ -295,6 +430,13 @@ impl JackTokenizer {
         }
     }
 
+    // string constants may hold characters that are markup in XML
+    fn escape_xml(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
     fn symbols_to_string(symbol: &Symbols) -> String {
         match symbol {
             Symbols::LCurly(c)
@@ -319,6 +461,30 @@ impl JackTokenizer {
         }
     }
 
+    pub fn symbol_char(symbol: &Symbols) -> char {
+        match symbol {
+            Symbols::LCurly(c)
+            | Symbols::RCurly(c)
+            | Symbols::RParen(c)
+            | Symbols::LParen(c)
+            | Symbols::RSquare(c)
+            | Symbols::LSquare(c)
+            | Symbols::Period(c)
+            | Symbols::Comma(c)
+            | Symbols::Semicolon(c)
+            | Symbols::Plus(c)
+            | Symbols::Minus(c)
+            | Symbols::Mult(c)
+            | Symbols::Div(c)
+            | Symbols::And(c)
+            | Symbols::Or(c)
+            | Symbols::Less(c)
+            | Symbols::Greater(c)
+            | Symbols::Eq(c)
+            | Symbols::Not(c) => *c,
+        }
+    }
+
     pub fn keywords_to_string(keyword: &Keywords) -> String {
         match keyword {
             Keywords::Class => "class".to_string(),
@@ -344,127 +510,4 @@ impl JackTokenizer {
             Keywords::Return => "return".to_string(),
         }
     }
-
-    fn remove_comments(jack_code: &String) -> String {
-        // start with "//" is inline comment
-        let mut is_in_inline_comment = false;
-        // start with "/**" and end with "*/" is block comment
-        let mut is_in_block_comment = false;
-
-        let mut jack_code_without_comments = String::from("");
-
-        let mut jack_code = jack_code.chars().peekable();
-
-        while jack_code.peek() != None {
-            let mut c = jack_code.next().unwrap();
-
-            if c == '/' && jack_code.peek() == Some(&'/') {
-                is_in_inline_comment = true;
-            }
-
-            if c == '/' && jack_code.peek() == Some(&'*') {
-                is_in_block_comment = true;
-            }
-
-            if c == '\n' {
-                is_in_inline_comment = false;
-            }
-
-            if c == '*' && jack_code.peek() == Some(&'/') && is_in_block_comment {
-                jack_code.next();
-                c = jack_code.next().unwrap();
-                is_in_block_comment = false;
-            }
-
-            if !(is_in_inline_comment || is_in_block_comment) {
-                jack_code_without_comments.push(c);
-            }
-        }
-
-        jack_code_without_comments.trim().to_string()
-    }
-
-    fn split_to_tokens(jack_code: &String) -> Vec<Token> {
-        let mut in_string = false;
-        let mut jack_code = jack_code.chars().peekable();
-        let mut tokens = Vec::new();
-        let mut token = "".to_string();
-
-        while jack_code.peek() != None {
-            let c = jack_code.next().unwrap();
-
-            if (c == ' ' || c == '\n') && !in_string {
-                continue;
-            }
-
-            if c == '"' {
-                in_string = !in_string;
-                continue;
-            }
-
-            if JackTokenizer::is_symbol(c) && !in_string {
-                tokens.push(JackTokenizer::make_symbol_token(c));
-                token = "".to_string();
-                continue;
-            }
-
-            token.push(c);
-
-            if jack_code.peek() == Some(&'"') && in_string {
-                in_string = !in_string;
-                tokens.push(Token::StringConstant(token));
-                jack_code.next();
-                token = "".to_string();
-                continue;
-            }
-
-            if JackTokenizer::is_symbol(*jack_code.peek().unwrap()) && !in_string {
-                token = token.trim().to_string();
-
-                if token == "" {
-                    continue;
-                }
-
-                if JackTokenizer::is_keyword(&token) {
-                    tokens.push(JackTokenizer::make_keyword_token(&token));
-                    token = "".to_string();
-                    continue;
-                }
-
-                if let Ok(int_value) = token.parse::<usize>() {
-                    tokens.push(Token::IntegerConstant(int_value));
-                    token = "".to_string();
-                    continue;
-                }
-
-                tokens.push(Token::Identifier(token));
-                token = "".to_string();
-                continue;
-            }
-
-            if (jack_code.peek() == Some(&' ') || jack_code.peek() == Some(&'\n')) && !in_string {
-                token = token.trim().to_string();
-
-                if token == "" {
-                    continue;
-                }
-                if JackTokenizer::is_keyword(&token) {
-                    tokens.push(JackTokenizer::make_keyword_token(&token));
-                    token = "".to_string();
-                    continue;
-                }
-
-                if let Ok(int_value) = token.parse::<usize>() {
-                    tokens.push(Token::IntegerConstant(int_value));
-                    token = "".to_string();
-                    continue;
-                }
-
-                tokens.push(Token::Identifier(token));
-
-                token = "".to_string();
-            }
-        }
-        tokens
-    }
 }
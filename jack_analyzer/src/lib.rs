@@ -0,0 +1,55 @@
+pub mod compilation_engine;
+pub mod jack_tokenizer;
+pub mod syntax_tree;
+pub mod verify;
+
+use std::io;
+
+use compilation_engine::CompilationEngine;
+use jack_tokenizer::JackTokenizer;
+use syntax_tree::Node;
+
+/// The `<tokens>` XML of one `.jack` file, as in `XT.xml`.
+pub fn tokens_xml(jack_code: &str) -> Result<String, io::Error> {
+    let mut out = Vec::new();
+    JackTokenizer::from_code(jack_code)?.write_tokens(&mut out)?;
+    Ok(String::from_utf8_lossy(&out).to_string())
+}
+
+/// The parse tree XML of one `.jack` file, as in `X.xml`.
+pub fn parse_tree_xml(jack_code: &str) -> Result<String, io::Error> {
+    let mut compilation_engine =
+        CompilationEngine::with_writer(JackTokenizer::from_code(jack_code)?, Vec::new());
+    compilation_engine.compile_class()?;
+    Ok(String::from_utf8_lossy(&compilation_engine.file).to_string())
+}
+
+/// The tokens of one `.jack` file as JSON, with where each one starts.
+pub fn tokens_json(jack_code: &str) -> Result<String, io::Error> {
+    let tokenizer = JackTokenizer::from_code(jack_code)?;
+    Ok(syntax_tree::tokens_json(
+        &tokenizer.tokens,
+        &tokenizer.locations,
+    ))
+}
+
+/// The parse tree of one `.jack` file as JSON.
+pub fn parse_tree_json(jack_code: &str) -> Result<String, io::Error> {
+    Ok(parse_tree(jack_code)?.to_json())
+}
+
+/// The tokens of one `.jack` file as an S-expression.
+pub fn tokens_sexp(jack_code: &str) -> Result<String, io::Error> {
+    Ok(syntax_tree::tokens_sexp(
+        &JackTokenizer::from_code(jack_code)?.tokens,
+    ))
+}
+
+/// The parse tree of one `.jack` file as an S-expression.
+pub fn parse_tree_sexp(jack_code: &str) -> Result<String, io::Error> {
+    Ok(parse_tree(jack_code)?.to_sexp())
+}
+
+fn parse_tree(jack_code: &str) -> Result<Node, io::Error> {
+    CompilationEngine::with_writer(JackTokenizer::from_code(jack_code)?, io::sink()).parse_class()
+}
@@ -1,63 +1,278 @@
-pub mod compilation_engine;
-pub mod jack_tokenizer;
-
 use std::{
-    env,
-    fs::{self, File},
+    env, fs, io,
+    path::{Path, PathBuf},
     process,
 };
 
-use compilation_engine::CompilationEngine;
-use jack_tokenizer::JackTokenizer;
+use jack_analyzer::verify;
+
+const USAGE: &str = "usage: jack_analyzer [--format xml|json|sexp] [--verify] [--out-dir <dir>] \
+                     <file.jack | dir>...";
+
+// what the token and parse tree files are written as
+enum Format {
+    Xml,
+    Json,
+    Sexp,
+}
+
+struct Args {
+    inputs: Vec<String>,
+    out_dir: Option<PathBuf>,
+    format: Format,
+    verify: bool,
+}
+
+// a .jack file and where its output goes, without the extension
+struct Source {
+    path: PathBuf,
+    output: PathBuf,
+}
 
 fn main() {
-    let target_name = get_target();
-    let mut jack_files = Vec::new();
+    let args = get_args();
+    let sources = jack_sources(&args.inputs, args.out_dir.as_deref());
 
-    if is_file(&target_name) {
-        jack_files.push(target_name.clone())
-    } else {
-        jack_files = get_target_type_files(&target_name, "jack");
+    if args.verify {
+        let mut matched = true;
+        for source in &sources {
+            matched &= verify_file(&source.path);
+        }
+        if !matched {
+            process::exit(1);
+        }
+        return;
     }
 
-    for jack_file in jack_files {
-        let mut tokenizer = JackTokenizer::new(&jack_file).unwrap_or_else(|_| {
-            eprintln!("{} does not exsit", jack_file);
-            process::exit(1);
-        });
-        let mut compilation_engine = CompilationEngine::new(&jack_file).unwrap_or_else(|_| {
-            eprintln!("{} does not exsit", jack_file);
-            process::exit(1);
-        });
-        let _ = tokenizer.write_token_file(&jack_file);
-        let _ = compilation_engine.compile_class();
+    for source in &sources {
+        let jack_code = read_source(source);
+        // X_token.xml and X_compile.xml, or the same names in another format
+        let (tokens, parse_tree, extension) =
+            dump(&jack_code, &args.format).unwrap_or_else(|err| {
+                fail(&format!("{}: {}", source.path.display(), err));
+            });
+        for (suffix, contents) in [("_token", tokens), ("_compile", parse_tree)] {
+            let file = with_suffix(&source.output, &format!("{}.{}", suffix, extension));
+            write_output(&file, contents);
+        }
     }
 }
 
-fn get_target() -> String {
-    let args: Vec<String> = env::args().collect();
-    let target_name = args.get(1).unwrap_or_else(|| {
-        eprintln!("not enough argumens");
-        process::exit(1);
+// Compares the XML of X.jack with the XT.xml and X.xml next to it,
+// printing the first difference of each, and whether everything matched.
+fn verify_file(jack_file: &Path) -> bool {
+    let jack_code = fs::read_to_string(jack_file).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", jack_file.display(), err));
     });
-    String::from(target_name)
+    let stem = jack_file.with_extension("");
+    let outputs = [
+        (
+            with_suffix(&stem, "T.xml"),
+            jack_analyzer::tokens_xml(&jack_code),
+        ),
+        (
+            with_suffix(&stem, ".xml"),
+            jack_analyzer::parse_tree_xml(&jack_code),
+        ),
+    ];
+
+    let jack_file = jack_file.display();
+    let mut matched = true;
+    for (reference, output) in outputs {
+        if !reference.exists() {
+            println!("{}: no {} to compare with", jack_file, reference.display());
+            continue;
+        }
+        let expected = fs::read_to_string(&reference).unwrap_or_else(|err| {
+            fail(&format!("{}: {}", reference.display(), err));
+        });
+        let reference = reference.display();
+        match output {
+            Err(err) => {
+                println!("{}: {}", jack_file, err);
+                matched = false;
+            }
+            Ok(actual) => match verify::compare(&expected, &actual) {
+                None => println!("{}: matches {}", jack_file, reference),
+                Some(mismatch) => {
+                    println!("{}: differs from {}", jack_file, reference);
+                    println!("{}", mismatch);
+                    matched = false;
+                }
+            },
+        }
+    }
+    matched
+}
+
+fn dump(jack_code: &str, format: &Format) -> Result<(String, String, &'static str), io::Error> {
+    Ok(match format {
+        Format::Xml => (
+            jack_analyzer::tokens_xml(jack_code)?,
+            jack_analyzer::parse_tree_xml(jack_code)?,
+            "xml",
+        ),
+        Format::Json => (
+            jack_analyzer::tokens_json(jack_code)?,
+            jack_analyzer::parse_tree_json(jack_code)?,
+            "json",
+        ),
+        Format::Sexp => (
+            jack_analyzer::tokens_sexp(jack_code)?,
+            jack_analyzer::parse_tree_sexp(jack_code)?,
+            "sexp",
+        ),
+    })
 }
 
-fn get_target_type_files(dir_name: &str, target_extension: &str) -> Vec<String> {
-    let entries = fs::read_dir("./".to_string() + dir_name).unwrap();
+// --format json or --format sexp writes the files in that format instead
+// of XML, --verify compares the XML with the course's files instead of
+// writing anything and --out-dir writes the files there instead of next to
+// each .jack file
+fn get_args() -> Args {
+    let mut args = Args {
+        inputs: Vec::new(),
+        out_dir: None,
+        format: Format::Xml,
+        verify: false,
+    };
+    let mut arguments = env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--verify" => args.verify = true,
+            "--format" => {
+                args.format = match arguments.next().as_deref() {
+                    Some("xml") => Format::Xml,
+                    Some("json") => Format::Json,
+                    Some("sexp") => Format::Sexp,
+                    _ => usage_error("--format needs xml, json or sexp"),
+                }
+            }
+            "-o" | "--out-dir" => match arguments.next() {
+                Some(out_dir) => args.out_dir = Some(PathBuf::from(out_dir)),
+                None => usage_error(&format!("{} needs a directory", argument)),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if argument.starts_with('-') => usage_error(&format!("unknown option {}", argument)),
+            _ => args.inputs.push(argument),
+        }
+    }
+    if args.inputs.is_empty() {
+        usage_error("no .jack file or directory given");
+    }
+    args
+}
 
-    let mut jack_files = Vec::<String>::new();
+// The .jack files of the inputs, the files in a directory found in the
+// directories under it too. Their output goes next to them, or under
+// out_dir the way they are under the input.
+fn jack_sources(inputs: &[String], out_dir: Option<&Path>) -> Vec<Source> {
+    let mut sources = Vec::new();
+    for input in inputs {
+        let input = Path::new(input);
+        let (root, files) = if input.is_dir() {
+            let files = jack_files(input);
+            if files.is_empty() {
+                fail(&format!("{}: no .jack files", input.display()));
+            }
+            (input, files)
+        } else if input.is_file() {
+            if input
+                .extension()
+                .is_none_or(|extension| extension != "jack")
+            {
+                fail(&format!("{}: not a .jack file", input.display()));
+            }
+            (
+                input.parent().unwrap_or(Path::new("")),
+                vec![input.to_path_buf()],
+            )
+        } else {
+            fail(&format!("{}: no such file or directory", input.display()));
+        };
+        for path in files {
+            let output = match out_dir {
+                Some(out_dir) => out_dir.join(path.strip_prefix(root).unwrap_or(&path)),
+                None => path.clone(),
+            };
+            let output = output.with_extension("");
+            match sources
+                .iter()
+                .find(|source: &&Source| source.output == output)
+            {
+                // a file given twice
+                Some(source) if source.path == path => {}
+                Some(source) => fail(&format!(
+                    "{} and {} would overwrite each other's output in {}",
+                    source.path.display(),
+                    path.display(),
+                    output.parent().unwrap_or(Path::new(".")).display()
+                )),
+                None => sources.push(Source { path, output }),
+            }
+        }
+    }
+    sources
+}
 
-    for entry in entries {
-        jack_files.push(entry.unwrap().path().to_str().unwrap().to_string());
+// the .jack files in the directory and the ones under it, sorted, leaving
+// out hidden directories and the ones symbolic links lead to
+fn jack_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = fs::read_dir(dir).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", dir.display(), err));
+    });
+    let mut files = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            if !hidden {
+                files.append(&mut jack_files(&path));
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "jack")
+        {
+            files.push(path);
+        }
     }
-    jack_files
-        .iter()
-        .filter(|&file_name| file_name.clone().contains(target_extension))
-        .cloned()
-        .collect()
+    files.sort();
+    files
+}
+
+fn read_source(source: &Source) -> String {
+    fs::read_to_string(&source.path).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", source.path.display(), err));
+    })
+}
+
+// writes the file, making the directories it goes in
+fn write_output(file: &Path, contents: String) {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(Err(err)) = dir.map(fs::create_dir_all) {
+        fail(&format!("{}: {}", file.display(), err));
+    }
+    fs::write(file, contents).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", file.display(), err));
+    });
+}
+
+// the path with the suffix added to its last part, as Main to Main.vm
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    fail(USAGE);
 }
 
-fn is_file(target_name: &str) -> bool {
-    target_name.contains(".jack")
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
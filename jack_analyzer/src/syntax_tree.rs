@@ -0,0 +1,149 @@
+use std::io::{self, Write};
+
+use crate::jack_tokenizer::{JackTokenizer, Location, Token};
+
+/// The parse tree of a class as the compilation engine reads it: each
+/// grammar rule, such as `letStatement`, with the rules and tokens it
+/// matched.
+pub enum Node {
+    Rule {
+        name: &'static str,
+        children: Vec<Node>,
+    },
+    Token {
+        token: Token,
+        location: Location,
+    },
+}
+
+impl Node {
+    /// The tree as the course's `X.xml`.
+    pub fn write_xml<W: Write>(&self, out: &mut W) -> Result<(), io::Error> {
+        match self {
+            Node::Rule { name, children } => {
+                out.write_all(format!("<{}>\n", name).as_bytes())?;
+                for child in children {
+                    child.write_xml(out)?;
+                }
+                out.write_all(format!("</{}>\n", name).as_bytes())
+            }
+            Node::Token { token, .. } => JackTokenizer::write_token(token, out),
+        }
+    }
+
+    /// `{"rule": "class", "children": [...]}`, with the tokens as in
+    /// `tokens_json` and a node per line.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(0, &mut json);
+        json.push('\n');
+        json
+    }
+
+    fn write_json(&self, depth: usize, json: &mut String) {
+        match self {
+            Node::Rule { name, children } if children.is_empty() => {
+                *json += &format!("{{\"rule\": \"{}\", \"children\": []}}", name);
+            }
+            Node::Rule { name, children } => {
+                *json += &format!("{{\"rule\": \"{}\", \"children\": [\n", name);
+                for (i, child) in children.iter().enumerate() {
+                    *json += &"  ".repeat(depth + 1);
+                    child.write_json(depth + 1, json);
+                    if i + 1 < children.len() {
+                        json.push(',');
+                    }
+                    json.push('\n');
+                }
+                *json += &format!("{}]}}", "  ".repeat(depth));
+            }
+            Node::Token { token, location } => *json += &token_json(token, *location),
+        }
+    }
+
+    /// `(class (keyword "class") ...)`, with a rule or token per line,
+    /// indented by its depth.
+    pub fn to_sexp(&self) -> String {
+        let mut sexp = String::new();
+        self.write_sexp(0, &mut sexp);
+        sexp.push('\n');
+        sexp
+    }
+
+    fn write_sexp(&self, depth: usize, sexp: &mut String) {
+        match self {
+            Node::Rule { name, children } => {
+                *sexp += &format!("({}", name);
+                for child in children {
+                    *sexp += &format!("\n{}", "  ".repeat(depth + 1));
+                    child.write_sexp(depth + 1, sexp);
+                }
+                sexp.push(')');
+            }
+            Node::Token { token, .. } => *sexp += &token_sexp(token),
+        }
+    }
+}
+
+/// `[{"type": "keyword", "value": "class", "line": 1, "column": 1}, ...]`,
+/// a token per line. Integer constants are numbers.
+pub fn tokens_json(tokens: &[Token], locations: &[Location]) -> String {
+    let lines: Vec<String> = tokens
+        .iter()
+        .zip(locations)
+        .map(|(token, location)| format!("  {}", token_json(token, *location)))
+        .collect();
+    if lines.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+/// `(tokens (keyword "class") ...)`, a token per line.
+pub fn tokens_sexp(tokens: &[Token]) -> String {
+    let mut sexp = String::from("(tokens");
+    for token in tokens {
+        sexp += &format!("\n  {}", token_sexp(token));
+    }
+    sexp + ")\n"
+}
+
+fn token_json(token: &Token, location: Location) -> String {
+    let value = match token {
+        Token::IntegerConstant(value) => value.to_string(),
+        _ => quote(&JackTokenizer::token_value(token)),
+    };
+    format!(
+        "{{\"type\": \"{}\", \"value\": {}, \"line\": {}, \"column\": {}}}",
+        JackTokenizer::token_kind(token),
+        value,
+        location.line,
+        location.column
+    )
+}
+
+fn token_sexp(token: &Token) -> String {
+    let value = match token {
+        Token::IntegerConstant(value) => value.to_string(),
+        _ => quote(&JackTokenizer::token_value(token)),
+    };
+    format!("({} {})", JackTokenizer::token_kind(token), value)
+}
+
+// a quoted string with `\` and `"` escaped, and control characters such
+// as tabs escaped the way JSON does
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' | '"' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
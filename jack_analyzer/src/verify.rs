@@ -0,0 +1,69 @@
+use std::fmt;
+
+// lines of the reference shown before the first difference
+const CONTEXT: usize = 2;
+
+/// Where our XML first differs from a reference file, comparing the way
+/// the course's TextComparer does: line by line, ignoring whitespace.
+pub struct Mismatch {
+    // the lines before the difference, the same in both, by their line
+    // number in the reference
+    pub context: Vec<(usize, String)>,
+    // the differing lines with their numbers; None past the end of a file
+    pub expected: Option<(usize, String)>,
+    pub actual: Option<(usize, String)>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (number, line) in &self.context {
+            writeln!(f, "  {:>5} | {}", number, line)?;
+        }
+        match &self.expected {
+            Some((number, line)) => writeln!(f, "- {:>5} | {}", number, line)?,
+            None => writeln!(f, "-       | (end of file)")?,
+        }
+        match &self.actual {
+            Some((number, line)) => write!(f, "+ {:>5} | {}", number, line),
+            None => write!(f, "+       | (end of file)"),
+        }
+    }
+}
+
+/// The first difference between the reference XML and ours, if any.
+/// Blank lines do not count.
+pub fn compare(expected: &str, actual: &str) -> Option<Mismatch> {
+    let expected = significant_lines(expected);
+    let actual = significant_lines(actual);
+    let same = expected
+        .iter()
+        .zip(&actual)
+        .take_while(|(expected, actual)| expected.2 == actual.2)
+        .count();
+    if same == expected.len() && same == actual.len() {
+        return None;
+    }
+
+    let line = |(number, text, _): &(usize, &str, String)| (*number, text.to_string());
+    Some(Mismatch {
+        context: expected[same.saturating_sub(CONTEXT)..same]
+            .iter()
+            .map(line)
+            .collect(),
+        expected: expected.get(same).map(line),
+        actual: actual.get(same).map(line),
+    })
+}
+
+// the line number, trimmed text and text without whitespace of each line
+// that is not blank
+fn significant_lines(text: &str) -> Vec<(usize, &str, String)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let normalized: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+            (i + 1, line.trim(), normalized)
+        })
+        .filter(|(_, _, normalized)| !normalized.is_empty())
+        .collect()
+}
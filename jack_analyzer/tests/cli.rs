@@ -0,0 +1,101 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+const MAIN: &str = "class Main { function void main() { return; } }\n";
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("jack_analyzer-cli-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+// the exit code and standard error of the analyzer
+fn analyzer(args: &[&Path]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jack_analyzer"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let (status, stderr) = analyzer(&[Path::new("--xml"), Path::new("Main.jack")]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("unknown option --xml\n"), "{}", stderr);
+    assert!(stderr.contains("usage: jack_analyzer"), "{}", stderr);
+
+    let (status, stderr) = analyzer(&[Path::new("--format"), Path::new("yaml")]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("--format needs xml, json or sexp\n"));
+
+    let dir = temp_dir("inputs");
+    write(&dir.join("notes.txt"), "");
+    let missing = dir.join("Missing.jack");
+    let (status, stderr) = analyzer(&[&missing]);
+    assert_eq!(status, Some(1));
+    assert_eq!(
+        stderr,
+        format!("{}: no such file or directory\n", missing.display())
+    );
+    let notes = dir.join("notes.txt");
+    let (status, stderr) = analyzer(&[&notes]);
+    assert_eq!(status, Some(1));
+    assert_eq!(stderr, format!("{}: not a .jack file\n", notes.display()));
+}
+
+#[test]
+fn writes_every_jack_file_under_the_out_dir() {
+    let dir = temp_dir("out_dir").join("jackfiles");
+    write(&dir.join("notes.txt"), "not Jack");
+    write(&dir.join("Main.jack"), MAIN);
+    write(&dir.join("game/Ball.jack"), "class Ball { }\n");
+    let out = dir.join("build");
+
+    let (status, stderr) = analyzer(&[
+        Path::new("--format"),
+        Path::new("json"),
+        &dir,
+        Path::new("-o"),
+        &out,
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    for file in [
+        "Main_token.json",
+        "Main_compile.json",
+        "game/Ball_token.json",
+        "game/Ball_compile.json",
+    ] {
+        assert!(out.join(file).exists(), "{}", file);
+    }
+    assert!(!dir.join("Main_token.json").exists());
+    assert!(!out.join("notes_token.json").exists());
+}
+
+#[test]
+fn names_the_file_with_the_error() {
+    let dir = temp_dir("error");
+    let main = dir.join("Main.jack");
+    write(&main, "class Main {\n  function void main() { do f(\"; }\n}\n");
+
+    let (status, stderr) = analyzer(&[&main]);
+    assert_eq!(status, Some(1));
+    assert!(
+        stderr.starts_with(&format!("{}: ", main.display())),
+        "{}",
+        stderr
+    );
+    assert!(!dir.join("Main_compile.xml").exists());
+}
@@ -0,0 +1,95 @@
+const MAIN: &str = "class Main {
+    function void main() {
+        do Output.printString(\"a\\b\");
+        return;
+    }
+}
+";
+
+#[test]
+fn dumps_tokens_as_json_with_their_locations() {
+    let json = jack_analyzer::tokens_json("class Main {\n  field int x; }").unwrap();
+    assert_eq!(
+        json,
+        "[
+  {\"type\": \"keyword\", \"value\": \"class\", \"line\": 1, \"column\": 1},
+  {\"type\": \"identifier\", \"value\": \"Main\", \"line\": 1, \"column\": 7},
+  {\"type\": \"symbol\", \"value\": \"{\", \"line\": 1, \"column\": 12},
+  {\"type\": \"keyword\", \"value\": \"field\", \"line\": 2, \"column\": 3},
+  {\"type\": \"keyword\", \"value\": \"int\", \"line\": 2, \"column\": 9},
+  {\"type\": \"identifier\", \"value\": \"x\", \"line\": 2, \"column\": 13},
+  {\"type\": \"symbol\", \"value\": \";\", \"line\": 2, \"column\": 14},
+  {\"type\": \"symbol\", \"value\": \"}\", \"line\": 2, \"column\": 16}
+]
+"
+    );
+    assert_eq!(jack_analyzer::tokens_json("").unwrap(), "[]\n");
+}
+
+#[test]
+fn dumps_tokens_as_s_expressions() {
+    let sexp = jack_analyzer::tokens_sexp("let x = 12; do f(\"<\");").unwrap();
+    assert_eq!(
+        sexp,
+        "(tokens
+  (keyword \"let\")
+  (identifier \"x\")
+  (symbol \"=\")
+  (integerConstant 12)
+  (symbol \";\")
+  (keyword \"do\")
+  (identifier \"f\")
+  (symbol \"(\")
+  (stringConstant \"<\")
+  (symbol \")\")
+  (symbol \";\"))
+"
+    );
+}
+
+#[test]
+fn dumps_the_parse_tree() {
+    let sexp = jack_analyzer::parse_tree_sexp(MAIN).unwrap();
+    assert!(sexp.starts_with(
+        "(class
+  (keyword \"class\")
+  (identifier \"Main\")
+  (symbol \"{\")
+  (subroutineDec
+    (keyword \"function\")"
+    ));
+    assert!(sexp.contains("(stringConstant \"a\\\\b\")"), "{}", sexp);
+    assert!(sexp.contains("(parameterList)"), "{}", sexp);
+    assert!(sexp.ends_with("  (symbol \"}\"))\n"));
+
+    let json = jack_analyzer::parse_tree_json(MAIN).unwrap();
+    assert!(json.starts_with(
+        "{\"rule\": \"class\", \"children\": [
+  {\"type\": \"keyword\", \"value\": \"class\", \"line\": 1, \"column\": 1},"
+    ));
+    assert!(json.contains("{\"rule\": \"parameterList\", \"children\": []}"));
+    assert!(
+        json.contains(
+            "{\"type\": \"stringConstant\", \"value\": \"a\\\\b\", \"line\": 3, \"column\": 31}"
+        ),
+        "{}",
+        json
+    );
+    assert!(json.ends_with("]}\n"));
+}
+
+#[test]
+fn nests_the_dumps_like_the_xml() {
+    let xml = jack_analyzer::parse_tree_xml(MAIN).unwrap();
+    let sexp = jack_analyzer::parse_tree_sexp(MAIN).unwrap();
+    let rules = |text: &str, open: &str| -> Vec<String> {
+        text.lines()
+            .map(str::trim)
+            .filter_map(|line| line.strip_prefix(open))
+            .filter(|rest| !rest.starts_with('/') && !rest.contains(' '))
+            .map(|rule| rule.trim_end_matches(['>', ')']).to_string())
+            .collect()
+    };
+    assert!(rules(&xml, "<").contains(&"doStatement".to_string()));
+    assert_eq!(rules(&xml, "<"), rules(&sexp, "("));
+}
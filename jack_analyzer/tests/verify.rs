@@ -0,0 +1,108 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use jack_analyzer::verify::compare;
+
+fn projects() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/10")
+}
+
+fn analyzer(args: &[&Path]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jack_analyzer"))
+        .arg("--verify")
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    )
+}
+
+#[test]
+fn ignores_whitespace_and_blank_lines() {
+    assert!(compare("<a>\n  <b> x </b>\n</a>\n", "<a>\r\n\n<b>x</b>\n  </a>").is_none());
+}
+
+#[test]
+fn shows_the_first_difference_in_context() {
+    let expected = "<a>\n<b>\n<c>\n\n<d>\n</a>\n";
+    let actual = "<a>\n<b>\n<c>\n<e>\n</a>\n";
+    let mismatch = compare(expected, actual).unwrap();
+    assert_eq!(
+        mismatch.context,
+        [(2, "<b>".to_string()), (3, "<c>".to_string())]
+    );
+    assert_eq!(mismatch.expected, Some((5, "<d>".to_string())));
+    assert_eq!(mismatch.actual, Some((4, "<e>".to_string())));
+    assert_eq!(
+        mismatch.to_string(),
+        "      2 | <b>\n      3 | <c>\n-     5 | <d>\n+     4 | <e>"
+    );
+
+    let mismatch = compare("<a>\n</a>\n", "<a>\n</a>\n<b>\n").unwrap();
+    assert_eq!(mismatch.expected, None);
+    assert_eq!(
+        mismatch.to_string(),
+        "      1 | <a>\n      2 | </a>\n-       | (end of file)\n+     3 | <b>"
+    );
+}
+
+#[test]
+fn matches_the_course_files() {
+    for dir in ["ArrayTest", "ExpressionLessSquare", "Square"] {
+        for entry in fs::read_dir(projects().join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "jack") {
+                continue;
+            }
+            let jack_code = fs::read_to_string(&path).unwrap();
+            let stem = path.with_extension("").display().to_string();
+            for (reference, actual) in [
+                (
+                    format!("{}T.xml", stem),
+                    jack_analyzer::tokens_xml(&jack_code).unwrap(),
+                ),
+                (
+                    format!("{}.xml", stem),
+                    jack_analyzer::parse_tree_xml(&jack_code).unwrap(),
+                ),
+            ] {
+                let expected = fs::read_to_string(&reference).unwrap();
+                if let Some(mismatch) = compare(&expected, &actual) {
+                    panic!("{} differs:\n{}", reference, mismatch);
+                }
+            }
+        }
+    }
+
+    let (status, stdout) = analyzer(&[&projects()]);
+    assert_eq!(status, Some(0), "{}", stdout);
+    assert_eq!(stdout.matches(": matches ").count(), 14, "{}", stdout);
+}
+
+#[test]
+fn fails_on_a_difference() {
+    let dir = env::temp_dir().join(format!("jack_analyzer-verify-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = projects().join("ArrayTest");
+    fs::copy(source.join("Main.jack"), dir.join("Main.jack")).unwrap();
+    fs::copy(source.join("MainT.xml"), dir.join("MainT.xml")).unwrap();
+    let reference = fs::read_to_string(source.join("Main.xml")).unwrap();
+    fs::write(
+        dir.join("Main.xml"),
+        reference.replacen("<keyword> var </keyword>", "<keyword> let </keyword>", 1),
+    )
+    .unwrap();
+
+    let (status, stdout) = analyzer(&[&dir.join("Main.jack")]);
+    assert_eq!(status, Some(1), "{}", stdout);
+    assert!(stdout.contains("MainT.xml"), "{}", stdout);
+    assert!(stdout.contains("Main.xml\n"), "{}", stdout);
+    assert!(stdout.contains("differs from"), "{}", stdout);
+    assert!(stdout.contains("<keyword> let </keyword>"), "{}", stdout);
+    assert!(stdout.contains("<keyword> var </keyword>"), "{}", stdout);
+}
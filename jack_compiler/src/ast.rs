@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crate::symbol_table::Kind;
+
+pub struct Class {
+    pub name: String,
+    pub class_var_decs: Vec<ClassVarDec>,
+    // already inlined where they are used
+    pub constants: Vec<Constant>,
+    pub subroutines: Vec<SubroutineDec>,
+}
+
+pub struct ClassVarDec {
+    // STATIC or FIELD
+    pub kind: Kind,
+    pub var_type: Type,
+    pub names: Vec<String>,
+}
+
+// `const type name = expression;` of the extended dialect
+pub struct Constant {
+    pub var_type: Type,
+    pub name: String,
+    pub value: i16,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    Class(String),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Char => write!(f, "char"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Class(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method,
+}
+
+impl fmt::Display for SubroutineKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constructor => write!(f, "constructor"),
+            Self::Function => write!(f, "function"),
+            Self::Method => write!(f, "method"),
+        }
+    }
+}
+
+pub struct SubroutineDec {
+    pub kind: SubroutineKind,
+    // None for void
+    pub return_type: Option<Type>,
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub var_decs: Vec<VarDec>,
+    pub statements: Vec<Statement>,
+}
+
+pub struct Parameter {
+    pub var_type: Type,
+    pub name: String,
+}
+
+pub struct VarDec {
+    pub var_type: Type,
+    pub names: Vec<String>,
+}
+
+pub enum Statement {
+    Let {
+        name: String,
+        index: Option<Expression>,
+        // `let x += 1` in the extended dialect
+        op: Option<BinaryOp>,
+        value: Expression,
+    },
+    If {
+        condition: Expression,
+        statements: Vec<Statement>,
+        else_statements: Option<Vec<Statement>>,
+    },
+    While {
+        condition: Expression,
+        statements: Vec<Statement>,
+    },
+    Do(SubroutineCall),
+    Return(Option<Expression>),
+    // the extended dialect's `for (i = 0; i < n; i += 1) { ... }`, where
+    // the first and last parts are Let statements; any part can be left
+    // out, and without a condition the loop runs until a break
+    For {
+        init: Option<Box<Statement>>,
+        condition: Option<Expression>,
+        update: Option<Box<Statement>>,
+        statements: Vec<Statement>,
+    },
+    Break,
+    Continue,
+}
+
+// Jack has no operator precedence, so `a + b * c` is parsed as
+// `Binary(Binary(a, +, b), *, c)`, unless the parser is asked for
+// conventional precedence. Parentheses are kept so that the tree can be
+// written back out the way it was read.
+pub enum Expression {
+    // 0..32767, or 32768 directly under a `-`
+    IntegerConstant(usize),
+    StringConstant(String),
+    KeywordConstant(KeywordConstant),
+    Var(String),
+    ArrayAccess(String, Box<Expression>),
+    Call(SubroutineCall),
+    Parenthesized(Box<Expression>),
+    Unary(UnaryOp, Box<Expression>),
+    Binary(Box<Expression>, BinaryOp, Box<Expression>),
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntegerConstant(value) => write!(f, "{}", value),
+            Self::StringConstant(value) => write!(f, "\"{}\"", value),
+            Self::KeywordConstant(constant) => write!(f, "{}", constant),
+            Self::Var(name) => write!(f, "{}", name),
+            Self::ArrayAccess(name, index) => write!(f, "{}[{}]", name, index),
+            Self::Call(call) => write!(f, "{}", call),
+            Self::Parenthesized(expression) => write!(f, "({})", expression),
+            Self::Unary(op, term) => write!(f, "{}{}", op, term),
+            Self::Binary(left, op, right) => write!(f, "{} {} {}", left, op, right),
+        }
+    }
+}
+
+pub struct SubroutineCall {
+    // class or variable name before the '.', None for `foo()`
+    pub receiver: Option<String>,
+    pub name: String,
+    pub arguments: Vec<Expression>,
+}
+
+impl fmt::Display for SubroutineCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(receiver) = &self.receiver {
+            write!(f, "{}.", receiver)?;
+        }
+        write!(f, "{}(", self.name)?;
+        for (i, argument) in self.arguments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", argument)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KeywordConstant {
+    True,
+    False,
+    Null,
+    This,
+}
+
+impl fmt::Display for KeywordConstant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::True => write!(f, "true"),
+            Self::False => write!(f, "false"),
+            Self::Null => write!(f, "null"),
+            Self::This => write!(f, "this"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Neg => write!(f, "-"),
+            Self::Not => write!(f, "~"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Lt,
+    Gt,
+    Eq,
+}
+
+impl BinaryOp {
+    /// How tightly the operator binds under conventional precedence, from
+    /// `|` (1) to `*` and `/` (5).
+    pub fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Lt | Self::Gt | Self::Eq => 3,
+            Self::Add | Self::Sub => 4,
+            Self::Mul | Self::Div => 5,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add => write!(f, "+"),
+            Self::Sub => write!(f, "-"),
+            Self::Mul => write!(f, "*"),
+            Self::Div => write!(f, "/"),
+            Self::And => write!(f, "&"),
+            Self::Or => write!(f, "|"),
+            Self::Lt => write!(f, "<"),
+            Self::Gt => write!(f, ">"),
+            Self::Eq => write!(f, "="),
+        }
+    }
+}
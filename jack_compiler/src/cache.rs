@@ -0,0 +1,66 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const CACHE_FILE: &str = ".jack_cache";
+
+/// The keys of the classes a directory's `.vm` files were last compiled
+/// from, kept in `.jack_cache` next to them, so that a build can skip the
+/// classes that have not changed.
+pub struct BuildCache {
+    path: PathBuf,
+    // the key of each .jack file by its file name
+    keys: HashMap<String, u64>,
+}
+
+impl BuildCache {
+    /// The cache of the directory, empty when there is none or it cannot
+    /// be read.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(CACHE_FILE);
+        let keys = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (key, file_name) = line.split_once(' ')?;
+                Some((file_name.to_string(), u64::from_str_radix(key, 16).ok()?))
+            })
+            .collect();
+        BuildCache { path, keys }
+    }
+
+    /// Whether the file was last compiled with this key.
+    pub fn is_fresh(&self, file_name: &str, key: u64) -> bool {
+        self.keys.get(file_name) == Some(&key)
+    }
+
+    pub fn update(&mut self, file_name: &str, key: u64) {
+        self.keys.insert(file_name.to_string(), key);
+    }
+
+    pub fn save(&self) -> Result<(), io::Error> {
+        let mut entries: Vec<_> = self.keys.iter().collect();
+        entries.sort();
+        let lines: String = entries
+            .into_iter()
+            .map(|(file_name, key)| format!("{:016x} {}\n", key, file_name))
+            .collect();
+        fs::write(&self.path, lines)
+    }
+}
+
+/// A 64-bit FNV-1a hash of the parts, which stays the same from one
+/// build of the compiler to the next, unlike `DefaultHasher`.
+pub fn content_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        // the length keeps ["ab", "c"] and ["a", "bc"] apart
+        for byte in (part.len() as u64).to_le_bytes().iter().chain(*part) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
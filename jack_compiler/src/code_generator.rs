@@ -0,0 +1,507 @@
+use crate::ast::{
+    BinaryOp, Class, Expression, KeywordConstant, Statement, SubroutineCall, SubroutineDec,
+    SubroutineKind, UnaryOp,
+};
+use crate::parser::CompileError;
+use crate::symbol_table::{Kind, SymbolTable};
+use crate::vm_writer::{Command, Segment, VMWriter};
+
+/// Translates the syntax tree of one class into VM code, following the
+/// conventions of the course compiler (labels, `Memory.alloc` in
+/// constructors, `Math.multiply`/`Math.divide` for `*` and `/`). Constant
+/// subexpressions are computed at compile time, and multiplying by a power
+/// of two is done with additions.
+pub struct CodeGenerator {
+    symbol_table: SymbolTable,
+    vm_writer: VMWriter,
+    class_name: String,
+    if_counter: usize,
+    while_counter: usize,
+    for_counter: usize,
+    // the labels continue and break jump to in each enclosing loop
+    loops: Vec<(String, String)>,
+}
+
+impl CodeGenerator {
+    pub fn new() -> Self {
+        CodeGenerator {
+            symbol_table: SymbolTable::new(),
+            vm_writer: VMWriter::new(),
+            class_name: String::new(),
+            if_counter: 0,
+            while_counter: 0,
+            for_counter: 0,
+            loops: Vec::new(),
+        }
+    }
+
+    pub fn compile_class(mut self, class: &Class) -> Result<String, CompileError> {
+        self.class_name = class.name.clone();
+
+        for class_var_dec in &class.class_var_decs {
+            for name in &class_var_dec.names {
+                self.symbol_table.define(
+                    name.clone(),
+                    class_var_dec.var_type.to_string(),
+                    class_var_dec.kind,
+                );
+            }
+        }
+
+        for subroutine in &class.subroutines {
+            self.compile_subroutine(subroutine)?;
+        }
+
+        Ok(self.vm_writer.close())
+    }
+
+    fn compile_subroutine(&mut self, subroutine: &SubroutineDec) -> Result<(), CompileError> {
+        self.symbol_table.start_subroutine();
+        self.if_counter = 0;
+        self.while_counter = 0;
+        self.for_counter = 0;
+
+        if subroutine.kind == SubroutineKind::Method {
+            self.symbol_table
+                .define("this".to_string(), self.class_name.clone(), Kind::ARG);
+        }
+        for parameter in &subroutine.parameters {
+            self.symbol_table.define(
+                parameter.name.clone(),
+                parameter.var_type.to_string(),
+                Kind::ARG,
+            );
+        }
+        for var_dec in &subroutine.var_decs {
+            for name in &var_dec.names {
+                self.symbol_table
+                    .define(name.clone(), var_dec.var_type.to_string(), Kind::VAR);
+            }
+        }
+
+        self.vm_writer.write_function(
+            &format!("{}.{}", self.class_name, subroutine.name),
+            self.symbol_table.var_count(Kind::VAR),
+        );
+
+        match subroutine.kind {
+            SubroutineKind::Constructor => {
+                self.vm_writer
+                    .write_push(Segment::Const, self.symbol_table.var_count(Kind::FIELD));
+                self.vm_writer.write_call("Memory.alloc", 1);
+                self.vm_writer.write_pop(Segment::Pointer, 0);
+            }
+            SubroutineKind::Method => {
+                self.vm_writer.write_push(Segment::Arg, 0);
+                self.vm_writer.write_pop(Segment::Pointer, 0);
+            }
+            SubroutineKind::Function => (),
+        }
+
+        self.compile_statements(&subroutine.statements)
+    }
+
+    fn compile_statements(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for statement in statements {
+            self.compile_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match statement {
+            Statement::Let {
+                name,
+                index,
+                op,
+                value,
+            } => match index {
+                Some(index) => {
+                    self.push_variable(name)?;
+                    self.compile_expression(index)?;
+                    self.vm_writer.write_arithmetic(Command::Add);
+                    if let Some(op) = op {
+                        // keep a copy of the address to store to
+                        self.vm_writer.write_pop(Segment::Temp, 1);
+                        self.vm_writer.write_push(Segment::Temp, 1);
+                        self.vm_writer.write_push(Segment::Temp, 1);
+                        self.vm_writer.write_pop(Segment::Pointer, 1);
+                        self.vm_writer.write_push(Segment::That, 0);
+                        self.compile_expression(value)?;
+                        self.write_op(*op);
+                    } else {
+                        self.compile_expression(value)?;
+                    }
+                    self.vm_writer.write_pop(Segment::Temp, 0);
+                    self.vm_writer.write_pop(Segment::Pointer, 1);
+                    self.vm_writer.write_push(Segment::Temp, 0);
+                    self.vm_writer.write_pop(Segment::That, 0);
+                }
+                None => {
+                    match op {
+                        Some(op) => {
+                            self.compile_binary(&Expression::Var(name.clone()), *op, value)?
+                        }
+                        None => self.compile_expression(value)?,
+                    }
+                    let (segment, index) = self.variable(name)?;
+                    self.vm_writer.write_pop(segment, index);
+                }
+            },
+            Statement::If {
+                condition,
+                statements,
+                else_statements,
+            } => {
+                let counter = self.if_counter;
+                self.if_counter += 1;
+                let if_true = format!("IF_TRUE{}", counter);
+                let if_false = format!("IF_FALSE{}", counter);
+
+                self.compile_expression(condition)?;
+                self.vm_writer.write_if(&if_true);
+                self.vm_writer.write_goto(&if_false);
+                self.vm_writer.write_label(&if_true);
+                self.compile_statements(statements)?;
+                match else_statements {
+                    Some(else_statements) => {
+                        let if_end = format!("IF_END{}", counter);
+                        self.vm_writer.write_goto(&if_end);
+                        self.vm_writer.write_label(&if_false);
+                        self.compile_statements(else_statements)?;
+                        self.vm_writer.write_label(&if_end);
+                    }
+                    None => self.vm_writer.write_label(&if_false),
+                }
+            }
+            Statement::While {
+                condition,
+                statements,
+            } => {
+                let counter = self.while_counter;
+                self.while_counter += 1;
+                let while_exp = format!("WHILE_EXP{}", counter);
+                let while_end = format!("WHILE_END{}", counter);
+
+                self.vm_writer.write_label(&while_exp);
+                self.compile_expression(condition)?;
+                self.vm_writer.write_arithmetic(Command::Not);
+                self.vm_writer.write_if(&while_end);
+                self.compile_loop_body(statements, &while_exp, &while_end)?;
+                self.vm_writer.write_goto(&while_exp);
+                self.vm_writer.write_label(&while_end);
+            }
+            Statement::For {
+                init,
+                condition,
+                update,
+                statements,
+            } => {
+                let counter = self.for_counter;
+                self.for_counter += 1;
+                let for_exp = format!("FOR_EXP{}", counter);
+                let for_update = format!("FOR_UPDATE{}", counter);
+                let for_end = format!("FOR_END{}", counter);
+
+                if let Some(init) = init {
+                    self.compile_statement(init)?;
+                }
+                self.vm_writer.write_label(&for_exp);
+                if let Some(condition) = condition {
+                    self.compile_expression(condition)?;
+                    self.vm_writer.write_arithmetic(Command::Not);
+                    self.vm_writer.write_if(&for_end);
+                }
+                self.compile_loop_body(statements, &for_update, &for_end)?;
+                self.vm_writer.write_label(&for_update);
+                if let Some(update) = update {
+                    self.compile_statement(update)?;
+                }
+                self.vm_writer.write_goto(&for_exp);
+                self.vm_writer.write_label(&for_end);
+            }
+            Statement::Break | Statement::Continue => {
+                let (next, end) = match self.loops.last() {
+                    Some(labels) => labels.clone(),
+                    None => {
+                        return Err(CompileError {
+                            class: self.class_name.clone(),
+                            message: "break or continue outside of a loop".to_string(),
+                            token: None,
+                            location: None,
+                            file: None,
+                        })
+                    }
+                };
+                match statement {
+                    Statement::Break => self.vm_writer.write_goto(&end),
+                    _ => self.vm_writer.write_goto(&next),
+                }
+            }
+            Statement::Do(call) => {
+                self.compile_subroutine_call(call)?;
+                self.vm_writer.write_pop(Segment::Temp, 0);
+            }
+            Statement::Return(value) => {
+                match value {
+                    Some(value) => self.compile_expression(value)?,
+                    None => self.vm_writer.write_push(Segment::Const, 0),
+                }
+                self.vm_writer.write_return();
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_loop_body(
+        &mut self,
+        statements: &[Statement],
+        continue_label: &str,
+        break_label: &str,
+    ) -> Result<(), CompileError> {
+        self.loops
+            .push((continue_label.to_string(), break_label.to_string()));
+        let result = self.compile_statements(statements);
+        self.loops.pop();
+        result
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<(), CompileError> {
+        if let Some(value) = constant_value(expression) {
+            self.write_constant(value);
+            return Ok(());
+        }
+        match expression {
+            Expression::IntegerConstant(value) => {
+                self.vm_writer.write_push(Segment::Const, *value);
+            }
+            Expression::StringConstant(value) => {
+                self.vm_writer
+                    .write_push(Segment::Const, value.chars().count());
+                self.vm_writer.write_call("String.new", 1);
+                for c in value.chars() {
+                    self.vm_writer.write_push(Segment::Const, c as usize);
+                    self.vm_writer.write_call("String.appendChar", 2);
+                }
+            }
+            Expression::KeywordConstant(constant) => match constant {
+                KeywordConstant::True => {
+                    self.vm_writer.write_push(Segment::Const, 0);
+                    self.vm_writer.write_arithmetic(Command::Not);
+                }
+                KeywordConstant::False | KeywordConstant::Null => {
+                    self.vm_writer.write_push(Segment::Const, 0);
+                }
+                KeywordConstant::This => self.vm_writer.write_push(Segment::Pointer, 0),
+            },
+            Expression::Var(name) => self.push_variable(name)?,
+            Expression::ArrayAccess(name, index) => {
+                self.push_variable(name)?;
+                self.compile_expression(index)?;
+                self.vm_writer.write_arithmetic(Command::Add);
+                self.vm_writer.write_pop(Segment::Pointer, 1);
+                self.vm_writer.write_push(Segment::That, 0);
+            }
+            Expression::Call(call) => self.compile_subroutine_call(call)?,
+            Expression::Parenthesized(expression) => self.compile_expression(expression)?,
+            Expression::Unary(op, term) => {
+                self.compile_expression(term)?;
+                match op {
+                    UnaryOp::Neg => self.vm_writer.write_arithmetic(Command::Neg),
+                    UnaryOp::Not => self.vm_writer.write_arithmetic(Command::Not),
+                }
+            }
+            Expression::Binary(left, op, right) => self.compile_binary(left, *op, right)?,
+        }
+        Ok(())
+    }
+
+    // `push constant` only takes 0..32767; a negative value is the bitwise
+    // not of a positive one
+    fn write_constant(&mut self, value: i16) {
+        if value >= 0 {
+            self.vm_writer.write_push(Segment::Const, value as usize);
+        } else {
+            self.vm_writer.write_push(Segment::Const, !value as usize);
+            self.vm_writer.write_arithmetic(Command::Not);
+        }
+    }
+
+    fn compile_binary(
+        &mut self,
+        left: &Expression,
+        op: BinaryOp,
+        right: &Expression,
+    ) -> Result<(), CompileError> {
+        match (op, constant_value(left), constant_value(right)) {
+            (BinaryOp::Add | BinaryOp::Sub, _, Some(0))
+            | (BinaryOp::Mul | BinaryOp::Div, _, Some(1)) => return self.compile_expression(left),
+            (BinaryOp::Add, Some(0), _) | (BinaryOp::Mul, Some(1), _) => {
+                return self.compile_expression(right)
+            }
+            (BinaryOp::Mul, _, Some(0)) if !has_call(left) => {
+                self.write_constant(0);
+                return Ok(());
+            }
+            (BinaryOp::Mul, Some(0), _) if !has_call(right) => {
+                self.write_constant(0);
+                return Ok(());
+            }
+            (BinaryOp::Mul, _, Some(factor)) if is_power_of_two(factor) => {
+                return self.compile_doubling(left, factor.trailing_zeros())
+            }
+            (BinaryOp::Mul, Some(factor), _) if is_power_of_two(factor) => {
+                return self.compile_doubling(right, factor.trailing_zeros())
+            }
+            _ => (),
+        }
+
+        self.compile_expression(left)?;
+        self.compile_expression(right)?;
+        self.write_op(op);
+        Ok(())
+    }
+
+    fn write_op(&mut self, op: BinaryOp) {
+        match op {
+            BinaryOp::Add => self.vm_writer.write_arithmetic(Command::Add),
+            BinaryOp::Sub => self.vm_writer.write_arithmetic(Command::Sub),
+            BinaryOp::Mul => self.vm_writer.write_call("Math.multiply", 2),
+            BinaryOp::Div => self.vm_writer.write_call("Math.divide", 2),
+            BinaryOp::And => self.vm_writer.write_arithmetic(Command::And),
+            BinaryOp::Or => self.vm_writer.write_arithmetic(Command::Or),
+            BinaryOp::Lt => self.vm_writer.write_arithmetic(Command::Lt),
+            BinaryOp::Gt => self.vm_writer.write_arithmetic(Command::Gt),
+            BinaryOp::Eq => self.vm_writer.write_arithmetic(Command::Eq),
+        }
+    }
+
+    // the expression times 2^times, adding it to itself through temp 0
+    fn compile_doubling(
+        &mut self,
+        expression: &Expression,
+        times: u32,
+    ) -> Result<(), CompileError> {
+        self.compile_expression(expression)?;
+        for _ in 0..times {
+            self.vm_writer.write_pop(Segment::Temp, 0);
+            self.vm_writer.write_push(Segment::Temp, 0);
+            self.vm_writer.write_push(Segment::Temp, 0);
+            self.vm_writer.write_arithmetic(Command::Add);
+        }
+        Ok(())
+    }
+
+    // foo(..)      -> method of this class, `this` is passed as argument 0
+    // var.foo(..)  -> method of the variable's class
+    // Class.foo(..) -> function or constructor
+    fn compile_subroutine_call(&mut self, call: &SubroutineCall) -> Result<(), CompileError> {
+        let (class_name, mut arg_num) = match &call.receiver {
+            None => {
+                self.vm_writer.write_push(Segment::Pointer, 0);
+                (self.class_name.clone(), 1)
+            }
+            Some(receiver) => match self.symbol_table.type_of(receiver) {
+                Some(class_name) => {
+                    let class_name = class_name.clone();
+                    self.push_variable(receiver)?;
+                    (class_name, 1)
+                }
+                None => (receiver.clone(), 0),
+            },
+        };
+
+        for argument in &call.arguments {
+            self.compile_expression(argument)?;
+            arg_num += 1;
+        }
+
+        self.vm_writer
+            .write_call(&format!("{}.{}", class_name, call.name), arg_num);
+        Ok(())
+    }
+
+    fn push_variable(&mut self, name: &str) -> Result<(), CompileError> {
+        let (segment, index) = self.variable(name)?;
+        self.vm_writer.write_push(segment, index);
+        Ok(())
+    }
+
+    fn variable(&self, name: &str) -> Result<(Segment, usize), CompileError> {
+        let segment = match self.symbol_table.kind_of(name) {
+            Some(Kind::STATIC) => Segment::Static,
+            Some(Kind::FIELD) => Segment::This,
+            Some(Kind::ARG) => Segment::Arg,
+            Some(Kind::VAR) => Segment::Local,
+            None => {
+                return Err(CompileError {
+                    class: self.class_name.clone(),
+                    message: format!("undefined variable {}", name),
+                    token: None,
+                    location: None,
+                    file: None,
+                })
+            }
+        };
+        Ok((segment, *self.symbol_table.index_of(name).unwrap()))
+    }
+}
+
+/// The value of an expression made only of constants, computed the way the
+/// Hack platform would: in 16 bits, with `true` as -1. Division by zero is
+/// left to `Math.divide` at run time.
+pub fn constant_value(expression: &Expression) -> Option<i16> {
+    match expression {
+        Expression::IntegerConstant(value) => Some(*value as i16),
+        Expression::KeywordConstant(KeywordConstant::True) => Some(-1),
+        Expression::KeywordConstant(KeywordConstant::False | KeywordConstant::Null) => Some(0),
+        Expression::Parenthesized(expression) => constant_value(expression),
+        Expression::Unary(op, term) => {
+            let value = constant_value(term)?;
+            match op {
+                UnaryOp::Neg => Some(value.wrapping_neg()),
+                UnaryOp::Not => Some(!value),
+            }
+        }
+        Expression::Binary(left, op, right) => {
+            let (left, right) = (constant_value(left)?, constant_value(right)?);
+            let truth = |condition: bool| if condition { -1 } else { 0 };
+            match op {
+                BinaryOp::Add => Some(left.wrapping_add(right)),
+                BinaryOp::Sub => Some(left.wrapping_sub(right)),
+                BinaryOp::Mul => Some(left.wrapping_mul(right)),
+                BinaryOp::Div if right == 0 => None,
+                BinaryOp::Div => Some(left.wrapping_div(right)),
+                BinaryOp::And => Some(left & right),
+                BinaryOp::Or => Some(left | right),
+                BinaryOp::Lt => Some(truth(left < right)),
+                BinaryOp::Gt => Some(truth(left > right)),
+                BinaryOp::Eq => Some(truth(left == right)),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_power_of_two(value: i16) -> bool {
+    value > 1 && value & (value - 1) == 0
+}
+
+// whether leaving the expression out could leave out a call's side effects
+fn has_call(expression: &Expression) -> bool {
+    match expression {
+        Expression::Call(_) => true,
+        Expression::ArrayAccess(_, index) => has_call(index),
+        Expression::Parenthesized(expression) | Expression::Unary(_, expression) => {
+            has_call(expression)
+        }
+        Expression::Binary(left, _, right) => has_call(left) || has_call(right),
+        _ => false,
+    }
+}
+
+impl Default for CodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
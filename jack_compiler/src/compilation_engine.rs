@@ -1,104 +1,141 @@
+use std::collections::HashSet;
+
 use crate::jack_tokenizer::JackTokenizer;
-use crate::jack_tokenizer::Token::{Identifier, IntegerConstant, Keyword, StringConstant, Symbol};
+use crate::jack_tokenizer::Token::{
+    CharConstant, Identifier, IntegerConstant, Keyword, RadixConstant, StringConstant, Symbol,
+};
 use crate::jack_tokenizer::{Keywords, Symbols, Token};
 
-use crate::symbol_table::SymbolTable;
-
-use std::fs::File;
-use std::io;
-use std::io::Write;
+use crate::parser::CompileError;
+use crate::symbol_table::{Kind, SymbolTable};
+
+/// Writes the parse tree of a class as the course's `X.xml`, with every
+/// identifier tagged by what it names, whether it is being defined or
+/// used and, for variables, its index in the symbol table:
+/// `<identifier> (field define 0) x </identifier>` or
+/// `<identifier> (class use) Square </identifier>`. The extended
+/// dialect's constants are tagged `(constant define)` and `(constant use)`.
+///
+/// It reads the tokens without checking the grammar, so the class should
+/// parse first.
+pub struct CompilationEngine {
+    tokenizer: JackTokenizer,
+    symbol_table: SymbolTable,
+    class_name: String,
+    // the class's constants, in the extended dialect
+    constants: HashSet<String>,
+    xml: String,
+}
 
-pub enum CompileStatus {
+// what an identifier names
+enum Category {
     Class,
-    VarDec,
     Subroutine,
-    ParameterList,
-    Statement,
-    Expression,
-}
-
-pub struct CompilationEngine {
-    pub file: File,
-    pub tokenizer: JackTokenizer,
-    pub symbol_table: SymbolTable,
-    pub compile_status: CompileStatus,
+    Variable,
+    Constant,
 }
 
 impl CompilationEngine {
-    pub fn new(file_name: &str) -> Result<Self, io::Error> {
-        let tokenizer = JackTokenizer::new(file_name)?;
-        let file_name = file_name.replace(".jack", "") + "_compile.xml";
-        Ok(CompilationEngine {
-            file: File::create(file_name)?,
+    pub fn new(tokenizer: JackTokenizer) -> Self {
+        CompilationEngine {
             tokenizer,
             symbol_table: SymbolTable::new(),
-            compile_status: CompileStatus::Class,
-        })
+            class_name: String::new(),
+            constants: HashSet::new(),
+            xml: String::new(),
+        }
     }
 
-    pub fn compile_class(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::Class;
-
-        self.file.write_all("<class>\n".as_bytes())?;
+    pub fn compile_class(mut self) -> Result<String, CompileError> {
+        self.xml += "<class>\n";
         self.write_token_and_advance()?;
 
         // write class name
-        self.write_token_and_advance()?;
+        self.class_name = self.tokenizer.identifier();
+        self.write_identifier(Category::Class, true)?;
         // write "{"
         self.write_token_and_advance()?;
 
-        while Self::is_class_var_dec_token(self.tokenizer.token_type()) {
-            Self::compile_class_var_dec(self)?;
+        loop {
+            if Self::is_class_var_dec_token(self.tokenizer.token_type()) {
+                self.compile_class_var_dec()?;
+            } else if self.is_word("const") {
+                self.compile_const_dec()?;
+            } else {
+                break;
+            }
         }
 
         while Self::is_subroutine_dec_token(self.tokenizer.token_type()) {
-            Self::compile_subroutine(self)?;
+            self.compile_subroutine()?;
         }
 
         // write "}"
         self.write_token_and_advance()?;
 
-        self.file.write_all("</class>\n".as_bytes())?;
+        self.xml += "</class>\n";
 
-        Ok(())
+        Ok(self.xml)
     }
 
-    fn compile_class_var_dec(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::VarDec;
-
-        let token = self.tokenizer.token_type();
-
-        self.file.write_all("<classVarDec>\n".as_bytes())?;
+    fn compile_class_var_dec(&mut self) -> Result<(), CompileError> {
+        self.xml += "<classVarDec>\n";
 
         // write static | field
-        self.symbol_table.set_token_kind(&token);
+        let kind = match self.tokenizer.token_type() {
+            Keyword(Keywords::Static) => Kind::STATIC,
+            _ => Kind::FIELD,
+        };
         self.write_token_and_advance()?;
 
-        // write variable type, ex. int, string
-        self.symbol_table.set_token_type(&token);
+        self.compile_var_names(kind)?;
+
+        // write ";"
         self.write_token_and_advance()?;
 
-        while !Self::is_semicolon(self.tokenizer.token_type()) {
+        self.xml += "</classVarDec>\n";
+        Ok(())
+    }
+
+    // 'const' type name '=' expression (',' name '=' expression)* ';'
+    fn compile_const_dec(&mut self) -> Result<(), CompileError> {
+        self.xml += "<constDec>\n";
+        // write const
+        self.write_word_and_advance();
+        self.write_type()?;
+        loop {
+            self.constants.insert(self.tokenizer.identifier());
+            self.write_identifier(Category::Constant, true)?;
+            // write '='
+            self.write_token_and_advance()?;
+            self.compile_expression()?;
+            if !Self::is_comma(self.tokenizer.token_type()) {
+                break;
+            }
+            // write ','
             self.write_token_and_advance()?;
         }
-
-        // write ";"
+        // write ';'
         self.write_token_and_advance()?;
-
-        self.file.write_all("</classVarDec>\n".as_bytes())?;
+        self.xml += "</constDec>\n";
         Ok(())
     }
 
-    fn compile_subroutine(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::Subroutine;
+    fn compile_subroutine(&mut self) -> Result<(), CompileError> {
+        self.symbol_table.start_subroutine();
+        // a method gets the object as argument 0
+        if let Keyword(Keywords::Method) = self.tokenizer.token_type() {
+            self.symbol_table
+                .define("this".to_string(), self.class_name.clone(), Kind::ARG);
+        }
 
-        self.file.write_all("<subroutineDec>\n".as_bytes())?;
+        self.xml += "<subroutineDec>\n";
         // write ('constrctor' | 'function' | 'method')
         self.write_token_and_advance()?;
         // write ('void' | type)
-        self.write_token_and_advance()?;
+        self.write_type()?;
         // write subroutine name
-        self.write_token_and_advance()?;
+        self.write_identifier(Category::Subroutine, true)?;
         // write '('
         self.write_token_and_advance()?;
 
@@ -107,7 +144,7 @@ impl CompilationEngine {
         // write ')'
         self.write_token_and_advance()?;
 
-        self.file.write_all("<subroutineBody>\n".as_bytes())?;
+        self.xml += "<subroutineBody>\n";
         // write '{'
         self.write_token_and_advance()?;
 
@@ -120,88 +157,113 @@ impl CompilationEngine {
 
         // write '}'
         self.write_token_and_advance()?;
-        self.file.write_all("</subroutineBody>\n".as_bytes())?;
-        self.file.write_all("</subroutineDec>\n".as_bytes())?;
+        self.xml += "</subroutineBody>\n";
+        self.xml += "</subroutineDec>\n";
         Ok(())
     }
 
-    fn compile_parameter_list(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::ParameterList;
-        let mut token = self.tokenizer.token_type();
-        self.symbol_table.set_token_kind(&token);
-
-        self.file.write_all("<parameterList>\n".as_bytes())?;
+    fn compile_parameter_list(&mut self) -> Result<(), CompileError> {
+        self.xml += "<parameterList>\n";
         while !Self::is_right_paran(self.tokenizer.token_type()) {
-            token = self.tokenizer.token_type();
-            self.symbol_table.set_token_type(&token);
-            self.write_token_and_advance()?;
+            if Self::is_comma(self.tokenizer.token_type()) {
+                // write ','
+                self.write_token_and_advance()?;
+            }
+            let var_type = self.write_type()?;
+            let name = self.tokenizer.identifier();
+            self.symbol_table.define(name, var_type, Kind::ARG);
+            self.write_identifier(Category::Variable, true)?;
         }
         // write ')'
-        self.file.write_all("</parameterList>\n".as_bytes())?;
+        self.xml += "</parameterList>\n";
         Ok(())
     }
 
-    fn compile_var_dec(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::VarDec;
+    fn compile_var_dec(&mut self) -> Result<(), CompileError> {
+        self.xml += "<varDec>\n";
+        // write var
+        self.write_token_and_advance()?;
+        self.compile_var_names(Kind::VAR)?;
+        // write ';'
+        self.write_token_and_advance()?;
+        self.xml += "</varDec>\n";
+        Ok(())
+    }
 
-        self.file.write_all("<varDec>\n".as_bytes())?;
-        while !Self::is_semicolon(self.tokenizer.token_type()) {
-            self.symbol_table
-                .set_token_kind(&self.tokenizer.token_type());
-            self.symbol_table
-                .set_token_type(&self.tokenizer.token_type());
+    // the type and the names of a declaration up to ';', defining them
+    fn compile_var_names(&mut self, kind: Kind) -> Result<(), CompileError> {
+        let var_type = self.write_type()?;
+        loop {
+            let name = self.tokenizer.identifier();
+            self.symbol_table.define(name, var_type.clone(), kind);
+            self.write_identifier(Category::Variable, true)?;
+            if !Self::is_comma(self.tokenizer.token_type()) {
+                return Ok(());
+            }
+            // write ','
             self.write_token_and_advance()?;
         }
-        // write ')'
-        self.write_token_and_advance()?;
-        self.file.write_all("</varDec>\n".as_bytes())?;
-        Ok(())
     }
 
-    fn compile_statements(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::Statement;
-        self.file.write_all("<statements>\n".as_bytes())?;
-        while Self::is_statement(self.tokenizer.token_type()) {
+    fn compile_statements(&mut self) -> Result<(), CompileError> {
+        self.xml += "<statements>\n";
+        loop {
             match self.tokenizer.token_type() {
                 Keyword(Keywords::Let) => self.compile_let()?,
                 Keyword(Keywords::If) => self.compile_if()?,
                 Keyword(Keywords::While) => self.compile_while()?,
                 Keyword(Keywords::Do) => self.compile_do()?,
                 Keyword(Keywords::Return) => self.compile_return()?,
-                _ => (),
+                // the extended dialect's statements
+                Identifier(word) if word == "for" => self.compile_for()?,
+                Identifier(word) if word == "break" || word == "continue" => {
+                    self.compile_jump(&word)?
+                }
+                _ => break,
             };
         }
-        self.file.write_all("</statements>\n".as_bytes())?;
+        self.xml += "</statements>\n";
         Ok(())
     }
 
-    fn compile_let(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<letStatement>\n".as_bytes())?;
+    fn compile_let(&mut self) -> Result<(), CompileError> {
+        self.xml += "<letStatement>\n";
 
         // write let
         self.write_token_and_advance()?;
-        // write var name
+        self.compile_assignment()?;
+
+        // write ";"
         self.write_token_and_advance()?;
+        self.xml += "</letStatement>\n";
+        Ok(())
+    }
+
+    // varName ('[' expression ']')? op? '=' expression, the op only in the
+    // extended dialect
+    fn compile_assignment(&mut self) -> Result<(), CompileError> {
+        // write var name
+        self.write_name()?;
 
         if Self::is_left_square(self.tokenizer.token_type()) {
-            // write '('
+            // write '['
             self.write_token_and_advance()?;
             self.compile_expression()?;
-            // write ')'
+            // write ']'
+            self.write_token_and_advance()?;
+        }
+        let next = &self.tokenizer.tokens[self.tokenizer.token_index + 1];
+        if matches!(next, Symbol(Symbols::Eq(_))) {
+            // write the op of `+=` and the like
             self.write_token_and_advance()?;
         }
         // write '='
         self.write_token_and_advance()?;
-        self.compile_expression()?;
-
-        // write ";"
-        self.write_token_and_advance()?;
-        self.file.write_all("</letStatement>\n".as_bytes())?;
-        Ok(())
+        self.compile_expression()
     }
 
-    fn compile_if(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<ifStatement>\n".as_bytes())?;
+    fn compile_if(&mut self) -> Result<(), CompileError> {
+        self.xml += "<ifStatement>\n";
 
         // write if
         self.write_token_and_advance()?;
@@ -219,23 +281,28 @@ impl CompilationEngine {
         if Self::is_else(self.tokenizer.token_type()) {
             // write else
             self.write_token_and_advance()?;
-            // write '{'
-            self.write_token_and_advance()?;
-            self.compile_statements()?;
-            // write '}'
-            self.write_token_and_advance()?;
+            if let Keyword(Keywords::If) = self.tokenizer.token_type() {
+                // else if, in the extended dialect
+                self.compile_if()?;
+            } else {
+                // write '{'
+                self.write_token_and_advance()?;
+                self.compile_statements()?;
+                // write '}'
+                self.write_token_and_advance()?;
+            }
         }
-        self.file.write_all("</ifStatement>\n".as_bytes())?;
+        self.xml += "</ifStatement>\n";
         Ok(())
     }
 
-    fn compile_do(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<doStatement>\n".as_bytes())?;
+    fn compile_do(&mut self) -> Result<(), CompileError> {
+        self.xml += "<doStatement>\n";
         // write "do"
         self.write_token_and_advance()?;
 
         // write subroutine name or (className | varName)
-        self.write_token_and_advance()?;
+        self.write_name()?;
 
         if Self::is_left_paran(self.tokenizer.token_type()) {
             // write '('
@@ -247,7 +314,7 @@ impl CompilationEngine {
             // write '.'
             self.write_token_and_advance()?;
             // write subroutine name
-            self.write_token_and_advance()?;
+            self.write_identifier(Category::Subroutine, false)?;
             // write '('
             self.write_token_and_advance()?;
             self.compile_expression_list()?;
@@ -257,12 +324,12 @@ impl CompilationEngine {
 
         // write ';'
         self.write_token_and_advance()?;
-        self.file.write_all("</doStatement>\n".as_bytes())?;
+        self.xml += "</doStatement>\n";
         Ok(())
     }
 
-    fn compile_while(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<whileStatement>\n".as_bytes())?;
+    fn compile_while(&mut self) -> Result<(), CompileError> {
+        self.xml += "<whileStatement>\n";
 
         // write while
         self.write_token_and_advance()?;
@@ -277,12 +344,60 @@ impl CompilationEngine {
         self.compile_statements()?;
         // write "}"
         self.write_token_and_advance()?;
-        self.file.write_all("</whileStatement>\n".as_bytes())?;
+        self.xml += "</whileStatement>\n";
         Ok(())
     }
 
-    fn compile_return(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<returnStatement>\n".as_bytes())?;
+    // 'for' '(' assignment? ';' expression? ';' assignment? ')' '{' statements '}'
+    fn compile_for(&mut self) -> Result<(), CompileError> {
+        self.xml += "<forStatement>\n";
+
+        // write for
+        self.write_word_and_advance();
+        // write '('
+        self.write_token_and_advance()?;
+        if !Self::is_semicolon(self.tokenizer.token_type()) {
+            self.xml += "<assignment>\n";
+            self.compile_assignment()?;
+            self.xml += "</assignment>\n";
+        }
+        // write ';'
+        self.write_token_and_advance()?;
+        if !Self::is_semicolon(self.tokenizer.token_type()) {
+            self.compile_expression()?;
+        }
+        // write ';'
+        self.write_token_and_advance()?;
+        if !Self::is_right_paran(self.tokenizer.token_type()) {
+            self.xml += "<assignment>\n";
+            self.compile_assignment()?;
+            self.xml += "</assignment>\n";
+        }
+        // write ')'
+        self.write_token_and_advance()?;
+
+        // write '{'
+        self.write_token_and_advance()?;
+        self.compile_statements()?;
+        // write '}'
+        self.write_token_and_advance()?;
+        self.xml += "</forStatement>\n";
+        Ok(())
+    }
+
+    // ('break' | 'continue') ';'
+    fn compile_jump(&mut self, word: &str) -> Result<(), CompileError> {
+        self.xml += &format!("<{}Statement>\n", word);
+        // write break | continue
+        self.write_word_and_advance();
+        // write ';'
+        self.write_token_and_advance()?;
+        self.xml += &format!("</{}Statement>\n", word);
+        Ok(())
+    }
+
+    fn compile_return(&mut self) -> Result<(), CompileError> {
+        self.xml += "<returnStatement>\n";
         // write return
         self.write_token_and_advance()?;
         if !Self::is_semicolon(self.tokenizer.token_type()) {
@@ -290,25 +405,24 @@ impl CompilationEngine {
         }
         // write ";"
         self.write_token_and_advance()?;
-        self.file.write_all("</returnStatement>\n".as_bytes())?;
+        self.xml += "</returnStatement>\n";
         Ok(())
     }
 
-    fn compile_expression(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::Expression;
-        self.file.write_all("<expression>\n".as_bytes())?;
+    fn compile_expression(&mut self) -> Result<(), CompileError> {
+        self.xml += "<expression>\n";
         self.compile_term()?;
         while Self::is_op_token(self.tokenizer.token_type()) {
             self.write_token_and_advance()?;
             self.compile_term()?;
         }
-        self.file.write_all("</expression>\n".as_bytes())?;
+        self.xml += "</expression>\n";
 
         Ok(())
     }
 
-    fn compile_term(&mut self) -> Result<(), io::Error> {
-        self.file.write_all("<term>\n".as_bytes())?;
+    fn compile_term(&mut self) -> Result<(), CompileError> {
+        self.xml += "<term>\n";
         match self.tokenizer.token_type() {
             Symbol(Symbols::LParen(_)) => {
                 // write '('
@@ -323,7 +437,10 @@ impl CompilationEngine {
                 self.compile_term()?;
             }
             _ => {
-                self.write_token_and_advance()?;
+                match self.tokenizer.token_type() {
+                    Identifier(_) => self.write_name()?,
+                    _ => self.write_token_and_advance()?,
+                }
                 match self.tokenizer.token_type() {
                     Symbol(Symbols::LSquare(_)) => {
                         // write '['
@@ -343,7 +460,7 @@ impl CompilationEngine {
                         // write '.'
                         self.write_token_and_advance()?;
                         // write subroutine name
-                        self.write_token_and_advance()?;
+                        self.write_identifier(Category::Subroutine, false)?;
                         // write '('
                         self.write_token_and_advance()?;
                         self.compile_expression_list()?;
@@ -354,14 +471,13 @@ impl CompilationEngine {
                 }
             }
         }
-        self.file.write_all("</term>\n".as_bytes())?;
+        self.xml += "</term>\n";
 
         Ok(())
     }
 
-    fn compile_expression_list(&mut self) -> Result<(), io::Error> {
-        self.compile_status = CompileStatus::Expression;
-        self.file.write_all("<expressionList>\n".as_bytes())?;
+    fn compile_expression_list(&mut self) -> Result<(), CompileError> {
+        self.xml += "<expressionList>\n";
         if !Self::is_right_paran(self.tokenizer.token_type()) {
             self.compile_expression()?;
             while Self::is_comma(self.tokenizer.token_type()) {
@@ -370,172 +486,167 @@ impl CompilationEngine {
                 self.compile_expression()?;
             }
         }
-        self.file.write_all("</expressionList>\n".as_bytes())?;
+        self.xml += "</expressionList>\n";
         Ok(())
     }
 
-    fn write_token_and_advance(&mut self) -> Result<(), io::Error> {
+    fn write_token_and_advance(&mut self) -> Result<(), CompileError> {
         let token = self.tokenizer.token_type();
-        match token {
+        self.xml += &match token {
             Keyword(keyword) => {
-                //TODO:ここにvarのときを場合わけ?
                 let keyword = JackTokenizer::keywords_to_string(&keyword);
-                self.file
-                    .write_all(format!("<keyword> {} </keyword>\n", keyword).as_bytes())?;
+                format!("<keyword> {} </keyword>\n", keyword)
             }
             Symbol(symbol) => {
                 let c = JackTokenizer::symbols_to_string(&symbol);
-                self.file
-                    .write_all(format!("<symbol> {} </symbol>\n", c).as_bytes())?;
+                format!("<symbol> {} </symbol>\n", c)
             }
-            IntegerConstant(num) => {
-                self.file.write_all(
-                    format!("<integerConstant> {} </integerConstant>\n", num).as_bytes(),
-                )?;
+            IntegerConstant(_) | CharConstant(_) | RadixConstant { .. } => {
+                let num = JackTokenizer::integer_value(&token);
+                format!("<integerConstant> {} </integerConstant>\n", num)
             }
-            StringConstant(s) => {
-                self.file
-                    .write_all(format!("<stringConstant> {} </stringConstant>\n", s).as_bytes())?;
-            }
-            //TODO: add symboltable tags
-            // only write tags when processing class_var_dec or subrountine_dec
-            // そもそもvarって来た時にvar 型 変数
-            // ってなってるからまとめて暑かった方がいいかも
-            Identifier(var_name) => match self.compile_status {
-                CompileStatus::VarDec | CompileStatus::ParameterList => {
-                    //TODO:
-                    // 今だと var Array とかの Arrayに対してもSymbolTableに追加してしまっている
-                    // var int a, var Array a, のようなときは aのみsymboltableに登録
-                    let exist = self.symbol_table.contains(&var_name);
-                    if !exist {
-                        self.symbol_table.define(
-                            var_name.clone(),
-                            self.symbol_table.get_current_token_type(),
-                            self.symbol_table.get_current_token_kind(),
-                        );
-                    }
-                    let kind = self.symbol_table.kind_of(&var_name).unwrap();
-                    let def_or_use = if exist { "use" } else { "define" };
-                    let index = self.symbol_table.index_of(&var_name).unwrap();
-                    self.file.write_all(
-                        format!(
-                            "<identifier> ({} {} {}) {} </identifier>\n",
-                            kind, def_or_use, index, var_name
-                        )
-                        .as_bytes(),
-                    )?;
-                }
-                CompileStatus::Expression => {
-                    let kind = self.symbol_table.kind_of(&var_name).unwrap();
-                    let def_or_use = "use";
-                    let index = self.symbol_table.index_of(&var_name).unwrap();
-                    self.file.write_all(
-                        format!(
-                            "<identifier> ({} {} {}) {} </identifier>\n",
-                            kind, def_or_use, index, var_name
-                        )
-                        .as_bytes(),
-                    )?;
-                }
-                _ => {
-                    self.file.write_all(
-                        format!("<identifier> {} </identifier>\n", var_name).as_bytes(),
-                    )?;
-                }
+            StringConstant(s) => format!(
+                "<stringConstant> {} </stringConstant>\n",
+                JackTokenizer::escape_xml(&s)
+            ),
+            Identifier(name) => format!("<identifier> {} </identifier>\n", name),
+        };
+        self.tokenizer.advance();
+        Ok(())
+    }
+
+    // writes a word the extended dialect gives a meaning, such as `for`,
+    // as the keyword it acts as
+    fn write_word_and_advance(&mut self) {
+        let word = self.tokenizer.identifier();
+        self.xml += &format!("<keyword> {} </keyword>\n", word);
+        self.tokenizer.advance();
+    }
+
+    // writes the identifier with its category, "define" or "use" and, for
+    // a variable, the index the symbol table gave it
+    fn write_identifier(&mut self, category: Category, define: bool) -> Result<(), CompileError> {
+        let name = self.tokenizer.identifier();
+        let usage = if define { "define" } else { "use" };
+        let tag = match category {
+            Category::Class => format!("class {}", usage),
+            Category::Subroutine => format!("subroutine {}", usage),
+            Category::Constant => format!("constant {}", usage),
+            Category::Variable => match (
+                self.symbol_table.kind_of(&name),
+                self.symbol_table.index_of(&name),
+            ) {
+                (Some(kind), Some(index)) => format!("{} {} {}", kind, usage, index),
+                _ => return Err(self.error(format!("undefined variable {}", name))),
             },
-        }
+        };
+        self.xml += &format!("<identifier> ({}) {} </identifier>\n", tag, name);
         self.tokenizer.advance();
         Ok(())
     }
 
-    fn is_class_var_dec_token(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Static) | Keyword(Keywords::Field) => true,
-            _ => false,
+    // a name in a statement or expression: a subroutine when it is called,
+    // a class when one of its subroutines is called on it, a constant when
+    // no variable hides it and otherwise a variable
+    fn write_name(&mut self) -> Result<(), CompileError> {
+        let name = self.tokenizer.identifier();
+        let next = &self.tokenizer.tokens[self.tokenizer.token_index + 1];
+        let category = if Self::is_left_paran(next.clone()) {
+            Category::Subroutine
+        } else if matches!(next, Symbol(Symbols::Period(_))) && !self.symbol_table.contains(&name) {
+            Category::Class
+        } else if self.constants.contains(&name) && !self.symbol_table.contains(&name) {
+            Category::Constant
+        } else {
+            Category::Variable
+        };
+        self.write_identifier(category, false)
+    }
+
+    // writes int, char, boolean, void or a class name, and returns it
+    fn write_type(&mut self) -> Result<String, CompileError> {
+        match self.tokenizer.token_type() {
+            Identifier(name) => {
+                self.write_identifier(Category::Class, false)?;
+                Ok(name)
+            }
+            _ => {
+                let keyword = self.tokenizer.keyword();
+                self.write_token_and_advance()?;
+                Ok(keyword)
+            }
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self.tokenizer.token_type(), Identifier(current) if current == word)
+    }
+
+    // an error at the current token
+    fn error(&self, message: String) -> CompileError {
+        let token = self.tokenizer.token_index;
+        CompileError {
+            class: self.class_name.clone(),
+            message,
+            token: Some(token),
+            location: self.tokenizer.location(token),
+            file: None,
         }
     }
 
+    fn is_class_var_dec_token(token: Token) -> bool {
+        matches!(token, Keyword(Keywords::Static) | Keyword(Keywords::Field))
+    }
+
     fn is_subroutine_dec_token(token: Token) -> bool {
-        match token {
+        matches!(
+            token,
             Keyword(Keywords::Constructor)
-            | Keyword(Keywords::Function)
-            | Keyword(Keywords::Method) => true,
-            _ => false,
-        }
+                | Keyword(Keywords::Function)
+                | Keyword(Keywords::Method)
+        )
     }
 
     fn is_comma(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::Comma(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::Comma(_)))
     }
 
     fn is_left_paran(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::LParen(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::LParen(_)))
     }
 
     fn is_right_paran(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::RParen(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::RParen(_)))
     }
 
     fn is_semicolon(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::Semicolon(_)) => true,
-            _ => false,
-        }
-    }
-
-    fn is_statement(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Let)
-            | Keyword(Keywords::If)
-            | Keyword(Keywords::While)
-            | Keyword(Keywords::Do)
-            | Keyword(Keywords::Return) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::Semicolon(_)))
     }
 
     fn is_right_curly(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::RCurly(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::RCurly(_)))
     }
 
     fn is_left_square(token: Token) -> bool {
-        match token {
-            Symbol(Symbols::LSquare(_)) => true,
-            _ => false,
-        }
+        matches!(token, Symbol(Symbols::LSquare(_)))
     }
 
     fn is_op_token(token: Token) -> bool {
-        match token {
+        matches!(
+            token,
             Symbol(Symbols::Plus(_))
-            | Symbol(Symbols::Minus(_))
-            | Symbol(Symbols::Mult(_))
-            | Symbol(Symbols::Div(_))
-            | Symbol(Symbols::And(_))
-            | Symbol(Symbols::Or(_))
-            | Symbol(Symbols::Greater(_))
-            | Symbol(Symbols::Less(_))
-            | Symbol(Symbols::Eq(_)) => true,
-            _ => false,
-        }
+                | Symbol(Symbols::Minus(_))
+                | Symbol(Symbols::Mult(_))
+                | Symbol(Symbols::Div(_))
+                | Symbol(Symbols::And(_))
+                | Symbol(Symbols::Or(_))
+                | Symbol(Symbols::Greater(_))
+                | Symbol(Symbols::Less(_))
+                | Symbol(Symbols::Eq(_))
+        )
     }
 
     fn is_else(token: Token) -> bool {
-        match token {
-            Keyword(Keywords::Else) => true,
-            _ => false,
-        }
+        matches!(token, Keyword(Keywords::Else))
     }
 }
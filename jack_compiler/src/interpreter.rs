@@ -0,0 +1,584 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::ast::{
+    BinaryOp, Class, Expression, KeywordConstant, Statement, SubroutineCall, SubroutineKind, Type,
+    UnaryOp,
+};
+use crate::jack_tokenizer::JackTokenizer;
+use crate::native_os::NativeOs;
+use crate::parser::{CompileError, Parser};
+use crate::symbol_table::Kind;
+
+pub const RAM_SIZE: usize = 32768;
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    // `Class.subroutine` of each active call, outermost first
+    pub call_stack: Vec<String>,
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.call_stack.last() {
+            Some(function) => write!(f, "{}: {}", function, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// How a run ended without an error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Exit {
+    // Sys.halt, or Main.main returned to Sys.init
+    Halted,
+    // the step limit was reached first
+    StepLimit,
+}
+
+// Why evaluation stopped unwinding the Rust stack.
+pub(crate) enum Interrupt {
+    Halt,
+    StepLimit,
+    Error(String),
+}
+
+pub(crate) type Eval<T> = Result<T, Interrupt>;
+
+pub(crate) fn error<T>(message: String) -> Eval<T> {
+    Err(Interrupt::Error(message))
+}
+
+// Where execution goes after a statement.
+enum Flow {
+    Next,
+    Return(i16),
+    Break,
+    Continue,
+}
+
+// A variable as the code generator would address it.
+#[derive(Clone)]
+struct Variable {
+    kind: Kind,
+    index: usize,
+    var_type: Type,
+}
+
+struct ClassInfo {
+    class: Rc<Class>,
+    statics: HashMap<String, Variable>,
+    fields: HashMap<String, Variable>,
+    field_count: usize,
+    // subroutine name -> index in `class.subroutines` and its arguments and locals
+    subroutines: HashMap<String, (usize, Rc<HashMap<String, Variable>>)>,
+}
+
+struct Frame {
+    class: usize,
+    // None in functions
+    this: Option<i16>,
+    args: Vec<i16>,
+    locals: Vec<i16>,
+    scope: Rc<HashMap<String, Variable>>,
+}
+
+/// Runs Jack classes by walking their syntax trees, without compiling them
+/// to VM code. Objects, arrays and strings live in a Hack-sized RAM just as
+/// they would on the Hack computer, so `Memory.peek` and the screen memory
+/// map behave the same. OS classes that are not among the loaded classes
+/// are provided natively (see `native_os`), and the screen is a headless
+/// buffer at `native_os::SCREEN` that can be inspected after a run.
+pub struct Interpreter {
+    classes: Vec<ClassInfo>,
+    class_index: HashMap<String, usize>,
+    statics: Vec<Vec<i16>>,
+    frames: Vec<Frame>,
+    call_stack: Vec<String>,
+    pub(crate) ram: Vec<i16>,
+    pub(crate) os: NativeOs,
+    steps: usize,
+    max_steps: usize,
+}
+
+impl Interpreter {
+    pub fn new(classes: Vec<Class>) -> Self {
+        let mut interpreter = Interpreter {
+            classes: Vec::new(),
+            class_index: HashMap::new(),
+            statics: Vec::new(),
+            frames: Vec::new(),
+            call_stack: Vec::new(),
+            ram: vec![0; RAM_SIZE],
+            os: NativeOs::new(),
+            steps: 0,
+            max_steps: usize::MAX,
+        };
+        for class in classes {
+            interpreter.add_class(class);
+        }
+        interpreter
+    }
+
+    /// Parses the source of each class, e.g. the `.jack` files of a
+    /// project directory.
+    pub fn from_sources(jack_codes: &[&str]) -> Result<Self, CompileError> {
+        let classes = jack_codes
+            .iter()
+            .map(|jack_code| Parser::new(JackTokenizer::from_code(jack_code)?).parse_class())
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(classes))
+    }
+
+    fn add_class(&mut self, class: Class) {
+        let mut statics = HashMap::new();
+        let mut fields = HashMap::new();
+        for class_var_dec in &class.class_var_decs {
+            let table = match class_var_dec.kind {
+                Kind::STATIC => &mut statics,
+                _ => &mut fields,
+            };
+            for name in &class_var_dec.names {
+                let variable = Variable {
+                    kind: class_var_dec.kind,
+                    index: table.len(),
+                    var_type: class_var_dec.var_type.clone(),
+                };
+                table.insert(name.clone(), variable);
+            }
+        }
+
+        let mut subroutines = HashMap::new();
+        for (index, subroutine) in class.subroutines.iter().enumerate() {
+            let mut scope = HashMap::new();
+            let mut arg_count = 0;
+            if subroutine.kind == SubroutineKind::Method {
+                arg_count = 1;
+            }
+            for parameter in &subroutine.parameters {
+                let variable = Variable {
+                    kind: Kind::ARG,
+                    index: arg_count,
+                    var_type: parameter.var_type.clone(),
+                };
+                scope.insert(parameter.name.clone(), variable);
+                arg_count += 1;
+            }
+            let mut local_count = 0;
+            for var_dec in &subroutine.var_decs {
+                for name in &var_dec.names {
+                    let variable = Variable {
+                        kind: Kind::VAR,
+                        index: local_count,
+                        var_type: var_dec.var_type.clone(),
+                    };
+                    scope.insert(name.clone(), variable);
+                    local_count += 1;
+                }
+            }
+            subroutines.insert(subroutine.name.clone(), (index, Rc::new(scope)));
+        }
+
+        self.class_index
+            .insert(class.name.clone(), self.classes.len());
+        self.statics.push(vec![0; statics.len()]);
+        self.classes.push(ClassInfo {
+            field_count: fields.len(),
+            class: Rc::new(class),
+            statics,
+            fields,
+            subroutines,
+        });
+    }
+
+    /// Runs the program from `Sys.init`, which calls `Main.main` unless a
+    /// loaded Sys class says otherwise, for at most `max_steps` statements.
+    pub fn run(&mut self, max_steps: usize) -> Result<Exit, RuntimeError> {
+        self.max_steps = self.steps.saturating_add(max_steps);
+        let result = self.call_function("Sys", "init", Vec::new());
+        self.max_steps = usize::MAX;
+        let exit = match result {
+            Ok(_) | Err(Interrupt::Halt) => Exit::Halted,
+            Err(Interrupt::StepLimit) => Exit::StepLimit,
+            Err(Interrupt::Error(message)) => return Err(self.runtime_error(message)),
+        };
+        self.frames.clear();
+        self.call_stack.clear();
+        Ok(exit)
+    }
+
+    /// Calls one function, e.g. `call("Math.multiply", &[6, 7])`, which is
+    /// handy for testing a class without a Main.
+    pub fn call(&mut self, function: &str, args: &[i16]) -> Result<i16, RuntimeError> {
+        let (class, name) = function.split_once('.').ok_or_else(|| RuntimeError {
+            call_stack: Vec::new(),
+            message: format!("{} is not of the form Class.function", function),
+        })?;
+        match self.call_function(class, name, args.to_vec()) {
+            Ok(value) => Ok(value),
+            Err(Interrupt::Halt) => Err(self.runtime_error("Sys.halt was called".to_string())),
+            Err(Interrupt::StepLimit) => {
+                Err(self.runtime_error(format!("stopped after {} steps", self.steps)))
+            }
+            Err(Interrupt::Error(message)) => Err(self.runtime_error(message)),
+        }
+    }
+
+    fn runtime_error(&mut self, message: String) -> RuntimeError {
+        self.frames.clear();
+        RuntimeError {
+            call_stack: std::mem::take(&mut self.call_stack),
+            message,
+        }
+    }
+
+    /// Statements executed so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    pub fn peek(&self, address: usize) -> i16 {
+        self.ram[address]
+    }
+
+    pub fn poke(&mut self, address: usize, value: i16) {
+        self.ram[address] = value;
+    }
+
+    fn is_loaded(&self, class: &str, name: &str) -> bool {
+        self.class_index
+            .get(class)
+            .is_some_and(|index| self.classes[*index].subroutines.contains_key(name))
+    }
+
+    // Loaded classes come first, so a class of one's own OS replaces the
+    // native one.
+    pub(crate) fn call_function(&mut self, class: &str, name: &str, args: Vec<i16>) -> Eval<i16> {
+        if !self.is_loaded(class, name) {
+            self.call_stack.push(format!("{}.{}", class, name));
+            let result = match self.call_native(class, name, &args) {
+                Some(result) => result?,
+                None => return error(format!("{}.{} is not defined", class, name)),
+            };
+            self.call_stack.pop();
+            return Ok(result);
+        }
+
+        let class_id = self.class_index[class];
+        let info = &self.classes[class_id];
+        let (index, scope) = info.subroutines[name].clone();
+        let class_rc = info.class.clone();
+        let field_count = info.field_count;
+        let subroutine = &class_rc.subroutines[index];
+
+        let expected = scope.values().filter(|var| var.kind == Kind::ARG).count()
+            + (subroutine.kind == SubroutineKind::Method) as usize;
+        if args.len() != expected {
+            return error(format!(
+                "{}.{} takes {} arguments but was called with {}",
+                class,
+                name,
+                expected,
+                args.len()
+            ));
+        }
+
+        self.call_stack.push(format!("{}.{}", class, name));
+        let this = match subroutine.kind {
+            SubroutineKind::Constructor => {
+                Some(self.call_function("Memory", "alloc", vec![field_count.max(1) as i16])?)
+            }
+            SubroutineKind::Method => Some(args[0]),
+            SubroutineKind::Function => None,
+        };
+        let local_count = scope.values().filter(|var| var.kind == Kind::VAR).count();
+        self.frames.push(Frame {
+            class: class_id,
+            this,
+            args,
+            locals: vec![0; local_count],
+            scope,
+        });
+
+        // a subroutine that falls off its end returns 0
+        let result = match self.execute_statements(&subroutine.statements)? {
+            Flow::Return(value) => value,
+            _ => 0,
+        };
+
+        self.frames.pop();
+        self.call_stack.pop();
+        Ok(result)
+    }
+
+    fn execute_statements(&mut self, statements: &[Statement]) -> Eval<Flow> {
+        for statement in statements {
+            match self.execute(statement)? {
+                Flow::Next => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn step(&mut self) -> Eval<()> {
+        if self.steps == self.max_steps {
+            return Err(Interrupt::StepLimit);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Eval<Flow> {
+        self.step()?;
+
+        match statement {
+            Statement::Let {
+                name,
+                index,
+                op,
+                value,
+            } => match index {
+                Some(index) => {
+                    let address = self.variable(name)?.wrapping_add(self.evaluate(index)?);
+                    let value = match op {
+                        Some(op) => {
+                            let old = self.load(address)?;
+                            let value = self.evaluate(value)?;
+                            self.binary(*op, old, value)?
+                        }
+                        None => self.evaluate(value)?,
+                    };
+                    self.store(address, value)?;
+                }
+                None => {
+                    let value = match op {
+                        Some(op) => {
+                            let old = self.variable(name)?;
+                            let value = self.evaluate(value)?;
+                            self.binary(*op, old, value)?
+                        }
+                        None => self.evaluate(value)?,
+                    };
+                    self.set_variable(name, value)?;
+                }
+            },
+            Statement::If {
+                condition,
+                statements,
+                else_statements,
+            } => {
+                if self.evaluate(condition)? != 0 {
+                    return self.execute_statements(statements);
+                } else if let Some(else_statements) = else_statements {
+                    return self.execute_statements(else_statements);
+                }
+            }
+            Statement::While {
+                condition,
+                statements,
+            } => {
+                while self.evaluate(condition)? != 0 {
+                    match self.execute_statements(statements)? {
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Break => break,
+                        Flow::Next | Flow::Continue => (),
+                    }
+                    self.step()?;
+                }
+            }
+            Statement::For {
+                init,
+                condition,
+                update,
+                statements,
+            } => {
+                if let Some(init) = init {
+                    self.execute(init)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if self.evaluate(condition)? == 0 {
+                            break;
+                        }
+                    }
+                    match self.execute_statements(statements)? {
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Break => break,
+                        Flow::Next | Flow::Continue => (),
+                    }
+                    if let Some(update) = update {
+                        self.execute(update)?;
+                    }
+                    self.step()?;
+                }
+            }
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Do(call) => {
+                self.call_subroutine(call)?;
+            }
+            Statement::Return(value) => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => 0,
+                };
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Eval<i16> {
+        match expression {
+            Expression::IntegerConstant(value) => Ok(*value as i16),
+            Expression::StringConstant(text) => {
+                let string = self.call_function("String", "new", vec![text.len() as i16])?;
+                for c in text.chars() {
+                    self.call_function("String", "appendChar", vec![string, c as i16])?;
+                }
+                Ok(string)
+            }
+            Expression::KeywordConstant(keyword) => match keyword {
+                KeywordConstant::True => Ok(-1),
+                KeywordConstant::False | KeywordConstant::Null => Ok(0),
+                KeywordConstant::This => self.this(),
+            },
+            Expression::Var(name) => self.variable(name),
+            Expression::ArrayAccess(name, index) => {
+                let address = self.variable(name)?.wrapping_add(self.evaluate(index)?);
+                self.load(address)
+            }
+            Expression::Call(call) => self.call_subroutine(call),
+            Expression::Parenthesized(inner) => self.evaluate(inner),
+            Expression::Unary(op, operand) => {
+                let value = self.evaluate(operand)?;
+                Ok(match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => !value,
+                })
+            }
+            Expression::Binary(left, op, right) => {
+                let (left, right) = (self.evaluate(left)?, self.evaluate(right)?);
+                self.binary(*op, left, right)
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: i16, right: i16) -> Eval<i16> {
+        let truth = |value: bool| if value { -1 } else { 0 };
+        match op {
+            BinaryOp::Add => Ok(left.wrapping_add(right)),
+            BinaryOp::Sub => Ok(left.wrapping_sub(right)),
+            BinaryOp::Mul => self.call_function("Math", "multiply", vec![left, right]),
+            BinaryOp::Div => self.call_function("Math", "divide", vec![left, right]),
+            BinaryOp::And => Ok(left & right),
+            BinaryOp::Or => Ok(left | right),
+            BinaryOp::Lt => Ok(truth(left < right)),
+            BinaryOp::Gt => Ok(truth(left > right)),
+            BinaryOp::Eq => Ok(truth(left == right)),
+        }
+    }
+
+    // foo(..) calls a method of this class, var.foo(..) a method of the
+    // variable's class and Class.foo(..) a function or constructor, as in
+    // the code generator.
+    fn call_subroutine(&mut self, call: &SubroutineCall) -> Eval<i16> {
+        let mut args = Vec::new();
+        let class = match &call.receiver {
+            None => {
+                args.push(self.this()?);
+                self.classes[self.frame().class].class.name.clone()
+            }
+            Some(receiver) => match self.lookup(receiver) {
+                Some(variable) => {
+                    args.push(self.variable(receiver)?);
+                    variable.var_type.to_string()
+                }
+                None => receiver.clone(),
+            },
+        };
+        for argument in &call.arguments {
+            args.push(self.evaluate(argument)?);
+        }
+        self.call_function(&class, &call.name, args)
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("no active subroutine")
+    }
+
+    fn lookup(&self, name: &str) -> Option<Variable> {
+        let frame = self.frame();
+        let class = &self.classes[frame.class];
+        frame
+            .scope
+            .get(name)
+            .or_else(|| class.fields.get(name))
+            .or_else(|| class.statics.get(name))
+            .cloned()
+    }
+
+    fn this(&self) -> Eval<i16> {
+        match self.frame().this {
+            Some(this) => Ok(this),
+            None => error("a function has no `this`".to_string()),
+        }
+    }
+
+    fn variable(&self, name: &str) -> Eval<i16> {
+        let variable = match self.lookup(name) {
+            Some(variable) => variable,
+            None => return error(format!("undefined variable {}", name)),
+        };
+        let frame = self.frame();
+        match variable.kind {
+            Kind::ARG => Ok(frame.args[variable.index]),
+            Kind::VAR => Ok(frame.locals[variable.index]),
+            Kind::STATIC => Ok(self.statics[frame.class][variable.index]),
+            Kind::FIELD => {
+                let this = self.this()?;
+                self.load(this.wrapping_add(variable.index as i16))
+            }
+        }
+    }
+
+    fn set_variable(&mut self, name: &str, value: i16) -> Eval<()> {
+        let variable = match self.lookup(name) {
+            Some(variable) => variable,
+            None => return error(format!("undefined variable {}", name)),
+        };
+        let class = self.frame().class;
+        match variable.kind {
+            Kind::ARG => self.frames.last_mut().unwrap().args[variable.index] = value,
+            Kind::VAR => self.frames.last_mut().unwrap().locals[variable.index] = value,
+            Kind::STATIC => self.statics[class][variable.index] = value,
+            Kind::FIELD => {
+                let this = self.this()?;
+                self.store(this.wrapping_add(variable.index as i16), value)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn load(&self, address: i16) -> Eval<i16> {
+        match self.ram.get(address as u16 as usize) {
+            Some(value) if address >= 0 => Ok(*value),
+            _ => error(format!("address {} is out of range", address)),
+        }
+    }
+
+    pub(crate) fn store(&mut self, address: i16, value: i16) -> Eval<()> {
+        if address < 0 {
+            return error(format!("address {} is out of range", address));
+        }
+        self.ram[address as usize] = value;
+        Ok(())
+    }
+}
@@ -1,11 +1,18 @@
 use std::{
+    collections::HashMap,
     fs::{self, File},
     io::{self, Write},
 };
 
+use crate::parser::CompileError;
+
 pub struct JackTokenizer {
     pub tokens: Vec<Token>,
     pub token_index: usize,
+    // where each token starts; empty for tokens not read from code
+    locations: Vec<Location>,
+    // API doc comments by the index of the token they come before
+    doc_comments: HashMap<usize, String>,
 }
 
 const SYMBOLS: [char; 19] = [
@@ -36,7 +43,7 @@ const KEYWORDS: [&str; 21] = [
     "return",
 ];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Keywords {
     Class,
     Constructor,
@@ -91,20 +98,334 @@ pub enum Token {
     IntegerConstant(usize),
     StringConstant(String),
     Identifier(String),
+    // the extended dialect's literals: 'A', and 0x1F or 0b1010 with the
+    // text as written
+    CharConstant(char),
+    RadixConstant { text: String, value: usize },
+}
+
+/// A token, or source text the parser skips but tools like the formatter
+/// keep.
+#[derive(Debug, Clone)]
+pub enum Lexeme {
+    Token(Token),
+    // `// ...` or `/* ... */` including the delimiters; `trailing` if code
+    // comes before it on the same line
+    Comment { text: String, trailing: bool },
+    // a line with nothing but whitespace
+    BlankLine,
+}
+
+/// A position in the source, counting lines and columns (in characters)
+/// from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+// Reads the source one character at a time, keeping track of where it is.
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    location: Location,
+    lexemes: Vec<(Lexeme, Location)>,
+    // whether a token, or any part of a comment, is on the current line
+    line_has_code: bool,
+    line_has_comment: bool,
+}
+
+impl Lexer {
+    fn new(jack_code: &str) -> Self {
+        Lexer {
+            chars: jack_code.chars().collect(),
+            position: 0,
+            location: Location::default(),
+            lexemes: Vec::new(),
+            line_has_code: false,
+            line_has_comment: false,
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            if !self.line_has_code && !self.line_has_comment {
+                let start = Location {
+                    line: self.location.line,
+                    column: 0,
+                };
+                self.lexemes.push((Lexeme::BlankLine, start));
+            }
+            self.location.line += 1;
+            self.location.column = 0;
+            self.line_has_code = false;
+            self.line_has_comment = false;
+        } else {
+            self.location.column += 1;
+        }
+        Some(c)
+    }
+
+    fn push_token(&mut self, token: Token, start: Location) {
+        self.lexemes.push((Lexeme::Token(token), start));
+        self.line_has_code = true;
+    }
+
+    fn lex(&mut self) -> Result<(), (String, Location)> {
+        while let Some(c) = self.peek(0) {
+            let start = self.location;
+            if c.is_whitespace() {
+                self.next();
+            } else if c == '/' && self.peek(1) == Some('/') {
+                self.line_comment();
+            } else if c == '/' && self.peek(1) == Some('*') {
+                self.block_comment()?;
+            } else if c == '"' {
+                self.next();
+                let mut string = String::new();
+                loop {
+                    match self.peek(0) {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(("unterminated string constant".to_string(), start))
+                        }
+                        Some(c) => string.push(c),
+                    }
+                    self.next();
+                }
+                self.next();
+                self.push_token(Token::StringConstant(string), start);
+            } else if c == '\'' {
+                self.next();
+                let token = match (self.next(), self.next()) {
+                    (Some(c), Some('\'')) if c.is_ascii() && !c.is_ascii_control() => {
+                        Token::CharConstant(c)
+                    }
+                    (Some(c), Some('\'')) if c != '\n' => {
+                        let message = format!("character '{}' is not in the Hack character set", c);
+                        return Err((message, start));
+                    }
+                    (Some(c), Some(next)) if c != '\n' && next != '\n' => {
+                        let message = "a character literal holds one character".to_string();
+                        return Err((message, start));
+                    }
+                    _ => return Err(("unterminated character literal".to_string(), start)),
+                };
+                self.push_token(token, start);
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .peek(0)
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    word.push(c);
+                    self.next();
+                }
+                let radix = match word.get(..2) {
+                    Some("0x" | "0X") => Some(16),
+                    Some("0b" | "0B") => Some(2),
+                    _ => None,
+                };
+                let token = if let Some(radix) = radix {
+                    match usize::from_str_radix(&word[2..], radix) {
+                        Ok(value) => Token::RadixConstant { text: word, value },
+                        Err(_) => {
+                            return Err((format!("invalid integer constant {}", word), start))
+                        }
+                    }
+                } else if c.is_ascii_digit() {
+                    match word.parse() {
+                        Ok(value) => Token::IntegerConstant(value),
+                        Err(_) => {
+                            return Err((format!("invalid integer constant {}", word), start))
+                        }
+                    }
+                } else if JackTokenizer::is_keyword(&word) {
+                    JackTokenizer::make_keyword_token(&word)
+                } else {
+                    Token::Identifier(word)
+                };
+                self.push_token(token, start);
+            } else if JackTokenizer::is_symbol(c) {
+                self.next();
+                self.push_token(JackTokenizer::make_symbol_token(c), start);
+            } else {
+                return Err((format!("unexpected character '{}'", c), start));
+            }
+        }
+        // a last line without a newline
+        if self.location.column > 0 && !self.line_has_code && !self.line_has_comment {
+            let start = Location {
+                line: self.location.line,
+                column: 0,
+            };
+            self.lexemes.push((Lexeme::BlankLine, start));
+        }
+        Ok(())
+    }
+
+    fn line_comment(&mut self) {
+        let start = self.location;
+        let mut text = String::new();
+        while let Some(c) = self.peek(0).filter(|c| *c != '\n') {
+            text.push(c);
+            self.next();
+        }
+        let comment = Lexeme::Comment {
+            text: text.trim_end().to_string(),
+            trailing: self.line_has_code,
+        };
+        self.lexemes.push((comment, start));
+        self.line_has_comment = true;
+    }
+
+    fn block_comment(&mut self) -> Result<(), (String, Location)> {
+        let start = self.location;
+        let trailing = self.line_has_code;
+        let mut text = String::new();
+        self.line_has_comment = true;
+        self.next();
+        self.next();
+        text.push_str("/*");
+        loop {
+            match self.peek(0) {
+                Some('*') if self.peek(1) == Some('/') => break,
+                None => return Err(("unterminated comment".to_string(), start)),
+                Some(c) => {
+                    if c != '\r' {
+                        text.push(c);
+                    }
+                    self.next();
+                    // the comment goes on on the next line
+                    self.line_has_comment = true;
+                }
+            }
+        }
+        self.next();
+        self.next();
+        text.push_str("*/");
+        let comment = Lexeme::Comment { text, trailing };
+        self.lexemes.push((comment, start));
+        Ok(())
+    }
+}
+
+// the name after `class` at the start of the code, for errors found before
+// the parser knows it
+fn class_name(lexemes: &[(Lexeme, Location)]) -> String {
+    let mut tokens = lexemes.iter().filter_map(|(lexeme, _)| match lexeme {
+        Lexeme::Token(token) => Some(token),
+        _ => None,
+    });
+    match (tokens.next(), tokens.next()) {
+        (Some(Token::Keyword(Keywords::Class)), Some(Token::Identifier(name))) => name.clone(),
+        _ => String::new(),
+    }
 }
 
 impl JackTokenizer {
     pub fn new(file_name: &str) -> Result<Self, io::Error> {
         let jack_code = fs::read_to_string(file_name)?;
-        let jack_code_without_comments = JackTokenizer::remove_comments(&jack_code);
+        JackTokenizer::from_code(&jack_code)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
 
-        let tokens = JackTokenizer::split_to_tokens(&jack_code_without_comments);
+    pub fn from_code(jack_code: &str) -> Result<Self, CompileError> {
+        let mut tokens = Vec::new();
+        let mut locations = Vec::new();
+        let mut doc_comments = HashMap::new();
+        let mut doc_comment = None;
+        for (lexeme, location) in JackTokenizer::located_lexemes(jack_code)? {
+            match lexeme {
+                Lexeme::Token(token) => {
+                    if let Some(doc_comment) = doc_comment.take() {
+                        doc_comments.insert(tokens.len(), doc_comment);
+                    }
+                    tokens.push(token);
+                    locations.push(location);
+                }
+                Lexeme::Comment { text, .. } if text.starts_with("/**") => doc_comment = Some(text),
+                _ => {}
+            }
+        }
         Ok(JackTokenizer {
             tokens,
             token_index: 0,
+            locations,
+            doc_comments,
         })
     }
 
+    /// Splits the code into tokens, comments and blank lines, in order.
+    pub fn lexemes(jack_code: &str) -> Result<Vec<Lexeme>, CompileError> {
+        Ok(JackTokenizer::located_lexemes(jack_code)?
+            .into_iter()
+            .map(|(lexeme, _)| lexeme)
+            .collect())
+    }
+
+    /// The lexemes with where each one starts.
+    pub fn located_lexemes(jack_code: &str) -> Result<Vec<(Lexeme, Location)>, CompileError> {
+        match JackTokenizer::lex(jack_code) {
+            (lexemes, None) => Ok(lexemes),
+            (_, Some(error)) => Err(error),
+        }
+    }
+
+    /// The lexemes up to the first error in the code, and the error, for
+    /// tools that still want to use the part of a file that can be read.
+    pub fn lex(jack_code: &str) -> (Vec<(Lexeme, Location)>, Option<CompileError>) {
+        let mut lexer = Lexer::new(jack_code);
+        let error = lexer.lex().err().map(|(message, location)| CompileError {
+            class: class_name(&lexer.lexemes),
+            message,
+            token: None,
+            location: Some(location),
+            file: None,
+        });
+        (lexer.lexemes, error)
+    }
+
+    /// The tokens of the code with where each one starts, the same tokens
+    /// the parser would see.
+    pub fn located_tokens(jack_code: &str) -> Result<Vec<(Token, Location)>, CompileError> {
+        Ok(JackTokenizer::located_lexemes(jack_code)?
+            .into_iter()
+            .filter_map(|(lexeme, location)| match lexeme {
+                Lexeme::Token(token) => Some((token, location)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    pub fn from_tokens(tokens: Vec<Token>) -> Self {
+        JackTokenizer {
+            tokens,
+            token_index: 0,
+            locations: Vec::new(),
+            doc_comments: HashMap::new(),
+        }
+    }
+
+    /// Where the token at `token_index` starts, if the tokens came from
+    /// source code.
+    pub fn location(&self, token_index: usize) -> Option<Location> {
+        self.locations.get(token_index).copied()
+    }
+
+    /// The `/** ... */` comment right before the token at `token_index`.
+    pub fn doc_comment(&self, token_index: usize) -> Option<&str> {
+        self.doc_comments
+            .get(&token_index)
+            .map(|text| text.as_str())
+    }
+
     pub fn create_token_xml_file(&self, file_name: &str) -> Result<(), io::Error> {
         let file_name = file_name.replace(".jack", "") + "_token.xml";
         let mut file = File::create(file_name)?;
@@ -126,7 +447,11 @@ impl JackTokenizer {
                 }
                 Token::StringConstant(s) => {
                     file.write_all(
-                        format!("<stringConstant> {} </stringConstant>\n", s).as_bytes(),
+                        format!(
+                            "<stringConstant> {} </stringConstant>\n",
+                            JackTokenizer::escape_xml(s)
+                        )
+                        .as_bytes(),
                     )?;
                 }
                 Token::Identifier(var_name) => {
@@ -134,6 +459,12 @@ impl JackTokenizer {
                         format!("<identifier> {} </identifier>\n", var_name).as_bytes(),
                     )?;
                 }
+                Token::CharConstant(_) | Token::RadixConstant { .. } => {
+                    let value = JackTokenizer::integer_value(token);
+                    file.write_all(
+                        format!("<integerConstant> {} </integerConstant>\n", value).as_bytes(),
+                    )?;
+                }
             }
         }
         file.write_all("</tokens>\n".as_bytes())?;
@@ -159,7 +490,7 @@ impl JackTokenizer {
             _ => &Keywords::Class,
         };
 
-        JackTokenizer::keywords_to_string(&keyword)
+        JackTokenizer::keywords_to_string(keyword)
     }
 
     pub fn symbol(&self) -> String {
@@ -169,7 +500,7 @@ impl JackTokenizer {
             _ => &Symbols::And('&'),
         };
 
-        JackTokenizer::symbols_to_string(&symbol)
+        JackTokenizer::symbols_to_string(symbol)
     }
 
     pub fn identifier(&self) -> String {
@@ -181,9 +512,14 @@ impl JackTokenizer {
     }
 
     pub fn int_val(&self) -> usize {
-        match self.tokens[self.token_index] {
-            Token::IntegerConstant(val) => val,
-            // this pattern match never used
+        JackTokenizer::integer_value(&self.tokens[self.token_index])
+    }
+
+    /// The value of any integer or character literal, 0 for other tokens.
+    pub fn integer_value(token: &Token) -> usize {
+        match token {
+            Token::IntegerConstant(value) | Token::RadixConstant { value, .. } => *value,
+            Token::CharConstant(c) => *c as usize,
             _ => 0,
         }
     }
@@ -200,8 +536,8 @@ impl JackTokenizer {
         SYMBOLS.contains(&c)
     }
 
-    fn is_keyword(str: &String) -> bool {
-        KEYWORDS.contains(&str.as_str())
+    fn is_keyword(word: &str) -> bool {
+        KEYWORDS.contains(&word)
     }
 
     fn make_symbol_token(symbol: char) -> Token {
@@ -280,6 +616,48 @@ impl JackTokenizer {
         }
     }
 
+    /// String constants may hold characters that are markup in XML.
+    pub fn escape_xml(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    /// The text of a `/** ... */` comment without the delimiters and the
+    /// `*`s starting its lines.
+    pub fn doc_text(comment: &str) -> String {
+        let text = comment.trim_start_matches("/**").trim_end_matches("*/");
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim().trim_start_matches('*').trim())
+            .collect();
+        lines.join("\n").trim().to_string()
+    }
+
+    pub fn symbol_char(symbol: &Symbols) -> char {
+        match symbol {
+            Symbols::LCurly(c)
+            | Symbols::RCurly(c)
+            | Symbols::RParen(c)
+            | Symbols::LParen(c)
+            | Symbols::RSquare(c)
+            | Symbols::LSquare(c)
+            | Symbols::Period(c)
+            | Symbols::Comma(c)
+            | Symbols::Semicolon(c)
+            | Symbols::Plus(c)
+            | Symbols::Minus(c)
+            | Symbols::Mult(c)
+            | Symbols::Div(c)
+            | Symbols::And(c)
+            | Symbols::Or(c)
+            | Symbols::Less(c)
+            | Symbols::Greater(c)
+            | Symbols::Eq(c)
+            | Symbols::Not(c) => *c,
+        }
+    }
+
     pub fn keywords_to_string(keyword: &Keywords) -> String {
         match keyword {
             Keywords::Class => "class".to_string(),
@@ -305,127 +683,4 @@ impl JackTokenizer {
             Keywords::Return => "return".to_string(),
         }
     }
-
-    fn remove_comments(jack_code: &String) -> String {
-        // start with "//" is inline comment
-        let mut is_in_inline_comment = false;
-        // start with "/**" and end with "*/" is block comment
-        let mut is_in_block_comment = false;
-
-        let mut jack_code_without_comments = String::from("");
-
-        let mut jack_code = jack_code.chars().peekable();
-
-        while jack_code.peek() != None {
-            let mut c = jack_code.next().unwrap();
-
-            if c == '/' && jack_code.peek() == Some(&'/') {
-                is_in_inline_comment = true;
-            }
-
-            if c == '/' && jack_code.peek() == Some(&'*') {
-                is_in_block_comment = true;
-            }
-
-            if c == '\n' {
-                is_in_inline_comment = false;
-            }
-
-            if c == '*' && jack_code.peek() == Some(&'/') && is_in_block_comment {
-                jack_code.next();
-                c = jack_code.next().unwrap();
-                is_in_block_comment = false;
-            }
-
-            if !(is_in_inline_comment || is_in_block_comment) {
-                jack_code_without_comments.push(c);
-            }
-        }
-
-        jack_code_without_comments.trim().to_string()
-    }
-
-    fn split_to_tokens(jack_code: &String) -> Vec<Token> {
-        let mut in_string = false;
-        let mut jack_code = jack_code.chars().peekable();
-        let mut tokens = Vec::new();
-        let mut token = "".to_string();
-
-        while jack_code.peek() != None {
-            let c = jack_code.next().unwrap();
-
-            if (c == ' ' || c == '\n') && !in_string {
-                continue;
-            }
-
-            if c == '"' {
-                in_string = !in_string;
-                continue;
-            }
-
-            if JackTokenizer::is_symbol(c) && !in_string {
-                tokens.push(JackTokenizer::make_symbol_token(c));
-                token = "".to_string();
-                continue;
-            }
-
-            token.push(c);
-
-            if jack_code.peek() == Some(&'"') && in_string {
-                in_string = !in_string;
-                tokens.push(Token::StringConstant(token));
-                jack_code.next();
-                token = "".to_string();
-                continue;
-            }
-
-            if JackTokenizer::is_symbol(*jack_code.peek().unwrap()) && !in_string {
-                token = token.trim().to_string();
-
-                if token == "" {
-                    continue;
-                }
-
-                if JackTokenizer::is_keyword(&token) {
-                    tokens.push(JackTokenizer::make_keyword_token(&token));
-                    token = "".to_string();
-                    continue;
-                }
-
-                if let Ok(int_value) = token.parse::<usize>() {
-                    tokens.push(Token::IntegerConstant(int_value));
-                    token = "".to_string();
-                    continue;
-                }
-
-                tokens.push(Token::Identifier(token));
-                token = "".to_string();
-                continue;
-            }
-
-            if (jack_code.peek() == Some(&' ') || jack_code.peek() == Some(&'\n')) && !in_string {
-                token = token.trim().to_string();
-
-                if token == "" {
-                    continue;
-                }
-                if JackTokenizer::is_keyword(&token) {
-                    tokens.push(JackTokenizer::make_keyword_token(&token));
-                    token = "".to_string();
-                    continue;
-                }
-
-                if let Ok(int_value) = token.parse::<usize>() {
-                    tokens.push(Token::IntegerConstant(int_value));
-                    token = "".to_string();
-                    continue;
-                }
-
-                tokens.push(Token::Identifier(token));
-
-                token = "".to_string();
-            }
-        }
-        tokens
-    }
 }
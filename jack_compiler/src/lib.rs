@@ -0,0 +1,45 @@
+pub mod ast;
+pub mod cache;
+pub mod code_generator;
+pub mod compilation_engine;
+pub mod interpreter;
+pub mod jack_tokenizer;
+pub mod native_os;
+pub mod parser;
+pub mod symbol_table;
+pub mod vm_writer;
+pub mod warnings;
+
+use ast::Class;
+use code_generator::CodeGenerator;
+use compilation_engine::CompilationEngine;
+use jack_tokenizer::JackTokenizer;
+use parser::{CompileError, ParseOptions, Parser};
+
+/// Compiles the source of one `.jack` class into VM code.
+pub fn compile(jack_code: &str) -> Result<String, CompileError> {
+    CodeGenerator::new().compile_class(&parse(jack_code)?)
+}
+
+/// The syntax tree of one `.jack` class, for compiling a whole program at
+/// once.
+pub fn parse(jack_code: &str) -> Result<Class, CompileError> {
+    parse_with(jack_code, ParseOptions::default())
+}
+
+pub fn parse_with(jack_code: &str, options: ParseOptions) -> Result<Class, CompileError> {
+    Parser::with_options(JackTokenizer::from_code(jack_code)?, options).parse_class()
+}
+
+/// The parse tree of one `.jack` class as XML, with each identifier tagged
+/// by what it names and its index in the symbol table, as the course
+/// suggests for testing a symbol table.
+pub fn annotated_xml(jack_code: &str) -> Result<String, CompileError> {
+    annotated_xml_with(jack_code, ParseOptions::default())
+}
+
+pub fn annotated_xml_with(jack_code: &str, options: ParseOptions) -> Result<String, CompileError> {
+    // the engine follows the tokens without checking them
+    parse_with(jack_code, options)?;
+    CompilationEngine::new(JackTokenizer::from_code(jack_code)?).compile_class()
+}
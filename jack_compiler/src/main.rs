@@ -1,54 +1,305 @@
-pub mod compilation_engine;
-pub mod jack_tokenizer;
-pub mod symbol_table;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process, thread,
+};
 
-use std::{env, fs, process};
+use jack_compiler::cache::{self, BuildCache};
+use jack_compiler::code_generator::CodeGenerator;
+use jack_compiler::parser::{Dialect, ParseOptions};
+use jack_compiler::warnings;
 
-use compilation_engine::CompilationEngine;
+const USAGE: &str = "usage: jack_compiler [--xml] [--strip] [--extended] [--precedence] \
+                     [--out-dir <dir>] <file.jack | dir>...";
+
+struct Args {
+    inputs: Vec<String>,
+    out_dir: Option<PathBuf>,
+    xml: bool,
+    strip: bool,
+    options: ParseOptions,
+}
+
+// a .jack file and where its output goes, without the extension
+struct Source {
+    path: PathBuf,
+    output: PathBuf,
+}
 
 fn main() {
-    let target_name = get_target();
-    let mut jack_files = Vec::new();
+    let args = get_args();
+    let sources = jack_sources(&args.inputs, args.out_dir.as_deref());
+
+    // --xml writes X_compile.xml, the parse tree with its identifiers
+    // annotated, instead of compiling
+    if args.xml {
+        for source in &sources {
+            let jack_code = read_source(source);
+            let xml =
+                jack_compiler::annotated_xml_with(&jack_code, args.options).unwrap_or_else(|err| {
+                    eprintln!("Error: {}", err.in_file(&source.path));
+                    process::exit(1);
+                });
+            write_output(&with_suffix(&source.output, "_compile.xml"), xml);
+        }
+        return;
+    }
+
+    // each directory is a program, and the files of one are compiled
+    // together to find the subroutines nothing calls
+    let mut programs: Vec<Vec<Source>> = Vec::new();
+    for source in sources {
+        match programs.last_mut() {
+            Some(program) if program[0].path.parent() == source.path.parent() => {
+                program.push(source)
+            }
+            _ => programs.push(vec![source]),
+        }
+    }
+    for program in &programs {
+        compile_program(program, &args);
+    }
+}
 
-    if is_file(&target_name) {
-        jack_files.push(target_name.clone())
-    } else {
-        jack_files = get_target_type_files(&target_name, "jack");
+fn compile_program(program: &[Source], args: &Args) {
+    let sources: Vec<String> = program.iter().map(read_source).collect();
+
+    // a class's VM code depends only on its source and the options, so
+    // those are checked before anything is parsed
+    let settings = format!(
+        "{} {:?} {}",
+        env!("CARGO_PKG_VERSION"),
+        args.options,
+        args.strip
+    );
+    let out_dir = program[0].output.parent().unwrap_or(Path::new(""));
+    let mut cache = BuildCache::load(out_dir);
+    let mut files = Vec::new();
+    for (source, jack_code) in program.iter().zip(&sources) {
+        let key = cache::content_hash(&[settings.as_bytes(), jack_code.as_bytes()]);
+        let file_name = source.path.file_name().unwrap_or_default();
+        let file_name = file_name.to_string_lossy().into_owned();
+        let vm_file = with_suffix(&source.output, ".vm");
+        let fresh = cache.is_fresh(&file_name, key) && vm_file.exists();
+        files.push((file_name, key, vm_file, fresh));
+    }
+    // nothing changed, so the warnings were given by the last build
+    if files.iter().all(|(_, _, _, fresh)| *fresh) {
+        return;
+    }
+
+    // the warnings and --strip look at the calls of the whole program, so
+    // all of its classes are parsed
+    let mut classes = Vec::new();
+    for (class, source) in in_parallel(&sources, |jack_code| {
+        jack_compiler::parse_with(jack_code, args.options)
+    })
+    .into_iter()
+    .zip(program)
+    {
+        classes.push(class.unwrap_or_else(|err| {
+            eprintln!("Error: {}", err.in_file(&source.path));
+            process::exit(1);
+        }));
+    }
+    for warning in warnings::program_warnings(&classes, &[]) {
+        eprintln!("{}", warning);
+    }
+    if args.strip {
+        warnings::remove_unreachable(&mut classes);
     }
 
-    for jack_file in jack_files {
-        let mut compilation_engine = CompilationEngine::new(&jack_file).unwrap_or_else(|_| {
-            eprintln!("{} does not exsit", jack_file);
+    // with --strip a change to one class can change which subroutines of
+    // the others are called, so they are all compiled again
+    let stale: Vec<_> = files
+        .iter()
+        .zip(&classes)
+        .zip(program)
+        .filter(|(((_, _, _, fresh), _), _)| args.strip || !fresh)
+        .map(|(((file_name, key, vm_file, _), class), source)| {
+            (file_name, *key, vm_file, class, source)
+        })
+        .collect();
+
+    let vm_codes = in_parallel(&stale, |(_, _, _, class, _)| {
+        CodeGenerator::new().compile_class(class)
+    });
+    for ((file_name, key, vm_file, _, source), vm_code) in stale.iter().zip(vm_codes) {
+        let vm_code = vm_code.unwrap_or_else(|err| {
+            eprintln!("Error: {}", err.in_file(&source.path));
             process::exit(1);
         });
-        let _ = compilation_engine.compile_class();
+        write_output(vm_file, vm_code);
+        cache.update(file_name, *key);
+    }
+    // the .vm files are written, so a cache that cannot be saved only
+    // costs a full build next time
+    if let Err(err) = cache.save() {
+        eprintln!("warning: {}", err);
     }
 }
 
-fn get_target() -> String {
-    let args: Vec<String> = env::args().collect();
-    let target_name = args.get(1).unwrap_or_else(|| {
-        eprintln!("not enough argumens");
-        process::exit(1);
-    });
-    String::from(target_name)
+// f of each item, with the items split between a thread for each core
+fn in_parallel<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
 }
 
-fn get_target_type_files(dir_name: &str, target_extension: &str) -> Vec<String> {
-    let entries = fs::read_dir("./".to_string() + dir_name).unwrap();
+// --strip leaves out the subroutines the program never calls,
+// --extended accepts the extended dialect, --precedence parses
+// expressions with conventional operator precedence and --out-dir writes
+// the output there instead of next to each .jack file
+fn get_args() -> Args {
+    let mut args = Args {
+        inputs: Vec::new(),
+        out_dir: None,
+        xml: false,
+        strip: false,
+        options: ParseOptions::default(),
+    };
+    let mut arguments = env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--xml" => args.xml = true,
+            "--strip" => args.strip = true,
+            "--extended" => args.options.dialect = Dialect::Extended,
+            "--precedence" => args.options.precedence = true,
+            "-o" | "--out-dir" => match arguments.next() {
+                Some(out_dir) => args.out_dir = Some(PathBuf::from(out_dir)),
+                None => usage_error(&format!("{} needs a directory", argument)),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if argument.starts_with('-') => usage_error(&format!("unknown option {}", argument)),
+            _ => args.inputs.push(argument),
+        }
+    }
+    if args.inputs.is_empty() {
+        usage_error("no .jack file or directory given");
+    }
+    args
+}
 
-    let mut jack_files = Vec::<String>::new();
+// The .jack files of the inputs, the files in a directory found in the
+// directories under it too. Their output goes next to them, or under
+// out_dir the way they are under the input.
+fn jack_sources(inputs: &[String], out_dir: Option<&Path>) -> Vec<Source> {
+    let mut sources = Vec::new();
+    for input in inputs {
+        let input = Path::new(input);
+        let (root, files) = if input.is_dir() {
+            let files = jack_files(input);
+            if files.is_empty() {
+                fail(&format!("{}: no .jack files", input.display()));
+            }
+            (input, files)
+        } else if input.is_file() {
+            if input
+                .extension()
+                .is_none_or(|extension| extension != "jack")
+            {
+                fail(&format!("{}: not a .jack file", input.display()));
+            }
+            (
+                input.parent().unwrap_or(Path::new("")),
+                vec![input.to_path_buf()],
+            )
+        } else {
+            fail(&format!("{}: no such file or directory", input.display()));
+        };
+        for path in files {
+            let output = match out_dir {
+                Some(out_dir) => out_dir.join(path.strip_prefix(root).unwrap_or(&path)),
+                None => path.clone(),
+            };
+            let output = output.with_extension("");
+            match sources
+                .iter()
+                .find(|source: &&Source| source.output == output)
+            {
+                // a file given twice
+                Some(source) if source.path == path => {}
+                Some(source) => fail(&format!(
+                    "{} and {} would overwrite each other's output in {}",
+                    source.path.display(),
+                    path.display(),
+                    output.parent().unwrap_or(Path::new(".")).display()
+                )),
+                None => sources.push(Source { path, output }),
+            }
+        }
+    }
+    sources
+}
 
-    for entry in entries {
-        jack_files.push(entry.unwrap().path().to_str().unwrap().to_string());
+// the .jack files in the directory and the ones under it, sorted, leaving
+// out hidden directories and the ones symbolic links lead to
+fn jack_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = fs::read_dir(dir).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", dir.display(), err));
+    });
+    let mut files = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            if !hidden {
+                files.append(&mut jack_files(&path));
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "jack")
+        {
+            files.push(path);
+        }
     }
-    jack_files
-        .iter()
-        .filter(|&file_name| file_name.clone().contains(target_extension))
-        .cloned()
-        .collect()
+    files.sort();
+    files
+}
+
+fn read_source(source: &Source) -> String {
+    fs::read_to_string(&source.path).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", source.path.display(), err));
+    })
+}
+
+// writes the file, making the directories it goes in
+fn write_output(file: &Path, contents: String) {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(Err(err)) = dir.map(fs::create_dir_all) {
+        fail(&format!("{}: {}", file.display(), err));
+    }
+    fs::write(file, contents).unwrap_or_else(|err| {
+        fail(&format!("{}: {}", file.display(), err));
+    });
+}
+
+// the path with the suffix added to its last part, as Main to Main.vm
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    fail(USAGE);
 }
 
-fn is_file(target_name: &str) -> bool {
-    target_name.contains(".jack")
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
@@ -0,0 +1,623 @@
+use std::collections::VecDeque;
+
+use crate::interpreter::{error, Eval, Interpreter, Interrupt};
+
+pub const HEAP_BASE: usize = 2048;
+pub const SCREEN: usize = 16384;
+pub const KBD: usize = 24576;
+pub const SCREEN_WIDTH: usize = 512;
+pub const SCREEN_HEIGHT: usize = 256;
+
+// the Hack keyboard codes for newline and backspace
+const NEW_LINE: i16 = 128;
+const BACKSPACE: i16 = 129;
+
+// text cursor grid of the Output class, with 8x11 pixel characters
+const ROWS: usize = 23;
+const COLUMNS: usize = 64;
+
+// Glyphs of the Output class for the box shown for unknown characters,
+// then ' ' to '~'. Each row is 8 pixels wide, bit 0 leftmost.
+const FONT: [[u8; 11]; 96] = [
+    [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0],  // box
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],           // ' '
+    [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0],   // '!'
+    [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0],        // '"'
+    [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0],   // '#'
+    [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0],  // '$'
+    [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0],     // '%'
+    [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0],  // '&'
+    [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0],         // "'"
+    [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0],       // '('
+    [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0],    // ')'
+    [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0],      // '*'
+    [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0],      // '+'
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0],         // ','
+    [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0],          // '-'
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0],         // '.'
+    [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0],       // '/'
+    [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0],  // '0'
+    [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0],  // '1'
+    [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0],    // '2'
+    [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0],  // '3'
+    [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0],  // '4'
+    [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0],    // '5'
+    [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0],     // '6'
+    [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0],  // '7'
+    [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0],  // '8'
+    [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0],  // '9'
+    [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0],       // ':'
+    [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0],       // ';'
+    [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0],       // '<'
+    [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0],         // '='
+    [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0],        // '>'
+    [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0],   // '?'
+    [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0],   // '@'
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],           // 'A'
+    [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0],  // 'B'
+    [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0],     // 'C'
+    [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0],  // 'D'
+    [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0],  // 'E'
+    [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0],     // 'F'
+    [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0],   // 'G'
+    [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // 'H'
+    [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // 'I'
+    [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0],  // 'J'
+    [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0],  // 'K'
+    [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0],        // 'L'
+    [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0],  // 'M'
+    [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0],  // 'N'
+    [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // 'O'
+    [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0],      // 'P'
+    [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0], // 'Q'
+    [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0],  // 'R'
+    [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0],   // 'S'
+    [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0],  // 'T'
+    [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // 'U'
+    [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0],  // 'V'
+    [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0],  // 'W'
+    [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0],  // 'X'
+    [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0],  // 'Y'
+    [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0],   // 'Z'
+    [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0],         // '['
+    [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0],       // '\\'
+    [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0],  // ']'
+    [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0],         // '^'
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0],          // '_'
+    [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0],         // '`'
+    [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0],     // 'a'
+    [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0],     // 'b'
+    [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0],       // 'c'
+    [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0],  // 'd'
+    [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0],      // 'e'
+    [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0],      // 'f'
+    [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0],   // 'g'
+    [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0],     // 'h'
+    [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0],   // 'i'
+    [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0],  // 'j'
+    [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0],     // 'k'
+    [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // 'l'
+    [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0],     // 'm'
+    [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0],     // 'n'
+    [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0],     // 'o'
+    [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0],      // 'p'
+    [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0],    // 'q'
+    [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0],        // 'r'
+    [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0],      // 's'
+    [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0],        // 't'
+    [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0],     // 'u'
+    [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0],     // 'v'
+    [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0],     // 'w'
+    [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0],     // 'x'
+    [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0],    // 'y'
+    [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0],      // 'z'
+    [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0],   // '{'
+    [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0],  // '|'
+    [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0],    // '}'
+    [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0],        // '~'
+];
+
+/// State of the natively implemented OS classes: the heap's free list,
+/// the Screen color, the Output cursor and the keys still to be typed.
+pub struct NativeOs {
+    // (address, size) of free heap blocks, in address order
+    free: Vec<(usize, usize)>,
+    // size of each allocated block, by address
+    allocated: std::collections::HashMap<usize, usize>,
+    color: bool,
+    row: usize,
+    column: usize,
+    output: String,
+    keys: VecDeque<i16>,
+}
+
+impl NativeOs {
+    pub fn new() -> Self {
+        NativeOs {
+            free: vec![(HEAP_BASE, SCREEN - HEAP_BASE)],
+            allocated: std::collections::HashMap::new(),
+            color: true,
+            row: 0,
+            column: 0,
+            output: String::new(),
+            keys: VecDeque::new(),
+        }
+    }
+}
+
+impl Default for NativeOs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Everything printed through the Output class so far, as text.
+    pub fn output(&self) -> &str {
+        &self.os.output
+    }
+
+    /// The screen memory map, 32 words per row of 512 pixels.
+    pub fn screen(&self) -> &[i16] {
+        &self.ram[SCREEN..KBD]
+    }
+
+    /// The screen as a plain PBM image.
+    pub fn screen_pbm(&self) -> String {
+        let mut image = format!("P1\n{} {}\n", SCREEN_WIDTH, SCREEN_HEIGHT);
+        for y in 0..SCREEN_HEIGHT {
+            let row: Vec<&str> = (0..SCREEN_WIDTH)
+                .map(|x| if self.pixel(x, y) { "1" } else { "0" })
+                .collect();
+            image += &row.join(" ");
+            image.push('\n');
+        }
+        image
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.ram[SCREEN + y * 32 + x / 16] >> (x % 16) & 1 == 1
+    }
+
+    /// Queues keys for the Keyboard class, which has no real keyboard to
+    /// read from. `\n` is typed as the Hack newline key. The first queued
+    /// key is held down (and shows in the keyboard memory map) until a
+    /// `readChar` takes it.
+    pub fn type_keys(&mut self, text: &str) {
+        for c in text.chars() {
+            let key = if c == '\n' { NEW_LINE } else { c as i16 };
+            self.os.keys.push_back(key);
+        }
+        self.ram[KBD] = self.os.keys.front().copied().unwrap_or(0);
+    }
+
+    // None if there is no such OS function
+    pub(crate) fn call_native(
+        &mut self,
+        class: &str,
+        name: &str,
+        args: &[i16],
+    ) -> Option<Eval<i16>> {
+        let arg = |index: usize| args.get(index).copied().unwrap_or(0);
+        let expected = match (class, name) {
+            ("Math" | "Memory" | "Screen" | "Output" | "Keyboard", "init")
+            | ("Sys", "init" | "halt")
+            | ("Screen", "clearScreen")
+            | ("Output", "println" | "backSpace")
+            | ("Keyboard", "keyPressed" | "readChar")
+            | ("String", "backSpace" | "doubleQuote" | "newLine") => 0,
+            ("Math", "abs" | "sqrt")
+            | ("Memory", "peek" | "alloc" | "deAlloc")
+            | ("Array", "new" | "dispose")
+            | ("String", "new" | "dispose" | "length" | "eraseLastChar" | "intValue")
+            | ("Screen", "setColor")
+            | ("Output", "printChar" | "printString" | "printInt")
+            | ("Keyboard", "readLine" | "readInt")
+            | ("Sys", "wait" | "error") => 1,
+            ("Math", "multiply" | "divide" | "min" | "max")
+            | ("Memory", "poke")
+            | ("String", "charAt" | "appendChar" | "setInt")
+            | ("Screen", "drawPixel")
+            | ("Output", "moveCursor") => 2,
+            ("String", "setCharAt") | ("Screen", "drawCircle") => 3,
+            ("Screen", "drawLine" | "drawRectangle") => 4,
+            _ => return None,
+        };
+        if args.len() != expected {
+            return Some(error(format!(
+                "takes {} arguments but was called with {}",
+                expected,
+                args.len()
+            )));
+        }
+
+        let result = match (class, name) {
+            (_, "init") if class != "Sys" => Ok(0),
+            ("Sys", "init") => self.sys_init(),
+            ("Sys", "halt") => Err(Interrupt::Halt),
+            ("Sys", "wait") if arg(0) < 0 => error("duration must be positive".to_string()),
+            ("Sys", "wait") => Ok(0),
+            ("Sys", "error") => error(format!("Sys.error({})", arg(0))),
+
+            ("Math", "abs") => Ok(arg(0).wrapping_abs()),
+            ("Math", "multiply") => Ok(arg(0).wrapping_mul(arg(1))),
+            ("Math", "divide") if arg(1) == 0 => error("division by zero".to_string()),
+            ("Math", "divide") => Ok(arg(0).wrapping_div(arg(1))),
+            ("Math", "min") => Ok(arg(0).min(arg(1))),
+            ("Math", "max") => Ok(arg(0).max(arg(1))),
+            ("Math", "sqrt") if arg(0) < 0 => {
+                error("cannot compute square root of a negative number".to_string())
+            }
+            ("Math", "sqrt") => Ok((arg(0) as f64).sqrt() as i16),
+
+            ("Memory", "peek") => self.load(arg(0)),
+            ("Memory", "poke") => self.store(arg(0), arg(1)).map(|_| 0),
+            ("Memory", "alloc") | ("Array", "new") => self.alloc(arg(0)),
+            ("Memory", "deAlloc") | ("Array", "dispose") | ("String", "dispose") => {
+                self.dealloc(arg(0)).map(|_| 0)
+            }
+
+            ("String", "new") => self.string_new(arg(0)),
+            ("String", "length") => self.load(arg(0).wrapping_add(1)),
+            ("String", "charAt") => self
+                .char_address(arg(0), arg(1))
+                .and_then(|address| self.load(address)),
+            ("String", "setCharAt") => self
+                .char_address(arg(0), arg(1))
+                .and_then(|address| self.store(address, arg(2)))
+                .map(|_| 0),
+            ("String", "appendChar") => self.append_char(arg(0), arg(1)),
+            ("String", "eraseLastChar") => self.erase_last_char(arg(0)),
+            ("String", "intValue") => self.int_value(arg(0)),
+            ("String", "setInt") => self.set_int(arg(0), arg(1)),
+            ("String", "backSpace") => Ok(BACKSPACE),
+            ("String", "doubleQuote") => Ok(34),
+            ("String", "newLine") => Ok(NEW_LINE),
+
+            ("Screen", "clearScreen") => {
+                self.ram[SCREEN..KBD].fill(0);
+                Ok(0)
+            }
+            ("Screen", "setColor") => {
+                self.os.color = arg(0) != 0;
+                Ok(0)
+            }
+            ("Screen", "drawPixel") => self.draw_pixel(arg(0), arg(1)).map(|_| 0),
+            ("Screen", "drawLine") => self.draw_line(arg(0), arg(1), arg(2), arg(3)).map(|_| 0),
+            ("Screen", "drawRectangle") => self
+                .draw_rectangle(arg(0), arg(1), arg(2), arg(3))
+                .map(|_| 0),
+            ("Screen", "drawCircle") => self.draw_circle(arg(0), arg(1), arg(2)).map(|_| 0),
+
+            ("Output", "moveCursor") => self.move_cursor(arg(0), arg(1)).map(|_| 0),
+            ("Output", "printChar") => self.print_char(arg(0)).map(|_| 0),
+            ("Output", "printString") => self.print_string(arg(0)).map(|_| 0),
+            ("Output", "printInt") => self.print_int(arg(0)).map(|_| 0),
+            ("Output", "println") => self.print_char(NEW_LINE).map(|_| 0),
+            ("Output", "backSpace") => self.print_char(BACKSPACE).map(|_| 0),
+
+            ("Keyboard", "keyPressed") => Ok(self.ram[KBD]),
+            ("Keyboard", "readChar") => self.read_char(),
+            ("Keyboard", "readLine") => self.read_line(arg(0)),
+            ("Keyboard", "readInt") => self.read_int(arg(0)),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    // Initializes the OS classes that are loaded as Jack code, then runs
+    // Main.main and halts.
+    fn sys_init(&mut self) -> Eval<i16> {
+        for class in ["Memory", "Math", "Screen", "Output", "Keyboard"] {
+            self.call_function(class, "init", Vec::new())?;
+        }
+        self.call_function("Main", "main", Vec::new())?;
+        Err(Interrupt::Halt)
+    }
+
+    // First fit over the free list; blocks are never split into pieces
+    // smaller than one word.
+    fn alloc(&mut self, size: i16) -> Eval<i16> {
+        if size <= 0 {
+            return error(format!("cannot allocate {} words", size));
+        }
+        let size = size as usize;
+        let block = match self.os.free.iter().position(|(_, free)| *free >= size) {
+            Some(block) => block,
+            None => return error(format!("heap overflow allocating {} words", size)),
+        };
+        let (address, free) = self.os.free[block];
+        if free == size {
+            self.os.free.remove(block);
+        } else {
+            self.os.free[block] = (address + size, free - size);
+        }
+        self.os.allocated.insert(address, size);
+        self.ram[address..address + size].fill(0);
+        Ok(address as i16)
+    }
+
+    fn dealloc(&mut self, address: i16) -> Eval<()> {
+        let size = match self.os.allocated.remove(&(address as u16 as usize)) {
+            Some(size) => size,
+            None => return error(format!("{} was not allocated", address)),
+        };
+        let address = address as usize;
+        let position = self.os.free.partition_point(|(free, _)| *free < address);
+        self.os.free.insert(position, (address, size));
+        // merge with the following and the preceding block
+        if position + 1 < self.os.free.len() && address + size == self.os.free[position + 1].0 {
+            self.os.free[position].1 += self.os.free.remove(position + 1).1;
+        }
+        if position > 0 {
+            let (previous, previous_size) = self.os.free[position - 1];
+            if previous + previous_size == address {
+                self.os.free[position - 1].1 += self.os.free.remove(position).1;
+            }
+        }
+        Ok(())
+    }
+
+    // A string is [maxLength, length, chars...]
+    fn string_new(&mut self, max_length: i16) -> Eval<i16> {
+        if max_length < 0 {
+            return error("maximum length must be non-negative".to_string());
+        }
+        let string = self.alloc(max_length + 2)?;
+        self.store(string, max_length)?;
+        Ok(string)
+    }
+
+    fn char_address(&self, string: i16, index: i16) -> Eval<i16> {
+        let length = self.load(string.wrapping_add(1))?;
+        if index < 0 || index >= length {
+            return error(format!("index {} is out of bounds", index));
+        }
+        Ok(string.wrapping_add(2).wrapping_add(index))
+    }
+
+    fn append_char(&mut self, string: i16, c: i16) -> Eval<i16> {
+        let (max_length, length) = (self.load(string)?, self.load(string.wrapping_add(1))?);
+        if length >= max_length {
+            return error("string is full".to_string());
+        }
+        self.store(string.wrapping_add(2).wrapping_add(length), c)?;
+        self.store(string.wrapping_add(1), length + 1)?;
+        Ok(string)
+    }
+
+    fn erase_last_char(&mut self, string: i16) -> Eval<i16> {
+        let length = self.load(string.wrapping_add(1))?;
+        if length == 0 {
+            return error("string is empty".to_string());
+        }
+        self.store(string.wrapping_add(1), length - 1)?;
+        Ok(0)
+    }
+
+    // leading digits, with an optional '-'
+    fn int_value(&self, string: i16) -> Eval<i16> {
+        let length = self.load(string.wrapping_add(1))?;
+        let mut value: i16 = 0;
+        let mut negative = false;
+        for index in 0..length {
+            let c = self.load(string.wrapping_add(2 + index))?;
+            if index == 0 && c == '-' as i16 {
+                negative = true;
+            } else if (b'0' as i16..=b'9' as i16).contains(&c) {
+                value = value.wrapping_mul(10).wrapping_add(c - b'0' as i16);
+            } else {
+                break;
+            }
+        }
+        Ok(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })
+    }
+
+    fn set_int(&mut self, string: i16, value: i16) -> Eval<i16> {
+        let digits = value.to_string();
+        let max_length = self.load(string)?;
+        if digits.len() > max_length as usize {
+            return error("string is too short for the number".to_string());
+        }
+        for (index, c) in digits.chars().enumerate() {
+            self.store(string.wrapping_add(2 + index as i16), c as i16)?;
+        }
+        self.store(string.wrapping_add(1), digits.len() as i16)?;
+        Ok(0)
+    }
+
+    fn check_point(x: i16, y: i16) -> Eval<(usize, usize)> {
+        if !(0..SCREEN_WIDTH as i16).contains(&x) || !(0..SCREEN_HEIGHT as i16).contains(&y) {
+            return error(format!("illegal pixel coordinates ({}, {})", x, y));
+        }
+        Ok((x as usize, y as usize))
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize) {
+        let word = &mut self.ram[SCREEN + y * 32 + x / 16];
+        let mask = 1 << (x % 16);
+        if self.os.color {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    fn draw_pixel(&mut self, x: i16, y: i16) -> Eval<()> {
+        let (x, y) = Self::check_point(x, y)?;
+        self.set_pixel(x, y);
+        Ok(())
+    }
+
+    fn draw_line(&mut self, x1: i16, y1: i16, x2: i16, y2: i16) -> Eval<()> {
+        Self::check_point(x1, y1)?;
+        Self::check_point(x2, y2)?;
+        let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+        let (step_x, step_y) = (if x1 < x2 { 1 } else { -1 }, if y1 < y2 { 1 } else { -1 });
+        let (mut x, mut y, mut err) = (x1, y1, dx + dy);
+        loop {
+            self.set_pixel(x as usize, y as usize);
+            if x == x2 && y == y2 {
+                return Ok(());
+            }
+            let doubled = 2 * err;
+            if doubled >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    fn draw_rectangle(&mut self, x1: i16, y1: i16, x2: i16, y2: i16) -> Eval<()> {
+        let (left, top) = Self::check_point(x1, y1)?;
+        let (right, bottom) = Self::check_point(x2, y2)?;
+        if left > right || top > bottom {
+            return error(format!(
+                "illegal rectangle ({}, {}, {}, {})",
+                x1, y1, x2, y2
+            ));
+        }
+        for y in top..=bottom {
+            for x in left..=right {
+                self.set_pixel(x, y);
+            }
+        }
+        Ok(())
+    }
+
+    // a filled disc, as the course OS draws it
+    fn draw_circle(&mut self, x: i16, y: i16, r: i16) -> Eval<()> {
+        Self::check_point(x, y)?;
+        if !(0..=181).contains(&r) {
+            return error(format!("illegal radius {}", r));
+        }
+        Self::check_point(x - r, y - r)?;
+        Self::check_point(x + r, y + r)?;
+        for dy in -r..=r {
+            let half = ((r as i32 * r as i32 - dy as i32 * dy as i32) as f64).sqrt() as i16;
+            for px in x - half..=x + half {
+                self.set_pixel(px as usize, (y + dy) as usize);
+            }
+        }
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, row: i16, column: i16) -> Eval<()> {
+        if !(0..ROWS as i16).contains(&row) || !(0..COLUMNS as i16).contains(&column) {
+            return error(format!("illegal cursor location ({}, {})", row, column));
+        }
+        self.os.row = row as usize;
+        self.os.column = column as usize;
+        Ok(())
+    }
+
+    fn print_string(&mut self, string: i16) -> Eval<()> {
+        let length = self.call_function("String", "length", vec![string])?;
+        for index in 0..length {
+            let c = self.call_function("String", "charAt", vec![string, index])?;
+            self.print_char(c)?;
+        }
+        Ok(())
+    }
+
+    fn print_int(&mut self, value: i16) -> Eval<()> {
+        for c in value.to_string().chars() {
+            self.print_char(c as i16)?;
+        }
+        Ok(())
+    }
+
+    fn print_char(&mut self, c: i16) -> Eval<()> {
+        match c {
+            NEW_LINE => {
+                self.os.output.push('\n');
+                self.os.column = 0;
+                self.os.row = (self.os.row + 1) % ROWS;
+            }
+            BACKSPACE => {
+                if self.os.output.ends_with(|c| c != '\n') {
+                    self.os.output.pop();
+                }
+                if self.os.column > 0 {
+                    self.os.column -= 1;
+                } else if self.os.row > 0 {
+                    self.os.row -= 1;
+                    self.os.column = COLUMNS - 1;
+                }
+                self.draw_char(b' ' as i16);
+            }
+            _ => {
+                self.os
+                    .output
+                    .push(char::from_u32(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+                self.draw_char(c);
+                self.os.column += 1;
+                if self.os.column == COLUMNS {
+                    self.os.column = 0;
+                    self.os.row = (self.os.row + 1) % ROWS;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // draws `c` at the cursor without moving it
+    fn draw_char(&mut self, c: i16) {
+        let glyph = match c {
+            32..=126 => FONT[(c - 31) as usize],
+            _ => FONT[0],
+        };
+        let address = SCREEN + self.os.row * 11 * 32 + self.os.column / 2;
+        for (line, bits) in glyph.iter().enumerate() {
+            let word = &mut self.ram[address + line * 32];
+            *word = if self.os.column.is_multiple_of(2) {
+                (*word & !0xff) | *bits as i16
+            } else {
+                (*word & 0xff) | ((*bits as i16) << 8)
+            };
+        }
+    }
+
+    // Takes the held key, echoing it like the OS does.
+    fn read_char(&mut self) -> Eval<i16> {
+        let key = match self.os.keys.pop_front() {
+            Some(key) => key,
+            None => return error("no more keyboard input".to_string()),
+        };
+        self.ram[KBD] = self.os.keys.front().copied().unwrap_or(0);
+        self.print_char(key)?;
+        Ok(key)
+    }
+
+    fn read_line(&mut self, message: i16) -> Eval<i16> {
+        self.print_string(message)?;
+        let mut line = Vec::new();
+        loop {
+            match self.read_char()? {
+                NEW_LINE => break,
+                BACKSPACE => {
+                    line.pop();
+                }
+                c => line.push(c),
+            }
+        }
+        let string = self.call_function("String", "new", vec![line.len().max(1) as i16])?;
+        for c in line {
+            self.call_function("String", "appendChar", vec![string, c])?;
+        }
+        Ok(string)
+    }
+
+    fn read_int(&mut self, message: i16) -> Eval<i16> {
+        let line = self.read_line(message)?;
+        self.call_function("String", "intValue", vec![line])
+    }
+}
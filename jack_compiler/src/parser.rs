@@ -0,0 +1,809 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::ast::{
+    BinaryOp, Class, ClassVarDec, Constant, Expression, KeywordConstant, Parameter, Statement,
+    SubroutineCall, SubroutineDec, SubroutineKind, Type, UnaryOp, VarDec,
+};
+use crate::code_generator::constant_value;
+use crate::jack_tokenizer::{JackTokenizer, Keywords, Location, Symbols, Token};
+use crate::symbol_table::Kind;
+
+// the largest integer constant of the Hack platform
+const MAX_INTEGER: usize = 32767;
+// hexadecimal and binary constants may set any of the 16 bits
+const MAX_BITS: usize = 0xFFFF;
+
+#[derive(Debug)]
+pub struct CompileError {
+    // class (file) the error was found in
+    pub class: String,
+    pub message: String,
+    // index of the token the parser stopped at, for syntax errors
+    pub token: Option<usize>,
+    // where in the source the error is, when the tokens came from code
+    pub location: Option<Location>,
+    // the file the code was read from, named instead of the class's file
+    pub file: Option<PathBuf>,
+}
+
+impl CompileError {
+    /// The error with the file the code was read from, for when it is not
+    /// named after its class.
+    pub fn in_file(self, file: &Path) -> Self {
+        CompileError {
+            file: Some(file.to_path_buf()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        } else if !self.class.is_empty() {
+            write!(f, "{}.jack: ", self.class)?;
+        }
+        if let Some(location) = self.location {
+            // editors count lines and columns from 1
+            write!(
+                f,
+                "line {}, column {}: ",
+                location.line + 1,
+                location.column + 1
+            )?;
+        }
+        write!(f, "{}", self.message)?;
+        if let (Some(token), None) = (self.token, self.location) {
+            write!(f, " (token {})", token + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// The Jack a parser accepts: the language of the course, or that plus
+/// `else if`, `for` loops, `break`/`continue`, compound assignment
+/// (`let x += 1`), `'A'`, `0x1F` and `0b1010` literals and `const`
+/// declarations, which compile to the same VM commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Standard,
+    Extended,
+}
+
+/// How a parser reads the source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    pub dialect: Dialect,
+    /// Parse expressions with conventional operator precedence, so that
+    /// `1 + 2 * 3` is 7, rather than strictly left to right as the Jack
+    /// specification says.
+    pub precedence: bool,
+}
+
+// the operators of compound assignments
+const COMPOUND_OPS: [char; 6] = ['+', '-', '*', '/', '&', '|'];
+
+/// Builds the syntax tree of one class from the tokens of a `.jack` file.
+pub struct Parser {
+    tokenizer: JackTokenizer,
+    class_name: String,
+    options: ParseOptions,
+    // how many loops the current statement is in, for break and continue
+    loop_depth: usize,
+    // the type and value of the class's constants, by name
+    constants: HashMap<String, (Type, i16)>,
+    // the names declared in the class or the current subroutine, which a
+    // constant cannot take or which hide one
+    class_variables: HashSet<String>,
+    subroutine_variables: HashSet<String>,
+}
+
+impl Parser {
+    pub fn new(tokenizer: JackTokenizer) -> Self {
+        Parser::with_options(tokenizer, ParseOptions::default())
+    }
+
+    pub fn with_options(tokenizer: JackTokenizer, options: ParseOptions) -> Self {
+        Parser {
+            tokenizer,
+            class_name: String::new(),
+            options,
+            loop_depth: 0,
+            constants: HashMap::new(),
+            class_variables: HashSet::new(),
+            subroutine_variables: HashSet::new(),
+        }
+    }
+
+    pub fn parse_class(&mut self) -> Result<Class, CompileError> {
+        self.expect_keyword(Keywords::Class)?;
+        let name = self.expect_identifier()?;
+        self.class_name = name.clone();
+        self.expect_symbol('{')?;
+
+        let mut class_var_decs = Vec::new();
+        let mut constants = Vec::new();
+        loop {
+            if self.is_keyword(Keywords::Static) || self.is_keyword(Keywords::Field) {
+                class_var_decs.push(self.parse_class_var_dec()?);
+            } else if self.is_identifier("const") {
+                constants.append(&mut self.parse_constants()?);
+            } else {
+                break;
+            }
+        }
+
+        let mut subroutines = Vec::new();
+        while self.is_keyword(Keywords::Constructor)
+            || self.is_keyword(Keywords::Function)
+            || self.is_keyword(Keywords::Method)
+        {
+            subroutines.push(self.parse_subroutine()?);
+        }
+
+        self.expect_symbol('}')?;
+        if self.tokenizer.has_more_tokens() {
+            return Err(self.error("expected end of file after the class"));
+        }
+
+        Ok(Class {
+            name,
+            class_var_decs,
+            constants,
+            subroutines,
+        })
+    }
+
+    fn parse_class_var_dec(&mut self) -> Result<ClassVarDec, CompileError> {
+        let kind = if self.is_keyword(Keywords::Static) {
+            Kind::STATIC
+        } else {
+            Kind::FIELD
+        };
+        self.tokenizer.advance();
+        let var_type = self.parse_type()?;
+        let start = self.tokenizer.token_index;
+        let names = self.parse_var_names()?;
+        for (i, name) in names.iter().enumerate() {
+            if self.constants.contains_key(name) {
+                // the names are separated by commas
+                let message = format!("{} is already declared as a constant", name);
+                return Err(self.error_at(start + 2 * i, message));
+            }
+            self.class_variables.insert(name.clone());
+        }
+        Ok(ClassVarDec {
+            kind,
+            var_type,
+            names,
+        })
+    }
+
+    // 'const' type name '=' expression (',' name '=' expression)* ';',
+    // where each expression must have a value at compile time
+    fn parse_constants(&mut self) -> Result<Vec<Constant>, CompileError> {
+        self.extension("a constant declaration")?;
+        self.tokenizer.advance();
+        let var_type = self.parse_type()?;
+        if let Type::Class(_) = var_type {
+            let message = "a constant must be an int, char or boolean".to_string();
+            return Err(self.error_at(self.tokenizer.token_index - 1, message));
+        }
+
+        let mut constants = Vec::new();
+        loop {
+            let name_index = self.tokenizer.token_index;
+            let name = self.expect_identifier()?;
+            if self.constants.contains_key(&name) || self.class_variables.contains(&name) {
+                let message = format!("{} is already declared", name);
+                return Err(self.error_at(name_index, message));
+            }
+            self.expect_symbol('=')?;
+            let value_index = self.tokenizer.token_index;
+            let value = self.parse_expression()?;
+            let Some(value) = constant_value(&value) else {
+                let message = format!("the value of {} is not known at compile time", name);
+                return Err(self.error_at(value_index, message));
+            };
+
+            self.constants
+                .insert(name.clone(), (var_type.clone(), value));
+            constants.push(Constant {
+                var_type: var_type.clone(),
+                name,
+                value,
+            });
+            if !self.is_symbol(',') {
+                break;
+            }
+            self.tokenizer.advance();
+        }
+        self.expect_symbol(';')?;
+        Ok(constants)
+    }
+
+    fn parse_subroutine(&mut self) -> Result<SubroutineDec, CompileError> {
+        let kind = if self.is_keyword(Keywords::Constructor) {
+            SubroutineKind::Constructor
+        } else if self.is_keyword(Keywords::Function) {
+            SubroutineKind::Function
+        } else {
+            SubroutineKind::Method
+        };
+        self.tokenizer.advance();
+
+        let return_type = if self.is_keyword(Keywords::Void) {
+            self.tokenizer.advance();
+            None
+        } else {
+            Some(self.parse_type()?)
+        };
+        let name = self.expect_identifier()?;
+
+        self.expect_symbol('(')?;
+        self.subroutine_variables.clear();
+        let mut parameters = Vec::new();
+        if !self.is_symbol(')') {
+            loop {
+                let var_type = self.parse_type()?;
+                let name = self.expect_identifier()?;
+                self.subroutine_variables.insert(name.clone());
+                parameters.push(Parameter { var_type, name });
+                if !self.is_symbol(',') {
+                    break;
+                }
+                self.tokenizer.advance();
+            }
+        }
+        self.expect_symbol(')')?;
+
+        self.expect_symbol('{')?;
+        let mut var_decs = Vec::new();
+        while self.is_keyword(Keywords::Var) {
+            self.tokenizer.advance();
+            let var_type = self.parse_type()?;
+            let names = self.parse_var_names()?;
+            self.subroutine_variables.extend(names.iter().cloned());
+            var_decs.push(VarDec { var_type, names });
+        }
+        let statements = self.parse_statements()?;
+        self.expect_symbol('}')?;
+
+        Ok(SubroutineDec {
+            kind,
+            return_type,
+            name,
+            parameters,
+            var_decs,
+            statements,
+        })
+    }
+
+    // varName (',' varName)* ';'
+    fn parse_var_names(&mut self) -> Result<Vec<String>, CompileError> {
+        let mut names = vec![self.expect_identifier()?];
+        while self.is_symbol(',') {
+            self.tokenizer.advance();
+            names.push(self.expect_identifier()?);
+        }
+        self.expect_symbol(';')?;
+        Ok(names)
+    }
+
+    fn parse_type(&mut self) -> Result<Type, CompileError> {
+        let var_type = match self.current()? {
+            Token::Keyword(Keywords::Int) => Type::Int,
+            Token::Keyword(Keywords::Char) => Type::Char,
+            Token::Keyword(Keywords::Boolean) => Type::Boolean,
+            Token::Identifier(name) => Type::Class(name),
+            _ => return Err(self.error("expected a type")),
+        };
+        self.tokenizer.advance();
+        Ok(var_type)
+    }
+
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, CompileError> {
+        let mut statements = Vec::new();
+        loop {
+            let statement = match self.current()? {
+                Token::Keyword(Keywords::Let) => self.parse_let()?,
+                Token::Keyword(Keywords::If) => self.parse_if()?,
+                Token::Keyword(Keywords::While) => self.parse_while()?,
+                Token::Keyword(Keywords::Do) => self.parse_do()?,
+                Token::Keyword(Keywords::Return) => self.parse_return()?,
+                // the extended dialect's statements are not keywords, so
+                // standard programs can still use the words as names
+                Token::Identifier(word) if word == "for" => self.parse_for()?,
+                Token::Identifier(word) if word == "break" || word == "continue" => {
+                    self.parse_jump(&word)?
+                }
+                _ => return Ok(statements),
+            };
+            statements.push(statement);
+        }
+    }
+
+    fn parse_let(&mut self) -> Result<Statement, CompileError> {
+        self.tokenizer.advance();
+        let statement = self.parse_assignment()?;
+        self.expect_symbol(';')?;
+        Ok(statement)
+    }
+
+    // varName ('[' expression ']')? op? '=' expression, without the `let`
+    fn parse_assignment(&mut self) -> Result<Statement, CompileError> {
+        if let Ok(Token::Identifier(name)) = self.current() {
+            if self.constant(&name).is_some() {
+                return Err(self.error_here(format!("cannot assign to constant {}", name)));
+            }
+        }
+        let name = self.expect_identifier()?;
+        let index = if self.is_symbol('[') {
+            self.tokenizer.advance();
+            let index = self.parse_expression()?;
+            self.expect_symbol(']')?;
+            Some(index)
+        } else {
+            None
+        };
+        let op = match self.binary_op() {
+            Some(op) if self.is_compound_assignment() => {
+                self.extension("compound assignment")?;
+                self.tokenizer.advance();
+                Some(op)
+            }
+            _ => None,
+        };
+        self.expect_symbol('=')?;
+        let value = self.parse_expression()?;
+        Ok(Statement::Let {
+            name,
+            index,
+            op,
+            value,
+        })
+    }
+
+    // an operator written right before `=`, as in `+=`
+    fn is_compound_assignment(&self) -> bool {
+        let index = self.tokenizer.token_index;
+        let is_op = COMPOUND_OPS.iter().any(|c| self.is_symbol(*c));
+        let is_eq = matches!(
+            self.tokenizer.tokens.get(index + 1),
+            Some(Token::Symbol(Symbols::Eq(_)))
+        );
+        let adjacent = match (
+            self.tokenizer.location(index),
+            self.tokenizer.location(index + 1),
+        ) {
+            (Some(op), Some(eq)) => op.line == eq.line && op.column + 1 == eq.column,
+            // tokens that did not come from code
+            _ => true,
+        };
+        is_op && is_eq && adjacent
+    }
+
+    fn parse_if(&mut self) -> Result<Statement, CompileError> {
+        self.tokenizer.advance();
+        let condition = self.parse_condition()?;
+        let statements = self.parse_block()?;
+        let else_statements = if self.is_keyword(Keywords::Else) {
+            self.tokenizer.advance();
+            if self.is_keyword(Keywords::If) {
+                self.extension("else if")?;
+                Some(vec![self.parse_if()?])
+            } else {
+                Some(self.parse_block()?)
+            }
+        } else {
+            None
+        };
+        Ok(Statement::If {
+            condition,
+            statements,
+            else_statements,
+        })
+    }
+
+    fn parse_while(&mut self) -> Result<Statement, CompileError> {
+        self.tokenizer.advance();
+        let condition = self.parse_condition()?;
+        let statements = self.parse_loop_body()?;
+        Ok(Statement::While {
+            condition,
+            statements,
+        })
+    }
+
+    // 'for' '(' assignment? ';' expression? ';' assignment? ')' '{' statements '}'
+    fn parse_for(&mut self) -> Result<Statement, CompileError> {
+        self.extension("a for loop")?;
+        self.tokenizer.advance();
+        self.expect_symbol('(')?;
+        let init = if self.is_symbol(';') {
+            None
+        } else {
+            Some(Box::new(self.parse_assignment()?))
+        };
+        self.expect_symbol(';')?;
+        let condition = if self.is_symbol(';') {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_symbol(';')?;
+        let update = if self.is_symbol(')') {
+            None
+        } else {
+            Some(Box::new(self.parse_assignment()?))
+        };
+        self.expect_symbol(')')?;
+        let statements = self.parse_loop_body()?;
+        Ok(Statement::For {
+            init,
+            condition,
+            update,
+            statements,
+        })
+    }
+
+    fn parse_loop_body(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.loop_depth += 1;
+        let statements = self.parse_block();
+        self.loop_depth -= 1;
+        statements
+    }
+
+    // ('break' | 'continue') ';'
+    fn parse_jump(&mut self, word: &str) -> Result<Statement, CompileError> {
+        self.extension(&format!("{} statement", word))?;
+        if self.loop_depth == 0 {
+            return Err(self.error_here(format!("{} outside of a loop", word)));
+        }
+        self.tokenizer.advance();
+        self.expect_symbol(';')?;
+        Ok(if word == "break" {
+            Statement::Break
+        } else {
+            Statement::Continue
+        })
+    }
+
+    // errors at a construct of the extended dialect when parsing standard
+    // Jack
+    fn extension(&self, construct: &str) -> Result<(), CompileError> {
+        match self.options.dialect {
+            Dialect::Extended => Ok(()),
+            Dialect::Standard => Err(self.error_here(format!(
+                "{} is not standard Jack; it needs the extended dialect",
+                construct
+            ))),
+        }
+    }
+
+    fn parse_do(&mut self) -> Result<Statement, CompileError> {
+        self.tokenizer.advance();
+        let name = self.expect_identifier()?;
+        let call = self.parse_subroutine_call(name)?;
+        self.expect_symbol(';')?;
+        Ok(Statement::Do(call))
+    }
+
+    fn parse_return(&mut self) -> Result<Statement, CompileError> {
+        self.tokenizer.advance();
+        let value = if self.is_symbol(';') {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_symbol(';')?;
+        Ok(Statement::Return(value))
+    }
+
+    // '(' expression ')'
+    fn parse_condition(&mut self) -> Result<Expression, CompileError> {
+        self.expect_symbol('(')?;
+        let condition = self.parse_expression()?;
+        self.expect_symbol(')')?;
+        Ok(condition)
+    }
+
+    // '{' statements '}'
+    fn parse_block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect_symbol('{')?;
+        let statements = self.parse_statements()?;
+        self.expect_symbol('}')?;
+        Ok(statements)
+    }
+
+    // term (op term)*, evaluated from left to right
+    pub fn parse_expression(&mut self) -> Result<Expression, CompileError> {
+        if self.options.precedence {
+            return self.parse_binary(1);
+        }
+        let mut expression = self.parse_term()?;
+        while let Some(op) = self.binary_op() {
+            self.tokenizer.advance();
+            let right = self.parse_term()?;
+            expression = Expression::Binary(Box::new(expression), op, Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    // precedence climbing: the operators of at least `min_precedence`,
+    // left-associative within a level
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, CompileError> {
+        let mut expression = self.parse_term()?;
+        while let Some(op) = self.binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.tokenizer.advance();
+            let right = self.parse_binary(op.precedence() + 1)?;
+            expression = Expression::Binary(Box::new(expression), op, Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn binary_op(&self) -> Option<BinaryOp> {
+        match self.tokenizer.tokens.get(self.tokenizer.token_index) {
+            Some(Token::Symbol(symbol)) => match JackTokenizer::symbol_char(symbol) {
+                '+' => Some(BinaryOp::Add),
+                '-' => Some(BinaryOp::Sub),
+                '*' => Some(BinaryOp::Mul),
+                '/' => Some(BinaryOp::Div),
+                '&' => Some(BinaryOp::And),
+                '|' => Some(BinaryOp::Or),
+                '<' => Some(BinaryOp::Lt),
+                '>' => Some(BinaryOp::Gt),
+                '=' => Some(BinaryOp::Eq),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Expression, CompileError> {
+        let term = match self.current()? {
+            Token::IntegerConstant(value) => {
+                if value > MAX_INTEGER {
+                    return Err(self.error_here(format!(
+                        "integer constant {} is out of range 0..{}",
+                        value, MAX_INTEGER
+                    )));
+                }
+                self.tokenizer.advance();
+                Expression::IntegerConstant(value)
+            }
+            Token::CharConstant(c) => {
+                self.extension("a character literal")?;
+                self.tokenizer.advance();
+                Expression::IntegerConstant(c as usize)
+            }
+            Token::RadixConstant { text, value } => {
+                self.extension("a hexadecimal or binary literal")?;
+                if value > MAX_BITS {
+                    return Err(self
+                        .error_here(format!("integer constant {} does not fit in 16 bits", text)));
+                }
+                self.tokenizer.advance();
+                literal(value as u16 as i16)
+            }
+            Token::StringConstant(value) => {
+                self.tokenizer.advance();
+                Expression::StringConstant(value)
+            }
+            Token::Keyword(keyword) => {
+                let constant = match keyword {
+                    Keywords::True => KeywordConstant::True,
+                    Keywords::False => KeywordConstant::False,
+                    Keywords::Null => KeywordConstant::Null,
+                    Keywords::This => KeywordConstant::This,
+                    _ => return Err(self.error("expected an expression")),
+                };
+                self.tokenizer.advance();
+                Expression::KeywordConstant(constant)
+            }
+            Token::Symbol(Symbols::LParen(_)) => {
+                self.tokenizer.advance();
+                let expression = self.parse_expression()?;
+                self.expect_symbol(')')?;
+                Expression::Parenthesized(Box::new(expression))
+            }
+            Token::Symbol(Symbols::Minus(_)) | Token::Symbol(Symbols::Not(_)) => {
+                let op = if self.is_symbol('-') {
+                    UnaryOp::Neg
+                } else {
+                    UnaryOp::Not
+                };
+                self.tokenizer.advance();
+                // -32768 is in range even though 32768 is not
+                if let (UnaryOp::Neg, Ok(Token::IntegerConstant(value))) = (op, self.current()) {
+                    if value == MAX_INTEGER + 1 {
+                        self.tokenizer.advance();
+                        return Ok(Expression::Unary(
+                            op,
+                            Box::new(Expression::IntegerConstant(value)),
+                        ));
+                    }
+                }
+                Expression::Unary(op, Box::new(self.parse_term()?))
+            }
+            Token::Identifier(name) => {
+                self.tokenizer.advance();
+                if self.is_symbol('[') {
+                    self.tokenizer.advance();
+                    let index = self.parse_expression()?;
+                    self.expect_symbol(']')?;
+                    Expression::ArrayAccess(name, Box::new(index))
+                } else if self.is_symbol('(') || self.is_symbol('.') {
+                    Expression::Call(self.parse_subroutine_call(name)?)
+                } else if let Some(constant) = self.constant(&name) {
+                    constant
+                } else {
+                    Expression::Var(name)
+                }
+            }
+            _ => return Err(self.error("expected an expression")),
+        };
+        Ok(term)
+    }
+
+    // called with the first identifier already consumed:
+    // name '(' expressionList ')' | name '.' name '(' expressionList ')'
+    fn parse_subroutine_call(&mut self, name: String) -> Result<SubroutineCall, CompileError> {
+        let (receiver, name) = if self.is_symbol('.') {
+            self.tokenizer.advance();
+            (Some(name), self.expect_identifier()?)
+        } else {
+            (None, name)
+        };
+
+        self.expect_symbol('(')?;
+        let mut arguments = Vec::new();
+        if !self.is_symbol(')') {
+            arguments.push(self.parse_expression()?);
+            while self.is_symbol(',') {
+                self.tokenizer.advance();
+                arguments.push(self.parse_expression()?);
+            }
+        }
+        self.expect_symbol(')')?;
+
+        Ok(SubroutineCall {
+            receiver,
+            name,
+            arguments,
+        })
+    }
+
+    fn current(&self) -> Result<Token, CompileError> {
+        if !self.tokenizer.has_more_tokens() {
+            return Err(self.error("unexpected end of file"));
+        }
+        Ok(self.tokenizer.token_type())
+    }
+
+    fn is_symbol(&self, c: char) -> bool {
+        match self.tokenizer.tokens.get(self.tokenizer.token_index) {
+            Some(Token::Symbol(symbol)) => JackTokenizer::symbol_char(symbol) == c,
+            _ => false,
+        }
+    }
+
+    // the expression a name stands for, unless a variable of the
+    // subroutine hides the constant
+    fn constant(&self, name: &str) -> Option<Expression> {
+        if self.subroutine_variables.contains(name) {
+            return None;
+        }
+        let constant = match self.constants.get(name)? {
+            (Type::Boolean, 0) => Expression::KeywordConstant(KeywordConstant::False),
+            (Type::Boolean, -1) => Expression::KeywordConstant(KeywordConstant::True),
+            (_, value) => literal(*value),
+        };
+        Some(constant)
+    }
+
+    // a word the extended dialect gives a meaning without making it a
+    // keyword
+    fn is_identifier(&self, word: &str) -> bool {
+        matches!(
+            self.tokenizer.tokens.get(self.tokenizer.token_index),
+            Some(Token::Identifier(current)) if current == word
+        )
+    }
+
+    fn is_keyword(&self, keyword: Keywords) -> bool {
+        match self.tokenizer.tokens.get(self.tokenizer.token_index) {
+            Some(Token::Keyword(current)) => *current == keyword,
+            _ => false,
+        }
+    }
+
+    fn expect_symbol(&mut self, c: char) -> Result<(), CompileError> {
+        if !self.is_symbol(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.tokenizer.advance();
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: Keywords) -> Result<(), CompileError> {
+        if !self.is_keyword(keyword.clone()) {
+            return Err(self.error(&format!(
+                "expected '{}'",
+                JackTokenizer::keywords_to_string(&keyword)
+            )));
+        }
+        self.tokenizer.advance();
+        Ok(())
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, CompileError> {
+        match self.current()? {
+            Token::Identifier(name) => {
+                self.tokenizer.advance();
+                Ok(name)
+            }
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn error(&self, message: &str) -> CompileError {
+        let found = match self.tokenizer.tokens.get(self.tokenizer.token_index) {
+            Some(token) => format!("'{}'", token_text(token)),
+            None => "end of file".to_string(),
+        };
+        self.error_here(format!("{} but found {}", message, found))
+    }
+
+    // an error at the current token
+    fn error_here(&self, message: String) -> CompileError {
+        self.error_at(self.tokenizer.token_index, message)
+    }
+
+    fn error_at(&self, token: usize, message: String) -> CompileError {
+        CompileError {
+            class: self.class_name.clone(),
+            message,
+            token: Some(token),
+            // the last token for an unexpected end of file
+            location: self
+                .tokenizer
+                .location(token)
+                .or_else(|| self.tokenizer.location(token.checked_sub(1)?)),
+            file: None,
+        }
+    }
+}
+
+// the expression of a 16-bit value, the way it would be written
+fn literal(value: i16) -> Expression {
+    if value >= 0 {
+        Expression::IntegerConstant(value as usize)
+    } else {
+        let magnitude = Expression::IntegerConstant(value.unsigned_abs() as usize);
+        Expression::Unary(UnaryOp::Neg, Box::new(magnitude))
+    }
+}
+
+pub fn token_text(token: &Token) -> String {
+    match token {
+        Token::Keyword(keyword) => JackTokenizer::keywords_to_string(keyword),
+        Token::Symbol(symbol) => JackTokenizer::symbol_char(symbol).to_string(),
+        Token::IntegerConstant(value) => value.to_string(),
+        Token::StringConstant(value) => format!("\"{}\"", value),
+        Token::Identifier(name) => name.to_string(),
+        Token::CharConstant(c) => format!("'{}'", c),
+        Token::RadixConstant { text, .. } => text.to_string(),
+    }
+}
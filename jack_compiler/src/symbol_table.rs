@@ -1,7 +1,5 @@
 use std::{collections::HashMap, fmt};
 
-use crate::jack_tokenizer::{Keywords, Token};
-
 #[derive(Eq, Hash, PartialEq, Copy, Clone)]
 pub enum Kind {
     STATIC,
@@ -22,7 +20,6 @@ impl fmt::Display for Kind {
 }
 
 pub struct Symbol {
-    symbol_name: String,
     symbol_type: String,
     symbol_kind: Kind,
     symbol_index: usize,
@@ -33,8 +30,12 @@ pub struct SymbolTable {
     class_kind_counter: HashMap<Kind, usize>,
     subroutine_table: HashMap<String, Symbol>,
     subroutine_kind_counter: HashMap<Kind, usize>,
-    current_symbol_type: String,
-    current_symbol_kind: Kind,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
 }
 
 impl SymbolTable {
@@ -50,22 +51,21 @@ impl SymbolTable {
             class_kind_counter,
             subroutine_table: HashMap::new(),
             subroutine_kind_counter,
-            current_symbol_type: "".to_string(),
-            current_symbol_kind: Kind::STATIC,
         }
     }
 
     pub fn start_subroutine(&mut self) {
         self.subroutine_table = HashMap::new();
+        self.subroutine_kind_counter.insert(Kind::ARG, 0);
+        self.subroutine_kind_counter.insert(Kind::VAR, 0);
     }
 
     pub fn define(&mut self, name: String, var_type: String, kind: Kind) {
         match kind {
             Kind::STATIC | Kind::FIELD => {
                 self.class_table.insert(
-                    name.to_string(),
+                    name,
                     Symbol {
-                        symbol_name: name,
                         symbol_type: var_type,
                         symbol_kind: kind,
                         symbol_index: self.var_count(kind),
@@ -75,9 +75,8 @@ impl SymbolTable {
             }
             Kind::ARG | Kind::VAR => {
                 self.subroutine_table.insert(
-                    name.to_string(),
+                    name,
                     Symbol {
-                        symbol_name: name,
                         symbol_type: var_type,
                         symbol_kind: kind,
                         symbol_index: self.var_count(kind),
@@ -131,32 +130,6 @@ impl SymbolTable {
         }
     }
 
-    pub fn set_token_type(&mut self, token: &Token) {
-        match token {
-            Token::Keyword(Keywords::Int) => self.current_symbol_type = "int".to_string(),
-            Token::Keyword(Keywords::Char) => self.current_symbol_type = "char".to_string(),
-            Token::Keyword(Keywords::Boolean) => self.current_symbol_type = "boolean".to_string(),
-            Token::Identifier(name) => self.current_symbol_type = name.to_string(),
-            _ => (),
-        }
-    }
-    pub fn set_token_kind(&mut self, token: &Token) {
-        match token {
-            Token::Keyword(Keywords::Static) => self.current_symbol_kind = Kind::STATIC,
-            Token::Keyword(Keywords::Field) => self.current_symbol_kind = Kind::FIELD,
-            Token::Keyword(Keywords::Var) => self.current_symbol_kind = Kind::VAR,
-            _ => self.current_symbol_kind = Kind::ARG,
-        }
-    }
-
-    pub fn get_current_token_type(&self) -> String {
-        self.current_symbol_type.clone()
-    }
-
-    pub fn get_current_token_kind(&self) -> Kind {
-        self.current_symbol_kind.clone()
-    }
-
     pub fn contains(&self, key: &String) -> bool {
         self.class_table.contains_key(key) || self.subroutine_table.contains_key(key)
     }
@@ -1,15 +1,116 @@
-pub struct VMWriter {}
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Const,
+    Arg,
+    Local,
+    Static,
+    This,
+    That,
+    Pointer,
+    Temp,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Const => write!(f, "constant"),
+            Self::Arg => write!(f, "argument"),
+            Self::Local => write!(f, "local"),
+            Self::Static => write!(f, "static"),
+            Self::This => write!(f, "this"),
+            Self::That => write!(f, "that"),
+            Self::Pointer => write!(f, "pointer"),
+            Self::Temp => write!(f, "temp"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add => write!(f, "add"),
+            Self::Sub => write!(f, "sub"),
+            Self::Neg => write!(f, "neg"),
+            Self::Eq => write!(f, "eq"),
+            Self::Gt => write!(f, "gt"),
+            Self::Lt => write!(f, "lt"),
+            Self::And => write!(f, "and"),
+            Self::Or => write!(f, "or"),
+            Self::Not => write!(f, "not"),
+        }
+    }
+}
+
+/// Collects VM commands in memory; `close` returns the `.vm` file contents.
+pub struct VMWriter {
+    code: String,
+}
 
 impl VMWriter {
-    pub fn new() {}
-    pub fn write_push() {}
-    pub fn write_pop() {}
-    pub fn write_arithmetic() {}
-    pub fn write_label() {}
-    pub fn write_goto() {}
-    pub fn write_if() {}
-    pub fn write_call() {}
-    pub fn write_function() {}
-    pub fn write_return() {}
-    pub fn close() {}
+    pub fn new() -> Self {
+        VMWriter {
+            code: String::new(),
+        }
+    }
+
+    pub fn write_push(&mut self, segment: Segment, index: usize) {
+        self.code += &format!("push {} {}\n", segment, index);
+    }
+
+    pub fn write_pop(&mut self, segment: Segment, index: usize) {
+        self.code += &format!("pop {} {}\n", segment, index);
+    }
+
+    pub fn write_arithmetic(&mut self, command: Command) {
+        self.code += &format!("{}\n", command);
+    }
+
+    pub fn write_label(&mut self, label: &str) {
+        self.code += &format!("label {}\n", label);
+    }
+
+    pub fn write_goto(&mut self, label: &str) {
+        self.code += &format!("goto {}\n", label);
+    }
+
+    pub fn write_if(&mut self, label: &str) {
+        self.code += &format!("if-goto {}\n", label);
+    }
+
+    pub fn write_call(&mut self, name: &str, arg_num: usize) {
+        self.code += &format!("call {} {}\n", name, arg_num);
+    }
+
+    pub fn write_function(&mut self, name: &str, local_num: usize) {
+        self.code += &format!("function {} {}\n", name, local_num);
+    }
+
+    pub fn write_return(&mut self) {
+        self.code += "return\n";
+    }
+
+    pub fn close(self) -> String {
+        self.code
+    }
+}
+
+impl Default for VMWriter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::ast::{BinaryOp, Class, Expression, Statement, SubroutineCall, SubroutineKind, Type};
+use crate::code_generator::constant_value;
+
+// where a program starts: the OS's Sys.init calls Main.main
+const ENTRY_POINTS: [&str; 2] = ["Sys.init", "Main.main"];
+
+#[derive(Debug)]
+pub struct Warning {
+    // class (file) the warning is about
+    pub class: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.jack: warning: {}", self.class, self.message)
+    }
+}
+
+/// Unused variables and code that never runs in the classes of a program,
+/// and its private subroutines, the ones named with a leading `_`, that
+/// nothing calls; any other subroutine may be there for other programs to
+/// call. The library classes, e.g. the OS, are only looked at for the calls
+/// they make.
+pub fn program_warnings(program: &[Class], library: &[Class]) -> Vec<Warning> {
+    let mut called = HashSet::new();
+    for class in program.iter().chain(library) {
+        for subroutine in &class.subroutines {
+            called.extend(calls(class, &subroutine.name));
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for class in program {
+        warnings.append(&mut class_warnings(class));
+        for subroutine in &class.subroutines {
+            let name = format!("{}.{}", class.name, subroutine.name);
+            if subroutine.name.starts_with('_')
+                && !called.contains(&name)
+                && !ENTRY_POINTS.contains(&name.as_str())
+            {
+                warnings.push(Warning {
+                    class: class.name.clone(),
+                    message: format!("{} {} is never called", subroutine.kind, name),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Unused variables, code that never runs and expressions whose mix of
+/// operators reads differently under conventional precedence in one class.
+pub fn class_warnings(class: &Class) -> Vec<Warning> {
+    let mut messages = Vec::new();
+    // fields and statics read or assigned anywhere in the class
+    let mut class_uses = Uses::default();
+
+    for subroutine in &class.subroutines {
+        let name = format!("{}.{}", class.name, subroutine.name);
+        let mut uses = Uses::default();
+        uses.statements(&subroutine.statements);
+        for (expression, left_to_right) in &uses.mixed_operators {
+            messages.push(format!(
+                "`{}` in {} is evaluated left to right, as `{}`; \
+                 add parentheses to make the order clear",
+                expression, name, left_to_right
+            ));
+        }
+
+        let locals: Vec<&String> = subroutine
+            .var_decs
+            .iter()
+            .flat_map(|var_dec| &var_dec.names)
+            .collect();
+        for local in &locals {
+            if let Some(problem) = uses.problem(local) {
+                messages.push(format!("local variable {} in {} {}", local, name, problem));
+            }
+        }
+        for parameter in &subroutine.parameters {
+            if let Some(problem) = uses.problem(&parameter.name) {
+                let message = format!("parameter {} of {} {}", parameter.name, name, problem);
+                messages.push(message);
+            }
+        }
+
+        // the subroutine's own variables hide the class's
+        let is_class_variable = |variable: &String| {
+            !locals.contains(&variable)
+                && !subroutine
+                    .parameters
+                    .iter()
+                    .any(|parameter| &parameter.name == variable)
+        };
+        class_uses
+            .read
+            .extend(uses.read.into_iter().filter(is_class_variable));
+        class_uses
+            .assigned
+            .extend(uses.assigned.into_iter().filter(is_class_variable));
+
+        dead_code(&subroutine.statements, &name, &mut messages);
+    }
+
+    for class_var_dec in &class.class_var_decs {
+        for variable in &class_var_dec.names {
+            if let Some(problem) = class_uses.problem(variable) {
+                let kind = class_var_dec.kind;
+                messages.push(format!("{} {} {}", kind, variable, problem));
+            }
+        }
+    }
+
+    messages
+        .into_iter()
+        .map(|message| Warning {
+            class: class.name.clone(),
+            message,
+        })
+        .collect()
+}
+
+/// Leaves out the subroutines a program can never call, starting from
+/// Sys.init and Main.main, to save ROM.
+pub fn remove_unreachable(classes: &mut [Class]) {
+    let reachable = reachable(classes);
+    for class in classes.iter_mut() {
+        let class_name = class.name.clone();
+        class.subroutines.retain(|subroutine| {
+            reachable.contains(&format!("{}.{}", class_name, subroutine.name))
+        });
+    }
+}
+
+// `Class.name` of every subroutine reachable from the entry points
+fn reachable(classes: &[Class]) -> HashSet<String> {
+    let by_class: HashMap<&str, &Class> = classes
+        .iter()
+        .map(|class| (class.name.as_str(), class))
+        .collect();
+    let mut reachable = HashSet::new();
+    let mut pending: Vec<String> = ENTRY_POINTS.iter().map(|name| name.to_string()).collect();
+
+    while let Some(name) = pending.pop() {
+        if !reachable.insert(name.clone()) {
+            continue;
+        }
+        let Some((class_name, subroutine)) = name.split_once('.') else {
+            continue;
+        };
+        if let Some(class) = by_class.get(class_name) {
+            pending.extend(calls(class, subroutine));
+        }
+    }
+    reachable
+}
+
+// The subroutines one subroutine calls, as `Class.name`, including the OS
+// subroutines the generated code calls for it.
+fn calls(class: &Class, subroutine_name: &str) -> Vec<String> {
+    let Some(subroutine) = class
+        .subroutines
+        .iter()
+        .find(|subroutine| subroutine.name == subroutine_name)
+    else {
+        return Vec::new();
+    };
+
+    let mut types: HashMap<&str, &Type> = HashMap::new();
+    for class_var_dec in &class.class_var_decs {
+        for name in &class_var_dec.names {
+            types.insert(name, &class_var_dec.var_type);
+        }
+    }
+    for parameter in &subroutine.parameters {
+        types.insert(&parameter.name, &parameter.var_type);
+    }
+    for var_dec in &subroutine.var_decs {
+        for name in &var_dec.names {
+            types.insert(name, &var_dec.var_type);
+        }
+    }
+
+    let mut uses = Uses::default();
+    uses.statements(&subroutine.statements);
+    let mut calls = uses.os_calls;
+    if subroutine.kind == SubroutineKind::Constructor {
+        calls.insert("Memory.alloc".to_string());
+    }
+    for (receiver, name) in uses.calls {
+        let class_name = match receiver {
+            None => class.name.clone(),
+            Some(receiver) => match types.get(receiver.as_str()) {
+                Some(Type::Class(class_name)) => class_name.clone(),
+                // an int or char has no subroutines to call
+                Some(_) => continue,
+                None => receiver,
+            },
+        };
+        calls.insert(format!("{}.{}", class_name, name));
+    }
+    calls.into_iter().collect()
+}
+
+// statements after a return, and loops and branches with a constant
+// condition
+fn dead_code(statements: &[Statement], name: &str, messages: &mut Vec<String>) {
+    for (i, statement) in statements.iter().enumerate() {
+        match statement {
+            Statement::Return(_) | Statement::Break | Statement::Continue
+                if i + 1 < statements.len() =>
+            {
+                let jump = match statement {
+                    Statement::Return(_) => "return",
+                    Statement::Break => "break",
+                    _ => "continue",
+                };
+                messages.push(format!("unreachable code after {} in {}", jump, name));
+            }
+            Statement::If {
+                condition,
+                statements,
+                else_statements,
+            } => {
+                match constant_value(condition) {
+                    Some(0) => messages.push(format!(
+                        "if in {} never runs: its condition is always false",
+                        name
+                    )),
+                    Some(_) if else_statements.is_some() => messages.push(format!(
+                        "else in {} never runs: its condition is always true",
+                        name
+                    )),
+                    _ => (),
+                }
+                dead_code(statements, name, messages);
+                if let Some(else_statements) = else_statements {
+                    dead_code(else_statements, name, messages);
+                }
+            }
+            Statement::While {
+                condition,
+                statements,
+            } => {
+                if constant_value(condition) == Some(0) {
+                    messages.push(format!(
+                        "while in {} never runs: its condition is always false",
+                        name
+                    ));
+                }
+                dead_code(statements, name, messages);
+            }
+            Statement::For {
+                condition,
+                statements,
+                ..
+            } => {
+                if condition.as_ref().and_then(constant_value) == Some(0) {
+                    messages.push(format!(
+                        "for in {} never runs: its condition is always false",
+                        name
+                    ));
+                }
+                dead_code(statements, name, messages);
+            }
+            _ => (),
+        }
+    }
+}
+
+// what the statements of a subroutine refer to
+#[derive(Default)]
+struct Uses {
+    read: HashSet<String>,
+    assigned: HashSet<String>,
+    // receiver and name of every call written in the code
+    calls: Vec<(Option<String>, String)>,
+    // OS subroutines the generated code calls
+    os_calls: HashSet<String>,
+    // expressions that conventional precedence would group differently,
+    // and how Jack groups them
+    mixed_operators: Vec<(String, String)>,
+}
+
+impl Uses {
+    fn problem(&self, variable: &String) -> Option<&'static str> {
+        if self.read.contains(variable) {
+            None
+        } else if self.assigned.contains(variable) {
+            Some("is assigned but never used")
+        } else {
+            Some("is never used")
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Let {
+                    name,
+                    index,
+                    op,
+                    value,
+                } => {
+                    match index {
+                        // the array's address is read
+                        Some(index) => {
+                            self.read.insert(name.clone());
+                            self.expression(index);
+                        }
+                        None => {
+                            self.assigned.insert(name.clone());
+                            // `let x += 1` reads x too
+                            if op.is_some() {
+                                self.read.insert(name.clone());
+                            }
+                        }
+                    }
+                    self.expression(value);
+                }
+                Statement::If {
+                    condition,
+                    statements,
+                    else_statements,
+                } => {
+                    self.expression(condition);
+                    self.statements(statements);
+                    if let Some(else_statements) = else_statements {
+                        self.statements(else_statements);
+                    }
+                }
+                Statement::While {
+                    condition,
+                    statements,
+                } => {
+                    self.expression(condition);
+                    self.statements(statements);
+                }
+                Statement::For {
+                    init,
+                    condition,
+                    update,
+                    statements,
+                } => {
+                    for assignment in init.iter().chain(update) {
+                        self.statements(std::slice::from_ref(assignment));
+                    }
+                    if let Some(condition) = condition {
+                        self.expression(condition);
+                    }
+                    self.statements(statements);
+                }
+                Statement::Do(call) => self.call(call),
+                Statement::Return(value) => {
+                    if let Some(value) = value {
+                        self.expression(value);
+                    }
+                }
+                Statement::Break | Statement::Continue => (),
+            }
+        }
+    }
+
+    fn call(&mut self, call: &SubroutineCall) {
+        if let Some(receiver) = &call.receiver {
+            self.read.insert(receiver.clone());
+        }
+        self.calls.push((call.receiver.clone(), call.name.clone()));
+        for argument in &call.arguments {
+            self.expression(argument);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::IntegerConstant(_) | Expression::KeywordConstant(_) => (),
+            Expression::StringConstant(_) => {
+                self.os_calls.insert("String.new".to_string());
+                self.os_calls.insert("String.appendChar".to_string());
+            }
+            Expression::Var(name) => {
+                self.read.insert(name.clone());
+            }
+            Expression::ArrayAccess(name, index) => {
+                self.read.insert(name.clone());
+                self.expression(index);
+            }
+            Expression::Call(call) => self.call(call),
+            Expression::Parenthesized(expression) | Expression::Unary(_, expression) => {
+                self.expression(expression)
+            }
+            Expression::Binary(..) => {
+                if regrouped(expression) {
+                    let left_to_right = left_to_right(expression);
+                    self.mixed_operators
+                        .push((expression.to_string(), left_to_right));
+                }
+                // the chain `a op b op c` is checked as a whole above, so
+                // only its operands are looked into
+                let mut operands = Vec::new();
+                let mut term = expression;
+                while let Expression::Binary(left, op, right) = term {
+                    match op {
+                        BinaryOp::Mul => self.os_calls.insert("Math.multiply".to_string()),
+                        BinaryOp::Div => self.os_calls.insert("Math.divide".to_string()),
+                        _ => false,
+                    };
+                    operands.push(right.as_ref());
+                    term = left;
+                }
+                operands.push(term);
+                for operand in operands.into_iter().rev() {
+                    self.expression(operand);
+                }
+            }
+        }
+    }
+}
+
+// Whether an operator in a left-to-right chain binds more tightly than one
+// before it, e.g. the `*` of `1 + 2 * 3`, so that conventional precedence
+// would group the chain differently. A chain parsed with precedence never
+// has one.
+fn regrouped(expression: &Expression) -> bool {
+    let mut term = expression;
+    while let Expression::Binary(left, op, _) = term {
+        if let Expression::Binary(_, left_op, _) = left.as_ref() {
+            if op.precedence() > left_op.precedence() {
+                return true;
+            }
+        }
+        term = left;
+    }
+    false
+}
+
+// the chain with the parentheses conventional precedence needs to read it
+// the way Jack does, e.g. `(1 + 2) * 3`
+fn left_to_right(expression: &Expression) -> String {
+    match expression {
+        Expression::Binary(left, op, right) => match left.as_ref() {
+            Expression::Binary(_, left_op, _) if left_op.precedence() < op.precedence() => {
+                format!("({}) {} {}", left_to_right(left), op, right)
+            }
+            _ => format!("{} {} {}", left_to_right(left), op, right),
+        },
+        _ => expression.to_string(),
+    }
+}
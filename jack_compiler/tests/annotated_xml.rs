@@ -0,0 +1,197 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jack_compiler::parser::{Dialect, ParseOptions};
+
+fn project(dir: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects")
+        .join(dir)
+}
+
+fn jack_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "jack")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn annotated(file: &Path) -> String {
+    let jack_code = fs::read_to_string(file).unwrap();
+    jack_compiler::annotated_xml(&jack_code)
+        .unwrap_or_else(|err| panic!("{}: {}", file.display(), err))
+}
+
+// the trimmed lines, with the `(...)` tag of each identifier left out
+fn without_tags(xml: &str) -> Vec<String> {
+    xml.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix("<identifier> (") {
+            Some(rest) => {
+                let name = &rest[rest.find(") ").unwrap() + 2..];
+                format!("<identifier> {}", name)
+            }
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+fn assert_has(xml: &str, file: &str, lines: &[&str]) {
+    for line in lines {
+        assert!(
+            xml.lines().any(|xml_line| xml_line == *line),
+            "{} has no `{}`",
+            file,
+            line
+        );
+    }
+}
+
+#[test]
+fn matches_the_course_parse_trees_without_the_tags() {
+    for dir in ["ArrayTest", "ExpressionLessSquare", "Square"] {
+        for file in jack_files(&project(&format!("10/{}", dir))) {
+            let expected = fs::read_to_string(file.with_extension("xml")).unwrap();
+            let expected: Vec<String> = expected
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            assert_eq!(
+                without_tags(&annotated(&file)),
+                expected,
+                "{}",
+                file.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn tags_variables_with_their_kind_and_index() {
+    let xml = annotated(&project("10/ArrayTest/Main.jack"));
+    assert_has(
+        &xml,
+        "ArrayTest/Main.jack",
+        &[
+            "<identifier> (class define) Main </identifier>",
+            "<identifier> (subroutine define) main </identifier>",
+            "<identifier> (class use) Array </identifier>",
+            "<identifier> (var define 0) a </identifier>",
+            "<identifier> (var define 3) sum </identifier>",
+            "<identifier> (var use 2) i </identifier>",
+            "<identifier> (class use) Keyboard </identifier>",
+            "<identifier> (subroutine use) readInt </identifier>",
+        ],
+    );
+
+    let xml = annotated(&project("10/Square/Square.jack"));
+    assert_has(
+        &xml,
+        "Square/Square.jack",
+        &[
+            "<identifier> (field define 0) x </identifier>",
+            "<identifier> (field define 2) size </identifier>",
+            "<identifier> (arg define 0) Ax </identifier>",
+            "<identifier> (field use 0) x </identifier>",
+            "<identifier> (arg use 0) Ax </identifier>",
+            "<identifier> (subroutine define) draw </identifier>",
+            "<identifier> (subroutine use) draw </identifier>",
+        ],
+    );
+
+    let xml = annotated(&project("10/Square/SquareGame.jack"));
+    assert_has(
+        &xml,
+        "Square/SquareGame.jack",
+        &[
+            "<identifier> (class use) Square </identifier>",
+            "<identifier> (field define 0) square </identifier>",
+            "<identifier> (var define 0) key </identifier>",
+            "<identifier> (field use 0) square </identifier>",
+        ],
+    );
+}
+
+#[test]
+fn tags_every_identifier_of_the_compiler_projects() {
+    for dir in [
+        "Average",
+        "ComplexArrays",
+        "ConvertToBin",
+        "Pong",
+        "Seven",
+        "Square",
+    ] {
+        for file in jack_files(&project(&format!("11/{}", dir))) {
+            let xml = annotated(&file);
+            for line in xml.lines().filter(|line| line.starts_with("<identifier>")) {
+                assert!(
+                    line.starts_with("<identifier> ("),
+                    "{}: untagged {}",
+                    file.display(),
+                    line
+                );
+            }
+        }
+    }
+
+    let xml = annotated(&project("11/Average/Main.jack"));
+    assert_has(
+        &xml,
+        "Average/Main.jack",
+        &[
+            "<identifier> (class use) Array </identifier>",
+            "<identifier> (var define 0) a </identifier>",
+            "<identifier> (var use 0) a </identifier>",
+        ],
+    );
+}
+
+#[test]
+fn follows_the_parse_options() {
+    let jack_code = "class Main {
+        const int SIZE = 4;
+        function int sum(Array a) {
+            var int total, i;
+            for (i = 0; i < SIZE; i += 1) {
+                if (a[i] < 0) { break; } else if (a[i] = 0) { continue; }
+                let total += a[i];
+            }
+            return total;
+        }
+    }";
+
+    assert!(jack_compiler::annotated_xml(jack_code).is_err());
+
+    let options = ParseOptions {
+        dialect: Dialect::Extended,
+        ..ParseOptions::default()
+    };
+    let xml = jack_compiler::annotated_xml_with(jack_code, options).unwrap();
+    assert_has(
+        &xml,
+        "the extended class",
+        &[
+            "<identifier> (constant define) SIZE </identifier>",
+            "<identifier> (constant use) SIZE </identifier>",
+            "<identifier> (class use) Array </identifier>",
+            "<identifier> (arg use 0) a </identifier>",
+            "<identifier> (var use 1) i </identifier>",
+            "<identifier> (var use 0) total </identifier>",
+            "<keyword> for </keyword>",
+            "<breakStatement>",
+            "<continueStatement>",
+        ],
+    );
+}
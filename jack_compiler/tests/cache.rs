@@ -0,0 +1,105 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use jack_compiler::cache::{content_hash, BuildCache};
+
+const MAIN: &str = "class Main {
+    function void main() { do Util.show(1); return; }
+}
+";
+
+const UTIL: &str = "class Util {
+    function void show(int n) { do Output.printInt(n); return; }
+}
+";
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("jack_compiler-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn compile(args: &[&str], dir: &Path) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jack_compiler"))
+        .args(args)
+        .arg(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn remembers_keys_between_builds() {
+    let dir = temp_dir("cache");
+    let mut cache = BuildCache::load(&dir);
+    assert!(!cache.is_fresh("Main.jack", 1));
+
+    cache.update("Main.jack", 0xabc);
+    cache.update("A B.jack", 2);
+    cache.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join(".jack_cache")).unwrap(),
+        "0000000000000002 A B.jack\n0000000000000abc Main.jack\n"
+    );
+
+    let cache = BuildCache::load(&dir);
+    assert!(cache.is_fresh("Main.jack", 0xabc));
+    assert!(cache.is_fresh("A B.jack", 2));
+    assert!(!cache.is_fresh("Main.jack", 0xabd));
+    assert!(!cache.is_fresh("Util.jack", 0xabc));
+}
+
+#[test]
+fn hashes_the_parts_apart() {
+    assert_eq!(content_hash(&[b"ab", b"c"]), content_hash(&[b"ab", b"c"]));
+    assert_ne!(content_hash(&[b"ab", b"c"]), content_hash(&[b"a", b"bc"]));
+    assert_ne!(content_hash(&[b"abc"]), content_hash(&[b"abd"]));
+    // the same in every build of the compiler, or caches would go stale
+    assert_eq!(content_hash(&[]), 0xcbf2_9ce4_8422_2325);
+}
+
+#[test]
+fn compiles_only_the_classes_that_changed() {
+    let dir = temp_dir("incremental");
+    fs::write(dir.join("Main.jack"), MAIN).unwrap();
+    fs::write(dir.join("Util.jack"), UTIL).unwrap();
+    let dir = dir.as_path();
+
+    compile(&[], dir);
+    let util_vm = fs::read_to_string(dir.join("Util.vm")).unwrap();
+    assert!(util_vm.starts_with("function Util.show 0"));
+
+    // a class that is compiled again loses the marker
+    let mark = |file: &str| fs::write(dir.join(file), "// marker\n").unwrap();
+    let marked = |file: &str| fs::read_to_string(dir.join(file)).unwrap() == "// marker\n";
+
+    mark("Main.vm");
+    mark("Util.vm");
+    compile(&[], dir);
+    assert!(marked("Main.vm") && marked("Util.vm"), "a hit was compiled");
+
+    fs::write(dir.join("Main.jack"), MAIN.replace("show(1)", "show(2)")).unwrap();
+    compile(&[], dir);
+    assert!(fs::read_to_string(dir.join("Main.vm"))
+        .unwrap()
+        .contains("push constant 2"));
+    assert!(marked("Util.vm"), "an unchanged class was compiled");
+
+    fs::remove_file(dir.join("Util.vm")).unwrap();
+    compile(&[], dir);
+    assert_eq!(fs::read_to_string(dir.join("Util.vm")).unwrap(), util_vm);
+
+    // other options can compile to other code
+    mark("Main.vm");
+    mark("Util.vm");
+    compile(&["--precedence"], dir);
+    assert!(!marked("Main.vm") && !marked("Util.vm"));
+}
@@ -0,0 +1,153 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+const MAIN: &str = "class Main { function void main() { return; } }\n";
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("jack_compiler-cli-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+// the exit code and standard error of the compiler
+fn compiler(args: &[&Path]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jack_compiler"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn rejects_unknown_options() {
+    let (status, stderr) = compiler(&[Path::new("--vm"), Path::new("Main.jack")]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("unknown option --vm\n"), "{}", stderr);
+    assert!(stderr.contains("usage: jack_compiler"), "{}", stderr);
+
+    let (status, stderr) = compiler(&[]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("no .jack file or directory given\n"));
+}
+
+#[test]
+fn reports_bad_inputs() {
+    let dir = temp_dir("inputs");
+    write(&dir.join("notes.txt"), "");
+    fs::create_dir(dir.join("empty")).unwrap();
+
+    let missing = dir.join("Missing.jack");
+    let (status, stderr) = compiler(&[&missing]);
+    assert_eq!(status, Some(1));
+    assert_eq!(
+        stderr,
+        format!("{}: no such file or directory\n", missing.display())
+    );
+
+    let notes = dir.join("notes.txt");
+    let (status, stderr) = compiler(&[&notes]);
+    assert_eq!(status, Some(1));
+    assert_eq!(stderr, format!("{}: not a .jack file\n", notes.display()));
+
+    let empty = dir.join("empty");
+    let (status, stderr) = compiler(&[&empty]);
+    assert_eq!(status, Some(1));
+    assert_eq!(stderr, format!("{}: no .jack files\n", empty.display()));
+}
+
+#[test]
+fn finds_jack_files_by_their_extension() {
+    // an absolute path, with "jack" in names that are not .jack files
+    let dir = temp_dir("discovery").join("jackfiles");
+    write(&dir.join("notes.jack.txt"), "not Jack");
+    write(&dir.join("Main.jack"), MAIN);
+    write(&dir.join("game/Ball.jack"), "class Ball { }\n");
+    write(&dir.join(".hidden/Broken.jack"), "class {");
+
+    let (status, stderr) = compiler(&[&dir]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert!(dir.join("Main.vm").exists());
+    assert!(dir.join("game/Ball.vm").exists());
+    assert!(!dir.join(".hidden/Broken.vm").exists());
+    assert!(!dir.join("notes.jack.vm").exists());
+}
+
+#[test]
+fn writes_under_the_out_dir() {
+    let dir = temp_dir("out_dir");
+    write(&dir.join("src/Main.jack"), MAIN);
+    write(&dir.join("src/game/Ball.jack"), "class Ball { }\n");
+    write(&dir.join("Extra.jack"), "class Extra { }\n");
+    let out = dir.join("build");
+
+    let (status, stderr) = compiler(&[
+        Path::new("--out-dir"),
+        &out,
+        &dir.join("src"),
+        &dir.join("Extra.jack"),
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert!(out.join("Main.vm").exists());
+    assert!(out.join("game/Ball.vm").exists());
+    assert!(out.join("Extra.vm").exists());
+    assert!(!dir.join("src/Main.vm").exists());
+    assert!(!dir.join("Extra.vm").exists());
+
+    let (status, stderr) = compiler(&[Path::new("-o")]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("-o needs a directory\n"), "{}", stderr);
+}
+
+#[test]
+fn refuses_to_overwrite_an_output() {
+    let dir = temp_dir("collision");
+    write(&dir.join("a/Main.jack"), MAIN);
+    write(&dir.join("b/Main.jack"), MAIN);
+    let out = dir.join("build");
+
+    let (status, stderr) = compiler(&[Path::new("-o"), &out, &dir.join("a"), &dir.join("b")]);
+    assert_eq!(status, Some(1));
+    assert_eq!(
+        stderr,
+        format!(
+            "{} and {} would overwrite each other's output in {}\n",
+            dir.join("a/Main.jack").display(),
+            dir.join("b/Main.jack").display(),
+            out.display()
+        )
+    );
+    assert!(!out.exists());
+
+    // the same file given twice is compiled once
+    let main = dir.join("a/Main.jack");
+    let (status, stderr) = compiler(&[&main, &main]);
+    assert_eq!(status, Some(0), "{}", stderr);
+}
+
+#[test]
+fn names_the_file_with_the_error() {
+    let dir = temp_dir("error");
+    let main = dir.join("Main.jack");
+    write(&main, "class Main {\n  function void main() { let; }\n}\n");
+
+    let (status, stderr) = compiler(&[&main]);
+    assert_eq!(status, Some(1));
+    assert!(
+        stderr.starts_with(&format!("Error: {}: line 2, column ", main.display())),
+        "{}",
+        stderr
+    );
+    assert!(!dir.join("Main.vm").exists());
+}
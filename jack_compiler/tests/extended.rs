@@ -0,0 +1,155 @@
+use jack_compiler::code_generator::CodeGenerator;
+use jack_compiler::parser::{Dialect, ParseOptions};
+use vm::VmSource;
+use vm_emulator::emulator::VmEmulator;
+
+const EXTENDED: ParseOptions = ParseOptions {
+    dialect: Dialect::Extended,
+    precedence: false,
+};
+
+// stores what Main.main returns in the first static, RAM[16]
+const SYS: &str = "class Sys {
+    static int result;
+    function void init() { let result = Main.main(); return; }
+}";
+
+// Compiles the classes and runs them on the VM emulator, which knows
+// nothing of the extensions, and returns the result of Main.main.
+fn run(main: &str) -> i16 {
+    let sources: Vec<VmSource> = [SYS, main]
+        .iter()
+        .map(|jack_code| {
+            let class = jack_compiler::parse_with(jack_code, EXTENDED)
+                .unwrap_or_else(|err| panic!("{}", err));
+            let vm_code = CodeGenerator::new().compile_class(&class).unwrap();
+            VmSource::new(&class.name, &vm_code)
+        })
+        .collect();
+    let mut emulator = VmEmulator::load_sources(&sources).unwrap();
+    for pointer in 0..5 {
+        emulator.poke(pointer, 256);
+    }
+    emulator.run(100_000).unwrap();
+    assert!(emulator.is_halted(), "Main.main did not return");
+    emulator.peek(16)
+}
+
+#[test]
+fn chains_else_ifs() {
+    let main = |n: i16| {
+        format!(
+            "class Main {{
+    function int main() {{
+        var int n;
+        let n = {};
+        if (n < 0) {{ return -1; }}
+        else if (n = 0) {{ return 0; }}
+        else if (n < 10) {{ return 1; }}
+        else {{ return 2; }}
+    }}
+}}",
+            n
+        )
+    };
+    assert_eq!(run(&main(-5)), -1);
+    assert_eq!(run(&main(0)), 0);
+    assert_eq!(run(&main(7)), 1);
+    assert_eq!(run(&main(10)), 2);
+}
+
+#[test]
+fn runs_for_loops_with_break_and_continue() {
+    // the odd numbers below 10, stopping at 7: 1 + 3 + 5
+    let sum = run("class Main {
+    function int main() {
+        var int i, sum;
+        for (i = 0; i < 10; i += 1) {
+            if (i = 7) { break; }
+            if ((i & 1) = 0) { continue; }
+            let sum += i;
+        }
+        return sum;
+    }
+}");
+    assert_eq!(sum, 9);
+
+    // break and continue only leave the innermost loop
+    let count = run("class Main {
+    function int main() {
+        var int i, j, count;
+        let i = 0;
+        while (i < 3) {
+            let i += 1;
+            for (j = 0; true; j += 1) {
+                if (j = 2) { break; }
+                let count += 1;
+            }
+            if (i = 2) { continue; }
+            let count += 10;
+        }
+        return count;
+    }
+}");
+    assert_eq!(count, 26);
+}
+
+#[test]
+fn assigns_with_compound_operators() {
+    let value = run("class Main {
+    function int main() {
+        var int x;
+        var Array a;
+        let x = 5;
+        let x += 3;
+        let x -= 1;
+        let x |= 8;
+        let x &= 13;
+        let a = 3000;
+        let a[2] = 4;
+        let a[2] += x;
+        return a[2];
+    }
+}");
+    assert_eq!(value, 17);
+}
+
+#[test]
+fn keeps_standard_jack_standard() {
+    let cases = [
+        (
+            "if (true) { } else if (false) { }",
+            "else if is not standard Jack; it needs the extended dialect",
+        ),
+        (
+            "for (i = 0; i < 1; i += 1) { }",
+            "a for loop is not standard Jack; it needs the extended dialect",
+        ),
+        (
+            "while (true) { break; }",
+            "break statement is not standard Jack; it needs the extended dialect",
+        ),
+        (
+            "let i += 1;",
+            "compound assignment is not standard Jack; it needs the extended dialect",
+        ),
+    ];
+    for (statement, message) in cases {
+        let jack_code = format!(
+            "class Main {{ function void main() {{ var int i; {} return; }} }}",
+            statement
+        );
+        let err = jack_compiler::parse(&jack_code).err().unwrap();
+        assert_eq!(err.message, message, "{}", statement);
+        assert!(jack_compiler::parse_with(&jack_code, EXTENDED).is_ok());
+    }
+}
+
+#[test]
+fn rejects_break_outside_of_a_loop() {
+    let jack_code = "class Main { function void main() { if (true) { continue; } return; } }";
+    let err = jack_compiler::parse_with(jack_code, EXTENDED)
+        .err()
+        .unwrap();
+    assert_eq!(err.message, "continue outside of a loop");
+}
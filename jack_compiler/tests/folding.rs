@@ -0,0 +1,107 @@
+use jack_compiler::jack_tokenizer::Location;
+
+// the VM code of `let x = <expression>;` in a function with locals x and y
+fn compile_let(expression: &str) -> Vec<String> {
+    let jack_code = format!(
+        "class Main {{
+    function void main() {{
+        var int x, y;
+        let x = {};
+        return;
+    }}
+}}",
+        expression
+    );
+    let vm_code =
+        jack_compiler::compile(&jack_code).unwrap_or_else(|err| panic!("{}: {}", expression, err));
+    let lines: Vec<String> = vm_code.lines().map(str::to_string).collect();
+    let end = lines.iter().position(|line| line == "pop local 0").unwrap();
+    // after `function Main.main 2`
+    lines[1..=end].to_vec()
+}
+
+#[test]
+fn folds_constant_expressions() {
+    assert_eq!(
+        compile_let("2 * 8 + 1"),
+        ["push constant 17", "pop local 0"]
+    );
+    assert_eq!(
+        compile_let("(10 - 3) / 2 = 3"),
+        ["push constant 0", "not", "pop local 0"]
+    );
+    assert_eq!(compile_let("~0"), ["push constant 0", "not", "pop local 0"]);
+    assert_eq!(
+        compile_let("true & false"),
+        ["push constant 0", "pop local 0"]
+    );
+}
+
+#[test]
+fn compiles_the_most_negative_integer() {
+    assert_eq!(
+        compile_let("-32768"),
+        ["push constant 32767", "not", "pop local 0"]
+    );
+    assert_eq!(compile_let("-1"), ["push constant 0", "not", "pop local 0"]);
+}
+
+#[test]
+fn leaves_out_identities() {
+    assert_eq!(compile_let("y * 1"), ["push local 1", "pop local 0"]);
+    assert_eq!(compile_let("0 + y"), ["push local 1", "pop local 0"]);
+    assert_eq!(compile_let("y - 0"), ["push local 1", "pop local 0"]);
+    assert_eq!(compile_let("y * 0"), ["push constant 0", "pop local 0"]);
+    // the call has to happen even though its result does not matter
+    assert!(compile_let("Main.f() * 0").contains(&"call Main.f 0".to_string()));
+}
+
+#[test]
+fn multiplies_by_powers_of_two_by_adding() {
+    let code = compile_let("y * 8");
+    assert!(
+        !code.iter().any(|line| line.starts_with("call")),
+        "{:?}",
+        code
+    );
+    assert_eq!(code.iter().filter(|line| *line == "add").count(), 3);
+
+    let code = compile_let("4 * y");
+    assert!(
+        !code.iter().any(|line| line.starts_with("call")),
+        "{:?}",
+        code
+    );
+    assert_eq!(code.iter().filter(|line| *line == "add").count(), 2);
+}
+
+#[test]
+fn leaves_other_arithmetic_to_the_os() {
+    assert!(compile_let("y * 6").contains(&"call Math.multiply 2".to_string()));
+    // dividing a negative number by shifting would round the wrong way
+    assert!(compile_let("y / 4").contains(&"call Math.divide 2".to_string()));
+    // division by zero is left to happen at run time
+    assert!(compile_let("1 / 0").contains(&"call Math.divide 2".to_string()));
+}
+
+#[test]
+fn rejects_integers_out_of_range() {
+    let err = jack_compiler::compile("class Main {\n    function int f() { return 32768; }\n}")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.message,
+        "integer constant 32768 is out of range 0..32767"
+    );
+    assert_eq!(
+        err.location,
+        Some(Location {
+            line: 1,
+            column: 30
+        })
+    );
+
+    assert!(
+        jack_compiler::compile("class Main {\n    function int f() { return 32767; }\n}").is_ok()
+    );
+}
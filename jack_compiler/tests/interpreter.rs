@@ -0,0 +1,111 @@
+use std::{fs, path::Path};
+
+use jack_compiler::interpreter::{Exit, Interpreter};
+
+fn project(dir: &str) -> Interpreter {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects")
+        .join(dir);
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "jack")
+        })
+        .collect();
+    files.sort();
+    let jack_codes: Vec<String> = files
+        .iter()
+        .map(|file| fs::read_to_string(file).unwrap())
+        .collect();
+    let jack_codes: Vec<&str> = jack_codes.iter().map(String::as_str).collect();
+    Interpreter::from_sources(&jack_codes).unwrap()
+}
+
+#[test]
+fn runs_the_course_programs() {
+    let mut interpreter = project("11/Seven");
+    assert_eq!(interpreter.run(1000).unwrap(), Exit::Halted);
+    assert_eq!(interpreter.output(), "7");
+
+    let mut interpreter = project("11/ConvertToBin");
+    interpreter.poke(8000, 13);
+    assert_eq!(interpreter.run(100_000).unwrap(), Exit::Halted);
+    let bits: Vec<i16> = (8001..8017)
+        .map(|address| interpreter.peek(address))
+        .collect();
+    assert_eq!(bits, [1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn reads_typed_keys() {
+    let mut interpreter = project("11/Average");
+    interpreter.type_keys("3\n10\n20\n36\n");
+    assert_eq!(interpreter.run(100_000).unwrap(), Exit::Halted);
+    assert!(
+        interpreter.output().ends_with("The average is 22"),
+        "{}",
+        interpreter.output()
+    );
+}
+
+#[test]
+fn calls_single_functions() {
+    let mut interpreter = Interpreter::from_sources(&["class Fib {
+        function int fib(int n) {
+            if (n < 2) { return n; }
+            return Fib.fib(n - 1) + Fib.fib(n - 2);
+        }
+    }"])
+    .unwrap();
+    assert_eq!(interpreter.call("Fib.fib", &[10]).unwrap(), 55);
+    assert_eq!(interpreter.call("Math.multiply", &[-6, 7]).unwrap(), -42);
+}
+
+#[test]
+fn draws_on_the_headless_screen() {
+    let mut interpreter = Interpreter::from_sources(&["class Main {
+        function void main() {
+            do Screen.drawLine(0, 0, 9, 0);
+            return;
+        }
+    }"])
+    .unwrap();
+    interpreter.run(1000).unwrap();
+    assert!((0..10).all(|x| interpreter.pixel(x, 0)));
+    assert!(!interpreter.pixel(10, 0));
+    assert!(!interpreter.pixel(0, 1));
+}
+
+#[test]
+fn stops_at_the_step_limit() {
+    let mut interpreter = Interpreter::from_sources(&["class Main {
+        function void main() {
+            while (true) { }
+            return;
+        }
+    }"])
+    .unwrap();
+    assert_eq!(interpreter.run(500).unwrap(), Exit::StepLimit);
+}
+
+#[test]
+fn reports_runtime_errors_with_the_function() {
+    let mut interpreter = Interpreter::from_sources(&["class Main {
+        function void main() {
+            do Output.printInt(Main.half(0));
+            return;
+        }
+        function int half(int n) {
+            return 1 / n;
+        }
+    }"])
+    .unwrap();
+    let err = interpreter.run(1000).unwrap_err();
+    assert_eq!(
+        err.call_stack,
+        ["Sys.init", "Main.main", "Main.half", "Math.divide"]
+    );
+    assert!(err.message.contains("division by zero"), "{}", err);
+}
@@ -0,0 +1,167 @@
+use jack_compiler::jack_tokenizer::JackTokenizer;
+use jack_compiler::parser::{Dialect, ParseOptions};
+
+const EXTENDED: ParseOptions = ParseOptions {
+    dialect: Dialect::Extended,
+    precedence: false,
+};
+
+fn compile(jack_code: &str, options: ParseOptions) -> Result<String, String> {
+    let class = jack_compiler::parse_with(jack_code, options).map_err(|err| err.message)?;
+    jack_compiler::code_generator::CodeGenerator::new()
+        .compile_class(&class)
+        .map_err(|err| err.message)
+}
+
+// the VM code of `return <expression>;`
+fn returned(expression: &str) -> Vec<String> {
+    let jack_code = format!(
+        "class Main {{ function int main() {{ return {}; }} }}",
+        expression
+    );
+    let vm_code = compile(&jack_code, EXTENDED).unwrap_or_else(|err| panic!("{}", err));
+    vm_code
+        .lines()
+        .skip(1)
+        .take_while(|line| *line != "return")
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn reads_the_new_literals() {
+    let tokens: Vec<String> = JackTokenizer::located_tokens("'A' ' ' 0x1F 0B101 12")
+        .unwrap()
+        .into_iter()
+        .map(|(token, _)| format!("{:?}", token))
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            "CharConstant('A')",
+            "CharConstant(' ')",
+            "RadixConstant { text: \"0x1F\", value: 31 }",
+            "RadixConstant { text: \"0B101\", value: 5 }",
+            "IntegerConstant(12)",
+        ]
+    );
+
+    for (jack_code, message) in [
+        ("'AB'", "a character literal holds one character"),
+        ("'A", "unterminated character literal"),
+        ("'é'", "character 'é' is not in the Hack character set"),
+        ("0x1G", "invalid integer constant 0x1G"),
+    ] {
+        let err = JackTokenizer::located_tokens(jack_code).err().unwrap();
+        assert_eq!(err.message, message, "{}", jack_code);
+    }
+}
+
+#[test]
+fn compiles_literals_to_constants() {
+    assert_eq!(returned("'A'"), ["push constant 65"]);
+    assert_eq!(returned("0x7FFF"), ["push constant 32767"]);
+    assert_eq!(returned("0b1010"), ["push constant 10"]);
+    // the top bit makes a negative number
+    assert_eq!(returned("0xFFFF"), ["push constant 0", "not"]);
+    assert_eq!(returned("0x8000"), ["push constant 32767", "not"]);
+
+    let err = compile(
+        "class Main { function int main() { return 0x10000; } }",
+        EXTENDED,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err, "integer constant 0x10000 does not fit in 16 bits");
+}
+
+#[test]
+fn inlines_constants() {
+    let vm_code = compile(
+        "class Main {
+    const int WIDTH = 32, HEIGHT = WIDTH * 2;
+    const char QUOTE = 34;
+    const boolean DEBUG = false;
+    static int count;
+
+    function int main() {
+        if (DEBUG) { do Output.printChar(QUOTE); }
+        return HEIGHT - 1;
+    }
+}",
+        EXTENDED,
+    )
+    .unwrap();
+    assert!(vm_code.contains("push constant 63\nreturn"), "{}", vm_code);
+    assert!(vm_code.contains("push constant 34\ncall Output.printChar 1"));
+    // constants take no static variable
+    assert!(!vm_code.contains("static"), "{}", vm_code);
+}
+
+#[test]
+fn checks_constant_declarations() {
+    let cases = [
+        (
+            "const Array A = 1;",
+            "a constant must be an int, char or boolean",
+        ),
+        ("const int A = 1, A = 2;", "A is already declared"),
+        ("static int A; const int A = 1;", "A is already declared"),
+        (
+            "const int A = 1; field int A;",
+            "A is already declared as a constant",
+        ),
+        (
+            "const int A = Main.f();",
+            "the value of A is not known at compile time",
+        ),
+        (
+            "const int A = 1; function void g() { let A = 2; return; }",
+            "cannot assign to constant A",
+        ),
+    ];
+    for (declarations, message) in cases {
+        let jack_code = format!(
+            "class Main {{ {} function int f() {{ return 0; }} }}",
+            declarations
+        );
+        assert_eq!(
+            compile(&jack_code, EXTENDED).err().as_deref(),
+            Some(message),
+            "{}",
+            declarations
+        );
+    }
+}
+
+#[test]
+fn keeps_standard_jack_standard() {
+    for (expression, message) in [
+        (
+            "'A'",
+            "a character literal is not standard Jack; it needs the extended dialect",
+        ),
+        (
+            "0x1F",
+            "a hexadecimal or binary literal is not standard Jack; it needs the extended dialect",
+        ),
+    ] {
+        let jack_code = format!(
+            "class Main {{ function int main() {{ return {}; }} }}",
+            expression
+        );
+        let err = compile(&jack_code, ParseOptions::default()).err().unwrap();
+        assert_eq!(err, message);
+    }
+
+    let err = compile(
+        "class Main { const int A = 1; function int main() { return A; } }",
+        ParseOptions::default(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err,
+        "a constant declaration is not standard Jack; it needs the extended dialect"
+    );
+}
@@ -0,0 +1,113 @@
+use jack_compiler::ast::{Expression, Statement};
+use jack_compiler::code_generator::constant_value;
+use jack_compiler::parser::{Dialect, ParseOptions};
+use jack_compiler::warnings::class_warnings;
+
+const PRECEDENCE: ParseOptions = ParseOptions {
+    dialect: Dialect::Standard,
+    precedence: true,
+};
+
+fn class_returning(expression: &str) -> String {
+    format!(
+        "class Main {{ function int main() {{ var int x, y; return {}; }} }}",
+        expression
+    )
+}
+
+// the expression with every binary operation in parentheses
+fn grouped(expression: &Expression) -> String {
+    match expression {
+        Expression::Binary(left, op, right) => {
+            format!("({} {} {})", grouped(left), op, grouped(right))
+        }
+        Expression::Parenthesized(expression) => grouped(expression),
+        Expression::Unary(op, term) => format!("{}{}", op, grouped(term)),
+        _ => expression.to_string(),
+    }
+}
+
+fn parse_expression(expression: &str, options: ParseOptions) -> Expression {
+    let mut class = jack_compiler::parse_with(&class_returning(expression), options)
+        .unwrap_or_else(|err| panic!("{}: {}", expression, err));
+    match class.subroutines.remove(0).statements.remove(0) {
+        Statement::Return(Some(expression)) => expression,
+        _ => panic!("no return statement"),
+    }
+}
+
+fn both_ways(expression: &str) -> (String, String) {
+    (
+        grouped(&parse_expression(expression, ParseOptions::default())),
+        grouped(&parse_expression(expression, PRECEDENCE)),
+    )
+}
+
+#[test]
+fn groups_by_precedence_when_asked() {
+    assert_eq!(
+        both_ways("1 + 2 * 3"),
+        ("((1 + 2) * 3)".to_string(), "(1 + (2 * 3))".to_string())
+    );
+    assert_eq!(
+        both_ways("x < y + 1 & y > 0 | x = 0"),
+        (
+            "((((((x < y) + 1) & y) > 0) | x) = 0)".to_string(),
+            "(((x < (y + 1)) & (y > 0)) | (x = 0))".to_string()
+        )
+    );
+    // operators of the same level still group to the left
+    assert_eq!(
+        both_ways("10 - 3 - 2 / 2 * 4").1,
+        "((10 - 3) - ((2 / 2) * 4))"
+    );
+    assert_eq!(both_ways("-x * (1 + y)").1, "(-x * (1 + y))");
+}
+
+#[test]
+fn computes_what_each_grouping_means() {
+    let value =
+        |expression: &str, options| constant_value(&parse_expression(expression, options)).unwrap();
+    assert_eq!(value("1 + 2 * 3", ParseOptions::default()), 9);
+    assert_eq!(value("1 + 2 * 3", PRECEDENCE), 7);
+    assert_eq!(value("1 < 2 + 3 & 4 > 5 - 6", ParseOptions::default()), -6);
+    assert_eq!(value("1 < 2 + 3 & 4 > 5 - 6", PRECEDENCE), -1);
+}
+
+#[test]
+fn warns_about_chains_precedence_would_regroup() {
+    let warnings = |expression: &str, options| -> Vec<String> {
+        let class = jack_compiler::parse_with(&class_returning(expression), options).unwrap();
+        class_warnings(&class)
+            .into_iter()
+            .map(|warning| warning.message)
+            .filter(|message| message.contains("left to right"))
+            .collect()
+    };
+
+    assert_eq!(
+        warnings("x + y * 2", ParseOptions::default()),
+        [
+            "`x + y * 2` in Main.main is evaluated left to right, as `(x + y) * 2`; \
+          add parentheses to make the order clear"
+        ]
+    );
+    assert_eq!(
+        warnings("x - 1 < y & x > 0", ParseOptions::default()),
+        [
+            "`x - 1 < y & x > 0` in Main.main is evaluated left to right, as \
+          `(x - 1 < y & x) > 0`; add parentheses to make the order clear"
+        ]
+    );
+
+    // nothing to regroup
+    for expression in ["x * 2 + y", "x + (y * 2)", "x - y + 1", "x < y & (x > 0)"] {
+        assert!(
+            warnings(expression, ParseOptions::default()).is_empty(),
+            "{}",
+            expression
+        );
+    }
+    // read the conventional way already
+    assert!(warnings("x + y * 2", PRECEDENCE).is_empty());
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use jack_compiler::jack_tokenizer::{JackTokenizer, Lexeme, Location};
+
+// the tokens as `Debug` text, which is enough to tell them apart
+fn tokens(jack_code: &str) -> Vec<String> {
+    JackTokenizer::located_tokens(jack_code)
+        .unwrap()
+        .into_iter()
+        .map(|(token, _)| format!("{:?}", token))
+        .collect()
+}
+
+#[test]
+fn treats_tabs_and_carriage_returns_as_whitespace() {
+    assert_eq!(
+        tokens("class\tMain {\r\n\tfield int x;\r\n}\r\n"),
+        tokens("class Main {\n    field int x;\n}\n")
+    );
+
+    let located = JackTokenizer::located_tokens("class Main {\r\n\tfield int x;\r\n}").unwrap();
+    assert_eq!(located[3].1, Location { line: 1, column: 1 });
+}
+
+#[test]
+fn keeps_comment_markers_inside_strings() {
+    let tokens = tokens("let s = \"http://x /* y */\"; // a comment\nlet t = 1;");
+    assert!(
+        tokens.contains(&"StringConstant(\"http://x /* y */\")".to_string()),
+        "{:?}",
+        tokens
+    );
+    assert_eq!(tokens.len(), 10);
+}
+
+#[test]
+fn reports_unterminated_literals_where_they_start() {
+    let err = JackTokenizer::from_code("class Main {\n    let s = \"abc\n}")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.location,
+        Some(Location {
+            line: 1,
+            column: 12
+        })
+    );
+    assert_eq!(err.message, "unterminated string constant");
+
+    let err = JackTokenizer::from_code("class Main {\n  /* never closed\n}")
+        .err()
+        .unwrap();
+    assert_eq!(err.location, Some(Location { line: 1, column: 2 }));
+    assert_eq!(err.message, "unterminated comment");
+    assert_eq!(
+        err.to_string(),
+        "Main.jack: line 2, column 3: unterminated comment"
+    );
+}
+
+#[test]
+fn keeps_comments_as_trivia() {
+    let jack_code =
+        "class Main {\n    /** Starts here. */\n    function void main() { return; } // done\n\n}";
+    let lexemes = JackTokenizer::lexemes(jack_code).unwrap();
+    let comments: Vec<(String, bool)> = lexemes
+        .iter()
+        .filter_map(|lexeme| match lexeme {
+            Lexeme::Comment { text, trailing } => Some((text.clone(), *trailing)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        comments,
+        [
+            ("/** Starts here. */".to_string(), false),
+            ("// done".to_string(), true),
+        ]
+    );
+    assert!(lexemes
+        .iter()
+        .any(|lexeme| matches!(lexeme, Lexeme::BlankLine)));
+
+    let tokenizer = JackTokenizer::from_code(jack_code).unwrap();
+    assert_eq!(tokenizer.doc_comment(3), Some("/** Starts here. */"));
+    assert_eq!(tokenizer.doc_comment(4), None);
+}
+
+#[test]
+fn names_the_file_an_error_is_in() {
+    let err = jack_compiler::parse("class Main {\n    function void main() { let = 1; }\n}")
+        .err()
+        .unwrap()
+        .in_file(Path::new("src/Game.jack"));
+    let message = err.to_string();
+    assert!(
+        message.starts_with("src/Game.jack: line 2, column "),
+        "{}",
+        message
+    );
+}
@@ -0,0 +1,149 @@
+use std::{fs, path::Path};
+
+use jack_compiler::ast::Class;
+use jack_compiler::parser::{Dialect, ParseOptions};
+use jack_compiler::warnings::{class_warnings, program_warnings, remove_unreachable};
+
+fn parse(jack_code: &str) -> Class {
+    let options = ParseOptions {
+        dialect: Dialect::Extended,
+        ..ParseOptions::default()
+    };
+    jack_compiler::parse_with(jack_code, options).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn messages(classes: &[Class]) -> Vec<String> {
+    program_warnings(classes, &[])
+        .into_iter()
+        .map(|warning| warning.to_string())
+        .collect()
+}
+
+#[test]
+fn finds_unused_variables() {
+    let class = parse(
+        "class Counter {
+    field int count, unused;
+    static int total;
+
+    method void add(int amount, int ignored) {
+        var int before, spare, step;
+        let before = count;
+        let step = 1;
+        let count += amount;
+        let total = total + step;
+        return;
+    }
+}",
+    );
+    let messages: Vec<String> = class_warnings(&class)
+        .into_iter()
+        .map(|warning| warning.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "local variable before in Counter.add is assigned but never used",
+            "local variable spare in Counter.add is never used",
+            "parameter ignored of Counter.add is never used",
+            "field unused is never used",
+        ]
+    );
+}
+
+#[test]
+fn finds_code_that_never_runs() {
+    let class = parse(
+        "class Main {
+    function void main() {
+        while (false) { do Output.printInt(1); }
+        if (0) { do Output.printInt(2); }
+        if (true) { do Output.printInt(3); } else { do Output.printInt(4); }
+        return;
+        do Output.printInt(5);
+    }
+}",
+    );
+    assert_eq!(
+        messages(&[class]),
+        [
+            "Main.jack: warning: while in Main.main never runs: its condition is always false",
+            "Main.jack: warning: if in Main.main never runs: its condition is always false",
+            "Main.jack: warning: else in Main.main never runs: its condition is always true",
+            "Main.jack: warning: unreachable code after return in Main.main",
+        ]
+    );
+}
+
+#[test]
+fn finds_private_subroutines_nothing_calls() {
+    let main = parse(
+        "class Main {
+    function void main() { do Helper._used(); return; }
+    function void _unused() { return; }
+}",
+    );
+    let helper = parse(
+        "class Helper {
+    function void _used() { return; }
+    function void _unused() { do Helper._alsoUnused(); return; }
+    function void _alsoUnused() { return; }
+    function void public() { return; }
+}",
+    );
+    assert_eq!(
+        messages(&[main, helper]),
+        [
+            "Main.jack: warning: function Main._unused is never called",
+            "Helper.jack: warning: function Helper._unused is never called",
+        ]
+    );
+}
+
+#[test]
+fn finds_no_problems_in_the_course_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/11/Pong");
+    let classes: Vec<Class> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "jack")
+        })
+        .map(|path| parse(&fs::read_to_string(path).unwrap()))
+        .collect();
+    assert_eq!(messages(&classes), Vec::<String>::new());
+}
+
+#[test]
+fn removes_uncalled_subroutines() {
+    let mut classes = vec![
+        parse(
+            "class Main {
+    function void main() { var Ball b; let b = Ball.new(); do b.move(); return; }
+    function void unused() { do Ball.stop(); return; }
+}",
+        ),
+        parse(
+            "class Ball {
+    constructor Ball new() { return this; }
+    method void move() { do bounce(); return; }
+    method void bounce() { return; }
+    function void stop() { return; }
+}",
+        ),
+    ];
+    remove_unreachable(&mut classes);
+
+    let names: Vec<Vec<&str>> = classes
+        .iter()
+        .map(|class| {
+            class
+                .subroutines
+                .iter()
+                .map(|subroutine| subroutine.name.as_str())
+                .collect()
+        })
+        .collect();
+    assert_eq!(names, [vec!["main"], vec!["new", "move", "bounce"]]);
+}
@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+
+use jack_compiler::jack_tokenizer::{Keywords, Location};
+use jack_compiler::symbol_table::Kind;
+
+use crate::index::{ClassIndex, Subroutine, Variable};
+use crate::project::Project;
+
+const PRIMITIVE_TYPES: [&str; 3] = ["int", "char", "boolean"];
+
+/// What a name in the code refers to.
+pub enum Symbol<'a> {
+    Variable(&'a Variable),
+    Subroutine {
+        class: &'a str,
+        subroutine: &'a Subroutine,
+    },
+    Class(&'a ClassIndex),
+}
+
+pub struct Diagnostic {
+    pub message: String,
+    pub range: (Location, Location),
+}
+
+/// The symbol of the identifier token at `token`.
+pub fn resolve<'a>(
+    class: &'a ClassIndex,
+    project: &'a Project,
+    token: usize,
+) -> Option<Symbol<'a>> {
+    let name = class.identifier(token)?;
+    let subroutine = class.subroutine_at(token);
+
+    if token >= 2 && class.is_symbol(token - 1, '.') {
+        let qualifier = class_of(class, project, token - 2)?;
+        let subroutine = qualifier.subroutine(name)?;
+        return Some(Symbol::Subroutine {
+            class: &qualifier.name,
+            subroutine,
+        });
+    }
+    let declared_here = class
+        .subroutines
+        .iter()
+        .find(|subroutine| subroutine.location == class.tokens[token].1);
+    if let Some(subroutine) = declared_here {
+        return Some(Symbol::Subroutine {
+            class: &class.name,
+            subroutine,
+        });
+    }
+    if class.is_symbol(token + 1, '(') {
+        return class.subroutine(name).map(|subroutine| Symbol::Subroutine {
+            class: &class.name,
+            subroutine,
+        });
+    }
+    if let Some(variable) = class.variable(name, subroutine) {
+        return Some(Symbol::Variable(variable));
+    }
+    if name == class.name {
+        return Some(Symbol::Class(class));
+    }
+    project
+        .class(name)
+        .map(|project_class| Symbol::Class(&project_class.index))
+}
+
+/// Where a symbol is declared: the file (None for this document) and the
+/// location of its name. The built-in OS classes have no file.
+pub fn definition(
+    class: &ClassIndex,
+    project: &Project,
+    symbol: &Symbol,
+) -> Option<(Option<PathBuf>, Location)> {
+    let in_class = |name: &str, location: Location| {
+        if name == class.name {
+            return Some((None, location));
+        }
+        let path = project.class(name)?.path.clone()?;
+        Some((Some(path), location))
+    };
+    match symbol {
+        Symbol::Variable(variable) => Some((None, variable.location)),
+        Symbol::Subroutine { class, subroutine } => in_class(class, subroutine.location),
+        Symbol::Class(index) => in_class(&index.name, index.location),
+    }
+}
+
+/// The class an expression before `.` is or holds: a class name, or a
+/// variable of a class type.
+pub fn class_of<'a>(
+    class: &'a ClassIndex,
+    project: &'a Project,
+    token: usize,
+) -> Option<&'a ClassIndex> {
+    let name = class.identifier(token)?;
+    let class_name = match class.variable(name, class.subroutine_at(token)) {
+        Some(variable) => variable.var_type.as_str(),
+        None => name,
+    };
+    if class_name == class.name {
+        return Some(class);
+    }
+    project
+        .class(class_name)
+        .map(|project_class| &project_class.index)
+}
+
+/// Syntax errors, names declared twice, unknown types and names, and calls
+/// that do not match the called subroutine.
+pub fn diagnostics(class: &ClassIndex, project: &Project) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut error = |message: String, range: (Location, Location)| {
+        diagnostics.push(Diagnostic { message, range })
+    };
+    let name_range = |name: &str, location: Location| {
+        let end = Location {
+            line: location.line,
+            column: location.column + name.chars().count(),
+        };
+        (location, end)
+    };
+
+    if let Some((message, range)) = &class.syntax_error {
+        error(message.clone(), *range);
+    }
+    for (name, location) in &class.duplicates {
+        error(
+            format!("{} is already declared", name),
+            name_range(name, *location),
+        );
+    }
+
+    let is_type = |name: &str| {
+        PRIMITIVE_TYPES.contains(&name) || name == class.name || project.class(name).is_some()
+    };
+    let variables = class.variables.iter().chain(
+        class
+            .subroutines
+            .iter()
+            .flat_map(|subroutine| subroutine.parameters.iter().chain(&subroutine.locals)),
+    );
+    for variable in variables {
+        if !is_type(&variable.var_type) {
+            error(
+                format!("unknown type {}", variable.var_type),
+                name_range(&variable.name, variable.location),
+            );
+        }
+    }
+
+    for subroutine in &class.subroutines {
+        if subroutine.return_type != "void" && !is_type(&subroutine.return_type) {
+            error(
+                format!("unknown return type {}", subroutine.return_type),
+                name_range(&subroutine.name, subroutine.location),
+            );
+        }
+        for token in subroutine.statements.clone() {
+            if let Some((message, range)) = check_token(class, project, subroutine, token) {
+                error(message, range);
+            }
+        }
+    }
+    diagnostics
+}
+
+// the problem with one token of a subroutine's statements, if any
+fn check_token(
+    class: &ClassIndex,
+    project: &Project,
+    subroutine: &Subroutine,
+    token: usize,
+) -> Option<(String, (Location, Location))> {
+    let range = (class.tokens[token].1, class.token_end(token));
+    let in_function = subroutine.kind == Keywords::Function;
+    if class.keyword(token) == Some(Keywords::This) && in_function {
+        return Some(("this cannot be used in a function".to_string(), range));
+    }
+    let name = class.identifier(token)?;
+    if token > 0 && class.is_symbol(token - 1, '.') {
+        // checked with the name before the `.`
+        return None;
+    }
+
+    if class.is_symbol(token + 1, '.') {
+        let member = class.identifier(token + 2)?;
+        let member_range = (class.tokens[token + 2].1, class.token_end(token + 2));
+        let variable = class.variable(name, Some(subroutine));
+        if let Some(variable) = variable {
+            if variable.kind == Kind::FIELD && in_function {
+                return Some((
+                    format!("field {} cannot be used in a function", name),
+                    range,
+                ));
+            }
+            if PRIMITIVE_TYPES.contains(&variable.var_type.as_str()) {
+                return Some((
+                    format!("{} is {}, not an object", name, variable.var_type),
+                    range,
+                ));
+            }
+        } else if name != class.name && project.class(name).is_none() {
+            return Some((format!("unknown class or variable {}", name), range));
+        }
+
+        // types that are not classes were reported where they were declared
+        let callee_class = class_of(class, project, token)?;
+        let callee = match callee_class.subroutine(member) {
+            Some(callee) => callee,
+            None => {
+                let message = format!("{} has no subroutine {}", callee_class.name, member);
+                return Some((message, member_range));
+            }
+        };
+        if variable.is_some() && !callee.is_method() {
+            let message = format!(
+                "{} is not a method; call it as {}.{}",
+                member, callee_class.name, member
+            );
+            return Some((message, member_range));
+        }
+        if variable.is_none() && callee.is_method() {
+            let message = format!("{}.{} is a method; call it on an object", name, member);
+            return Some((message, member_range));
+        }
+        return check_arguments(class, callee, token + 3, member_range);
+    }
+
+    if class.is_symbol(token + 1, '(') {
+        let callee = match class.subroutine(name) {
+            Some(callee) => callee,
+            None => return Some((format!("{} has no subroutine {}", class.name, name), range)),
+        };
+        if callee.is_method() && in_function {
+            let message = format!("method {} cannot be called from a function", name);
+            return Some((message, range));
+        }
+        return check_arguments(class, callee, token + 1, range);
+    }
+
+    match class.variable(name, Some(subroutine)) {
+        Some(variable) if variable.kind == Kind::FIELD && in_function => Some((
+            format!("field {} cannot be used in a function", name),
+            range,
+        )),
+        Some(_) => None,
+        None => Some((format!("undefined variable {}", name), range)),
+    }
+}
+
+// the number of arguments in the list opening at `open`
+fn check_arguments(
+    class: &ClassIndex,
+    callee: &Subroutine,
+    open: usize,
+    range: (Location, Location),
+) -> Option<(String, (Location, Location))> {
+    if !class.is_symbol(open, '(') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut count = usize::from(!class.is_symbol(open + 1, ')'));
+    let mut i = open;
+    while i < class.tokens.len() {
+        if class.is_symbol(i, '(') || class.is_symbol(i, '[') {
+            depth += 1;
+        } else if class.is_symbol(i, ')') || class.is_symbol(i, ']') {
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        } else if class.is_symbol(i, ',') && depth == 1 {
+            count += 1;
+        }
+        i += 1;
+    }
+    if count == callee.parameters.len() {
+        return None;
+    }
+    let arguments = |count: usize| match count {
+        1 => "1 argument".to_string(),
+        _ => format!("{} arguments", count),
+    };
+    let message = format!(
+        "{} takes {} but is given {}",
+        callee.name,
+        arguments(callee.parameters.len()),
+        count
+    );
+    Some((message, range))
+}
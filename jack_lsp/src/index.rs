@@ -0,0 +1,419 @@
+use std::ops::Range;
+
+use jack_compiler::jack_tokenizer::{JackTokenizer, Keywords, Lexeme, Location, Token};
+use jack_compiler::parser::{token_text, Parser};
+use jack_compiler::symbol_table::{Kind, SymbolTable};
+
+/// A declared static, field, argument or local, numbered as the compiler
+/// numbers it.
+pub struct Variable {
+    pub name: String,
+    pub var_type: String,
+    pub kind: Kind,
+    pub index: usize,
+    pub location: Location,
+}
+
+impl Variable {
+    /// `field int x`
+    pub fn declaration(&self) -> String {
+        format!("{} {} {}", self.kind, self.var_type, self.name)
+    }
+
+    /// The VM segment and index the variable lives at, e.g. `this 0`.
+    pub fn address(&self) -> String {
+        let segment = match self.kind {
+            Kind::STATIC => "static",
+            Kind::FIELD => "this",
+            Kind::ARG => "argument",
+            Kind::VAR => "local",
+        };
+        format!("{} {}", segment, self.index)
+    }
+}
+
+pub struct Subroutine {
+    // constructor, function or method
+    pub kind: Keywords,
+    pub return_type: String,
+    pub name: String,
+    pub parameters: Vec<Variable>,
+    pub locals: Vec<Variable>,
+    pub location: Location,
+    // from the `constructor`/`function`/`method` keyword to after the
+    // closing brace
+    pub range: (Location, Location),
+    // token indexes of the statements
+    pub statements: Range<usize>,
+    pub doc: Option<String>,
+}
+
+impl Subroutine {
+    /// `method void setCharAt(int j, char c)`
+    pub fn signature(&self) -> String {
+        self.qualified_signature("")
+    }
+
+    /// `method void String.setCharAt(int j, char c)`
+    pub fn signature_in(&self, class: &str) -> String {
+        self.qualified_signature(&format!("{}.", class))
+    }
+
+    fn qualified_signature(&self, qualifier: &str) -> String {
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {}", parameter.var_type, parameter.name))
+            .collect();
+        format!(
+            "{} {} {}{}({})",
+            JackTokenizer::keywords_to_string(&self.kind),
+            self.return_type,
+            qualifier,
+            self.name,
+            parameters.join(", ")
+        )
+    }
+
+    pub fn is_method(&self) -> bool {
+        self.kind == Keywords::Method
+    }
+}
+
+/// The declarations of one class with where they are, read straight from
+/// the tokens so that a class with syntax errors is still indexed up to
+/// where it makes sense.
+pub struct ClassIndex {
+    pub name: String,
+    pub location: Location,
+    pub variables: Vec<Variable>,
+    pub subroutines: Vec<Subroutine>,
+    pub tokens: Vec<(Token, Location)>,
+    pub doc: Option<String>,
+    // the first error reading or parsing the code, and where it is
+    pub syntax_error: Option<(String, (Location, Location))>,
+    // declarations of names already declared in the same scope
+    pub duplicates: Vec<(String, Location)>,
+}
+
+impl ClassIndex {
+    pub fn new(jack_code: &str) -> Self {
+        let mut tokens = Vec::new();
+        // doc comments by the index of the token after them
+        let mut docs = Vec::new();
+        // the code up to an error the tokenizer found is still indexed
+        let (lexemes, lex_error) = JackTokenizer::lex(jack_code);
+        for (lexeme, location) in lexemes {
+            match lexeme {
+                Lexeme::Token(token) => tokens.push((token, location)),
+                Lexeme::Comment { text, .. } if text.starts_with("/**") => {
+                    docs.push((tokens.len(), JackTokenizer::doc_text(&text)))
+                }
+                _ => (),
+            }
+        }
+
+        let plain_tokens = tokens.iter().map(|(token, _)| token.clone()).collect();
+        let parse_error = Parser::new(JackTokenizer::from_tokens(plain_tokens))
+            .parse_class()
+            .err()
+            .map(|err| (err.message, err.token.unwrap_or(tokens.len())));
+
+        let mut index = ClassIndex {
+            name: String::new(),
+            location: Location::default(),
+            variables: Vec::new(),
+            subroutines: Vec::new(),
+            tokens,
+            doc: None,
+            syntax_error: None,
+            duplicates: Vec::new(),
+        };
+        index.syntax_error = match (parse_error, lex_error) {
+            // the parser only ran out of tokens where the tokenizer stopped
+            (Some((_, token)), Some(err)) if token >= index.tokens.len() => {
+                let start = err.location.unwrap_or_default();
+                let end = Location {
+                    line: start.line,
+                    column: start.column + 1,
+                };
+                Some((err.message, (start, end)))
+            }
+            (Some((message, token)), _) => {
+                let start = match index.tokens.get(token) {
+                    Some((_, start)) => *start,
+                    None => index.token_end(token.saturating_sub(1)),
+                };
+                Some((message, (start, index.token_end(token))))
+            }
+            (None, _) => None,
+        };
+        index.scan(&docs);
+        index
+    }
+
+    pub fn subroutine(&self, name: &str) -> Option<&Subroutine> {
+        self.subroutines
+            .iter()
+            .find(|subroutine| subroutine.name == name)
+    }
+
+    /// The subroutine whose body holds the token.
+    pub fn subroutine_at(&self, token: usize) -> Option<&Subroutine> {
+        self.subroutines.iter().find(|subroutine| {
+            let (start, end) = subroutine.range;
+            let location = self.tokens[token].1;
+            start <= location && location < end
+        })
+    }
+
+    /// A name as seen from inside `subroutine`: its locals and arguments,
+    /// then the class's fields and statics.
+    pub fn variable<'a>(
+        &'a self,
+        name: &str,
+        subroutine: Option<&'a Subroutine>,
+    ) -> Option<&'a Variable> {
+        subroutine
+            .into_iter()
+            .flat_map(|subroutine| subroutine.locals.iter().chain(&subroutine.parameters))
+            .chain(&self.variables)
+            .find(|variable| variable.name == name)
+    }
+
+    /// The index of the token at (or just before, for completion) the
+    /// location.
+    pub fn token_at(&self, location: Location) -> Option<usize> {
+        self.tokens.iter().rposition(|(token, start)| {
+            *start <= location
+                && start.line == location.line
+                && location.column <= start.column + token_length(token)
+        })
+    }
+
+    /// Where the token ends.
+    pub fn token_end(&self, token: usize) -> Location {
+        match self.tokens.get(token) {
+            Some((token, start)) => Location {
+                line: start.line,
+                column: start.column + token_length(token),
+            },
+            None => self.tokens.last().map_or(Location::default(), |_| {
+                self.token_end(self.tokens.len() - 1)
+            }),
+        }
+    }
+
+    // Reads the declarations, skipping what does not fit the grammar.
+    fn scan(&mut self, docs: &[(usize, String)]) {
+        let doc = |token: usize| {
+            docs.iter()
+                .find(|(before, _)| *before == token)
+                .map(|(_, text)| text.clone())
+        };
+        let mut symbol_table = SymbolTable::new();
+        let mut i = 0;
+
+        if self.keyword(0) == Some(Keywords::Class) {
+            if let Some((Token::Identifier(name), location)) = self.tokens.get(1) {
+                self.name = name.clone();
+                self.location = *location;
+            }
+            self.doc = doc(0);
+            i = 2;
+        }
+
+        while i < self.tokens.len() {
+            match self.keyword(i) {
+                Some(kind @ (Keywords::Static | Keywords::Field)) => {
+                    let kind = if kind == Keywords::Static {
+                        Kind::STATIC
+                    } else {
+                        Kind::FIELD
+                    };
+                    let (mut variables, next) = self.var_list(i + 1, kind, &mut symbol_table);
+                    let mut duplicates = duplicates(&variables, &[&self.variables]);
+                    self.duplicates.append(&mut duplicates);
+                    self.variables.append(&mut variables);
+                    i = next;
+                }
+                Some(kind @ (Keywords::Constructor | Keywords::Function | Keywords::Method)) => {
+                    let subroutine_doc = doc(i);
+                    i = self.subroutine_dec(i, kind, subroutine_doc, &mut symbol_table);
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    // `type name, name ...;` from `start`, returning the index after it
+    fn var_list(
+        &self,
+        start: usize,
+        kind: Kind,
+        symbol_table: &mut SymbolTable,
+    ) -> (Vec<Variable>, usize) {
+        let mut variables = Vec::new();
+        let var_type = match self.tokens.get(start) {
+            Some((token, _)) => token_text(token),
+            None => return (variables, start),
+        };
+        let mut i = start + 1;
+        while let Some((token, location)) = self.tokens.get(i) {
+            match token {
+                Token::Identifier(name) => {
+                    symbol_table.define(name.clone(), var_type.clone(), kind);
+                    variables.push(Variable {
+                        name: name.clone(),
+                        var_type: var_type.clone(),
+                        kind,
+                        index: symbol_table.var_count(kind) - 1,
+                        location: *location,
+                    });
+                }
+                _ if self.is_symbol(i, ',') => (),
+                _ if self.is_symbol(i, ';') => return (variables, i + 1),
+                _ => return (variables, i),
+            }
+            i += 1;
+        }
+        (variables, i)
+    }
+
+    fn subroutine_dec(
+        &mut self,
+        start: usize,
+        kind: Keywords,
+        doc: Option<String>,
+        symbol_table: &mut SymbolTable,
+    ) -> usize {
+        let return_type = match self.tokens.get(start + 1) {
+            Some((token, _)) => token_text(token),
+            None => return start + 1,
+        };
+        let (name, location) = match self.tokens.get(start + 2) {
+            Some((Token::Identifier(name), location)) => (name.clone(), *location),
+            _ => return start + 2,
+        };
+
+        symbol_table.start_subroutine();
+        if kind == Keywords::Method {
+            symbol_table.define("this".to_string(), self.name.clone(), Kind::ARG);
+        }
+        let mut parameters = Vec::new();
+        let mut i = start + 3;
+        if self.is_symbol(i, '(') {
+            i += 1;
+            while i + 1 < self.tokens.len() && !self.is_symbol(i, ')') {
+                if let (var_type, Token::Identifier(name)) =
+                    (&self.tokens[i].0, &self.tokens[i + 1].0)
+                {
+                    symbol_table.define(name.clone(), token_text(var_type), Kind::ARG);
+                    parameters.push(Variable {
+                        name: name.clone(),
+                        var_type: token_text(var_type),
+                        kind: Kind::ARG,
+                        index: symbol_table.var_count(Kind::ARG) - 1,
+                        location: self.tokens[i + 1].1,
+                    });
+                    i += 2;
+                }
+                if self.is_symbol(i, ',') {
+                    i += 1;
+                } else if !self.is_symbol(i, ')') {
+                    break;
+                }
+            }
+            if self.is_symbol(i, ')') {
+                i += 1;
+            }
+        }
+        let mut duplicates = duplicates(&parameters, &[]);
+
+        let mut locals = Vec::new();
+        let body_start = i;
+        if self.is_symbol(i, '{') {
+            i += 1;
+            while self.keyword(i) == Some(Keywords::Var) {
+                let (mut variables, next) = self.var_list(i + 1, Kind::VAR, symbol_table);
+                duplicates.append(&mut self::duplicates(&variables, &[&parameters, &locals]));
+                locals.append(&mut variables);
+                i = next;
+            }
+        }
+        let statements_start = i;
+
+        // the closing brace of the body, or where the next subroutine starts
+        let mut depth = 0;
+        let mut end = body_start;
+        while end < self.tokens.len() {
+            if self.is_symbol(end, '{') {
+                depth += 1;
+            } else if self.is_symbol(end, '}') {
+                depth -= 1;
+                if depth <= 0 {
+                    break;
+                }
+            } else if matches!(
+                self.keyword(end),
+                Some(Keywords::Constructor | Keywords::Function | Keywords::Method)
+            ) {
+                end -= 1;
+                break;
+            }
+            end += 1;
+        }
+        let end = end.min(self.tokens.len().saturating_sub(1));
+        self.duplicates.append(&mut duplicates);
+
+        self.subroutines.push(Subroutine {
+            kind,
+            return_type,
+            name,
+            parameters,
+            locals,
+            location,
+            range: (self.tokens[start].1, self.token_end(end)),
+            statements: statements_start..end.max(statements_start),
+            doc,
+        });
+        end + 1
+    }
+
+    pub fn keyword(&self, token: usize) -> Option<Keywords> {
+        match self.tokens.get(token) {
+            Some((Token::Keyword(keyword), _)) => Some(keyword.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_symbol(&self, token: usize, c: char) -> bool {
+        matches!(self.tokens.get(token), Some((Token::Symbol(symbol), _)) if JackTokenizer::symbol_char(symbol) == c)
+    }
+
+    pub fn identifier(&self, token: usize) -> Option<&str> {
+        match self.tokens.get(token) {
+            Some((Token::Identifier(name), _)) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+// the variables declared again, after earlier in the list or in `earlier`
+fn duplicates(variables: &[Variable], earlier: &[&[Variable]]) -> Vec<(String, Location)> {
+    let mut duplicates = Vec::new();
+    for (i, variable) in variables.iter().enumerate() {
+        let declared = variables[..i]
+            .iter()
+            .chain(earlier.iter().flat_map(|variables| variables.iter()))
+            .any(|other| other.name == variable.name);
+        if declared {
+            duplicates.push((variable.name.clone(), variable.location));
+        }
+    }
+    duplicates
+}
+
+pub fn token_length(token: &Token) -> usize {
+    token_text(token).chars().count()
+}
@@ -0,0 +1,274 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+/// Just enough JSON for the messages of the language server protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // members in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(c) => Err(format!("unexpected '{}' after the JSON value", c)),
+            None => Ok(value),
+        }
+    }
+
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// The member of an object, Null if there is none.
+    pub fn get(&self, name: &str) -> &Json {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, value)| value)
+                .unwrap_or(&Json::Null),
+            _ => &Json::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(number) if *number >= 0.0 => Some(*number as usize),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Json::Null
+    }
+}
+
+impl From<&str> for Json {
+    fn from(string: &str) -> Self {
+        Json::String(string.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(string: String) -> Self {
+        Json::String(string)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(number: usize) -> Self {
+        Json::Number(number as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Self {
+        Json::Array(values)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(string) => write_string(f, string),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                if chars.next() != Some('"') {
+                    return Err("expected a member name".to_string());
+                }
+                let name = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err("expected ':'".to_string());
+                }
+                members.push((name, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(members)),
+                    _ => return Err("expected ',' or '}'".to_string()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(values)),
+                    _ => return Err("expected ',' or ']'".to_string()),
+                }
+            }
+        }
+        Some('"') => {
+            chars.next();
+            Ok(Json::String(parse_string(chars)?))
+        }
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) = chars.peek() {
+                if !(c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                    break;
+                }
+                number.push(*c);
+                chars.next();
+            }
+            number
+                .parse()
+                .map(Json::Number)
+                .map_err(|_| format!("invalid number {}", number))
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(c) = chars.peek() {
+                if !c.is_ascii_alphabetic() {
+                    break;
+                }
+                word.push(*c);
+                chars.next();
+            }
+            match word.as_str() {
+                "null" => Ok(Json::Null),
+                "true" => Ok(Json::Bool(true)),
+                "false" => Ok(Json::Bool(false)),
+                _ => Err(format!("unexpected '{}'", word)),
+            }
+        }
+        None => Err("unexpected end of JSON".to_string()),
+    }
+}
+
+// after the opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('u') => {
+                    let code = parse_hex4(chars)?;
+                    // a surrogate pair for characters outside the BMP
+                    let code = if (0xd800..0xdc00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("unpaired surrogate".to_string());
+                        }
+                        let low = parse_hex4(chars)?;
+                        0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                    } else {
+                        code
+                    };
+                    string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(c) => string.push(c),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => string.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\u{}", hex))
+}
@@ -0,0 +1,5 @@
+pub mod analysis;
+pub mod index;
+pub mod json;
+pub mod project;
+pub mod server;
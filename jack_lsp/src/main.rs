@@ -0,0 +1,13 @@
+use std::{io, process};
+
+use jack_lsp::server::Server;
+
+// Editors start the server with no arguments and talk to it over stdio.
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(err) = Server::new().run(&mut stdin.lock(), &mut stdout.lock()) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
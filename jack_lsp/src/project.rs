@@ -0,0 +1,77 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::index::ClassIndex;
+
+// The OS API as declared by the course's stub classes, for completion and
+// hover in programs that do not include their own OS.
+const OS_CLASSES: [&str; 8] = [
+    include_str!("../../projects/12/Array.jack"),
+    include_str!("../../projects/12/Keyboard.jack"),
+    include_str!("../../projects/12/Math.jack"),
+    include_str!("../../projects/12/Memory.jack"),
+    include_str!("../../projects/12/Output.jack"),
+    include_str!("../../projects/12/Screen.jack"),
+    include_str!("../../projects/12/String.jack"),
+    include_str!("../../projects/12/Sys.jack"),
+];
+
+pub struct ProjectClass {
+    pub index: ClassIndex,
+    // None for the built-in OS classes
+    pub path: Option<PathBuf>,
+}
+
+/// The classes of the `.jack` files in one directory, where open documents
+/// replace what is on disk, together with the OS classes the directory
+/// does not define itself.
+pub struct Project {
+    classes: HashMap<String, ProjectClass>,
+}
+
+impl Project {
+    pub fn load(dir: &Path, open_documents: &HashMap<PathBuf, String>) -> Self {
+        let mut classes = HashMap::new();
+        for jack_code in OS_CLASSES {
+            let index = ClassIndex::new(jack_code);
+            classes.insert(index.name.clone(), ProjectClass { index, path: None });
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jack"))
+            .collect();
+        for path in open_documents.keys() {
+            if path.parent() == Some(dir) && !files.contains(path) {
+                files.push(path.clone());
+            }
+        }
+
+        for path in files {
+            let jack_code = match open_documents.get(&path) {
+                Some(jack_code) => jack_code.clone(),
+                None => match fs::read_to_string(&path) {
+                    Ok(jack_code) => jack_code,
+                    Err(_) => continue,
+                },
+            };
+            let index = ClassIndex::new(&jack_code);
+            let path = Some(path);
+            classes.insert(index.name.clone(), ProjectClass { index, path });
+        }
+        Project { classes }
+    }
+
+    pub fn class(&self, name: &str) -> Option<&ProjectClass> {
+        self.classes.get(name)
+    }
+
+    pub fn class_names(&self) -> impl Iterator<Item = &String> {
+        self.classes.keys()
+    }
+}
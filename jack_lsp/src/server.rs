@@ -0,0 +1,502 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use jack_compiler::jack_tokenizer::{Keywords, Location};
+use jack_compiler::symbol_table::Kind;
+
+use crate::analysis::{self, Symbol};
+use crate::index::{ClassIndex, Subroutine, Variable};
+use crate::json::Json;
+use crate::project::Project;
+
+// JSON-RPC error code for requests the server does not handle
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// LSP enumerations
+const SEVERITY_ERROR: usize = 1;
+const TEXT_SYNC_FULL: usize = 1;
+const SYMBOL_CLASS: usize = 5;
+const SYMBOL_METHOD: usize = 6;
+const SYMBOL_FIELD: usize = 8;
+const SYMBOL_CONSTRUCTOR: usize = 9;
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+const COMPLETION_METHOD: usize = 2;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_CONSTRUCTOR: usize = 4;
+const COMPLETION_FIELD: usize = 5;
+const COMPLETION_VARIABLE: usize = 6;
+const COMPLETION_CLASS: usize = 7;
+
+/// A language server for Jack over stdio: diagnostics, hover, go to
+/// definition, completion and document symbols. Documents are synced in
+/// full, and each one is analysed together with the other `.jack` files of
+/// its directory.
+pub struct Server {
+    documents: HashMap<PathBuf, String>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Serves messages until `exit` or the end of the input.
+    pub fn run(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(input)? {
+            let message = match Json::parse(&message) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            let method = message.get("method").as_str().unwrap_or_default();
+            if method == "exit" {
+                break;
+            }
+            let params = message.get("params");
+            let id = message.get("id");
+
+            if id.is_null() {
+                for notification in self.notify(method, params) {
+                    write_message(output, &notification)?;
+                }
+                continue;
+            }
+            let response = match self.request(method, params) {
+                Some(result) => Json::object(vec![
+                    ("jsonrpc", "2.0".into()),
+                    ("id", id.clone()),
+                    ("result", result),
+                ]),
+                None => Json::object(vec![
+                    ("jsonrpc", "2.0".into()),
+                    ("id", id.clone()),
+                    (
+                        "error",
+                        Json::object(vec![
+                            ("code", Json::Number(METHOD_NOT_FOUND)),
+                            ("message", format!("{} is not supported", method).into()),
+                        ]),
+                    ),
+                ]),
+            };
+            write_message(output, &response)?;
+        }
+        Ok(())
+    }
+
+    // the notifications to send back, i.e. new diagnostics
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let document = params.get("textDocument");
+        let path = match document.get("uri").as_str() {
+            Some(uri) => uri_to_path(uri),
+            None => return Vec::new(),
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = document.get("text").as_str().unwrap_or_default();
+                self.documents.insert(path.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                let changes = match params.get("contentChanges") {
+                    Json::Array(changes) => changes,
+                    _ => return Vec::new(),
+                };
+                if let Some(text) = changes
+                    .last()
+                    .and_then(|change| change.get("text").as_str())
+                {
+                    self.documents.insert(path.clone(), text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+                return vec![publish_diagnostics(&path, Vec::new())];
+            }
+            _ => return Vec::new(),
+        }
+
+        // a change can fix or break the other open classes of the project
+        let dir = directory(&path);
+        let project = Project::load(&dir, &self.documents);
+        self.documents
+            .iter()
+            .filter(|(other, _)| directory(other) == dir)
+            .map(|(path, text)| {
+                let class = ClassIndex::new(text);
+                let diagnostics = analysis::diagnostics(&class, &project)
+                    .into_iter()
+                    .map(|diagnostic| {
+                        Json::object(vec![
+                            ("range", range(diagnostic.range)),
+                            ("severity", SEVERITY_ERROR.into()),
+                            ("source", "jack".into()),
+                            ("message", diagnostic.message.into()),
+                        ])
+                    })
+                    .collect();
+                publish_diagnostics(path, diagnostics)
+            })
+            .collect()
+    }
+
+    // None for requests the server does not know
+    fn request(&self, method: &str, params: &Json) -> Option<Json> {
+        let result = match method {
+            "initialize" => Json::object(vec![
+                (
+                    "capabilities",
+                    Json::object(vec![
+                        ("textDocumentSync", TEXT_SYNC_FULL.into()),
+                        ("hoverProvider", true.into()),
+                        ("definitionProvider", true.into()),
+                        (
+                            "completionProvider",
+                            Json::object(vec![("triggerCharacters", vec![".".into()].into())]),
+                        ),
+                        ("documentSymbolProvider", true.into()),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object(vec![("name", env!("CARGO_PKG_NAME").into())]),
+                ),
+            ]),
+            "shutdown" => Json::Null,
+            "textDocument/hover" => self.with_document(params, hover),
+            "textDocument/definition" => self.with_document(params, definition),
+            "textDocument/completion" => self.with_document(params, completion),
+            "textDocument/documentSymbol" => self.with_document(params, document_symbols),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    // Runs a request about an open document at a position.
+    fn with_document(&self, params: &Json, handler: fn(&Request) -> Option<Json>) -> Json {
+        let path = match params.get("textDocument").get("uri").as_str() {
+            Some(uri) => uri_to_path(uri),
+            None => return Json::Null,
+        };
+        let text = match self.documents.get(&path) {
+            Some(text) => text,
+            None => return Json::Null,
+        };
+        let position = params.get("position");
+        let request = Request {
+            class: ClassIndex::new(text),
+            project: Project::load(&directory(&path), &self.documents),
+            path: path.clone(),
+            location: Location {
+                line: position.get("line").as_usize().unwrap_or(0),
+                column: position.get("character").as_usize().unwrap_or(0),
+            },
+        };
+        handler(&request).unwrap_or(Json::Null)
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Request {
+    class: ClassIndex,
+    project: Project,
+    path: PathBuf,
+    location: Location,
+}
+
+impl Request {
+    fn symbol(&self) -> Option<Symbol<'_>> {
+        let token = self.class.token_at(self.location)?;
+        analysis::resolve(&self.class, &self.project, token)
+    }
+}
+
+fn hover(request: &Request) -> Option<Json> {
+    let (code, notes) = match request.symbol()? {
+        Symbol::Variable(variable) => (variable.declaration(), variable.address()),
+        Symbol::Subroutine { class, subroutine } => (
+            subroutine.signature_in(class),
+            subroutine.doc.clone().unwrap_or_default(),
+        ),
+        Symbol::Class(class) => (
+            format!("class {}", class.name),
+            class.doc.clone().unwrap_or_default(),
+        ),
+    };
+    let mut text = format!("```jack\n{}\n```", code);
+    if !notes.is_empty() {
+        text += &format!("\n\n{}", notes);
+    }
+    Some(Json::object(vec![(
+        "contents",
+        Json::object(vec![("kind", "markdown".into()), ("value", text.into())]),
+    )]))
+}
+
+fn definition(request: &Request) -> Option<Json> {
+    let symbol = request.symbol()?;
+    let (path, location) = analysis::definition(&request.class, &request.project, &symbol)?;
+    let path = path.unwrap_or_else(|| request.path.clone());
+    let name_length = match symbol {
+        Symbol::Variable(variable) => variable.name.chars().count(),
+        Symbol::Subroutine { subroutine, .. } => subroutine.name.chars().count(),
+        Symbol::Class(class) => class.name.chars().count(),
+    };
+    let end = Location {
+        line: location.line,
+        column: location.column + name_length,
+    };
+    Some(Json::object(vec![
+        ("uri", path_to_uri(&path).into()),
+        ("range", range((location, end))),
+    ]))
+}
+
+// Members after `x.`, otherwise everything in scope.
+fn completion(request: &Request) -> Option<Json> {
+    let class = &request.class;
+    // the last token starting before the cursor
+    let before = class
+        .tokens
+        .iter()
+        .rposition(|(_, start)| *start < request.location);
+    let qualifier = match before {
+        Some(token) if class.is_symbol(token, '.') => token.checked_sub(1),
+        Some(token) if token >= 2 && class.is_symbol(token - 1, '.') => Some(token - 2),
+        _ => None,
+    };
+
+    let mut items = Vec::new();
+    if let Some(qualifier) = qualifier {
+        let is_variable = class
+            .identifier(qualifier)
+            .and_then(|name| class.variable(name, class.subroutine_at(qualifier)))
+            .is_some();
+        let callee_class = analysis::class_of(class, &request.project, qualifier)?;
+        for subroutine in &callee_class.subroutines {
+            // methods on objects, constructors and functions on classes
+            if subroutine.is_method() == is_variable {
+                items.push(subroutine_item(subroutine));
+            }
+        }
+        return Some(items.into());
+    }
+
+    let subroutine = before.and_then(|token| class.subroutine_at(token));
+    if let Some(subroutine) = subroutine {
+        for variable in subroutine.locals.iter().chain(&subroutine.parameters) {
+            items.push(variable_item(variable));
+        }
+    }
+    for variable in &class.variables {
+        items.push(variable_item(variable));
+    }
+    for subroutine in &class.subroutines {
+        items.push(subroutine_item(subroutine));
+    }
+    for name in request.project.class_names() {
+        items.push(Json::object(vec![
+            ("label", name.as_str().into()),
+            ("kind", COMPLETION_CLASS.into()),
+        ]));
+    }
+    Some(items.into())
+}
+
+fn variable_item(variable: &Variable) -> Json {
+    let kind = match variable.kind {
+        Kind::FIELD | Kind::STATIC => COMPLETION_FIELD,
+        Kind::ARG | Kind::VAR => COMPLETION_VARIABLE,
+    };
+    Json::object(vec![
+        ("label", variable.name.as_str().into()),
+        ("kind", kind.into()),
+        ("detail", variable.declaration().into()),
+    ])
+}
+
+fn subroutine_item(subroutine: &Subroutine) -> Json {
+    let kind = match subroutine.kind {
+        Keywords::Constructor => COMPLETION_CONSTRUCTOR,
+        Keywords::Method => COMPLETION_METHOD,
+        _ => COMPLETION_FUNCTION,
+    };
+    let mut item = vec![
+        ("label", subroutine.name.as_str().into()),
+        ("kind", kind.into()),
+        ("detail", subroutine.signature().into()),
+    ];
+    if let Some(doc) = &subroutine.doc {
+        item.push(("documentation", doc.as_str().into()));
+    }
+    Json::object(item)
+}
+
+fn document_symbols(request: &Request) -> Option<Json> {
+    let class = &request.class;
+    if class.name.is_empty() {
+        return Some(Vec::new().into());
+    }
+    let name_range = |name: &str, location: Location| {
+        let end = Location {
+            line: location.line,
+            column: location.column + name.chars().count(),
+        };
+        range((location, end))
+    };
+
+    let mut children = Vec::new();
+    for variable in &class.variables {
+        let kind = match variable.kind {
+            Kind::FIELD => SYMBOL_FIELD,
+            _ => SYMBOL_VARIABLE,
+        };
+        children.push(Json::object(vec![
+            ("name", variable.name.as_str().into()),
+            ("detail", variable.declaration().into()),
+            ("kind", kind.into()),
+            ("range", name_range(&variable.name, variable.location)),
+            (
+                "selectionRange",
+                name_range(&variable.name, variable.location),
+            ),
+        ]));
+    }
+    for subroutine in &class.subroutines {
+        let kind = match subroutine.kind {
+            Keywords::Constructor => SYMBOL_CONSTRUCTOR,
+            Keywords::Method => SYMBOL_METHOD,
+            _ => SYMBOL_FUNCTION,
+        };
+        children.push(Json::object(vec![
+            ("name", subroutine.name.as_str().into()),
+            ("detail", subroutine.signature().into()),
+            ("kind", kind.into()),
+            ("range", range(subroutine.range)),
+            (
+                "selectionRange",
+                name_range(&subroutine.name, subroutine.location),
+            ),
+        ]));
+    }
+
+    let start = class
+        .tokens
+        .first()
+        .map(|(_, start)| *start)
+        .unwrap_or_default();
+    let end = class.token_end(class.tokens.len().saturating_sub(1));
+    Some(
+        vec![Json::object(vec![
+            ("name", class.name.as_str().into()),
+            ("kind", SYMBOL_CLASS.into()),
+            ("range", range((start, end))),
+            ("selectionRange", name_range(&class.name, class.location)),
+            ("children", children.into()),
+        ])]
+        .into(),
+    )
+}
+
+fn publish_diagnostics(path: &Path, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", path_to_uri(path).into()),
+                ("diagnostics", diagnostics.into()),
+            ]),
+        ),
+    ])
+}
+
+fn range((start, end): (Location, Location)) -> Json {
+    let position = |location: Location| {
+        Json::object(vec![
+            ("line", location.line.into()),
+            ("character", location.column.into()),
+        ])
+    };
+    Json::object(vec![("start", position(start)), ("end", position(end))])
+}
+
+fn directory(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+// `file:///home/a%20b/Main.jack` -> `/home/a b/Main.jack`
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::new();
+    let mut chars = path.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+            let decoded = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match decoded {
+                Some(decoded) => bytes.push(decoded),
+                None => bytes.extend(std::iter::once(byte).chain(hex)),
+            }
+        } else {
+            bytes.push(byte);
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri += &format!("%{:02X}", byte);
+        }
+    }
+    uri
+}
+
+// The body of the next message, None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).to_string()))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
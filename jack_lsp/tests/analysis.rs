@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use jack_compiler::jack_tokenizer::Location;
+use jack_lsp::analysis::{self, Symbol};
+use jack_lsp::index::ClassIndex;
+use jack_lsp::project::Project;
+
+const POINT: &str = "class Point {
+    field int x, y;
+
+    constructor Point new(int ax, int ay) {
+        let x = ax;
+        let y = ay;
+        return this;
+    }
+
+    method int getX() { return x; }
+}
+";
+
+fn project_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("jack_lsp-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("Point.jack"), POINT).unwrap();
+    dir
+}
+
+fn messages(jack_code: &str, dir: &Path) -> Vec<String> {
+    let project = Project::load(dir, &HashMap::new());
+    analysis::diagnostics(&ClassIndex::new(jack_code), &project)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn finds_no_problems_in_the_course_programs() {
+    for dir in [
+        "Average",
+        "ComplexArrays",
+        "ConvertToBin",
+        "Pong",
+        "Seven",
+        "Square",
+    ] {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../projects/11")
+            .join(dir);
+        let project = Project::load(&dir, &HashMap::new());
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "jack")
+            {
+                let class = ClassIndex::new(&fs::read_to_string(&path).unwrap());
+                let diagnostics = analysis::diagnostics(&class, &project);
+                let messages: Vec<&str> = diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.message.as_str())
+                    .collect();
+                assert!(messages.is_empty(), "{}: {:?}", path.display(), messages);
+            }
+        }
+    }
+}
+
+#[test]
+fn reports_semantic_errors() {
+    let dir = project_dir("diagnostics");
+    let jack_code = "class Main {
+    function void main() {
+        var Point p;
+        var Pint q;
+        let p = Point.new(1);
+        do p.getY();
+        do Point.getX();
+        let z = 0;
+        return;
+    }
+}
+";
+    assert_eq!(
+        messages(jack_code, &dir),
+        [
+            "unknown type Pint",
+            "new takes 2 arguments but is given 1",
+            "Point has no subroutine getY",
+            "Point.getX is a method; call it on an object",
+            "undefined variable z",
+        ]
+    );
+}
+
+#[test]
+fn reports_syntax_errors() {
+    let dir = project_dir("syntax");
+    let messages = messages(
+        "class Main {\n    function void main() {\n        let = 1;\n",
+        &dir,
+    );
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+}
+
+#[test]
+fn finds_definitions_in_other_classes() {
+    let dir = project_dir("definition");
+    let jack_code = "class Main {
+    function void main() {
+        var Point p;
+        let p = Point.new(1, 2);
+        do Output.printInt(p.getX());
+        return;
+    }
+}
+";
+    let project = Project::load(&dir, &HashMap::new());
+    let class = ClassIndex::new(jack_code);
+    let at = |line: usize, column: usize| {
+        let token = class.token_at(Location { line, column }).unwrap();
+        analysis::resolve(&class, &project, token).unwrap()
+    };
+
+    // `getX` in `p.getX()`
+    let symbol = at(4, 30);
+    match &symbol {
+        Symbol::Subroutine { class, subroutine } => {
+            assert_eq!(*class, "Point");
+            assert_eq!(subroutine.name, "getX");
+        }
+        _ => panic!("getX is not a subroutine"),
+    }
+    let (path, location) = analysis::definition(&class, &project, &symbol).unwrap();
+    assert_eq!(path, Some(dir.join("Point.jack")));
+    assert_eq!(
+        location,
+        Location {
+            line: 9,
+            column: 15
+        }
+    );
+
+    // `p` in `let p`, declared in this document
+    let symbol = at(3, 12);
+    assert!(matches!(symbol, Symbol::Variable(variable) if variable.var_type == "Point"));
+    let (path, location) = analysis::definition(&class, &project, &symbol).unwrap();
+    assert_eq!(path, None);
+    assert_eq!(
+        location,
+        Location {
+            line: 2,
+            column: 18
+        }
+    );
+
+    // the OS classes have no file to go to
+    let symbol = at(4, 12);
+    assert!(matches!(symbol, Symbol::Class(index) if index.name == "Output"));
+    assert!(analysis::definition(&class, &project, &symbol).is_none());
+}
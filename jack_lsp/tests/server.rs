@@ -0,0 +1,144 @@
+use std::{env, fs, io::Cursor, process};
+
+use jack_lsp::json::Json;
+use jack_lsp::server::Server;
+
+const MAIN: &str = "class Main {
+    function void main() {
+        var int count;
+        let count = 1;
+        do Main.show(count);
+        return;
+    }
+
+    function void show(int n) { do Output.printInt(n); return; }
+}
+";
+
+fn frame(message: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+}
+
+// the messages the server sends back for `messages`
+fn session(messages: &[String]) -> Vec<Json> {
+    let input: String = messages.iter().map(|message| frame(message)).collect();
+    let mut output = Vec::new();
+    Server::new()
+        .run(&mut Cursor::new(input), &mut output)
+        .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let mut replies = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(start) = rest.find("\r\n\r\n") {
+        let length: usize = rest["Content-Length: ".len()..start].parse().unwrap();
+        let body = &rest[start + 4..start + 4 + length];
+        replies.push(Json::parse(body).unwrap());
+        rest = &rest[start + 4 + length..];
+    }
+    replies
+}
+
+fn string(text: &str) -> String {
+    Json::String(text.to_string()).to_string()
+}
+
+#[test]
+fn answers_requests_about_an_open_document() {
+    let dir = env::temp_dir().join(format!("jack_lsp-server-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let uri = format!("file://{}/Main.jack", dir.display());
+    let document = format!(r#"{{"uri":{}}}"#, string(&uri));
+    let request = |id: usize, method: &str, line: usize, character: usize| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{{"textDocument":{},"position":{{"line":{},"character":{}}}}}}}"#,
+            id, method, document, line, character
+        )
+    };
+
+    let replies = session(&[
+        r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#.to_string(),
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":{},"text":{}}}}}}}"#,
+            string(&uri),
+            string(MAIN)
+        ),
+        request(1, "textDocument/hover", 3, 13),
+        request(2, "textDocument/definition", 4, 17),
+        request(3, "textDocument/documentSymbol", 0, 0),
+        r#"{"jsonrpc":"2.0","id":4,"method":"workspace/symbol","params":{}}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ]);
+    assert_eq!(replies.len(), 6);
+
+    let capabilities = replies[0].get("result").get("capabilities");
+    assert!(!capabilities.get("hoverProvider").is_null());
+
+    let diagnostics = &replies[1];
+    assert_eq!(
+        diagnostics.get("method").as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    assert_eq!(
+        diagnostics.get("params").get("uri").as_str(),
+        Some(uri.as_str())
+    );
+    assert_eq!(
+        diagnostics.get("params").get("diagnostics").to_string(),
+        "[]"
+    );
+
+    let hover = replies[2].get("result").get("contents").get("value");
+    assert_eq!(
+        hover.as_str(),
+        Some("```jack\nvar int count\n```\n\nlocal 0")
+    );
+
+    let definition = replies[3].get("result");
+    assert_eq!(definition.get("uri").as_str(), Some(uri.as_str()));
+    let start = definition.get("range").get("start");
+    assert_eq!(start.get("line").as_usize(), Some(8));
+    assert_eq!(start.get("character").as_usize(), Some(18));
+
+    let symbols = replies[4].get("result").to_string();
+    for name in ["\"Main\"", "\"main\"", "\"show\""] {
+        assert!(symbols.contains(name), "no {} in {}", name, symbols);
+    }
+
+    assert_eq!(replies[5].get("error").get("code").to_string(), "-32601");
+}
+
+#[test]
+fn publishes_the_problems_of_a_changed_document() {
+    let dir = env::temp_dir().join(format!("jack_lsp-change-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let uri = string(&format!("file://{}/Main.jack", dir.display()));
+    let broken = MAIN.replace("let count = 1;", "let total = 1;");
+
+    let replies = session(&[
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":{},"text":{}}}}}}}"#,
+            uri,
+            string(MAIN)
+        ),
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":{}}},"contentChanges":[{{"text":{}}}]}}}}"#,
+            uri,
+            string(&broken)
+        ),
+    ]);
+    assert_eq!(replies.len(), 2);
+
+    let diagnostics = replies[1].get("params").get("diagnostics");
+    let diagnostic = match diagnostics {
+        Json::Array(diagnostics) if diagnostics.len() == 1 => &diagnostics[0],
+        _ => panic!("expected one diagnostic, got {}", diagnostics),
+    };
+    assert_eq!(
+        diagnostic.get("message").as_str(),
+        Some("undefined variable total")
+    );
+    let start = diagnostic.get("range").get("start");
+    assert_eq!(start.get("line").as_usize(), Some(3));
+    assert_eq!(start.get("character").as_usize(), Some(12));
+}
@@ -0,0 +1,97 @@
+use jack_compiler::ast::Type;
+use jack_compiler::jack_tokenizer::JackTokenizer;
+
+use crate::{summary, ClassDoc};
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; }
+code { font-family: monospace; }
+table { border-collapse: collapse; }
+td { border-top: 1px solid #ddd; padding: 0.3em 1em 0.3em 0; vertical-align: top; }
+h3 { margin-top: 2em; }";
+
+/// `index.html`, the list of every class.
+pub fn index_page(classes: &[ClassDoc]) -> String {
+    let mut body = String::from("<h1>Classes</h1>\n<table>\n");
+    for class in classes {
+        let description = class.description.as_deref().map(summary);
+        body += &format!(
+            "<tr><td><a href=\"{0}.html\"><code>{0}</code></a></td><td>{1}</td></tr>\n",
+            class.name,
+            escape(&description.unwrap_or_default())
+        );
+    }
+    body += "</table>\n";
+    page("Classes", &body)
+}
+
+/// `<class>.html`, with the types that are classes of `classes` linked to
+/// their pages.
+pub fn class_page(class: &ClassDoc, classes: &[ClassDoc]) -> String {
+    let type_name = |var_type: &Type| match var_type {
+        Type::Class(name) if classes.iter().any(|class| &class.name == name) => {
+            format!("<a href=\"{0}.html\">{0}</a>", name)
+        }
+        _ => var_type.to_string(),
+    };
+
+    let mut body = String::from("<nav><a href=\"index.html\">All classes</a></nav>\n");
+    body += &format!("<h1>class {}</h1>\n", class.name);
+    body += &paragraphs(class.description.as_deref());
+
+    if !class.variables.is_empty() {
+        body += "<h2>Class variables</h2>\n<table>\n";
+        for variable in &class.variables {
+            body += &format!(
+                "<tr><td><code>{}</code></td><td>{}</td></tr>\n",
+                variable.declaration(type_name),
+                paragraphs(variable.description.as_deref())
+            );
+        }
+        body += "</table>\n";
+    }
+
+    if !class.subroutines.is_empty() {
+        body += "<h2>Subroutines</h2>\n<table>\n";
+        for subroutine in &class.subroutines {
+            let description = subroutine.description.as_deref().map(summary);
+            body += &format!(
+                "<tr><td><a href=\"#{}\"><code>{}</code></a></td><td>{}</td></tr>\n",
+                subroutine.name,
+                subroutine.name,
+                escape(&description.unwrap_or_default())
+            );
+        }
+        body += "</table>\n";
+        for subroutine in &class.subroutines {
+            body += &format!(
+                "<h3 id=\"{}\"><code>{}</code></h3>\n",
+                subroutine.name,
+                subroutine.signature(type_name)
+            );
+            body += &paragraphs(subroutine.description.as_deref());
+        }
+    }
+    page(&class.name, &body)
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        title, STYLE, body
+    )
+}
+
+// a `<p>` for each paragraph of a description
+fn paragraphs(description: Option<&str>) -> String {
+    description
+        .unwrap_or_default()
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph)))
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    JackTokenizer::escape_xml(text)
+}
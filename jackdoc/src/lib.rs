@@ -0,0 +1,193 @@
+pub mod html;
+pub mod markdown;
+
+use std::collections::HashMap;
+
+use jack_compiler::ast::{Parameter, SubroutineKind, Type};
+use jack_compiler::jack_tokenizer::{JackTokenizer, Keywords, Symbols, Token};
+use jack_compiler::parser::{CompileError, Dialect, ParseOptions, Parser};
+
+/// What the documentation says about one class: its declarations, with
+/// the `/** ... */` comment written right before each of them.
+pub struct ClassDoc {
+    pub name: String,
+    pub description: Option<String>,
+    pub variables: Vec<VariableDoc>,
+    pub subroutines: Vec<SubroutineDoc>,
+}
+
+/// A static, field or constant of a class.
+pub struct VariableDoc {
+    // "static", "field" or "const"
+    pub kind: String,
+    pub var_type: Type,
+    pub name: String,
+    // only constants have a value
+    pub value: Option<i16>,
+    pub description: Option<String>,
+}
+
+pub struct SubroutineDoc {
+    pub kind: SubroutineKind,
+    // None for void
+    pub return_type: Option<Type>,
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub description: Option<String>,
+}
+
+/// Reads the declarations and doc comments of one class. Code that does
+/// not parse has no documentation, and the parse error is returned.
+pub fn class_doc(jack_code: &str, dialect: Dialect) -> Result<ClassDoc, CompileError> {
+    let tokenizer = JackTokenizer::from_code(jack_code)?;
+    let mut docs = Docs::new(&tokenizer);
+    let options = ParseOptions {
+        dialect,
+        ..ParseOptions::default()
+    };
+    let class = Parser::with_options(tokenizer, options).parse_class()?;
+
+    let mut variables = Vec::new();
+    for class_var_dec in &class.class_var_decs {
+        for name in &class_var_dec.names {
+            variables.push(VariableDoc {
+                kind: class_var_dec.kind.to_string(),
+                var_type: class_var_dec.var_type.clone(),
+                name: name.clone(),
+                value: None,
+                description: docs.variables.get(name).cloned(),
+            });
+        }
+    }
+    for constant in class.constants {
+        variables.push(VariableDoc {
+            kind: "const".to_string(),
+            description: docs.variables.get(&constant.name).cloned(),
+            var_type: constant.var_type,
+            name: constant.name,
+            value: Some(constant.value),
+        });
+    }
+
+    let subroutines = class
+        .subroutines
+        .into_iter()
+        .map(|subroutine| SubroutineDoc {
+            description: docs.subroutines.remove(&subroutine.name),
+            kind: subroutine.kind,
+            return_type: subroutine.return_type,
+            name: subroutine.name,
+            parameters: subroutine.parameters,
+        })
+        .collect();
+
+    Ok(ClassDoc {
+        name: class.name,
+        description: docs.class,
+        variables,
+        subroutines,
+    })
+}
+
+impl SubroutineDoc {
+    /// `function int max(int a, int b)`, with each type written by
+    /// `type_name`, so that pages can link the types to their classes.
+    pub fn signature(&self, type_name: impl Fn(&Type) -> String) -> String {
+        let return_type = match &self.return_type {
+            Some(return_type) => type_name(return_type),
+            None => "void".to_string(),
+        };
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {}", type_name(&parameter.var_type), parameter.name))
+            .collect();
+        format!(
+            "{} {} {}({})",
+            self.kind,
+            return_type,
+            self.name,
+            parameters.join(", ")
+        )
+    }
+}
+
+impl VariableDoc {
+    /// `static int n` or `const int MAX = 100`, like `SubroutineDoc::signature`.
+    pub fn declaration(&self, type_name: impl Fn(&Type) -> String) -> String {
+        let declaration = format!("{} {} {}", self.kind, type_name(&self.var_type), self.name);
+        match self.value {
+            Some(value) => format!("{} = {}", declaration, value),
+            None => declaration,
+        }
+    }
+}
+
+/// The first sentence of a description, for lists of classes and
+/// subroutines.
+pub fn summary(description: &str) -> String {
+    let paragraph = description.split("\n\n").next().unwrap_or_default();
+    let paragraph = paragraph.lines().collect::<Vec<_>>().join(" ");
+    match paragraph.find(". ") {
+        Some(end) => paragraph[..=end].to_string(),
+        None => paragraph,
+    }
+}
+
+// the text of the doc comments, by what they document
+struct Docs {
+    class: Option<String>,
+    variables: HashMap<String, String>,
+    subroutines: HashMap<String, String>,
+}
+
+impl Docs {
+    fn new(tokenizer: &JackTokenizer) -> Self {
+        let mut docs = Docs {
+            class: None,
+            variables: HashMap::new(),
+            subroutines: HashMap::new(),
+        };
+        let tokens = &tokenizer.tokens;
+        for (i, token) in tokens.iter().enumerate() {
+            let Some(comment) = tokenizer.doc_comment(i) else {
+                continue;
+            };
+            let text = JackTokenizer::doc_text(comment);
+            if text.is_empty() {
+                continue;
+            }
+            if let Token::Keyword(Keywords::Class) = token {
+                docs.class = Some(text);
+                continue;
+            }
+            // the declared name follows the keyword and the type
+            let name = match tokens.get(i + 2) {
+                Some(Token::Identifier(name)) => name.clone(),
+                _ => continue,
+            };
+            match token {
+                Token::Keyword(Keywords::Constructor | Keywords::Function | Keywords::Method) => {
+                    docs.subroutines.insert(name, text);
+                }
+                // one comment describes every name of `static int x, y;`
+                Token::Keyword(Keywords::Static | Keywords::Field) => {
+                    let names = tokens
+                        .iter()
+                        .skip(i + 2)
+                        .take_while(|token| !matches!(token, Token::Symbol(Symbols::Semicolon(_))));
+                    for token in names {
+                        if let Token::Identifier(name) = token {
+                            docs.variables.insert(name.clone(), text.clone());
+                        }
+                    }
+                }
+                Token::Identifier(word) if word == "const" => {
+                    docs.variables.insert(name, text);
+                }
+                _ => (),
+            }
+        }
+        docs
+    }
+}
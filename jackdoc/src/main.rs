@@ -0,0 +1,103 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use jack_compiler::parser::Dialect;
+use jackdoc::{html, markdown};
+
+const USAGE: &str = "usage: jackdoc [--markdown] [--extended] [-o <dir>] <file.jack | dir>...";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut markdown = false;
+    let mut dialect = Dialect::Standard;
+    let mut out_dir = PathBuf::from("doc");
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--markdown" => markdown = true,
+            "--extended" => dialect = Dialect::Extended,
+            "-o" => match args.next() {
+                Some(dir) => out_dir = PathBuf::from(dir),
+                None => usage_error("-o needs a directory"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option {}", arg)),
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        usage_error("no .jack file or directory given");
+    }
+
+    let mut classes = Vec::new();
+    for input in &inputs {
+        for file in jack_files(Path::new(input)) {
+            let jack_code = fs::read_to_string(&file).unwrap_or_else(|err| {
+                eprintln!("{}: {}", file.display(), err);
+                process::exit(1);
+            });
+            let class = jackdoc::class_doc(&jack_code, dialect).unwrap_or_else(|err| {
+                eprintln!("Error: {}", err);
+                process::exit(1);
+            });
+            classes.push(class);
+        }
+    }
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // a page for each class and an index linking to them
+    let mut pages = Vec::new();
+    if markdown {
+        pages.push(("index.md".to_string(), markdown::index_page(&classes)));
+        for class in &classes {
+            pages.push((format!("{}.md", class.name), markdown::class_page(class)));
+        }
+    } else {
+        pages.push(("index.html".to_string(), html::index_page(&classes)));
+        for class in &classes {
+            let page = html::class_page(class, &classes);
+            pages.push((format!("{}.html", class.name), page));
+        }
+    }
+
+    fs::create_dir_all(&out_dir).unwrap_or_else(|err| {
+        eprintln!("{}: {}", out_dir.display(), err);
+        process::exit(1);
+    });
+    for (name, page) in pages {
+        let file = out_dir.join(name);
+        fs::write(&file, page).unwrap_or_else(|err| {
+            eprintln!("{}: {}", file.display(), err);
+            process::exit(1);
+        });
+    }
+}
+
+// the file itself, or the .jack files directly inside a directory
+fn jack_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    let entries = fs::read_dir(path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path.display(), err);
+        process::exit(1);
+    });
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.extension().is_some_and(|ext| ext == "jack"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
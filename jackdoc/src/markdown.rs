@@ -0,0 +1,51 @@
+use crate::{summary, ClassDoc};
+
+/// `index.md`, the list of every class.
+pub fn index_page(classes: &[ClassDoc]) -> String {
+    let mut page = String::from("# Classes\n\n");
+    for class in classes {
+        page += &format!("- [{0}]({0}.md)", class.name);
+        if let Some(description) = &class.description {
+            page += &format!(": {}", summary(description));
+        }
+        page += "\n";
+    }
+    page
+}
+
+/// `<class>.md`. Types are not linked, since signatures are code.
+pub fn class_page(class: &ClassDoc) -> String {
+    let type_name = |var_type: &_| format!("{}", var_type);
+
+    let mut page = String::from("[All classes](index.md)\n\n");
+    page += &format!("# class {}\n\n", class.name);
+    if let Some(description) = &class.description {
+        page += &format!("{}\n\n", description);
+    }
+
+    if !class.variables.is_empty() {
+        page += "## Class variables\n\n";
+        for variable in &class.variables {
+            page += &format!("- `{}`", variable.declaration(type_name));
+            if let Some(description) = &variable.description {
+                // a list item is one line
+                page += &format!(": {}", description.lines().collect::<Vec<_>>().join(" "));
+            }
+            page += "\n";
+        }
+        page += "\n";
+    }
+
+    if !class.subroutines.is_empty() {
+        page += "## Subroutines\n\n";
+        for subroutine in &class.subroutines {
+            page += &format!("### {}\n\n", subroutine.name);
+            page += &format!("```\n{}\n```\n\n", subroutine.signature(type_name));
+            if let Some(description) = &subroutine.description {
+                page += &format!("{}\n\n", description);
+            }
+        }
+    }
+    page.truncate(page.trim_end().len());
+    page + "\n"
+}
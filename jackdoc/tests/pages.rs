@@ -0,0 +1,109 @@
+use std::process::Command;
+
+use jack_compiler::parser::Dialect;
+use jackdoc::{class_doc, html, markdown, summary, ClassDoc};
+
+const STACK: &str = "/**
+ * A stack of <int> values, for code like `a < b && c > d`.
+ *
+ * Pushing past the \"capacity\" fails.
+ */
+class Stack {
+    /** The items; index 0 is the bottom. */
+    field Array items;
+    field int size;
+
+    /** A stack for at most n < 100 items. Allocates the array. */
+    constructor Stack new(int n) { let items = Array.new(n); return this; }
+
+    /** Pushes x & returns nothing. */
+    method void push(int x) { let items[size] = x; let size = size + 1; return; }
+
+    method Stack copy() { return this; }
+}
+";
+
+fn stack() -> ClassDoc {
+    class_doc(STACK, Dialect::Standard).unwrap()
+}
+
+#[test]
+fn reads_the_doc_comments() {
+    let class = stack();
+    assert_eq!(
+        class.description.as_deref(),
+        Some(
+            "A stack of <int> values, for code like `a < b && c > d`.\n\n\
+             Pushing past the \"capacity\" fails."
+        )
+    );
+    assert_eq!(
+        class.variables[0].description.as_deref(),
+        Some("The items; index 0 is the bottom.")
+    );
+    assert_eq!(class.variables[1].description, None);
+    assert_eq!(class.subroutines[2].description, None);
+    assert_eq!(
+        summary(class.subroutines[0].description.as_deref().unwrap()),
+        "A stack for at most n < 100 items."
+    );
+}
+
+#[test]
+fn escapes_html() {
+    let classes = [stack()];
+    let page = html::class_page(&classes[0], &classes);
+    assert!(
+        page.contains(
+            "<p>A stack of &lt;int&gt; values, for code like `a &lt; b &amp;&amp; c &gt; d`.</p>"
+        ),
+        "{}",
+        page
+    );
+    assert!(page.contains("<p>Pushing past the \"capacity\" fails.</p>"));
+    assert!(page.contains("<td>A stack for at most n &lt; 100 items.</td>"));
+    assert!(page.contains("<p>Pushes x &amp; returns nothing.</p>"));
+    assert!(!page.contains("<int>"));
+
+    let index = html::index_page(&classes);
+    assert!(
+        index.contains("<td>A stack of &lt;int&gt; values"),
+        "{}",
+        index
+    );
+}
+
+#[test]
+fn links_the_documented_classes() {
+    let classes = [stack()];
+    let page = html::class_page(&classes[0], &classes);
+    assert!(page.contains("<code>method <a href=\"Stack.html\">Stack</a> copy()</code>"));
+    // Array is not one of the documented classes
+    assert!(page.contains("<code>field Array items</code>"));
+}
+
+#[test]
+fn writes_markdown() {
+    let page = markdown::class_page(&stack());
+    assert!(page.starts_with("[All classes](index.md)\n\n# class Stack\n\n"));
+    assert!(page.contains("- `field Array items`: The items; index 0 is the bottom.\n"));
+    assert!(page.contains(
+        "### push\n\n```\nmethod void push(int x)\n```\n\nPushes x & returns nothing.\n"
+    ));
+    assert_eq!(
+        markdown::index_page(&[stack()]),
+        "# Classes\n\n- [Stack](Stack.md): A stack of <int> values, for code like `a < b && c > d`.\n"
+    );
+}
+
+#[test]
+fn rejects_unknown_options() {
+    let output = Command::new(env!("CARGO_BIN_EXE_jackdoc"))
+        .args(["--html", "src"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown option --html"), "{}", stderr);
+    assert!(stderr.contains("usage: jackdoc"), "{}", stderr);
+}
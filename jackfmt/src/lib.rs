@@ -0,0 +1,263 @@
+use jack_compiler::jack_tokenizer::{JackTokenizer, Keywords, Lexeme, Symbols, Token};
+use jack_compiler::parser::{token_text, CompileError, Dialect, ParseOptions, Parser};
+
+const INDENT: &str = "    ";
+
+/// Reformats the source of one class: four-space indentation, one
+/// statement per line, opening braces at the end of the line and `} else {`,
+/// single spaces around binary operators and after commas and keywords.
+/// Comments are kept where they were relative to the code, and runs of
+/// blank lines become one. Code that does not parse is left alone and the
+/// parse error returned.
+pub fn format(jack_code: &str) -> Result<String, CompileError> {
+    format_with(jack_code, Dialect::Standard)
+}
+
+/// Reformats code of the given dialect; `for` headers stay on one line and
+/// compound assignments are written `x += 1`.
+pub fn format_with(jack_code: &str, dialect: Dialect) -> Result<String, CompileError> {
+    let options = ParseOptions {
+        dialect,
+        ..ParseOptions::default()
+    };
+    Parser::with_options(JackTokenizer::from_code(jack_code)?, options).parse_class()?;
+
+    let mut formatter = Formatter::new();
+    for (lexeme, location) in JackTokenizer::located_lexemes(jack_code)? {
+        match lexeme {
+            Lexeme::Token(token) => formatter.token(token),
+            Lexeme::Comment { text, trailing } => {
+                formatter.comment(&text, trailing, location.column)
+            }
+            Lexeme::BlankLine => formatter.blank_line(),
+        }
+    }
+    Ok(formatter.finish())
+}
+
+/// Whether the code is already formatted.
+pub fn is_formatted(jack_code: &str) -> Result<bool, CompileError> {
+    Ok(format(jack_code)? == jack_code)
+}
+
+struct Formatter {
+    out: String,
+    // the line being built, without its indentation
+    line: String,
+    line_indent: usize,
+    indent: usize,
+    // a comment broke a statement over lines; its later lines are indented
+    // one more level
+    continuation: bool,
+    // the current line is complete and the next token starts a new one
+    line_done: bool,
+    blank_line: bool,
+    // the last written line ended with `{`
+    after_brace: bool,
+    previous: Option<Token>,
+    previous_unary: bool,
+    after_comment: bool,
+    // open parentheses, inside which `;` does not end the line
+    paren_depth: usize,
+}
+
+impl Formatter {
+    fn new() -> Self {
+        Formatter {
+            out: String::new(),
+            line: String::new(),
+            line_indent: 0,
+            indent: 0,
+            continuation: false,
+            line_done: false,
+            blank_line: false,
+            after_brace: false,
+            previous: None,
+            previous_unary: false,
+            after_comment: false,
+            paren_depth: 0,
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        if is_symbol(&token, '}') {
+            self.indent = self.indent.saturating_sub(1);
+            self.blank_line = false;
+            if !self.line.is_empty() {
+                self.line_done = true;
+            }
+        }
+
+        let joins_brace = matches!(token, Token::Keyword(Keywords::Else))
+            && self.line_done
+            && self
+                .previous
+                .as_ref()
+                .is_some_and(|previous| is_symbol(previous, '}'));
+        if joins_brace {
+            self.line_done = false;
+        } else if self.line_done {
+            self.end_line();
+        }
+
+        if self.line.is_empty() {
+            self.start_line();
+        } else if self.after_comment || self.needs_space(&token) {
+            self.line.push(' ');
+        }
+        self.line += &token_text(&token);
+
+        if is_symbol(&token, '{') {
+            self.indent += 1;
+        }
+        if is_symbol(&token, '(') {
+            self.paren_depth += 1;
+        } else if is_symbol(&token, ')') {
+            self.paren_depth = self.paren_depth.saturating_sub(1);
+        }
+        let ends_statement = is_symbol(&token, ';') && self.paren_depth == 0;
+        if is_symbol(&token, '{') || ends_statement || is_symbol(&token, '}') {
+            self.line_done = true;
+        }
+        self.previous_unary =
+            is_symbol(&token, '~') || (is_symbol(&token, '-') && self.is_operand_position());
+        self.previous = Some(token);
+        self.after_comment = false;
+    }
+
+    // `column` is where the comment started, which its later lines are
+    // indented relative to
+    fn comment(&mut self, text: &str, trailing: bool, column: usize) {
+        if trailing && !self.line.is_empty() {
+            self.line.push(' ');
+            self.line += text.lines().next().unwrap_or_default();
+            if text.starts_with("/*") && !text.contains('\n') {
+                self.after_comment = true;
+                return;
+            }
+            // a line comment (or a block comment spanning lines) ends the line
+            if !self.line_done {
+                self.continuation = true;
+            }
+            for line in text.lines().skip(1) {
+                self.end_line();
+                self.start_line();
+                self.line = comment_line(line, column);
+            }
+            self.line_done = true;
+            return;
+        }
+
+        if !self.line.is_empty() {
+            if !self.line_done {
+                self.continuation = true;
+            }
+            self.end_line();
+        }
+        let mut lines = text.lines();
+        self.start_line();
+        self.line = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            self.end_line();
+            self.start_line();
+            self.line = comment_line(line, column);
+        }
+        self.end_line();
+        self.after_comment = false;
+    }
+
+    fn blank_line(&mut self) {
+        // blank lines inside a statement are dropped
+        if self.line.is_empty() || self.line_done {
+            self.blank_line = true;
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.end_line();
+        self.out
+    }
+
+    // writes the blank line before the new line if there is one
+    fn start_line(&mut self) {
+        if self.blank_line && !self.after_brace && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.blank_line = false;
+        self.line_indent = self.indent + usize::from(self.continuation);
+    }
+
+    fn end_line(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        let line = std::mem::take(&mut self.line);
+        self.after_brace = line.ends_with('{');
+        self.out += &INDENT.repeat(self.line_indent);
+        self.out += &line;
+        self.out.push('\n');
+
+        if self.line_done {
+            self.continuation = false;
+        }
+        self.line_done = false;
+    }
+
+    fn needs_space(&self, token: &Token) -> bool {
+        let previous = match &self.previous {
+            Some(previous) => previous,
+            None => return false,
+        };
+        if self.previous_unary
+            || [';', ',', ')', ']', '.']
+                .iter()
+                .any(|c| is_symbol(token, *c))
+            || ['(', '[', '.'].iter().any(|c| is_symbol(previous, *c))
+        {
+            return false;
+        }
+        // the `+=` of a compound assignment, which standard Jack cannot have
+        if is_symbol(token, '=')
+            && ['+', '-', '*', '/', '&', '|']
+                .iter()
+                .any(|c| is_symbol(previous, *c))
+        {
+            return false;
+        }
+        // calls, subroutine declarations and array indexing, but not the
+        // header of a `for` loop
+        if is_symbol(token, '(') || is_symbol(token, '[') {
+            return !matches!(previous, Token::Identifier(_)) || self.line == "for";
+        }
+        true
+    }
+
+    // whether the next token starts an operand, so that `-` is negation
+    fn is_operand_position(&self) -> bool {
+        match &self.previous {
+            None => true,
+            Some(Token::Keyword(keyword)) => *keyword == Keywords::Return,
+            Some(Token::Symbol(symbol)) => !matches!(
+                symbol,
+                Symbols::RParen(_) | Symbols::RSquare(_) | Symbols::RCurly(_)
+            ),
+            Some(_) => false,
+        }
+    }
+}
+
+fn is_symbol(token: &Token, c: char) -> bool {
+    matches!(token, Token::Symbol(symbol) if JackTokenizer::symbol_char(symbol) == c)
+}
+
+// a later line of a block comment, with ` * ...` lines aligned under the
+// opening `/*` and other lines keeping their indentation past its column
+fn comment_line(line: &str, column: usize) -> String {
+    let line = line.trim_end();
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('*') {
+        return format!(" {}", trimmed);
+    }
+    let indentation = line.chars().count() - trimmed.chars().count();
+    line.chars().skip(indentation.min(column)).collect()
+}
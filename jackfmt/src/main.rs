@@ -0,0 +1,85 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use jack_compiler::parser::Dialect;
+
+const USAGE: &str = "usage: jackfmt [--check] [--extended] <file.jack | dir>...";
+
+fn main() {
+    let mut check = false;
+    let mut dialect = Dialect::Standard;
+    let mut inputs = Vec::new();
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--check" => check = true,
+            "--extended" => dialect = Dialect::Extended,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if argument.starts_with('-') => usage_error(&format!("unknown option {}", argument)),
+            _ => inputs.push(argument),
+        }
+    }
+    if inputs.is_empty() {
+        usage_error("no .jack file or directory given");
+    }
+
+    let mut unformatted = false;
+    for input in inputs {
+        for file in jack_files(Path::new(&input)) {
+            let jack_code = fs::read_to_string(&file).unwrap_or_else(|err| {
+                eprintln!("{}: {}", file.display(), err);
+                process::exit(1);
+            });
+            let formatted = jackfmt::format_with(&jack_code, dialect).unwrap_or_else(|err| {
+                eprintln!("Error: {}", err);
+                process::exit(1);
+            });
+            if formatted == jack_code {
+                continue;
+            }
+
+            if check {
+                // like `rustfmt --check`, list the files and fail
+                println!("{} is not formatted", file.display());
+                unformatted = true;
+            } else {
+                fs::write(&file, formatted).unwrap_or_else(|err| {
+                    eprintln!("{}: {}", file.display(), err);
+                    process::exit(1);
+                });
+            }
+        }
+    }
+
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+// the file itself, or the .jack files directly inside a directory
+fn jack_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    let entries = fs::read_dir(path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path.display(), err);
+        process::exit(1);
+    });
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.extension().is_some_and(|ext| ext == "jack"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+#[test]
+fn keeps_the_indentation_of_block_comment_prose() {
+    let jack_code = "\
+/** Implements a graphical square.
+    The square has top-left x and y coordinates,
+      and a size. */
+class Square {
+        /** Draws the square.
+         * Uses the current color.
+             Indented prose.
+         */
+    method void draw() { return; }
+}
+";
+    let expected = "\
+/** Implements a graphical square.
+    The square has top-left x and y coordinates,
+      and a size. */
+class Square {
+    /** Draws the square.
+     * Uses the current color.
+         Indented prose.
+     */
+    method void draw() {
+        return;
+    }
+}
+";
+    assert_eq!(jackfmt::format(jack_code).unwrap(), expected);
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    let projects = Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects");
+    for dir in ["09/Square", "10/Square", "11/Pong", "11/ComplexArrays"] {
+        let mut files: Vec<_> = fs::read_dir(projects.join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "jack")
+            })
+            .collect();
+        files.sort();
+        for file in files {
+            let formatted = jackfmt::format(&fs::read_to_string(&file).unwrap()).unwrap();
+            assert!(
+                jackfmt::is_formatted(&formatted).unwrap(),
+                "{} changes when formatted again",
+                file.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::{fmt, io};
+
+use assembler::parser::ParseError;
+use jack_compiler::interpreter::RuntimeError;
+use jack_compiler::parser::CompileError;
+use vm::VmError;
+use vm_emulator::emulator::EmulatorError;
+
+#[derive(Debug)]
+pub enum N2tError {
+    Usage(String),
+    Io { path: String, err: io::Error },
+    Compile(CompileError),
+    Link(String),
+    Translate(VmError),
+    Assemble(ParseError),
+    Run(EmulatorError),
+    Interpret(RuntimeError),
+}
+
+impl fmt::Display for N2tError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{}", message),
+            Self::Io { path, err } => write!(f, "{}: {}", path, err),
+            Self::Compile(err) => write!(f, "{}", err),
+            Self::Link(message) => write!(f, "{}", message),
+            Self::Translate(err) => write!(f, "{}", err),
+            Self::Assemble(err) => write!(f, "{}", err),
+            Self::Run(err) => write!(f, "{}", err),
+            Self::Interpret(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for N2tError {}
+
+impl From<CompileError> for N2tError {
+    fn from(err: CompileError) -> Self {
+        N2tError::Compile(err)
+    }
+}
+
+impl From<VmError> for N2tError {
+    fn from(err: VmError) -> Self {
+        N2tError::Translate(err)
+    }
+}
+
+impl From<ParseError> for N2tError {
+    fn from(err: ParseError) -> Self {
+        N2tError::Assemble(err)
+    }
+}
+
+impl From<EmulatorError> for N2tError {
+    fn from(err: EmulatorError) -> Self {
+        N2tError::Run(err)
+    }
+}
+
+impl From<RuntimeError> for N2tError {
+    fn from(err: RuntimeError) -> Self {
+        N2tError::Interpret(err)
+    }
+}
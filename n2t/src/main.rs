@@ -0,0 +1,445 @@
+mod error;
+mod pipeline;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+};
+
+use jack_compiler::interpreter::{Exit, Interpreter};
+use jack_compiler::parser::{Dialect, ParseOptions};
+use vm::{translate_with, TranslateOptions, VmSource};
+use vm_emulator::emulator::{VmEmulator, SP, STACK_BASE};
+
+use error::N2tError;
+use pipeline::{
+    check_sys_init, collect_files, compile_jack, compile_library, create_dir, file_stem, link,
+    os_files, read_file, write_file,
+};
+
+const DEFAULT_MAX_STEPS: usize = 10_000_000;
+
+const USAGE: &str = "usage: n2t <command> <inputs>... [options]
+
+commands:
+    build      compile .jack (or .vm) files and write a .hack program
+    analyze    write the token and parse tree XML of .jack files
+    lib        compile a library of .jack files, such as the OS, into .vm
+               files to link with --lib
+    translate  translate .vm files into one .asm program
+    assemble   assemble .asm files into .hack files
+    run        run .jack or .vm files in the VM emulator
+    interpret  run .jack files directly, with the OS implemented natively
+
+options:
+    -o <dir>          write output files to <dir> instead of next to the inputs
+    --emit <kinds>    also write intermediate files, e.g. --emit vm,asm
+    --os              link the OS classes from $N2T_OS_DIR, or else from the
+                      projects/12 above the current directory or above n2t
+    --os-dir <dir>    link the OS classes from <dir>
+    --lib <dir>       link the .vm files in <dir>, e.g. an OS built with `n2t lib`;
+                      the program's own classes replace library and OS classes
+    --strip           leave out the subroutines the program never calls
+    --extended        accept else if, for, break, continue, let x += 1, 'A',
+                      0x1F and 0b1010 literals and const declarations
+    --precedence      parse expressions with conventional operator precedence
+    --steps <n>       stop `run` after n VM commands, or `interpret` after n
+                      statements (default 10000000)
+    --keys <text>     keys typed for Keyboard reads in `interpret`, where \\n is Enter
+    --screen <file>   write the screen as a PBM image after `interpret`
+    --annotate        see `vm --annotate`
+    --debug           see `vm --debug`";
+
+enum Command {
+    Build,
+    Analyze,
+    Lib,
+    Translate,
+    Assemble,
+    Run,
+    Interpret,
+}
+
+struct Options {
+    command: Command,
+    inputs: Vec<String>,
+    out_dir: Option<PathBuf>,
+    emit_vm: bool,
+    emit_asm: bool,
+    os_dir: Option<PathBuf>,
+    lib_dir: Option<PathBuf>,
+    strip: bool,
+    parse: ParseOptions,
+    max_steps: usize,
+    annotate: bool,
+    debug: bool,
+    keys: String,
+    screen: Option<PathBuf>,
+}
+
+fn main() {
+    let options = get_options().unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    let result = match options.command {
+        Command::Build => build(&options),
+        Command::Analyze => analyze(&options),
+        Command::Lib => lib(&options),
+        Command::Translate => translate(&options),
+        Command::Assemble => assemble(&options),
+        Command::Run => run(&options),
+        Command::Interpret => interpret(&options),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+// Jack -> VM -> Hack assembly -> .hack, entirely in memory. Only the .hack
+// file is written unless --emit asks for the intermediate stages.
+fn build(options: &Options) -> Result<(), N2tError> {
+    let sources = load_program(options)?;
+    check_sys_init(&sources)?;
+    let out_dir = out_dir(options)?;
+    let name = program_name(&options.inputs[0]);
+
+    if options.emit_vm {
+        write_vm_files(&sources, &out_dir)?;
+    }
+    let asm = translate_sources(options, &sources, &out_dir, &name)?;
+    let hack = assembler::assemble(&asm)?;
+    write_file(&out_dir.join(name + ".hack"), &hack)
+}
+
+fn analyze(options: &Options) -> Result<(), N2tError> {
+    let out_dir = out_dir(options)?;
+
+    for file in collect_files(&options.inputs, "jack")? {
+        let jack_code = read_file(&file)?;
+        let name = file_stem(&file);
+        let io_error = |err| N2tError::Io {
+            path: file.display().to_string(),
+            err,
+        };
+
+        let tokens = jack_analyzer::tokens_xml(&jack_code).map_err(io_error)?;
+        write_file(&out_dir.join(name.clone() + "_token.xml"), &tokens)?;
+        let parse_tree = jack_analyzer::parse_tree_xml(&jack_code).map_err(io_error)?;
+        write_file(&out_dir.join(name + "_compile.xml"), &parse_tree)?;
+    }
+    Ok(())
+}
+
+// Each class into a .vm file, to link with programs by --lib.
+fn lib(options: &Options) -> Result<(), N2tError> {
+    let files = collect_files(&options.inputs, "jack")?;
+    let (sources, warnings) = compile_library(&files, options.parse)?;
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    write_vm_files(&sources, &out_dir(options)?)
+}
+
+fn translate(options: &Options) -> Result<(), N2tError> {
+    let sources = read_vm_files(&options.inputs)?;
+    let out_dir = out_dir(options)?;
+    let name = program_name(&options.inputs[0]);
+
+    let asm = translate_sources(options, &sources, &out_dir, &name)?;
+    write_file(&out_dir.join(name + ".asm"), &asm)
+}
+
+fn assemble(options: &Options) -> Result<(), N2tError> {
+    let out_dir = out_dir(options)?;
+
+    for file in collect_files(&options.inputs, "asm")? {
+        let hack = assembler::assemble(&read_file(&file)?)?;
+        write_file(&out_dir.join(file_stem(&file) + ".hack"), &hack)?;
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), N2tError> {
+    let sources = load_program(options)?;
+    // VM programs, such as those of project 7, may start at their first
+    // command instead
+    if is_jack_program(&options.inputs) {
+        check_sys_init(&sources)?;
+    }
+    if options.emit_vm {
+        write_vm_files(&sources, &out_dir(options)?)?;
+    }
+
+    let mut emulator = VmEmulator::load_sources(&sources)?;
+    emulator.poke(SP, STACK_BASE as i16);
+    if let Err(err) = emulator.run(options.max_steps) {
+        eprintln!("call stack: {}", emulator.call_stack().join(" > "));
+        return Err(err.into());
+    }
+
+    let state = if emulator.is_halted() {
+        "halted"
+    } else {
+        "stopped"
+    };
+    println!("{} after {} steps", state, emulator.steps());
+    println!("SP: {}", emulator.peek(0));
+    println!("stack: {:?}", emulator.stack());
+    Ok(())
+}
+
+// Jack classes given with --os or --os-dir replace the native OS class of
+// the same name, and the program's own classes replace both.
+fn interpret(options: &Options) -> Result<(), N2tError> {
+    let mut files = Vec::new();
+    if let Some(os_dir) = &options.os_dir {
+        files = os_files(os_dir)?;
+    }
+    files.append(&mut collect_files(&options.inputs, "jack")?);
+    let jack_codes = files
+        .iter()
+        .map(|file| read_file(file))
+        .collect::<Result<Vec<_>, _>>()?;
+    let classes = jack_codes
+        .iter()
+        .zip(&files)
+        .map(|(jack_code, file)| {
+            jack_compiler::parse_with(jack_code, options.parse).map_err(|err| err.in_file(file))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut interpreter = Interpreter::new(classes);
+    interpreter.type_keys(&options.keys);
+    let result = interpreter.run(options.max_steps);
+    print!("{}", interpreter.output());
+    if !interpreter.output().is_empty() && !interpreter.output().ends_with('\n') {
+        println!();
+    }
+    if let Some(screen) = &options.screen {
+        write_file(screen, &interpreter.screen_pbm())?;
+    }
+    if let Err(err) = &result {
+        eprintln!("call stack: {}", err.call_stack.join(" > "));
+    }
+
+    let state = match result? {
+        Exit::Halted => "halted",
+        Exit::StepLimit => "stopped",
+    };
+    println!("{} after {} steps", state, interpreter.steps());
+    Ok(())
+}
+
+// The VM code of a Jack program, its classes followed by the OS classes
+// when --os is given, or the VM files themselves for a VM program, and
+// then the classes of the --lib directory the program does not define.
+fn load_program(options: &Options) -> Result<Vec<VmSource>, N2tError> {
+    let mut sources = if is_jack_program(&options.inputs) {
+        let os = match &options.os_dir {
+            Some(os_dir) => os_files(os_dir)?,
+            None => Vec::new(),
+        };
+        let program = collect_files(&options.inputs, "jack")?;
+        let (sources, warnings) = compile_jack(&program, &os, options.parse, options.strip)?;
+        for warning in warnings {
+            eprintln!("{}", warning);
+        }
+        sources
+    } else {
+        read_vm_files(&options.inputs)?
+    };
+    if let Some(lib_dir) = &options.lib_dir {
+        link(
+            &mut sources,
+            read_vm_files(&[lib_dir.display().to_string()])?,
+        );
+    }
+    Ok(sources)
+}
+
+fn translate_sources(
+    options: &Options,
+    sources: &[VmSource],
+    out_dir: &Path,
+    name: &str,
+) -> Result<String, N2tError> {
+    let translate_options = TranslateOptions {
+        annotate: options.annotate,
+        debug: options.debug,
+        ..TranslateOptions::default()
+    };
+    let asm = translate_with(sources, &translate_options)?.asm;
+    if options.emit_asm {
+        write_file(&out_dir.join(name.to_string() + ".asm"), &asm)?;
+    }
+    Ok(asm)
+}
+
+fn read_vm_files(inputs: &[String]) -> Result<Vec<VmSource>, N2tError> {
+    collect_files(inputs, "vm")?
+        .iter()
+        .map(|file| Ok(VmSource::new(&file_stem(file), &read_file(file)?)))
+        .collect()
+}
+
+fn write_vm_files(sources: &[VmSource], out_dir: &Path) -> Result<(), N2tError> {
+    for source in sources {
+        write_file(&out_dir.join(source.name.clone() + ".vm"), &source.code)?;
+    }
+    Ok(())
+}
+
+// A program is Jack if its inputs are .jack files or directories with
+// .jack files in them, otherwise it is taken to be VM code.
+fn is_jack_program(inputs: &[String]) -> bool {
+    inputs.iter().all(|input| {
+        let path = Path::new(input);
+        if path.is_dir() {
+            collect_files(std::slice::from_ref(input), "jack").is_ok()
+        } else {
+            path.extension().is_some_and(|ext| ext == "jack")
+        }
+    })
+}
+
+// -o dir, otherwise the directory of the first input
+fn out_dir(options: &Options) -> Result<PathBuf, N2tError> {
+    let out_dir = match &options.out_dir {
+        Some(out_dir) => out_dir.clone(),
+        None => {
+            let input = Path::new(&options.inputs[0]);
+            if input.is_dir() {
+                input.to_path_buf()
+            } else {
+                input
+                    .parent()
+                    .map(|parent| parent.to_path_buf())
+                    .unwrap_or_default()
+            }
+        }
+    };
+    if !out_dir.as_os_str().is_empty() {
+        create_dir(&out_dir)?;
+    }
+    Ok(out_dir)
+}
+
+// dir -> dir, X.jack -> X
+fn program_name(input: &str) -> String {
+    let path = Path::new(input);
+    if path.is_dir() {
+        path.canonicalize()
+            .ok()
+            .and_then(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "out".to_string())
+    } else {
+        file_stem(path)
+    }
+}
+
+fn get_options() -> Result<Options, N2tError> {
+    let mut args = env::args().skip(1);
+    let usage = |message: &str| N2tError::Usage(message.to_string());
+
+    let command = match args.next().as_deref() {
+        Some("build") => Command::Build,
+        Some("analyze") => Command::Analyze,
+        Some("lib") => Command::Lib,
+        Some("translate") => Command::Translate,
+        Some("assemble") => Command::Assemble,
+        Some("run") => Command::Run,
+        Some("interpret") => Command::Interpret,
+        Some(command) => return Err(usage(&format!("unknown command {}", command))),
+        None => return Err(usage("no command given")),
+    };
+
+    let mut options = Options {
+        command,
+        inputs: Vec::new(),
+        out_dir: None,
+        emit_vm: false,
+        emit_asm: false,
+        os_dir: None,
+        lib_dir: None,
+        strip: false,
+        parse: ParseOptions::default(),
+        max_steps: DEFAULT_MAX_STEPS,
+        annotate: false,
+        debug: false,
+        keys: String::new(),
+        screen: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| usage(&format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "-o" => options.out_dir = Some(PathBuf::from(value("-o")?)),
+            "--emit" => {
+                for kind in value("--emit")?.split(',') {
+                    match kind {
+                        "vm" => options.emit_vm = true,
+                        "asm" => options.emit_asm = true,
+                        _ => return Err(usage(&format!("cannot emit {}", kind))),
+                    }
+                }
+            }
+            "--os" => options.os_dir = Some(default_os_dir()?),
+            "--os-dir" => options.os_dir = Some(PathBuf::from(value("--os-dir")?)),
+            "--lib" => options.lib_dir = Some(PathBuf::from(value("--lib")?)),
+            "--strip" => options.strip = true,
+            "--extended" => options.parse.dialect = Dialect::Extended,
+            "--precedence" => options.parse.precedence = true,
+            "--steps" => {
+                let steps = value("--steps")?;
+                options.max_steps = steps
+                    .parse()
+                    .map_err(|_| usage(&format!("invalid step count {}", steps)))?;
+            }
+            "--keys" => options.keys = value("--keys")?.replace("\\n", "\n"),
+            "--screen" => options.screen = Some(PathBuf::from(value("--screen")?)),
+            "--annotate" => options.annotate = true,
+            "--debug" => options.debug = true,
+            _ if arg.starts_with('-') => return Err(usage(&format!("unknown option {}", arg))),
+            _ => options.inputs.push(arg),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        return Err(usage("no inputs given"));
+    }
+    Ok(options)
+}
+
+// $N2T_OS_DIR, or else the closest projects/12 with the OS in it above the
+// current directory and then above the n2t executable
+fn default_os_dir() -> Result<PathBuf, N2tError> {
+    if let Some(os_dir) = env::var_os("N2T_OS_DIR") {
+        return Ok(PathBuf::from(os_dir));
+    }
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let starts = [env::current_dir().ok(), exe_dir];
+    starts
+        .iter()
+        .flatten()
+        .flat_map(|start| start.ancestors())
+        .map(|dir| dir.join("projects").join("12"))
+        .find(|os_dir| os_dir.join("Sys.jack").is_file())
+        .ok_or_else(|| {
+            N2tError::Usage(
+                "cannot find the OS for --os; set N2T_OS_DIR or use --os-dir <dir>".to_string(),
+            )
+        })
+}
@@ -0,0 +1,193 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jack_compiler::ast::Class;
+use jack_compiler::code_generator::CodeGenerator;
+use jack_compiler::parser::ParseOptions;
+use jack_compiler::warnings::{self, Warning};
+use vm::VmSource;
+
+use crate::error::N2tError;
+
+/// Every input is either a file with the given extension or a directory
+/// whose files with that extension are taken in sorted order.
+pub fn collect_files(inputs: &[String], extension: &str) -> Result<Vec<PathBuf>, N2tError> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut dir_files = Vec::new();
+            let entries = fs::read_dir(path).map_err(|err| io_error(path, err))?;
+            for entry in entries {
+                let file_path = entry.map_err(|err| io_error(path, err))?.path();
+                if file_path.is_file() && has_extension(&file_path, extension) {
+                    dir_files.push(file_path);
+                }
+            }
+            if dir_files.is_empty() {
+                return Err(N2tError::Usage(format!(
+                    "no .{} files found in {}",
+                    extension, input
+                )));
+            }
+            dir_files.sort();
+            files.append(&mut dir_files);
+        } else if path.is_file() {
+            if !has_extension(path, extension) {
+                return Err(N2tError::Usage(format!(
+                    "{} is not a .{} file",
+                    input, extension
+                )));
+            }
+            files.push(path.to_path_buf());
+        } else {
+            return Err(N2tError::Usage(format!("{} does not exist", input)));
+        }
+    }
+
+    Ok(files)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext == extension)
+}
+
+/// `Main.jack` -> `Main`
+pub fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn read_file(path: &Path) -> Result<String, N2tError> {
+    fs::read_to_string(path).map_err(|err| io_error(path, err))
+}
+
+pub fn write_file(path: &Path, contents: &str) -> Result<(), N2tError> {
+    fs::write(path, contents).map_err(|err| io_error(path, err))
+}
+
+pub fn create_dir(path: &Path) -> Result<(), N2tError> {
+    fs::create_dir_all(path).map_err(|err| io_error(path, err))
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> N2tError {
+    N2tError::Io {
+        path: path.display().to_string(),
+        err,
+    }
+}
+
+/// Compiles each `.jack` file of a program and of the OS it is linked with
+/// into one in-memory `.vm` file of the same name, with warnings about the
+/// program's own files. `strip` leaves out the subroutines that can never
+/// be called. The OS is always parsed left to right, as it was written,
+/// and its classes that the program defines itself are left out.
+pub fn compile_jack(
+    program: &[PathBuf],
+    os: &[PathBuf],
+    options: ParseOptions,
+    strip: bool,
+) -> Result<(Vec<VmSource>, Vec<Warning>), N2tError> {
+    let os: Vec<&PathBuf> = os
+        .iter()
+        .filter(|os_file| {
+            !program
+                .iter()
+                .any(|file| file_stem(file) == file_stem(os_file))
+        })
+        .collect();
+    let mut classes = Vec::new();
+    for file in program {
+        classes.push(parse_file(file, options)?);
+    }
+    let os_options = ParseOptions {
+        precedence: false,
+        ..options
+    };
+    for file in &os {
+        classes.push(parse_file(file, os_options)?);
+    }
+    let (program_classes, os_classes) = classes.split_at(program.len());
+    let warnings = warnings::program_warnings(program_classes, os_classes);
+    if strip {
+        warnings::remove_unreachable(&mut classes);
+    }
+
+    let mut sources = Vec::new();
+    for (file, class) in program.iter().chain(os.iter().copied()).zip(&classes) {
+        let vm_code = CodeGenerator::new()
+            .compile_class(class)
+            .map_err(|err| err.in_file(file))?;
+        sources.push(VmSource::new(&file_stem(file), &vm_code));
+    }
+    Ok((sources, warnings))
+}
+
+// errors name the file, which need not be named after its class
+fn parse_file(file: &Path, options: ParseOptions) -> Result<Class, N2tError> {
+    let jack_code = read_file(file)?;
+    Ok(jack_compiler::parse_with(&jack_code, options).map_err(|err| err.in_file(file))?)
+}
+
+/// The OS classes are the `.jack` files directly inside `os_dir`, e.g.
+/// projects/12/Math.jack; the test directories next to them are skipped.
+pub fn os_files(os_dir: &Path) -> Result<Vec<PathBuf>, N2tError> {
+    collect_files(&[os_dir.to_string_lossy().to_string()], "jack")
+}
+
+/// Compiles the `.jack` files of a library, such as the OS, into `.vm`
+/// files to link with programs later. The warnings are those of each class
+/// alone, since a library's subroutines are there to be called by others.
+pub fn compile_library(
+    files: &[PathBuf],
+    options: ParseOptions,
+) -> Result<(Vec<VmSource>, Vec<Warning>), N2tError> {
+    let mut sources = Vec::new();
+    let mut warnings = Vec::new();
+    for file in files {
+        let class = parse_file(file, options)?;
+        warnings.append(&mut warnings::class_warnings(&class));
+        let vm_code = CodeGenerator::new()
+            .compile_class(&class)
+            .map_err(|err| err.in_file(file))?;
+        sources.push(VmSource::new(&file_stem(file), &vm_code));
+    }
+    Ok((sources, warnings))
+}
+
+/// Adds the classes of a compiled library to the program, except those
+/// the program defines itself.
+pub fn link(program: &mut Vec<VmSource>, library: Vec<VmSource>) {
+    for source in library {
+        if !program.iter().any(|class| class.name == source.name) {
+            program.push(source);
+        }
+    }
+}
+
+/// A program that starts the way the bootstrap code does needs a
+/// `Sys.init`, which for a Jack program comes with the OS.
+pub fn check_sys_init(sources: &[VmSource]) -> Result<(), N2tError> {
+    let defines_sys_init = sources.iter().any(|source| {
+        source.code.lines().any(|line| {
+            let mut words = line
+                .split("//")
+                .next()
+                .unwrap_or_default()
+                .split_whitespace();
+            words.next() == Some("function") && words.next() == Some("Sys.init")
+        })
+    });
+    if defines_sys_init {
+        return Ok(());
+    }
+    Err(N2tError::Link(
+        "no Sys.init to start the program; link the OS with --os, --os-dir or --lib, \
+         or define Sys.init"
+            .to_string(),
+    ))
+}
@@ -0,0 +1,152 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+const MAIN: &str = "class Main {
+    function void main() { do Output.printInt(Math.abs(-5)); return; }
+}
+";
+
+// replaces the OS's Math
+const MATH: &str = "class Math {
+    function int abs(int x) { return 42; }
+}
+";
+
+fn os_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/12")
+}
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("n2t-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// the exit code and standard error of n2t
+fn n2t(args: &[&Path]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_n2t"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+fn program(dir: &Path) -> PathBuf {
+    let program = dir.join("Program");
+    fs::create_dir_all(&program).unwrap();
+    fs::write(program.join("Main.jack"), MAIN).unwrap();
+    program
+}
+
+// the OS compiled into dir/os
+fn os_library(dir: &Path) -> PathBuf {
+    let os = dir.join("os");
+    let (status, stderr) = n2t(&[Path::new("lib"), &os_dir(), Path::new("-o"), &os]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    os
+}
+
+#[test]
+fn compiles_the_os_into_a_library() {
+    let dir = temp_dir("lib");
+    let os = os_library(&dir);
+    let mut classes: Vec<String> = fs::read_dir(&os)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    classes.sort();
+    assert_eq!(
+        classes,
+        [
+            "Array.vm",
+            "Keyboard.vm",
+            "Math.vm",
+            "Memory.vm",
+            "Output.vm",
+            "Screen.vm",
+            "String.vm",
+            "Sys.vm",
+        ]
+    );
+    // the test programs next to the OS are not part of it
+    assert!(read(&os.join("Sys.vm")).contains("function Sys.init 0"));
+}
+
+#[test]
+fn needs_sys_init() {
+    let dir = temp_dir("sys_init");
+    let program = program(&dir);
+    let (status, stderr) = n2t(&[Path::new("build"), &program]);
+    assert_eq!(status, Some(1));
+    assert_eq!(
+        stderr,
+        "Error: no Sys.init to start the program; link the OS with --os, --os-dir or --lib, \
+         or define Sys.init\n"
+    );
+    assert!(!program.join("Program.hack").exists());
+
+    let (status, stderr) = n2t(&[Path::new("run"), &program]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.starts_with("Error: no Sys.init"), "{}", stderr);
+}
+
+#[test]
+fn links_the_library_with_the_program() {
+    let dir = temp_dir("link");
+    let os = os_library(&dir);
+    let program = program(&dir);
+    fs::write(program.join("Math.jack"), MATH).unwrap();
+    let out = dir.join("out");
+
+    let (status, stderr) = n2t(&[
+        Path::new("build"),
+        &program,
+        Path::new("--lib"),
+        &os,
+        Path::new("--emit"),
+        Path::new("vm"),
+        Path::new("-o"),
+        &out,
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert!(!read(&out.join("Program.hack")).is_empty());
+
+    // the program's Math replaces the library's
+    let math = read(&out.join("Math.vm"));
+    assert!(math.contains("push constant 42"), "{}", math);
+    assert!(!math.contains("function Math.multiply"), "{}", math);
+    assert_eq!(read(&out.join("Sys.vm")), read(&os.join("Sys.vm")));
+    assert_eq!(read(&out.join("Output.vm")), read(&os.join("Output.vm")));
+}
+
+#[test]
+fn links_the_os_sources_too() {
+    let dir = temp_dir("os_dir");
+    let program = program(&dir);
+    let out = dir.join("out");
+
+    let (status, stderr) = n2t(&[
+        Path::new("build"),
+        &program,
+        Path::new("--os-dir"),
+        &os_dir(),
+        Path::new("--emit"),
+        Path::new("vm"),
+        Path::new("-o"),
+        &out,
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert!(read(&out.join("Sys.vm")).contains("function Sys.init 0"));
+    assert!(read(&out.join("Math.vm")).contains("function Math.abs 0"));
+}
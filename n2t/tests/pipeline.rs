@@ -0,0 +1,107 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+// a program that needs no OS
+const SYS: &str = "class Sys {
+    function void init() { var int x; let x = 2 + 3; return; }
+}
+";
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("n2t-{}-{}", test, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn n2t(args: &[&Path]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_n2t"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+#[test]
+fn builds_a_program_through_every_stage() {
+    let dir = temp_dir("build");
+    let program = dir.join("Adder");
+    fs::create_dir_all(&program).unwrap();
+    fs::write(program.join("Sys.jack"), SYS).unwrap();
+    let out = dir.join("out");
+
+    let (status, _, stderr) = n2t(&[
+        Path::new("build"),
+        &program,
+        Path::new("--emit"),
+        Path::new("vm,asm"),
+        Path::new("-o"),
+        &out,
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+
+    let vm_code = read(&out.join("Sys.vm"));
+    assert!(vm_code.starts_with("function Sys.init 1\n"), "{}", vm_code);
+    let asm = read(&out.join("Adder.asm"));
+    assert!(asm.starts_with("@256"), "{}", asm);
+    assert_eq!(
+        read(&out.join("Adder.hack")),
+        assembler::assemble(&asm).unwrap()
+    );
+    // only what --emit asks for is written
+    assert!(!program.join("Sys.vm").exists());
+
+    // the stages one at a time give the same program
+    let staged = dir.join("staged");
+    let (status, _, stderr) = n2t(&[
+        Path::new("translate"),
+        &out.join("Sys.vm"),
+        Path::new("-o"),
+        &staged,
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    let (status, _, stderr) = n2t(&[Path::new("assemble"), &staged.join("Sys.asm")]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert_eq!(
+        read(&staged.join("Sys.hack")),
+        read(&out.join("Adder.hack"))
+    );
+}
+
+#[test]
+fn runs_a_program() {
+    let dir = temp_dir("run");
+    fs::write(dir.join("Sys.jack"), SYS).unwrap();
+
+    let (status, stdout, stderr) = n2t(&[
+        Path::new("run"),
+        &dir.join("Sys.jack"),
+        Path::new("--steps"),
+        Path::new("100"),
+    ]);
+    assert_eq!(status, Some(0), "{}", stderr);
+    assert!(stdout.contains(" after "), "{}", stdout);
+    assert!(stdout.contains("SP: "), "{}", stdout);
+}
+
+#[test]
+fn rejects_unknown_commands() {
+    let (status, _, stderr) = n2t(&[Path::new("compile"), Path::new("Main.jack")]);
+    assert_eq!(status, Some(1));
+    assert!(
+        stderr.starts_with("unknown command compile\n\nusage: n2t"),
+        "{}",
+        stderr
+    );
+}
@@ -1,31 +1,69 @@
-use std::fs::File;
+use std::fmt;
 use std::io::{self, Write};
 
 use crate::parser::CommandType;
 
-pub struct CodeWriter {
-    file: File,
+/// RAM cell that the debug halt loops store their error code in.
+pub const ERROR_CODE_ADDRESS: usize = 15;
+pub const STACK_OVERFLOW: usize = 1;
+pub const STACK_UNDERFLOW: usize = 2;
+pub const SEGFAULT: usize = 3;
+
+const STACK_BASE: usize = 256;
+const STACK_END: usize = 2047;
+// this/that may point into the heap, the screen or the keyboard register
+const HEAP_BASE: usize = 2048;
+const KEYBOARD: usize = 24576;
+
+/// ROM addresses `rom_start..rom_end` were generated from `command`
+/// at `file:line`.
+pub struct SourceMapEntry {
+    pub rom_start: usize,
+    pub rom_end: usize,
+    pub file: String,
+    pub line: usize,
+    pub command: String,
+}
+
+// one `start end file line command` line per entry, tab separated, with
+// `end` exclusive
+impl fmt::Display for SourceMapEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.rom_start, self.rom_end, self.file, self.line, self.command
+        )
+    }
+}
+
+pub struct CodeWriter<W: Write> {
+    out: W,
     filename: String,
+    // labels are written as `function$label`
+    current_function: String,
     label_counter: usize,
     fn_call_counter: usize,
+    annotate: bool,
+    debug: bool,
+    rom_address: usize,
+    current_command: Option<SourceMapEntry>,
+    source_map: Vec<SourceMapEntry>,
 }
 
-impl CodeWriter {
-    pub fn new(filename: &str) -> Result<Self, io::Error> {
-        let striped_filename = filename
-            .replace(".asm", "")
-            .rsplit('/')
-            .next()
-            .unwrap()
-            .to_string();
-        match File::create(filename) {
-            Ok(file) => Ok(CodeWriter {
-                file,
-                filename: striped_filename,
-                label_counter: 0,
-                fn_call_counter: 0,
-            }),
-            Err(e) => Err(e),
+impl<W: Write> CodeWriter<W> {
+    pub fn new(out: W) -> Self {
+        CodeWriter {
+            out,
+            filename: String::new(),
+            current_function: String::new(),
+            label_counter: 0,
+            fn_call_counter: 0,
+            annotate: false,
+            debug: false,
+            rom_address: 0,
+            current_command: None,
+            source_map: Vec::new(),
         }
     }
 
@@ -40,369 +78,542 @@ impl CodeWriter {
         Ok(())
     }
 
-    pub fn write_init(&mut self) {
-        self.file.write_all("@256\n".as_bytes());
-        self.file.write_all("D=A\n".as_bytes());
-        self.write_to_d("SP");
-        self.write_call("Sys.init", 0);
+    /// When enabled, every VM command is preceded by a comment such as
+    /// `// Main.vm:23 push local 2`.
+    pub fn set_annotate(&mut self, annotate: bool) {
+        self.annotate = annotate;
+    }
+
+    /// When enabled, pushes, calls and function entries check that there is
+    /// room on the stack before writing to it, pops that SP stays above 256
+    /// and `this`/`that` accesses stay within the heap, screen and keyboard.
+    /// A failed check jumps to `$$STACK_OVERFLOW`, `$$STACK_UNDERFLOW` or
+    /// `$$SEGFAULT`, which store their error code in RAM[ERROR_CODE_ADDRESS]
+    /// and halt. `close` writes these handlers.
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Marks the start of the code for the VM command at `line` of the
+    /// current file. Must be called before the command is written.
+    pub fn begin_command(&mut self, line: usize, command: &str) -> io::Result<()> {
+        self.end_command();
+        let file = self.filename.clone() + ".vm";
+        if self.annotate {
+            self.write(&format!("// {}:{} {}\n", file, line, command))?;
+        }
+        self.current_command = Some(SourceMapEntry {
+            rom_start: self.rom_address,
+            rom_end: self.rom_address,
+            file,
+            line,
+            command: command.to_string(),
+        });
+        Ok(())
+    }
+
+    fn end_command(&mut self) {
+        if let Some(mut entry) = self.current_command.take() {
+            entry.rom_end = self.rom_address;
+            // labels generate no instructions and can not be looked up
+            if entry.rom_end > entry.rom_start {
+                self.source_map.push(entry);
+            }
+        }
+    }
+
+    pub fn source_map(&mut self) -> &[SourceMapEntry] {
+        self.end_command();
+        &self.source_map
+    }
+
+    pub fn write_init(&mut self) -> io::Result<()> {
+        self.write("@256\n")?;
+        self.write("D=A\n")?;
+        self.write_from_d("SP")?;
+        self.write_call("Sys.init", 0)?;
+
+        // a return from Sys.init stops instead of running into the code
+        // after the bootstrap
+        if self.debug {
+            self.write("@$$HALT\n")?;
+            self.write("0;JMP\n")?;
+        }
+        Ok(())
+    }
+
+    // written once, after all the code; the labels are global, so they are
+    // not written through write_goto
+    fn write_debug_handlers(&mut self) -> io::Result<()> {
+        self.write("($$HALT)\n")?;
+        self.write("@$$HALT\n")?;
+        self.write("0;JMP\n")?;
+
+        for (label, error_code) in [
+            ("$$STACK_OVERFLOW", STACK_OVERFLOW),
+            ("$$STACK_UNDERFLOW", STACK_UNDERFLOW),
+            ("$$SEGFAULT", SEGFAULT),
+        ] {
+            self.write(&("(".to_string() + label + ")\n"))?;
+            self.write(&("@".to_string() + &error_code.to_string() + "\n"))?;
+            self.write("D=A\n")?;
+            self.write(&("@R".to_string() + &ERROR_CODE_ADDRESS.to_string() + "\n"))?;
+            self.write("M=D\n")?;
+            self.write(&("(".to_string() + label + "_HALT)\n"))?;
+            self.write(&("@".to_string() + label + "_HALT\n"))?;
+            self.write("0;JMP\n")?;
+        }
+        Ok(())
+    }
+
+    // before `words` are pushed: SP + words must not exceed STACK_END, so
+    // nothing is written past the stack
+    fn write_overflow_check(&mut self, words: usize) -> io::Result<()> {
+        self.write("@SP\n")?;
+        self.write("D=M\n")?;
+        let limit = STACK_END.saturating_sub(words);
+        self.write(&("@".to_string() + &limit.to_string() + "\n"))?;
+        self.write("D=D-A\n")?;
+        self.write("@$$STACK_OVERFLOW\n")?;
+        self.write("D;JGT\n")?;
+        Ok(())
+    }
+
+    // before a pop SP must be above STACK_BASE
+    fn write_underflow_check(&mut self) -> io::Result<()> {
+        self.write("@SP\n")?;
+        self.write("D=M\n")?;
+        self.write(&("@".to_string() + &STACK_BASE.to_string() + "\n"))?;
+        self.write("D=D-A\n")?;
+        self.write("@$$STACK_UNDERFLOW\n")?;
+        self.write("D;JLE\n")?;
+        Ok(())
+    }
+
+    // checks the address in D and leaves it in D
+    fn write_heap_check(&mut self) -> io::Result<()> {
+        self.write("@R13\n")?;
+        self.write("M=D\n")?;
+        self.write(&("@".to_string() + &HEAP_BASE.to_string() + "\n"))?;
+        self.write("D=D-A\n")?;
+        self.write("@$$SEGFAULT\n")?;
+        self.write("D;JLT\n")?;
+        self.write("@R13\n")?;
+        self.write("D=M\n")?;
+        self.write(&("@".to_string() + &KEYBOARD.to_string() + "\n"))?;
+        self.write("D=D-A\n")?;
+        self.write("@$$SEGFAULT\n")?;
+        self.write("D;JGT\n")?;
+        self.write("@R13\n")?;
+        self.write("D=M\n")?;
+        Ok(())
     }
 
-    pub fn write_arithmetic(&mut self, command: &str) {
+    pub fn write_arithmetic(&mut self, command: &str) -> io::Result<()> {
         match command {
             "add" => {
-                self.write_arithmetic_to_d("+");
-                self.sp_sub1();
-                self.write_d_to_stack();
+                self.write_arithmetic_to_d("+")?;
+                self.sp_sub1()?;
+                self.write_d_to_stack()?;
             }
             "sub" => {
-                self.write_arithmetic_to_d("-");
-                self.sp_sub1();
-                self.write_d_to_stack()
+                self.write_arithmetic_to_d("-")?;
+                self.sp_sub1()?;
+                self.write_d_to_stack()?
             }
             "not" => {
-                self.pop_to_d();
-                self.file.write_all("M=!D\n".as_bytes());
-                self.sp_add1();
+                self.pop_to_d()?;
+                self.write("M=!D\n")?;
+                self.sp_add1()?;
             }
             "neg" => {
-                self.pop_to_d();
-                self.file.write_all("M=-D\n".as_bytes());
-                self.sp_add1();
+                self.pop_to_d()?;
+                self.write("M=-D\n")?;
+                self.sp_add1()?;
             }
             "and" => {
-                self.write_arithmetic_to_d("&");
-                self.sp_sub1();
-                self.write_d_to_stack();
+                self.write_arithmetic_to_d("&")?;
+                self.sp_sub1()?;
+                self.write_d_to_stack()?;
             }
             "or" => {
-                self.write_arithmetic_to_d("|");
-                self.sp_sub1();
-                self.write_d_to_stack();
+                self.write_arithmetic_to_d("|")?;
+                self.sp_sub1()?;
+                self.write_d_to_stack()?;
             }
-            "eq" => self.write_comparison_to_d("JEQ"),
+            "eq" => self.write_comparison_to_d("JEQ")?,
 
-            "gt" => self.write_comparison_to_d("JGT"),
+            "gt" => self.write_comparison_to_d("JGT")?,
 
-            "lt" => self.write_comparison_to_d("JLT"),
+            "lt" => self.write_comparison_to_d("JLT")?,
 
             _ => (),
         }
+        Ok(())
     }
 
-    pub fn write_push_pop(&mut self, command_type: CommandType, segment: &str, index: usize) {
+    pub fn write_push_pop(
+        &mut self,
+        command_type: CommandType,
+        segment: &str,
+        index: usize,
+    ) -> io::Result<()> {
+        if self.debug {
+            match command_type {
+                CommandType::CPush => self.write_overflow_check(1)?,
+                CommandType::CPop => self.write_underflow_check()?,
+                _ => (),
+            }
+        }
+
         match command_type {
             CommandType::CPush => match segment {
                 "constant" => {
-                    self.file
-                        .write_all(("@".to_string() + &index.to_string() + "\n").as_bytes());
-                    self.file.write_all("D=A\n".as_bytes());
+                    self.write(&("@".to_string() + &index.to_string() + "\n"))?;
+                    self.write("D=A\n")?;
 
-                    self.write_d_to_stack();
+                    self.write_d_to_stack()?;
                 }
-                "local" => self.push("LCL", index),
-                "argument" => self.push("ARG", index),
-                "this" => self.push("THIS", index),
-                "that" => self.push("THAT", index),
-                "pointer" => self.push("pointer", index),
-                "temp" => self.push("temp", index),
-                "static" => self.push("static", index),
+                "local" => self.push("LCL", index)?,
+                "argument" => self.push("ARG", index)?,
+                "this" => self.push("THIS", index)?,
+                "that" => self.push("THAT", index)?,
+                "pointer" => self.push("pointer", index)?,
+                "temp" => self.push("temp", index)?,
+                "static" => self.push("static", index)?,
                 _ => (),
             },
             CommandType::CPop => match segment {
-                "local" => self.pop("LCL", index),
-                "argument" => self.pop("ARG", index),
-                "this" => self.pop("THIS", index),
-                "that" => self.pop("THAT", index),
-                "pointer" => self.pop("pointer", index),
-                "temp" => self.pop("temp", index),
-                "static" => self.pop("static", index),
+                "local" => self.pop("LCL", index)?,
+                "argument" => self.pop("ARG", index)?,
+                "this" => self.pop("THIS", index)?,
+                "that" => self.pop("THAT", index)?,
+                "pointer" => self.pop("pointer", index)?,
+                "temp" => self.pop("temp", index)?,
+                "static" => self.pop("static", index)?,
                 _ => (),
             },
             _ => (),
         }
+        Ok(())
     }
 
-    pub fn write_label(&mut self, label_name: &str) {
-        self.file
-            .write_all(("(".to_string() + label_name + ")\n").as_bytes());
+    pub fn write_label(&mut self, label_name: &str) -> io::Result<()> {
+        let label = self.scoped_label(label_name);
+        self.write(&("(".to_string() + &label + ")\n"))?;
         // self.label_counter += 1;
+        Ok(())
+    }
+
+    pub fn write_goto(&mut self, label_name: &str) -> io::Result<()> {
+        let label = self.scoped_label(label_name);
+        self.write(&("@".to_string() + &label + "\n"))?;
+        self.write("0;JMP\n")?;
+        Ok(())
     }
 
-    pub fn write_goto(&mut self, label_name: &str) {
-        self.file
-            .write_all(("@".to_string() + label_name + "\n").as_bytes());
-        self.file.write_all("0;JMP\n".as_bytes());
+    pub fn write_if(&mut self, label_name: &str) -> io::Result<()> {
+        self.pop_to_d()?;
+        let label = self.scoped_label(label_name);
+        self.write(&("@".to_string() + &label + "\n"))?;
+        self.write("D;JNE\n")?;
+        Ok(())
     }
 
-    pub fn write_if(&mut self, label_name: &str) {
-        self.pop_to_d();
-        self.file
-            .write_all(("@".to_string() + label_name + "\n").as_bytes());
-        self.file.write_all("D;JNE\n".as_bytes());
+    // Labels only have to be unique within a function, e.g. the compiler
+    // emits WHILE_EXP0 in every subroutine with a loop. Labels outside of
+    // any function (the project 7/8 tests) are written as they are.
+    fn scoped_label(&self, label_name: &str) -> String {
+        if self.current_function.is_empty() {
+            label_name.to_string()
+        } else {
+            format!("{}${}", self.current_function, label_name)
+        }
     }
 
-    pub fn write_function(&mut self, func_name: &str, local_num: usize) {
-        self.file
-            .write_all(("(".to_string() + func_name + ")\n").as_bytes());
-        self.file.write_all("D=0\n".as_bytes());
+    pub fn write_function(&mut self, func_name: &str, local_num: usize) -> io::Result<()> {
+        self.current_function = func_name.to_string();
+        self.write(&("(".to_string() + func_name + ")\n"))?;
+        if self.debug && local_num > 0 {
+            self.write_overflow_check(local_num)?;
+        }
+        self.write("D=0\n")?;
         for _ in 0..local_num {
-            self.write_d_to_stack();
+            self.write_d_to_stack()?;
         }
+        Ok(())
     }
 
-    pub fn write_return(&mut self) {
-        self.write_to_d("LCL");
-        self.write_from_d("FRAME");
+    pub fn write_return(&mut self) -> io::Result<()> {
+        self.write_to_d("LCL")?;
+        self.write_from_d("FRAME")?;
 
-        self.file.write_all("D=D-1\n".as_bytes());
-        self.file.write_all("D=D-1\n".as_bytes());
-        self.file.write_all("D=D-1\n".as_bytes());
-        self.file.write_all("D=D-1\n".as_bytes());
-        self.file.write_all("D=D-1\n".as_bytes());
-        self.file.write_all("A=D\n".as_bytes());
-        self.file.write_all("D=M\n".as_bytes());
-        self.write_from_d("RET");
+        self.write("D=D-1\n")?;
+        self.write("D=D-1\n")?;
+        self.write("D=D-1\n")?;
+        self.write("D=D-1\n")?;
+        self.write("D=D-1\n")?;
+        self.write("A=D\n")?;
+        self.write("D=M\n")?;
+        self.write_from_d("RET")?;
 
-        self.pop_to_d();
-        self.write_d_to_pointed("ARG");
+        self.pop_to_d()?;
+        self.write_d_to_pointed("ARG")?;
 
-        self.file.write_all("@ARG\n".as_bytes());
-        self.file.write_all("D=M\n".as_bytes());
-        self.file.write_all("D=D+1\n".as_bytes());
+        self.write("@ARG\n")?;
+        self.write("D=M\n")?;
+        self.write("D=D+1\n")?;
 
-        self.write_from_d("SP");
+        self.write_from_d("SP")?;
 
-        self.sub1("FRAME");
-        self.write_pointed_to_d("FRAME");
-        self.write_from_d("THAT");
+        self.sub1("FRAME")?;
+        self.write_pointed_to_d("FRAME")?;
+        self.write_from_d("THAT")?;
 
-        self.sub1("FRAME");
-        self.write_pointed_to_d("FRAME");
-        self.write_from_d("THIS");
+        self.sub1("FRAME")?;
+        self.write_pointed_to_d("FRAME")?;
+        self.write_from_d("THIS")?;
 
-        self.sub1("FRAME");
-        self.write_pointed_to_d("FRAME");
-        self.write_from_d("ARG");
+        self.sub1("FRAME")?;
+        self.write_pointed_to_d("FRAME")?;
+        self.write_from_d("ARG")?;
 
-        self.sub1("FRAME");
-        self.write_pointed_to_d("FRAME");
-        self.write_from_d("LCL");
+        self.sub1("FRAME")?;
+        self.write_pointed_to_d("FRAME")?;
+        self.write_from_d("LCL")?;
 
-        self.file.write_all("@RET\n".as_bytes());
-        self.file.write_all("A=M\n".as_bytes());
-        self.file.write_all("0;JMP\n".as_bytes());
+        self.write("@RET\n")?;
+        self.write("A=M\n")?;
+        self.write("0;JMP\n")?;
+        Ok(())
     }
 
-    pub fn write_call(&mut self, func_name: &str, arg_num: usize) {
-        self.file.write_all(
-            ("@".to_string() + func_name + "_RET" + &self.fn_call_counter.to_string() + "\n")
-                .as_bytes(),
-        );
-        self.file.write_all("D=A\n".as_bytes());
-        self.write_d_to_stack();
+    pub fn write_call(&mut self, func_name: &str, arg_num: usize) -> io::Result<()> {
+        // the return address and the caller's LCL, ARG, THIS and THAT
+        if self.debug {
+            self.write_overflow_check(5)?;
+        }
+        self.write(
+            &("@".to_string() + func_name + "_RET" + &self.fn_call_counter.to_string() + "\n"),
+        )?;
+        self.write("D=A\n")?;
+        self.write_d_to_stack()?;
 
-        self.write_to_d("LCL");
-        self.write_d_to_stack();
+        self.write_to_d("LCL")?;
+        self.write_d_to_stack()?;
 
-        self.write_to_d("ARG");
-        self.write_d_to_stack();
+        self.write_to_d("ARG")?;
+        self.write_d_to_stack()?;
 
-        self.write_to_d("THIS");
-        self.write_d_to_stack();
+        self.write_to_d("THIS")?;
+        self.write_d_to_stack()?;
 
-        self.write_to_d("THAT");
-        self.write_d_to_stack();
+        self.write_to_d("THAT")?;
+        self.write_d_to_stack()?;
 
-        self.write_to_d("SP");
+        self.write_to_d("SP")?;
         for _ in 0..(arg_num + 5) {
-            self.file.write_all("D=D-1\n".as_bytes());
+            self.write("D=D-1\n")?;
         }
 
-        self.write_from_d("ARG");
+        self.write_from_d("ARG")?;
 
-        self.write_to_d("SP");
-        self.write_from_d("LCL");
+        self.write_to_d("SP")?;
+        self.write_from_d("LCL")?;
 
-        self.write_goto(func_name);
+        // function names are global, so not written through write_goto
+        self.write(&("@".to_string() + func_name + "\n"))?;
+        self.write("0;JMP\n")?;
 
-        self.file.write_all(
-            ("(".to_string() + func_name + "_RET" + &self.fn_call_counter.to_string() + ")\n")
-                .as_bytes(),
-        );
+        self.write(
+            &("(".to_string() + func_name + "_RET" + &self.fn_call_counter.to_string() + ")\n"),
+        )?;
 
         self.fn_call_counter += 1;
+        Ok(())
     }
 
-    fn add1(&mut self, dest: &str) {
-        self.file
-            .write_all(("@".to_string() + dest + "\n").as_bytes());
-        self.file.write_all("M=M+1\n".as_bytes());
+    fn add1(&mut self, dest: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + dest + "\n"))?;
+        self.write("M=M+1\n")?;
+        Ok(())
     }
 
-    fn sub1(&mut self, dest: &str) {
-        self.file
-            .write_all(("@".to_string() + dest + "\n").as_bytes());
-        self.file.write_all("M=M-1\n".as_bytes());
+    fn sub1(&mut self, dest: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + dest + "\n"))?;
+        self.write("M=M-1\n")?;
+        Ok(())
     }
 
-    fn sp_add1(&mut self) {
-        self.add1("SP");
+    fn sp_add1(&mut self) -> io::Result<()> {
+        self.add1("SP")?;
+        Ok(())
     }
 
-    fn sp_sub1(&mut self) {
-        self.sub1("SP");
+    fn sp_sub1(&mut self) -> io::Result<()> {
+        self.sub1("SP")?;
+        Ok(())
     }
 
-    fn push(&mut self, dest: &str, offset: usize) {
+    fn push(&mut self, dest: &str, offset: usize) -> io::Result<()> {
         match dest {
             "pointer" | "temp" => {
                 let base_address = if dest == "pointer" { 3 } else { 5 };
 
-                self.file.write_all(
-                    ("@".to_string() + &((base_address + offset).to_string()) + "\n").as_bytes(),
-                );
-                self.file.write_all("D=M\n".as_bytes());
-                self.write_d_to_stack();
+                self.write(&("@".to_string() + &((base_address + offset).to_string()) + "\n"))?;
+                self.write("D=M\n")?;
+                self.write_d_to_stack()?;
             }
             "static" => {
-                self.file.write_all(
-                    ("@".to_string() + &self.filename + "." + &offset.to_string() + "\n")
-                        .as_bytes(),
-                );
-                self.file.write_all("D=M\n".as_bytes());
-                self.write_d_to_stack();
+                self.write(&("@".to_string() + &self.filename + "." + &offset.to_string() + "\n"))?;
+                self.write("D=M\n")?;
+                self.write_d_to_stack()?;
             }
             _ => {
-                self.file
-                    .write_all(("@".to_string() + dest + "\n").as_bytes());
-                self.file.write_all("D=M\n".as_bytes());
+                self.write(&("@".to_string() + dest + "\n"))?;
+                self.write("D=M\n")?;
+
+                self.write(&("@".to_string() + &offset.to_string() + "\n"))?;
+                self.write("D=D+A\n")?;
 
-                self.file
-                    .write_all(("@".to_string() + &offset.to_string() + "\n").as_bytes());
-                self.file.write_all("D=D+A\n".as_bytes());
+                if self.debug && (dest == "THIS" || dest == "THAT") {
+                    self.write_heap_check()?;
+                }
 
-                self.file.write_all("A=D\n".as_bytes());
-                self.file.write_all("D=M\n".as_bytes());
+                self.write("A=D\n")?;
+                self.write("D=M\n")?;
 
-                self.write_d_to_stack();
+                self.write_d_to_stack()?;
             }
         }
+        Ok(())
     }
 
-    fn pop(&mut self, dest: &str, offset: usize) {
+    fn pop(&mut self, dest: &str, offset: usize) -> io::Result<()> {
         match dest {
             "pointer" | "temp" => {
                 let base_address = if dest == "pointer" { 3 } else { 5 };
 
-                self.pop_to_d();
+                self.pop_to_d()?;
 
-                self.file.write_all(
-                    ("@".to_string() + &((base_address + offset).to_string()) + "\n").as_bytes(),
-                );
-                self.file.write_all("M=D\n".as_bytes());
+                self.write(&("@".to_string() + &((base_address + offset).to_string()) + "\n"))?;
+                self.write("M=D\n")?;
             }
             "static" => {
-                self.pop_to_d();
+                self.pop_to_d()?;
 
-                self.file.write_all(
-                    ("@".to_string() + &self.filename + "." + &offset.to_string() + "\n")
-                        .as_bytes(),
-                );
-                self.file.write_all("M=D\n".as_bytes());
+                self.write(&("@".to_string() + &self.filename + "." + &offset.to_string() + "\n"))?;
+                self.write("M=D\n")?;
             }
             _ => {
-                self.file
-                    .write_all(("@".to_string() + dest + "\n").as_bytes());
-                self.file.write_all("D=M\n".as_bytes());
+                self.write(&("@".to_string() + dest + "\n"))?;
+                self.write("D=M\n")?;
+
+                self.write(&("@".to_string() + &offset.to_string() + "\n"))?;
+                self.write("D=D+A\n")?;
 
-                self.file
-                    .write_all(("@".to_string() + &offset.to_string() + "\n").as_bytes());
-                self.file.write_all("D=D+A\n".as_bytes());
+                if self.debug && (dest == "THIS" || dest == "THAT") {
+                    self.write_heap_check()?;
+                }
 
-                self.file.write_all("@R13\n".as_bytes());
-                self.file.write_all("M=D\n".as_bytes());
+                self.write("@R13\n")?;
+                self.write("M=D\n")?;
 
-                self.pop_to_d();
+                self.pop_to_d()?;
 
-                self.write_d_to_pointed("R13");
+                self.write_d_to_pointed("R13")?;
             }
         }
+        Ok(())
     }
 
-    fn pop_to_d(&mut self) {
-        self.sp_sub1();
-        self.write_pointed_to_d("SP");
+    fn pop_to_d(&mut self) -> io::Result<()> {
+        self.sp_sub1()?;
+        self.write_pointed_to_d("SP")?;
+        Ok(())
     }
 
-    fn write_d_to_stack(&mut self) {
-        self.write_d_to_pointed("SP");
-        self.sp_add1();
+    fn write_d_to_stack(&mut self) -> io::Result<()> {
+        self.write_d_to_pointed("SP")?;
+        self.sp_add1()?;
+        Ok(())
     }
 
-    fn write_to_d(&mut self, from: &str) {
-        self.file
-            .write_all(("@".to_string() + from + "\n").as_bytes());
-        self.file.write_all("D=M\n".as_bytes());
+    fn write_to_d(&mut self, from: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + from + "\n"))?;
+        self.write("D=M\n")?;
+        Ok(())
     }
 
-    fn write_from_d(&mut self, to: &str) {
-        self.file
-            .write_all(("@".to_string() + to + "\n").as_bytes());
-        self.file.write_all("M=D\n".as_bytes());
+    fn write_from_d(&mut self, to: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + to + "\n"))?;
+        self.write("M=D\n")?;
+        Ok(())
     }
 
-    fn write_pointed_to_d(&mut self, from: &str) {
-        self.file
-            .write_all(("@".to_string() + from + "\n").as_bytes());
-        self.file.write_all("A=M\n".as_bytes());
-        self.file.write_all("D=M\n".as_bytes());
+    fn write_pointed_to_d(&mut self, from: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + from + "\n"))?;
+        self.write("A=M\n")?;
+        self.write("D=M\n")?;
+        Ok(())
     }
 
-    fn write_d_to_pointed(&mut self, to: &str) {
-        self.file
-            .write_all(("@".to_string() + to + "\n").as_bytes());
-        self.file.write_all("A=M\n".as_bytes());
-        self.file.write_all("M=D\n".as_bytes());
+    fn write_d_to_pointed(&mut self, to: &str) -> io::Result<()> {
+        self.write(&("@".to_string() + to + "\n"))?;
+        self.write("A=M\n")?;
+        self.write("M=D\n")?;
+        Ok(())
     }
 
-    fn write_arithmetic_to_d(&mut self, op: &str) {
-        self.sp_sub1();
-        self.pop_to_d();
-        self.sp_add1();
-        self.file.write_all("@SP\n".as_bytes());
-        self.file.write_all("A=M\n".as_bytes());
-        self.file
-            .write_all(("D=D".to_string() + op + "M\n").as_bytes());
+    fn write_arithmetic_to_d(&mut self, op: &str) -> io::Result<()> {
+        self.sp_sub1()?;
+        self.pop_to_d()?;
+        self.sp_add1()?;
+        self.write("@SP\n")?;
+        self.write("A=M\n")?;
+        self.write(&("D=D".to_string() + op + "M\n"))?;
+        Ok(())
     }
 
-    fn write_comparison_to_d(&mut self, mnemonic: &str) {
-        self.write_arithmetic_to_d("-");
+    fn write_comparison_to_d(&mut self, mnemonic: &str) -> io::Result<()> {
+        self.write_arithmetic_to_d("-")?;
 
-        self.file
-            .write_all(("@LABEL".to_string() + &self.label_counter.to_string() + "\n").as_bytes());
+        self.write(&("@LABEL".to_string() + &self.label_counter.to_string() + "\n"))?;
         self.label_counter += 1;
 
-        self.file
-            .write_all(("D;".to_string() + mnemonic + "\n").as_bytes());
-        self.file.write_all("@0\n".as_bytes());
-        self.file.write_all("D=A\n".as_bytes());
-        self.file
-            .write_all(("@LABEL".to_string() + &self.label_counter.to_string() + "\n").as_bytes());
+        self.write(&("D;".to_string() + mnemonic + "\n"))?;
+        self.write("@0\n")?;
+        self.write("D=A\n")?;
+        self.write(&("@LABEL".to_string() + &self.label_counter.to_string() + "\n"))?;
         self.label_counter += 1;
-        self.file.write_all("0;JMP\n".as_bytes());
+        self.write("0;JMP\n")?;
 
-        self.file.write_all(
-            ("(LABEL".to_string() + &((self.label_counter - 2).to_string()) + ")\n").as_bytes(),
-        );
-        self.file.write_all("@1\n".as_bytes());
-        self.file.write_all("D=-A\n".as_bytes());
-        self.file.write_all(
-            ("(LABEL".to_string() + &((self.label_counter - 1).to_string()) + ")\n").as_bytes(),
-        );
+        self.write(&("(LABEL".to_string() + &((self.label_counter - 2).to_string()) + ")\n"))?;
+        self.write("@1\n")?;
+        self.write("D=-A\n")?;
+        self.write(&("(LABEL".to_string() + &((self.label_counter - 1).to_string()) + ")\n"))?;
 
-        self.sp_sub1();
-        self.write_d_to_stack();
+        self.sp_sub1()?;
+        self.write_d_to_stack()?;
+        Ok(())
     }
 
-    pub fn close() {}
+    // every line that is not a label or a comment takes one ROM address
+    fn write(&mut self, code: &str) -> io::Result<()> {
+        self.rom_address += code
+            .lines()
+            .filter(|line| !(line.is_empty() || line.starts_with('(') || line.starts_with("//")))
+            .count();
+        self.out.write_all(code.as_bytes())
+    }
+
+    /// Flushes the output and hands it back together with the source map.
+    pub fn close(mut self) -> io::Result<(W, Vec<SourceMapEntry>)> {
+        self.end_command();
+        if self.debug {
+            self.write_debug_handlers()?;
+        }
+        self.out.flush()?;
+        Ok((self.out, self.source_map))
+    }
 }
@@ -0,0 +1,289 @@
+pub mod code_writer;
+pub mod parser;
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use code_writer::{CodeWriter, SourceMapEntry};
+use parser::{CommandType, Parser};
+
+const ARITHMETIC_COMMANDS: [&str; 9] = ["add", "sub", "neg", "eq", "gt", "lt", "and", "or", "not"];
+const SEGMENTS: [&str; 8] = [
+    "constant", "local", "argument", "this", "that", "pointer", "temp", "static",
+];
+
+/// One `.vm` file. `name` is the file name without directory and
+/// extension, e.g. `Main`, and prefixes the file's static variables.
+pub struct VmSource {
+    pub name: String,
+    pub code: String,
+}
+
+impl VmSource {
+    pub fn new(name: &str, code: &str) -> Self {
+        VmSource {
+            name: name.to_string(),
+            code: code.to_string(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, VmError> {
+        let code = fs::read_to_string(path).map_err(|err| VmError::Io {
+            path: path.display().to_string(),
+            err,
+        })?;
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(VmSource { name, code })
+    }
+}
+
+#[derive(Debug)]
+pub enum VmError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    NotFound(String),
+    Syntax {
+        file: String,
+        line: usize,
+        command: String,
+    },
+    DuplicateFunction {
+        name: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "{}: {}", path, err),
+            Self::NotFound(message) => write!(f, "{}", message),
+            Self::Syntax {
+                file,
+                line,
+                command,
+            } => write!(f, "{}.vm:{}: invalid command \"{}\"", file, line, command),
+            Self::DuplicateFunction {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "function {} is defined in both {}.vm and {}.vm",
+                name, first, second
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl From<io::Error> for VmError {
+    fn from(err: io::Error) -> Self {
+        VmError::Io {
+            path: "<output>".to_string(),
+            err,
+        }
+    }
+}
+
+pub struct TranslateOptions {
+    /// Emit the `SP = 256; call Sys.init` bootstrap code.
+    pub bootstrap: bool,
+    /// See `CodeWriter::set_annotate`.
+    pub annotate: bool,
+    /// See `CodeWriter::set_debug`.
+    pub debug: bool,
+}
+
+impl Default for TranslateOptions {
+    fn default() -> Self {
+        TranslateOptions {
+            bootstrap: true,
+            annotate: false,
+            debug: false,
+        }
+    }
+}
+
+pub struct Translation {
+    pub asm: String,
+    pub source_map: Vec<SourceMapEntry>,
+}
+
+/// Translates the given files, in order, into one Hack assembly program.
+pub fn translate(files: &[VmSource]) -> Result<String, VmError> {
+    Ok(translate_with(files, &TranslateOptions::default())?.asm)
+}
+
+pub fn translate_with(
+    files: &[VmSource],
+    options: &TranslateOptions,
+) -> Result<Translation, VmError> {
+    check_duplicate_functions(files)?;
+
+    let mut code_writer = CodeWriter::new(Vec::new());
+    code_writer.set_annotate(options.annotate);
+    code_writer.set_debug(options.debug);
+    if options.bootstrap {
+        code_writer.write_init()?;
+    }
+
+    for file in files {
+        translate_file(&mut code_writer, file)?;
+    }
+
+    let (asm, source_map) = code_writer.close()?;
+    Ok(Translation {
+        asm: String::from_utf8_lossy(&asm).to_string(),
+        source_map,
+    })
+}
+
+/// Writes the code for one file to `code_writer`.
+pub fn translate_file<W: io::Write>(
+    code_writer: &mut CodeWriter<W>,
+    file: &VmSource,
+) -> Result<(), VmError> {
+    code_writer.set_file_name(&file.name)?;
+
+    let mut parser = Parser::from_code(&file.code);
+
+    while parser.has_more_commands() {
+        parser.advance();
+        check_command(&parser, &file.name)?;
+
+        code_writer.begin_command(parser.line_number(), &parser.command())?;
+        match parser.command_type() {
+            CommandType::CArithmetic => code_writer.write_arithmetic(&parser.command())?,
+            CommandType::CPush | CommandType::CPop => {
+                code_writer.write_push_pop(parser.command_type(), &parser.arg1(), parser.arg2())?
+            }
+            CommandType::CGoto => code_writer.write_goto(&parser.arg1())?,
+            CommandType::CLabel => code_writer.write_label(&parser.arg1())?,
+            CommandType::CIf => code_writer.write_if(&parser.arg1())?,
+            CommandType::CFunction => code_writer.write_function(&parser.arg1(), parser.arg2())?,
+            CommandType::CReturn => code_writer.write_return()?,
+            CommandType::CCall => code_writer.write_call(&parser.arg1(), parser.arg2())?,
+        }
+    }
+    Ok(())
+}
+
+// The parser guesses the command type from its first characters, so the
+// command is checked here before arg1/arg2 are trusted.
+fn check_command(parser: &Parser, file_name: &str) -> Result<(), VmError> {
+    let command = parser.command();
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let is_number = |word: &str| word.parse::<usize>().is_ok();
+
+    let valid = match parser.command_type() {
+        CommandType::CArithmetic => words.len() == 1 && ARITHMETIC_COMMANDS.contains(&words[0]),
+        CommandType::CPush | CommandType::CPop => {
+            words.len() == 3
+                && (words[0] == "push" || (words[0] == "pop" && words[1] != "constant"))
+                && SEGMENTS.contains(&words[1])
+                && is_number(words[2])
+        }
+        CommandType::CLabel | CommandType::CGoto | CommandType::CIf => {
+            words.len() == 2 && ["label", "goto", "if-goto"].contains(&words[0])
+        }
+        CommandType::CFunction | CommandType::CCall => {
+            words.len() == 3 && ["function", "call"].contains(&words[0]) && is_number(words[2])
+        }
+        CommandType::CReturn => words == ["return"],
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(VmError::Syntax {
+            file: file_name.to_string(),
+            line: parser.line_number(),
+            command,
+        })
+    }
+}
+
+pub fn check_duplicate_functions(files: &[VmSource]) -> Result<(), VmError> {
+    let mut defined_in: HashMap<String, String> = HashMap::new();
+
+    for file in files {
+        let mut parser = Parser::from_code(&file.code);
+
+        while parser.has_more_commands() {
+            parser.advance();
+            if let CommandType::CFunction = parser.command_type() {
+                check_command(&parser, &file.name)?;
+                let function_name = parser.arg1();
+                if let Some(first) = defined_in.get(&function_name) {
+                    return Err(VmError::DuplicateFunction {
+                        name: function_name,
+                        first: first.clone(),
+                        second: file.name.clone(),
+                    });
+                }
+                defined_in.insert(function_name, file.name.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Every input is either a .vm file or a directory whose .vm files are taken
+/// in sorted order, so the generated .asm is the same on every machine.
+/// Inputs keep the order they were given in and a file is only read once.
+pub fn extract_vm_files(inputs: &[String]) -> Result<Vec<PathBuf>, VmError> {
+    let mut files: Vec<PathBuf> = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+        let mut input_files = Vec::new();
+        let io_error = |err| VmError::Io {
+            path: input.to_string(),
+            err,
+        };
+
+        if path.is_dir() {
+            for entry in fs::read_dir(path).map_err(io_error)? {
+                let file_path = entry.map_err(io_error)?.path();
+                if file_path
+                    .extension()
+                    .is_some_and(|extension| extension == "vm")
+                {
+                    input_files.push(file_path);
+                }
+            }
+            input_files.sort();
+        } else if path.is_file() {
+            if path.extension().is_none_or(|extension| extension != "vm") {
+                return Err(VmError::NotFound(format!("{} is not a .vm file", input)));
+            }
+            input_files.push(path.to_path_buf());
+        } else {
+            return Err(VmError::NotFound(format!("{} does not exist", input)));
+        }
+
+        for file in input_files {
+            let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+            if !files
+                .iter()
+                .any(|known| known.canonicalize().unwrap_or_else(|_| known.clone()) == canonical)
+            {
+                files.push(file);
+            }
+        }
+    }
+
+    Ok(files)
+}
@@ -1,81 +1,131 @@
-use std::{env, fs, path::PathBuf};
+use std::{env, fs, path::Path, process};
 
-use parser::Parser;
+use vm::{extract_vm_files, translate_with, TranslateOptions, VmError, VmSource};
 
-use crate::{code_writer::CodeWriter, parser::CommandType};
-
-mod code_writer;
-mod parser;
+struct Options {
+    inputs: Vec<String>,
+    out_filename: Option<String>,
+    annotate: bool,
+    source_map: bool,
+    debug: bool,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let options = get_options();
 
-    // let file_name = &args[1];
-    let dir_name = &args[1];
+    if let Err(err) = run(&options) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
 
-    match extrac_vm_files(dir_name) {
-        Ok(mut vm_files) => {
-            let out_filename = dir_name.to_string()
-                + "/"
-                + &dir_name.split("/").last().unwrap().to_string()
-                + ".asm";
+fn run(options: &Options) -> Result<(), VmError> {
+    let inputs = &options.inputs;
+    let vm_files = extract_vm_files(inputs)?;
+    if vm_files.is_empty() {
+        return Err(VmError::NotFound(format!(
+            "no .vm files found in {}",
+            inputs.join(", ")
+        )));
+    }
 
-            let mut code_writer = CodeWriter::new(&out_filename).unwrap();
-            code_writer.write_init();
+    let sources = vm_files
+        .iter()
+        .map(|file| VmSource::read(file))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let translate_options = TranslateOptions {
+        annotate: options.annotate,
+        debug: options.debug,
+        ..TranslateOptions::default()
+    };
+    let translation = translate_with(&sources, &translate_options)?;
+
+    let out_filename = options
+        .out_filename
+        .clone()
+        .unwrap_or_else(|| default_out_filename(&inputs[0]));
+    write_file(&out_filename, &translation.asm)?;
+
+    if options.source_map {
+        let map_filename = Path::new(&out_filename)
+            .with_extension("map")
+            .to_string_lossy()
+            .to_string();
+        let map: String = translation
+            .source_map
+            .iter()
+            .map(|entry| entry.to_string() + "\n")
+            .collect();
+        write_file(&map_filename, &map)?;
+    }
+    Ok(())
+}
 
-            if let Some(index) = vm_files
-                .iter()
-                .position(|file| file.to_str().unwrap() == dir_name.to_string() + "/Sys.vm")
-            {
-                let removed_element = vm_files.remove(index);
+fn write_file(filename: &str, contents: &str) -> Result<(), VmError> {
+    fs::write(filename, contents).map_err(|err| VmError::Io {
+        path: filename.to_string(),
+        err,
+    })
+}
 
-                vm_files.insert(0, removed_element);
+const USAGE: &str =
+    "usage: vm <dir | file.vm>... [-o out.asm] [--annotate] [--source-map] [--debug]";
+
+// --annotate    precede each command's code with `// File.vm:line command`
+// --source-map  write out.map mapping ROM address ranges to VM file and line
+// --debug       emit stack and this/that bounds checks, see CodeWriter::set_debug
+fn get_options() -> Options {
+    let mut options = Options {
+        inputs: Vec::new(),
+        out_filename: None,
+        annotate: false,
+        source_map: false,
+        debug: false,
+    };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => {
+                options.out_filename = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("-o needs an output file name");
+                    process::exit(1);
+                }))
             }
-
-            for file_name in vm_files {
-                let file_name = file_name.to_str().unwrap();
-
-                code_writer.set_file_name(file_name);
-
-                let mut parser = Parser::new(&file_name).unwrap();
-
-                while parser.has_more_commands() {
-                    parser.advance();
-                    match parser.command_type() {
-                        CommandType::CArithmetic => code_writer.write_arithmetic(&parser.command()),
-                        CommandType::CPush | CommandType::CPop => code_writer.write_push_pop(
-                            parser.command_type(),
-                            &parser.arg1(),
-                            parser.arg2(),
-                        ),
-                        CommandType::CGoto => code_writer.write_goto(&parser.arg1()),
-                        CommandType::CLabel => code_writer.write_label(&parser.arg1()),
-                        CommandType::CIf => code_writer.write_if(&parser.arg1()),
-                        CommandType::CFunction => {
-                            code_writer.write_function(&parser.arg1(), parser.arg2())
-                        }
-                        CommandType::CReturn => code_writer.write_return(),
-                        CommandType::CCall => code_writer.write_call(&parser.arg1(), parser.arg2()),
-                    }
-                }
+            "--annotate" => options.annotate = true,
+            "--source-map" => options.source_map = true,
+            "--debug" => options.debug = true,
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                process::exit(1);
             }
+            _ => options.inputs.push(arg),
         }
-        Err(err) => eprintln!("Error: {}", err),
     }
-}
-
-fn extrac_vm_files(dir_name: &str) -> Result<Vec<PathBuf>, std::io::Error> {
-    let entries = fs::read_dir(dir_name)?;
-    let mut files = Vec::new();
 
-    for entry in entries {
-        let file_path = entry?.path();
-        if let Some(extension) = file_path.extension() {
-            if extension == "vm" {
-                files.push(file_path);
-            }
-        }
+    if options.inputs.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(1);
     }
+    options
+}
 
-    Ok(files)
+// dir   -> dir/dir.asm
+// X.vm  -> X.asm
+fn default_out_filename(input: &str) -> String {
+    let path = Path::new(input);
+    if path.is_dir() {
+        let dir_name = path
+            .canonicalize()
+            .ok()
+            .and_then(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "out".to_string());
+        path.join(dir_name + ".asm").to_string_lossy().to_string()
+    } else {
+        path.with_extension("asm").to_string_lossy().to_string()
+    }
 }
@@ -1,8 +1,10 @@
-use std::{fmt::Error, fs::File, io::BufRead, io::BufReader};
+use std::{fmt::Error, fs};
 
 pub struct Parser {
     pub commands: Vec<String>,
     pub commnad_number: usize,
+    // 1-based line in the source file of each entry in `commands`
+    pub line_numbers: Vec<usize>,
 }
 
 pub enum CommandType {
@@ -19,28 +21,31 @@ pub enum CommandType {
 
 impl Parser {
     pub fn new(filename: &str) -> Result<Self, Error> {
-        match File::open(filename) {
-            Ok(file) => {
-                let reader = BufReader::new(file);
-                let commands: Vec<String> = reader
-                    .lines()
-                    .map(|line| {
-                        let line = line.unwrap().trim().to_string();
-                        let removed_inline_comment =
-                            line.split("//").next().unwrap_or("").trim().to_string();
-                        removed_inline_comment
-                    })
-                    .filter(|line| !Parser::is_commnet_or_whitespace(&line))
-                    .collect();
-                Ok(Parser {
-                    commands,
-                    commnad_number: 0,
-                })
-            }
+        match fs::read_to_string(filename) {
+            Ok(code) => Ok(Parser::from_code(&code)),
             _ => Err(Error),
         }
     }
 
+    pub fn from_code(code: &str) -> Self {
+        let (line_numbers, commands): (Vec<usize>, Vec<String>) = code
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let line = line.trim().to_string();
+                let removed_inline_comment =
+                    line.split("//").next().unwrap_or("").trim().to_string();
+                (index + 1, removed_inline_comment)
+            })
+            .filter(|(_, line)| !Parser::is_commnet_or_whitespace(line))
+            .unzip();
+        Parser {
+            commands,
+            commnad_number: 0,
+            line_numbers,
+        }
+    }
+
     pub fn has_more_commands(&self) -> bool {
         self.commnad_number < self.commands.len()
     }
@@ -74,20 +79,24 @@ impl Parser {
     pub fn arg1(&self) -> String {
         match self.command_type() {
             CommandType::CArithmetic => {
-                self.command().split(" ").collect::<Vec<&str>>()[0].to_string()
+                self.command().split_whitespace().collect::<Vec<&str>>()[0].to_string()
             }
-            _ => self.command().split(" ").collect::<Vec<&str>>()[1].to_string(),
+            _ => self.command().split_whitespace().collect::<Vec<&str>>()[1].to_string(),
         }
     }
 
     pub fn arg2(&self) -> usize {
-        self.command().split(" ").collect::<Vec<&str>>()[2]
+        self.command().split_whitespace().collect::<Vec<&str>>()[2]
             .parse()
             .unwrap()
     }
 
     fn is_commnet_or_whitespace(command: &str) -> bool {
-        command.starts_with("//") || command == ""
+        command.starts_with("//") || command.is_empty()
+    }
+
+    pub fn line_number(&self) -> usize {
+        self.line_numbers[self.commnad_number - 1]
     }
 
     pub fn command(&self) -> String {
@@ -0,0 +1,62 @@
+use vm::{TranslateOptions, VmSource};
+
+const MAIN: &str = "\
+// adds two numbers
+function Main.add 0
+push argument 0
+push argument 1
+
+add
+return
+";
+
+fn translate(annotate: bool) -> vm::Translation {
+    let options = TranslateOptions {
+        bootstrap: false,
+        annotate,
+        ..TranslateOptions::default()
+    };
+    vm::translate_with(&[VmSource::new("Main", MAIN)], &options).unwrap()
+}
+
+// the instructions, without labels and comments
+fn instructions(asm: &str) -> Vec<&str> {
+    asm.lines()
+        .filter(|line| !line.starts_with('(') && !line.starts_with("//"))
+        .collect()
+}
+
+#[test]
+fn comments_each_command_with_its_file_and_line() {
+    let asm = translate(true).asm;
+    let comments: Vec<&str> = asm.lines().filter(|line| line.starts_with("//")).collect();
+    assert_eq!(
+        comments,
+        [
+            "// Main.vm:2 function Main.add 0",
+            "// Main.vm:3 push argument 0",
+            "// Main.vm:4 push argument 1",
+            "// Main.vm:6 add",
+            "// Main.vm:7 return",
+        ]
+    );
+    // the comments are all that changes
+    assert_eq!(instructions(&asm), instructions(&translate(false).asm));
+}
+
+#[test]
+fn maps_every_instruction_to_its_command() {
+    let translation = translate(false);
+    let map = &translation.source_map;
+    assert_eq!(map.len(), 5);
+    assert_eq!(map[0].rom_start, 0);
+    for pair in map.windows(2) {
+        assert_eq!(pair[0].rom_end, pair[1].rom_start);
+    }
+    assert_eq!(
+        map.last().unwrap().rom_end,
+        instructions(&translation.asm).len()
+    );
+    assert_eq!((map[3].line, map[3].command.as_str()), (6, "add"));
+    assert_eq!(map[3].to_string().split('\t').nth(2), Some("Main.vm"));
+}
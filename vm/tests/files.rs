@@ -0,0 +1,44 @@
+use std::path::Path;
+
+fn project(dir: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects/08")
+        .join(dir)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn names(files: &[std::path::PathBuf]) -> Vec<String> {
+    files
+        .iter()
+        .map(|file| file.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn takes_a_directory_in_sorted_order() {
+    let files = vm::extract_vm_files(&[project("FunctionCalls/StaticsTest")]).unwrap();
+    assert_eq!(names(&files), ["Class1.vm", "Class2.vm", "Sys.vm"]);
+}
+
+#[test]
+fn keeps_the_order_of_the_inputs_and_reads_a_file_once() {
+    let dir = project("FunctionCalls/FibonacciElement");
+    let sys = format!("{}/Sys.vm", dir);
+    let files = vm::extract_vm_files(&[sys.clone(), dir, sys]).unwrap();
+    assert_eq!(names(&files), ["Sys.vm", "Main.vm"]);
+}
+
+#[test]
+fn rejects_missing_inputs_and_other_files() {
+    let missing = project("FunctionCalls/NoSuchDir");
+    assert!(matches!(
+        vm::extract_vm_files(&[missing]),
+        Err(vm::VmError::NotFound(_))
+    ));
+    let tst = project("FunctionCalls/StaticsTest/StaticsTest.tst");
+    assert!(matches!(
+        vm::extract_vm_files(&[tst]),
+        Err(vm::VmError::NotFound(_))
+    ));
+}
@@ -0,0 +1,168 @@
+use std::{fs, path::Path};
+
+use vm::{TranslateOptions, VmSource};
+
+const RAM_SIZE: usize = 32768;
+
+// A Hack CPU running the assembled program for a number of cycles, the way
+// the course CPU emulator runs a `repeat n { ticktock; }` script.
+fn run_hack(hack: &str, ram: &mut [i16], cycles: usize) {
+    let rom: Vec<u16> = hack
+        .lines()
+        .map(|line| u16::from_str_radix(line, 2).unwrap())
+        .collect();
+    let (mut a, mut d, mut pc) = (0i16, 0i16, 0usize);
+    for _ in 0..cycles {
+        let Some(&instruction) = rom.get(pc) else {
+            return;
+        };
+        if instruction & 0x8000 == 0 {
+            a = instruction as i16;
+            pc += 1;
+            continue;
+        }
+        let y = if instruction & 0x1000 != 0 {
+            ram[a as u16 as usize]
+        } else {
+            a
+        };
+        let bit = |n: u16| instruction & (1 << n) != 0;
+        let mut x = if bit(11) { 0 } else { d };
+        if bit(10) {
+            x = !x;
+        }
+        let mut y = if bit(9) { 0 } else { y };
+        if bit(8) {
+            y = !y;
+        }
+        let mut out = if bit(7) { x.wrapping_add(y) } else { x & y };
+        if bit(6) {
+            out = !out;
+        }
+        // M is written through the A of before this instruction
+        if bit(3) {
+            ram[a as u16 as usize] = out;
+        }
+        if bit(5) {
+            a = out;
+        }
+        if bit(4) {
+            d = out;
+        }
+        let jump = (bit(2) && out < 0) || (bit(1) && out == 0) || (bit(0) && out > 0);
+        pc = if jump { a as u16 as usize } else { pc + 1 };
+    }
+}
+
+// the RAM settings, cycle count and RAM addresses output by a course .tst
+fn parse_script(script: &str) -> (Vec<(usize, i16)>, usize, Vec<usize>) {
+    let mut settings = Vec::new();
+    let mut cycles = 0;
+    let mut outputs = Vec::new();
+    for word in script
+        .lines()
+        .map(|line| line.split("//").next().unwrap())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split([' ', '\t', ',', ';'])
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .windows(3)
+    {
+        let ram_address = |word: &str| -> Option<usize> {
+            word.strip_prefix("RAM[")?.split(']').next()?.parse().ok()
+        };
+        match word {
+            ["set", address, value] => {
+                settings.push((ram_address(address).unwrap(), value.parse().unwrap()))
+            }
+            ["repeat", count, _] => cycles = count.parse().unwrap(),
+            _ => {}
+        }
+    }
+    let output_list = script.split("output-list").nth(1).unwrap();
+    for column in output_list.split(';').next().unwrap().split_whitespace() {
+        let address = column.strip_prefix("RAM[").unwrap().split(']').next();
+        outputs.push(address.unwrap().parse().unwrap());
+    }
+    (settings, cycles, outputs)
+}
+
+// the values of the .cmp file's table
+fn parse_compare(compare: &str) -> Vec<i16> {
+    compare
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('|')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().unwrap())
+        .collect()
+}
+
+fn check_project(name: &str, debug: bool) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects/08/FunctionCalls")
+        .join(name);
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "vm"))
+        .collect();
+    files.sort();
+    let sources: Vec<VmSource> = files
+        .iter()
+        .map(|file| VmSource::read(file).unwrap())
+        .collect();
+    let options = TranslateOptions {
+        // SimpleFunction has no Sys.init and its script sets up the stack
+        bootstrap: files.iter().any(|file| file.ends_with("Sys.vm")),
+        debug,
+        ..TranslateOptions::default()
+    };
+    let asm = vm::translate_with(&sources, &options).unwrap().asm;
+    let hack = assembler::assemble(&asm).unwrap();
+
+    let script = fs::read_to_string(dir.join(format!("{}.tst", name))).unwrap();
+    let (settings, cycles, outputs) = parse_script(&script);
+    let mut ram = vec![0; RAM_SIZE];
+    for (address, value) in settings {
+        ram[address] = value;
+    }
+    // the checks take cycles of their own; the programs end in a loop
+    let cycles = if debug { cycles * 4 } else { cycles };
+    run_hack(&hack, &mut ram, cycles);
+
+    let actual: Vec<i16> = outputs.iter().map(|address| ram[*address]).collect();
+    let expected = parse_compare(&fs::read_to_string(dir.join(format!("{}.cmp", name))).unwrap());
+    assert_eq!(actual, expected, "{} (debug: {})", name, debug);
+    // no check failed, which would have stored its code in R15
+    if debug {
+        assert_eq!(ram[15], 0, "{} stopped at a debug check", name);
+    }
+}
+
+#[test]
+fn translates_the_function_call_projects() {
+    for name in [
+        "SimpleFunction",
+        "NestedCall",
+        "FibonacciElement",
+        "StaticsTest",
+    ] {
+        check_project(name, false);
+    }
+}
+
+#[test]
+fn translates_the_function_call_projects_with_debug_checks() {
+    for name in [
+        "SimpleFunction",
+        "NestedCall",
+        "FibonacciElement",
+        "StaticsTest",
+    ] {
+        check_project(name, true);
+    }
+}
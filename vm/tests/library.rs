@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+
+use vm::{code_writer::CodeWriter, translate, translate_file, VmError, VmSource};
+
+const MAIN: &str = "function Main.main 0
+push constant 7
+push constant 8
+add
+return
+";
+
+// a writer that fails once `limit` bytes have been written
+struct Failing {
+    written: usize,
+    limit: usize,
+}
+
+impl Write for Failing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.limit {
+            return Err(io::Error::other("disk full"));
+        }
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn writes_to_any_writer() {
+    let file = VmSource::new("Main", MAIN);
+
+    let mut code_writer = CodeWriter::new(Vec::new());
+    translate_file(&mut code_writer, &file).unwrap();
+    let (asm, _) = code_writer.close().unwrap();
+    let asm = String::from_utf8(asm).unwrap();
+
+    assert!(asm.contains("(Main.main)"));
+    assert!(asm.contains("@7"));
+    assert!(asm.contains("@8"));
+
+    let full = translate(&[file]).unwrap();
+    assert!(full.starts_with("@256"), "no bootstrap in {}", full);
+    assert!(full.ends_with(&asm), "the file's code is not at the end");
+}
+
+#[test]
+fn passes_on_write_errors() {
+    let mut code_writer = CodeWriter::new(Failing {
+        written: 0,
+        limit: 16,
+    });
+    let result = translate_file(&mut code_writer, &VmSource::new("Main", MAIN));
+    match result {
+        Err(VmError::Io { err, .. }) => assert_eq!(err.to_string(), "disk full"),
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(()) => panic!("the write error was dropped"),
+    }
+}
+
+#[test]
+fn reports_the_file_and_line_of_a_bad_command() {
+    let file = VmSource::new("Main", "function Main.main 0\npush constant\nreturn\n");
+    match translate(&[file]) {
+        Err(VmError::Syntax {
+            file,
+            line,
+            command,
+        }) => {
+            assert_eq!(file, "Main");
+            assert_eq!(line, 2);
+            assert_eq!(command, "push constant");
+        }
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("the bad command was translated"),
+    }
+}
+
+#[test]
+fn rejects_a_function_defined_twice() {
+    let files = [VmSource::new("Main", MAIN), VmSource::new("Other", MAIN)];
+    let err = translate(&files).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function Main.main is defined in both Main.vm and Other.vm"
+    );
+}
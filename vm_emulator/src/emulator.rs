@@ -0,0 +1,601 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use vm::parser::{CommandType, Parser};
+use vm::VmSource;
+
+pub const RAM_SIZE: usize = 32768;
+
+pub const SP: usize = 0;
+pub const LCL: usize = 1;
+pub const ARG: usize = 2;
+pub const THIS: usize = 3;
+pub const THAT: usize = 4;
+
+/// Where the bootstrap code points SP before calling Sys.init.
+pub const STACK_BASE: usize = 256;
+
+const POINTER_BASE: usize = 3;
+const TEMP_BASE: usize = 5;
+const STATIC_BASE: usize = 16;
+const STATIC_END: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Constant,
+    Local,
+    Argument,
+    This,
+    That,
+    Pointer,
+    Temp,
+    Static,
+}
+
+impl Segment {
+    fn from_name(name: &str) -> Option<Segment> {
+        match name {
+            "constant" => Some(Segment::Constant),
+            "local" => Some(Segment::Local),
+            "argument" => Some(Segment::Argument),
+            "this" => Some(Segment::This),
+            "that" => Some(Segment::That),
+            "pointer" => Some(Segment::Pointer),
+            "temp" => Some(Segment::Temp),
+            "static" => Some(Segment::Static),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constant => write!(f, "constant"),
+            Self::Local => write!(f, "local"),
+            Self::Argument => write!(f, "argument"),
+            Self::This => write!(f, "this"),
+            Self::That => write!(f, "that"),
+            Self::Pointer => write!(f, "pointer"),
+            Self::Temp => write!(f, "temp"),
+            Self::Static => write!(f, "static"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+impl ArithmeticOp {
+    fn from_name(name: &str) -> Option<ArithmeticOp> {
+        match name {
+            "add" => Some(ArithmeticOp::Add),
+            "sub" => Some(ArithmeticOp::Sub),
+            "neg" => Some(ArithmeticOp::Neg),
+            "eq" => Some(ArithmeticOp::Eq),
+            "gt" => Some(ArithmeticOp::Gt),
+            "lt" => Some(ArithmeticOp::Lt),
+            "and" => Some(ArithmeticOp::And),
+            "or" => Some(ArithmeticOp::Or),
+            "not" => Some(ArithmeticOp::Not),
+            _ => None,
+        }
+    }
+}
+
+// Labels are not instructions: they are resolved to the index of the
+// instruction that follows them, so `goto` and `if-goto` carry that index.
+// For the static segment the operand is the RAM address assigned to the
+// variable of the file the command came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Arithmetic(ArithmeticOp),
+    Push(Segment, usize),
+    Pop(Segment, usize),
+    Goto(usize),
+    IfGoto(usize),
+    Function {
+        name: String,
+        local_num: usize,
+    },
+    Call {
+        name: String,
+        arg_num: usize,
+        target: Option<usize>,
+    },
+    Return,
+}
+
+#[derive(Debug)]
+pub enum EmulatorError {
+    Load(String),
+    Syntax { file: String, command: String },
+    UndefinedLabel { file: String, label: String },
+    DuplicateFunction(String),
+    UndefinedFunction(String),
+    TooManyStatics(String),
+    OutOfBounds { pc: usize, address: i32 },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(message) => write!(f, "{}", message),
+            Self::Syntax { file, command } => {
+                write!(f, "{}: invalid command \"{}\"", file, command)
+            }
+            Self::UndefinedLabel { file, label } => {
+                write!(f, "{}: label {} is not defined", file, label)
+            }
+            Self::DuplicateFunction(name) => write!(f, "function {} is defined twice", name),
+            Self::UndefinedFunction(name) => write!(f, "function {} is not defined", name),
+            Self::TooManyStatics(file) => {
+                write!(f, "{}: static segment exceeds RAM[16..255]", file)
+            }
+            Self::OutOfBounds { pc, address } => write!(
+                f,
+                "instruction {} accessed RAM[{}] which is out of bounds",
+                pc, address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+pub struct VmEmulator {
+    program: Vec<Instruction>,
+    functions: HashMap<String, usize>,
+    ram: Vec<i16>,
+    pc: usize,
+    call_stack: Vec<String>,
+    // 1 when execution started inside Sys.init, which has no caller frame
+    entry_depth: usize,
+    steps: usize,
+    halted: bool,
+}
+
+impl VmEmulator {
+    /// Loads a single `.vm` file or every `.vm` file in a directory.
+    pub fn load(path: &str) -> Result<Self, EmulatorError> {
+        let path = Path::new(path);
+        let files = if path.is_dir() {
+            extract_vm_files(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        Self::load_files(&files)
+    }
+
+    pub fn load_files(files: &[PathBuf]) -> Result<Self, EmulatorError> {
+        let sources = files
+            .iter()
+            .map(|file| {
+                VmSource::read(file)
+                    .map_err(|_| EmulatorError::Load(format!("{} does not exist", file.display())))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::load_sources(&sources)
+    }
+
+    /// Loads VM code that is already in memory, e.g. the output of the
+    /// Jack compiler, without writing it out to `.vm` files first.
+    pub fn load_sources(sources: &[VmSource]) -> Result<Self, EmulatorError> {
+        let mut program = Vec::new();
+        let mut functions = HashMap::new();
+        let mut next_static = STATIC_BASE;
+
+        for source in sources {
+            let file_name = source.name.clone() + ".vm";
+            let mut parser = Parser::from_code(&source.code);
+
+            let mut statics: HashMap<usize, usize> = HashMap::new();
+            let mut labels: HashMap<String, usize> = HashMap::new();
+            let mut jumps: Vec<(usize, String)> = Vec::new();
+            let mut scope = String::new();
+
+            while parser.has_more_commands() {
+                parser.advance();
+                let syntax_error = || EmulatorError::Syntax {
+                    file: file_name.clone(),
+                    command: parser.command(),
+                };
+                let words: Vec<String> = parser
+                    .command()
+                    .split_whitespace()
+                    .map(|word| word.to_string())
+                    .collect();
+
+                match parser.command_type() {
+                    CommandType::CArithmetic => {
+                        let op = ArithmeticOp::from_name(&words[0]).ok_or_else(syntax_error)?;
+                        program.push(Instruction::Arithmetic(op));
+                    }
+                    CommandType::CPush | CommandType::CPop => {
+                        if words.len() != 3 {
+                            return Err(syntax_error());
+                        }
+                        let segment = Segment::from_name(&words[1]).ok_or_else(syntax_error)?;
+                        let mut index: usize = words[2].parse().map_err(|_| syntax_error())?;
+                        if segment == Segment::Static {
+                            index = match statics.get(&index) {
+                                Some(address) => *address,
+                                None => {
+                                    if next_static > STATIC_END {
+                                        return Err(EmulatorError::TooManyStatics(file_name));
+                                    }
+                                    statics.insert(index, next_static);
+                                    next_static += 1;
+                                    next_static - 1
+                                }
+                            };
+                        }
+                        if words[0] == "push" {
+                            program.push(Instruction::Push(segment, index));
+                        } else {
+                            if segment == Segment::Constant {
+                                return Err(syntax_error());
+                            }
+                            program.push(Instruction::Pop(segment, index));
+                        }
+                    }
+                    CommandType::CLabel => {
+                        let label = words.get(1).ok_or_else(syntax_error)?;
+                        labels.insert(scope.clone() + "$" + label, program.len());
+                    }
+                    CommandType::CGoto | CommandType::CIf => {
+                        let label = words.get(1).ok_or_else(syntax_error)?;
+                        jumps.push((program.len(), scope.clone() + "$" + label));
+                        if words[0] == "goto" {
+                            program.push(Instruction::Goto(0));
+                        } else {
+                            program.push(Instruction::IfGoto(0));
+                        }
+                    }
+                    CommandType::CFunction => {
+                        if words.len() != 3 {
+                            return Err(syntax_error());
+                        }
+                        let local_num = words[2].parse().map_err(|_| syntax_error())?;
+                        scope = words[1].clone();
+                        if functions.insert(scope.clone(), program.len()).is_some() {
+                            return Err(EmulatorError::DuplicateFunction(scope));
+                        }
+                        program.push(Instruction::Function {
+                            name: scope.clone(),
+                            local_num,
+                        });
+                    }
+                    CommandType::CCall => {
+                        if words.len() != 3 {
+                            return Err(syntax_error());
+                        }
+                        program.push(Instruction::Call {
+                            name: words[1].clone(),
+                            arg_num: words[2].parse().map_err(|_| syntax_error())?,
+                            target: None,
+                        });
+                    }
+                    CommandType::CReturn => program.push(Instruction::Return),
+                }
+            }
+
+            for (index, label) in jumps {
+                let target = *labels
+                    .get(&label)
+                    .ok_or_else(|| EmulatorError::UndefinedLabel {
+                        file: file_name.clone(),
+                        label: label.rsplit('$').next().unwrap_or("").to_string(),
+                    })?;
+                match &mut program[index] {
+                    Instruction::Goto(to) | Instruction::IfGoto(to) => *to = target,
+                    _ => (),
+                }
+            }
+        }
+
+        // calls may refer to functions defined in files loaded later,
+        // so they are resolved once every file has been read
+        for instruction in program.iter_mut() {
+            if let Instruction::Call { name, target, .. } = instruction {
+                *target = functions.get(name).copied();
+            }
+        }
+
+        let mut emulator = VmEmulator {
+            program,
+            functions,
+            ram: vec![0; RAM_SIZE],
+            pc: 0,
+            call_stack: Vec::new(),
+            entry_depth: 0,
+            steps: 0,
+            halted: false,
+        };
+        emulator.reset();
+        Ok(emulator)
+    }
+
+    /// Restarts the program, from `Sys.init` if it is defined or from the
+    /// first instruction otherwise. RAM is left untouched.
+    pub fn reset(&mut self) {
+        self.steps = 0;
+        self.call_stack.clear();
+        match self.functions.get("Sys.init") {
+            Some(index) => {
+                self.pc = *index;
+                self.call_stack.push("Sys.init".to_string());
+                self.entry_depth = 1;
+            }
+            None => {
+                self.pc = 0;
+                self.entry_depth = 0;
+            }
+        }
+        self.halted = self.program.is_empty();
+    }
+
+    /// Executes one VM command. Returns `false` once the program has halted,
+    /// either by running past its last command or by returning from the
+    /// outermost function.
+    pub fn step(&mut self) -> Result<bool, EmulatorError> {
+        if self.halted {
+            return Ok(false);
+        }
+
+        let pc = self.pc;
+        self.pc += 1;
+        self.steps += 1;
+
+        match &self.program[pc] {
+            Instruction::Arithmetic(op) => {
+                let op = *op;
+                self.execute_arithmetic(op)?
+            }
+            Instruction::Push(segment, index) => {
+                let value = match segment {
+                    Segment::Constant => *index as i16,
+                    _ => {
+                        let address = self.segment_address(*segment, *index)?;
+                        self.ram[address]
+                    }
+                };
+                self.push(value)?;
+            }
+            Instruction::Pop(segment, index) => {
+                let address = self.segment_address(*segment, *index)?;
+                let value = self.pop()?;
+                self.ram[address] = value;
+            }
+            Instruction::Goto(to) => self.pc = *to,
+            Instruction::IfGoto(to) => {
+                let to = *to;
+                if self.pop()? != 0 {
+                    self.pc = to;
+                }
+            }
+            Instruction::Function { local_num, .. } => {
+                for _ in 0..*local_num {
+                    self.push(0)?;
+                }
+            }
+            Instruction::Call {
+                name,
+                arg_num,
+                target,
+            } => {
+                let to = target.ok_or_else(|| EmulatorError::UndefinedFunction(name.clone()))?;
+                let name = name.clone();
+                let arg_num = *arg_num;
+
+                self.push(self.pc as i16)?;
+                for pointer in [LCL, ARG, THIS, THAT] {
+                    self.push(self.ram[pointer])?;
+                }
+                let sp = self.ram[SP];
+                self.ram[ARG] = sp - 5 - arg_num as i16;
+                self.ram[LCL] = sp;
+
+                self.call_stack.push(name);
+                self.pc = to;
+            }
+            Instruction::Return => self.execute_return()?,
+        }
+
+        if self.pc >= self.program.len() {
+            self.halted = true;
+        }
+
+        Ok(!self.halted)
+    }
+
+    /// Executes up to `max_steps` commands and returns how many were run.
+    pub fn run(&mut self, max_steps: usize) -> Result<usize, EmulatorError> {
+        let mut executed = 0;
+        while executed < max_steps && !self.halted {
+            self.step()?;
+            executed += 1;
+        }
+        Ok(executed)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn current_instruction(&self) -> Option<&Instruction> {
+        if self.halted {
+            return None;
+        }
+        self.program.get(self.pc)
+    }
+
+    pub fn current_function(&self) -> Option<&str> {
+        self.call_stack.last().map(|name| name.as_str())
+    }
+
+    pub fn call_stack(&self) -> &[String] {
+        &self.call_stack
+    }
+
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    pub fn peek(&self, address: usize) -> i16 {
+        self.ram[address]
+    }
+
+    pub fn poke(&mut self, address: usize, value: i16) {
+        self.ram[address] = value;
+    }
+
+    /// The working stack from RAM[256] up to the stack pointer.
+    pub fn stack(&self) -> &[i16] {
+        let sp = (self.ram[SP] as usize).clamp(STACK_BASE, RAM_SIZE);
+        &self.ram[STACK_BASE..sp]
+    }
+
+    /// Reads `segment[index]` the way a `push` command would.
+    pub fn segment(&self, segment: Segment, index: usize) -> Result<i16, EmulatorError> {
+        match segment {
+            Segment::Constant => Ok(index as i16),
+            _ => Ok(self.ram[self.segment_address(segment, index)?]),
+        }
+    }
+
+    pub fn segment_address(&self, segment: Segment, index: usize) -> Result<usize, EmulatorError> {
+        let address = match segment {
+            Segment::Local => self.ram[LCL] as i32 + index as i32,
+            Segment::Argument => self.ram[ARG] as i32 + index as i32,
+            Segment::This => self.ram[THIS] as i32 + index as i32,
+            Segment::That => self.ram[THAT] as i32 + index as i32,
+            Segment::Pointer => (POINTER_BASE + index) as i32,
+            Segment::Temp => (TEMP_BASE + index) as i32,
+            Segment::Static => index as i32,
+            Segment::Constant => -1,
+        };
+        self.check_address(address)
+    }
+
+    fn check_address(&self, address: i32) -> Result<usize, EmulatorError> {
+        if address < 0 || address as usize >= RAM_SIZE {
+            return Err(EmulatorError::OutOfBounds {
+                pc: self.pc.saturating_sub(1),
+                address,
+            });
+        }
+        Ok(address as usize)
+    }
+
+    fn push(&mut self, value: i16) -> Result<(), EmulatorError> {
+        let sp = self.check_address(self.ram[SP] as i32)?;
+        self.ram[sp] = value;
+        self.ram[SP] += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i16, EmulatorError> {
+        let sp = self.check_address(self.ram[SP] as i32 - 1)?;
+        self.ram[SP] -= 1;
+        Ok(self.ram[sp])
+    }
+
+    fn execute_arithmetic(&mut self, op: ArithmeticOp) -> Result<(), EmulatorError> {
+        let y = self.pop()?;
+        let value = match op {
+            ArithmeticOp::Neg => y.wrapping_neg(),
+            ArithmeticOp::Not => !y,
+            _ => {
+                let x = self.pop()?;
+                match op {
+                    ArithmeticOp::Add => x.wrapping_add(y),
+                    ArithmeticOp::Sub => x.wrapping_sub(y),
+                    ArithmeticOp::And => x & y,
+                    ArithmeticOp::Or => x | y,
+                    ArithmeticOp::Eq => -((x == y) as i16),
+                    ArithmeticOp::Gt => -((x > y) as i16),
+                    ArithmeticOp::Lt => -((x < y) as i16),
+                    ArithmeticOp::Neg | ArithmeticOp::Not => unreachable!(),
+                }
+            }
+        };
+        self.push(value)
+    }
+
+    fn execute_return(&mut self) -> Result<(), EmulatorError> {
+        // Sys.init was entered without a caller frame, there is nothing to
+        // restore when it returns
+        if self.entry_depth > 0 && self.call_stack.len() <= self.entry_depth {
+            self.call_stack.clear();
+            self.halted = true;
+            return Ok(());
+        }
+
+        let frame = self.ram[LCL] as i32;
+        let return_address = self.ram[self.check_address(frame - 5)?];
+
+        let value = self.pop()?;
+        let arg = self.check_address(self.ram[ARG] as i32)?;
+        self.ram[arg] = value;
+        self.ram[SP] = self.ram[ARG] + 1;
+
+        for (offset, pointer) in [THAT, THIS, ARG, LCL].iter().enumerate() {
+            let address = self.check_address(frame - 1 - offset as i32)?;
+            self.ram[*pointer] = self.ram[address];
+        }
+
+        if self.call_stack.len() <= self.entry_depth {
+            self.call_stack.clear();
+            self.halted = true;
+        } else {
+            self.call_stack.pop();
+            self.pc = return_address as u16 as usize;
+        }
+        Ok(())
+    }
+}
+
+pub fn extract_vm_files(dir: &Path) -> Result<Vec<PathBuf>, EmulatorError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|err| EmulatorError::Load(format!("{}: {}", dir.display(), err)))?;
+    let mut files = Vec::new();
+
+    for entry in entries.flatten() {
+        let file_path = entry.path();
+        if file_path
+            .extension()
+            .is_some_and(|extension| extension == "vm")
+        {
+            files.push(file_path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
@@ -0,0 +1,69 @@
+use std::{env, process};
+
+use vm_emulator::emulator::{VmEmulator, SP, STACK_BASE};
+use vm_emulator::test_script::run_script;
+
+const DEFAULT_MAX_STEPS: usize = 10_000_000;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let target_name = args.get(1).unwrap_or_else(|| {
+        eprintln!("usage: vm_emulator <file.vm | dir | script.tst> [max steps]");
+        process::exit(1);
+    });
+
+    if target_name.ends_with(".tst") {
+        run_test_script(target_name);
+        return;
+    }
+
+    let max_steps = match args.get(2) {
+        Some(steps) => steps.parse().unwrap_or_else(|_| {
+            eprintln!("invalid step count {}", steps);
+            process::exit(1);
+        }),
+        None => DEFAULT_MAX_STEPS,
+    };
+
+    let mut emulator = VmEmulator::load(target_name).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    // test scripts set SP themselves, a plain run starts like the bootstrap
+    emulator.poke(SP, STACK_BASE as i16);
+
+    if let Err(err) = emulator.run(max_steps) {
+        eprintln!("Error: {}", err);
+        eprintln!("call stack: {}", emulator.call_stack().join(" > "));
+        process::exit(1);
+    }
+
+    let state = if emulator.is_halted() {
+        "halted"
+    } else {
+        "stopped"
+    };
+    println!("{} after {} steps", state, emulator.steps());
+    println!("SP: {}", emulator.peek(0));
+    println!("stack: {:?}", emulator.stack());
+}
+
+fn run_test_script(script_name: &str) {
+    let result = run_script(script_name).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+
+    match result.mismatch {
+        Some(mismatch) => {
+            eprintln!("Comparison failure at line {}", mismatch.line);
+            eprintln!("expected: {}", mismatch.expected);
+            eprintln!("actual:   {}", mismatch.actual);
+            process::exit(1);
+        }
+        None if result.compare_file.is_some() => {
+            println!("End of script - Comparison ended successfully")
+        }
+        None => println!("End of script"),
+    }
+}
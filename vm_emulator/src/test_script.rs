@@ -0,0 +1,400 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::emulator::{EmulatorError, Segment, VmEmulator, ARG, LCL, RAM_SIZE, SP, THAT, THIS};
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(String),
+    Syntax(String),
+    Emulator(EmulatorError),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "{}", message),
+            Self::Syntax(message) => write!(f, "script error: {}", message),
+            Self::Emulator(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<EmulatorError> for ScriptError {
+    fn from(err: EmulatorError) -> Self {
+        ScriptError::Emulator(err)
+    }
+}
+
+enum Command {
+    Load(Option<String>),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Output,
+    Set(String, i16),
+    VmStep,
+    Repeat(usize, Vec<Command>),
+    Echo,
+}
+
+#[derive(Clone)]
+struct OutputColumn {
+    name: String,
+    format: char,
+    left: usize,
+    width: usize,
+    right: usize,
+}
+
+impl OutputColumn {
+    fn parse(spec: &str) -> Result<Self, ScriptError> {
+        let invalid = || ScriptError::Syntax(format!("invalid output column {}", spec));
+        let (name, format) = spec.split_once('%').unwrap_or((spec, "D1.6.1"));
+        let mut chars = format.chars();
+        let format = chars.next().ok_or_else(invalid)?;
+        let widths: Vec<usize> = chars
+            .as_str()
+            .split('.')
+            .map(|width| width.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        if widths.len() != 3 || !"DXB".contains(format) {
+            return Err(invalid());
+        }
+        Ok(OutputColumn {
+            name: name.to_string(),
+            format,
+            left: widths[0],
+            width: widths[1],
+            right: widths[2],
+        })
+    }
+
+    fn header(&self) -> String {
+        let total = self.left + self.width + self.right;
+        let name: String = self.name.chars().take(total).collect();
+        let padding = total - name.len();
+        format!(
+            "{}{}{}",
+            " ".repeat(padding / 2),
+            name,
+            " ".repeat(padding - padding / 2)
+        )
+    }
+
+    fn value(&self, value: i16) -> String {
+        let value = match self.format {
+            'X' => format!("{:04X}", value),
+            'B' => format!("{:016b}", value),
+            _ => value.to_string(),
+        };
+        format!(
+            "{}{:>width$}{}",
+            " ".repeat(self.left),
+            value,
+            " ".repeat(self.right),
+            width = self.width
+        )
+    }
+}
+
+pub struct Mismatch {
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+pub struct ScriptResult {
+    pub output: String,
+    pub output_file: Option<PathBuf>,
+    pub compare_file: Option<PathBuf>,
+    pub mismatch: Option<Mismatch>,
+}
+
+/// Runs a `*VME.tst` script from projects/07 or 08. The output file is
+/// written next to the script and compared against the `compare-to` file
+/// the way the course TextComparer does, ignoring whitespace.
+pub fn run_script(script_name: &str) -> Result<ScriptResult, ScriptError> {
+    let script = fs::read_to_string(script_name)
+        .map_err(|err| ScriptError::Io(format!("{}: {}", script_name, err)))?;
+    let dir = Path::new(script_name)
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+    let tokens = tokenize(&script);
+    let commands = parse_commands(&mut tokens.iter().peekable(), false)?;
+
+    let mut runner = ScriptRunner {
+        dir,
+        emulator: None,
+        columns: Vec::new(),
+        output: String::new(),
+        output_file: None,
+        compare_file: None,
+    };
+    runner.execute(&commands)?;
+
+    if let Some(output_file) = &runner.output_file {
+        fs::write(output_file, &runner.output)
+            .map_err(|err| ScriptError::Io(format!("{}: {}", output_file.display(), err)))?;
+    }
+
+    let mismatch = match &runner.compare_file {
+        Some(compare_file) => {
+            let expected = fs::read_to_string(compare_file)
+                .map_err(|err| ScriptError::Io(format!("{}: {}", compare_file.display(), err)))?;
+            compare(&expected, &runner.output)
+        }
+        None => None,
+    };
+
+    Ok(ScriptResult {
+        output: runner.output,
+        output_file: runner.output_file,
+        compare_file: runner.compare_file,
+        mismatch,
+    })
+}
+
+fn compare(expected: &str, actual: &str) -> Option<Mismatch> {
+    let strip = |line: &str| line.split_whitespace().collect::<String>();
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 0;
+
+    loop {
+        line += 1;
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (Some(expected), Some(actual)) if strip(expected) == strip(actual) => (),
+            (expected, actual) => {
+                return Some(Mismatch {
+                    line,
+                    expected: expected.unwrap_or("").to_string(),
+                    actual: actual.unwrap_or("").to_string(),
+                })
+            }
+        }
+    }
+}
+
+struct ScriptRunner {
+    dir: PathBuf,
+    emulator: Option<VmEmulator>,
+    columns: Vec<OutputColumn>,
+    output: String,
+    output_file: Option<PathBuf>,
+    compare_file: Option<PathBuf>,
+}
+
+impl ScriptRunner {
+    fn execute(&mut self, commands: &[Command]) -> Result<(), ScriptError> {
+        for command in commands {
+            match command {
+                Command::Load(file) => {
+                    let path = match file {
+                        Some(file) => self.dir.join(file),
+                        None => self.dir.clone(),
+                    };
+                    self.emulator = Some(VmEmulator::load(path.to_str().unwrap_or(""))?);
+                }
+                Command::OutputFile(file) => self.output_file = Some(self.dir.join(file)),
+                Command::CompareTo(file) => self.compare_file = Some(self.dir.join(file)),
+                Command::OutputList(columns) => {
+                    self.columns = columns.clone();
+                    let headers: Vec<String> =
+                        self.columns.iter().map(|column| column.header()).collect();
+                    self.output += &format!("|{}|\n", headers.join("|"));
+                }
+                Command::Output => {
+                    let mut values = Vec::new();
+                    for column in &self.columns {
+                        let address = self.address_of(&column.name)?;
+                        values.push(column.value(self.emulator()?.peek(address)));
+                    }
+                    self.output += &format!("|{}|\n", values.join("|"));
+                }
+                Command::Set(target, value) => {
+                    let address = self.address_of(target)?;
+                    self.emulator_mut()?.poke(address, *value);
+                }
+                Command::VmStep => {
+                    self.emulator_mut()?.step()?;
+                }
+                Command::Repeat(count, body) => {
+                    for _ in 0..*count {
+                        self.execute(body)?;
+                    }
+                }
+                Command::Echo => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn emulator(&self) -> Result<&VmEmulator, ScriptError> {
+        self.emulator
+            .as_ref()
+            .ok_or_else(|| ScriptError::Syntax("no program has been loaded".to_string()))
+    }
+
+    fn emulator_mut(&mut self) -> Result<&mut VmEmulator, ScriptError> {
+        self.emulator
+            .as_mut()
+            .ok_or_else(|| ScriptError::Syntax("no program has been loaded".to_string()))
+    }
+
+    // resolves "sp", "local", "RAM[i]", "argument[i]" ... to a RAM address
+    fn address_of(&self, target: &str) -> Result<usize, ScriptError> {
+        let unknown = || ScriptError::Syntax(format!("unknown variable {}", target));
+        let (name, index) = match target.split_once('[') {
+            Some((name, rest)) => {
+                let index = rest
+                    .strip_suffix(']')
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .ok_or_else(unknown)?;
+                (name, Some(index))
+            }
+            None => (target, None),
+        };
+
+        let segment = match (name, index) {
+            ("RAM", Some(index)) if index < RAM_SIZE => return Ok(index),
+            ("sp", None) => return Ok(SP),
+            ("local", None) => return Ok(LCL),
+            ("argument", None) => return Ok(ARG),
+            ("this", None) => return Ok(THIS),
+            ("that", None) => return Ok(THAT),
+            ("local", Some(_)) => Segment::Local,
+            ("argument", Some(_)) => Segment::Argument,
+            ("this", Some(_)) => Segment::This,
+            ("that", Some(_)) => Segment::That,
+            ("temp", Some(_)) => Segment::Temp,
+            ("pointer", Some(_)) => Segment::Pointer,
+            _ => return Err(unknown()),
+        };
+        Ok(self
+            .emulator()?
+            .segment_address(segment, index.unwrap_or(0))?)
+    }
+}
+
+fn tokenize(script: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = script.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            push_token(&mut tokens, &mut token);
+            continue;
+        }
+        if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut last = ' ';
+            for c in chars.by_ref() {
+                if last == '*' && c == '/' {
+                    break;
+                }
+                last = c;
+            }
+            push_token(&mut tokens, &mut token);
+            continue;
+        }
+        if c.is_whitespace() {
+            push_token(&mut tokens, &mut token);
+        } else if ",;!{}".contains(c) {
+            push_token(&mut tokens, &mut token);
+            tokens.push(c.to_string());
+        } else {
+            token.push(c);
+        }
+    }
+    push_token(&mut tokens, &mut token);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<String>, token: &mut String) {
+    if !token.is_empty() {
+        tokens.push(std::mem::take(token));
+    }
+}
+
+fn parse_commands<'a, I>(
+    tokens: &mut std::iter::Peekable<I>,
+    in_block: bool,
+) -> Result<Vec<Command>, ScriptError>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut commands = Vec::new();
+
+    while let Some(word) = tokens.next() {
+        let mut args = Vec::new();
+        match word.as_str() {
+            "}" if in_block => return Ok(commands),
+            "," | ";" | "!" => continue,
+            "repeat" => {
+                let count = tokens
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or_else(|| ScriptError::Syntax("repeat needs a count".to_string()))?;
+                if tokens.next().map(|token| token.as_str()) != Some("{") {
+                    return Err(ScriptError::Syntax("repeat needs a block".to_string()));
+                }
+                commands.push(Command::Repeat(count, parse_commands(tokens, true)?));
+                continue;
+            }
+            _ => (),
+        }
+
+        while let Some(arg) = tokens.peek() {
+            if [",", ";", "!", "}"].contains(&arg.as_str()) {
+                break;
+            }
+            args.push(tokens.next().unwrap().clone());
+        }
+
+        let missing = || ScriptError::Syntax(format!("{} needs an argument", word));
+        let command = match word.as_str() {
+            "load" => Command::Load(args.first().cloned()),
+            "output-file" => Command::OutputFile(args.first().cloned().ok_or_else(missing)?),
+            "compare-to" => Command::CompareTo(args.first().cloned().ok_or_else(missing)?),
+            "output-list" => Command::OutputList(
+                args.iter()
+                    .map(|column| OutputColumn::parse(column))
+                    .collect::<Result<_, _>>()?,
+            ),
+            "output" => Command::Output,
+            "vmstep" => Command::VmStep,
+            "echo" => Command::Echo,
+            "set" => {
+                if args.len() != 2 {
+                    return Err(missing());
+                }
+                let value = args[1]
+                    .parse()
+                    .map_err(|_| ScriptError::Syntax(format!("invalid value {}", args[1])))?;
+                Command::Set(args[0].clone(), value)
+            }
+            _ => return Err(ScriptError::Syntax(format!("unknown command {}", word))),
+        };
+        commands.push(command);
+    }
+
+    if in_block {
+        return Err(ScriptError::Syntax(
+            "repeat block is not closed".to_string(),
+        ));
+    }
+    Ok(commands)
+}
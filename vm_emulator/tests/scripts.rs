@@ -0,0 +1,56 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use vm_emulator::test_script::run_script;
+
+const SCRIPTS: [&str; 11] = [
+    "07/StackArithmetic/SimpleAdd/SimpleAddVME.tst",
+    "07/StackArithmetic/StackTest/StackTestVME.tst",
+    "07/MemoryAccess/BasicTest/BasicTestVME.tst",
+    "07/MemoryAccess/PointerTest/PointerTestVME.tst",
+    "07/MemoryAccess/StaticTest/StaticTestVME.tst",
+    "08/ProgramFlow/BasicLoop/BasicLoopVME.tst",
+    "08/ProgramFlow/FibonacciSeries/FibonacciSeriesVME.tst",
+    "08/FunctionCalls/SimpleFunction/SimpleFunctionVME.tst",
+    "08/FunctionCalls/NestedCall/NestedCallVME.tst",
+    "08/FunctionCalls/FibonacciElement/FibonacciElementVME.tst",
+    "08/FunctionCalls/StaticsTest/StaticsTestVME.tst",
+];
+
+// a copy of the script's directory, so that its .out file is not written
+// into the projects
+fn copy_of(script: &str) -> PathBuf {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects")
+        .join(script);
+    let dir = script.parent().unwrap();
+    let copy = env::temp_dir()
+        .join(format!("vm_emulator-{}", std::process::id()))
+        .join(dir.file_name().unwrap());
+    fs::create_dir_all(&copy).unwrap();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            fs::copy(&path, copy.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    copy.join(script.file_name().unwrap())
+}
+
+#[test]
+fn runs_the_course_vm_emulator_scripts() {
+    for script in SCRIPTS {
+        let copy = copy_of(script);
+        let result =
+            run_script(copy.to_str().unwrap()).unwrap_or_else(|err| panic!("{}: {}", script, err));
+        assert!(result.compare_file.is_some(), "{} compares nothing", script);
+        if let Some(mismatch) = result.mismatch {
+            panic!(
+                "{}: line {}: expected {}, got {}",
+                script, mismatch.line, mismatch.expected, mismatch.actual
+            );
+        }
+    }
+}